@@ -0,0 +1,92 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use common_datavalues::aggregate_types;
+use common_datavalues::prelude::*;
+use common_exception::Result;
+
+use crate::scalars::function_factory::FunctionDescription;
+use crate::scalars::function_factory::FunctionFeatures;
+use crate::scalars::Function;
+
+#[derive(Clone)]
+pub struct CoalesceFunction {
+    _display_name: String,
+}
+
+impl CoalesceFunction {
+    pub fn try_create_func(display_name: &str) -> Result<Box<dyn Function>> {
+        Ok(Box::new(CoalesceFunction {
+            _display_name: display_name.to_string(),
+        }))
+    }
+
+    pub fn desc() -> FunctionDescription {
+        FunctionDescription::creator(Box::new(Self::try_create_func))
+            .features(FunctionFeatures::default().deterministic())
+    }
+}
+
+impl Function for CoalesceFunction {
+    fn name(&self) -> &str {
+        "CoalesceFunction"
+    }
+
+    fn return_type(&self, args: &[DataType]) -> Result<DataType> {
+        aggregate_types(args)
+    }
+
+    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
+        Ok(true)
+    }
+
+    // The first non-NULL argument of each row, the arguments are cast to their common type.
+    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+        let data_types: Vec<DataType> = columns.iter().map(|c| c.data_type().clone()).collect();
+        let data_type = self.return_type(&data_types)?;
+        if data_type == DataType::Null {
+            return Ok(DataColumn::Constant(DataValue::Null, input_rows));
+        }
+
+        let mut values = vec![DataValue::from(&data_type); input_rows];
+        for column in columns {
+            if column.data_type() == &DataType::Null {
+                continue;
+            }
+
+            let column_values = column.column().cast_with_type(&data_type)?.to_values()?;
+            for (value, column_value) in values.iter_mut().zip(column_values) {
+                if value.is_null() {
+                    *value = column_value;
+                }
+            }
+        }
+
+        let result = DataValue::try_into_data_array(&values, &data_type)?;
+        Ok(result.into())
+    }
+
+    // coalesce(expr, ...)
+    fn variadic_arguments(&self) -> Option<(usize, usize)> {
+        Some((1, usize::MAX))
+    }
+}
+
+impl fmt::Display for CoalesceFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "COALESCE")
+    }
+}
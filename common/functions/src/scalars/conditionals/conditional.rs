@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use crate::scalars::function_factory::FunctionFactory;
+use crate::scalars::CoalesceFunction;
 use crate::scalars::IfFunction;
+use crate::scalars::NullIfFunction;
 
 #[derive(Clone)]
 pub struct ConditionalFunction;
@@ -21,5 +23,8 @@ pub struct ConditionalFunction;
 impl ConditionalFunction {
     pub fn register(factory: &mut FunctionFactory) {
         factory.register("if", IfFunction::desc());
+        factory.register("coalesce", CoalesceFunction::desc());
+        factory.register_alias("ifnull", "coalesce");
+        factory.register("nullif", NullIfFunction::desc());
     }
 }
@@ -12,8 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod coalesce;
 mod conditional;
 mod r#if;
+mod nullif;
 
+pub use coalesce::CoalesceFunction;
 pub use conditional::ConditionalFunction;
+pub use nullif::NullIfFunction;
 pub use r#if::IfFunction;
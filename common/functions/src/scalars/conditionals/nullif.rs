@@ -0,0 +1,95 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use common_datavalues::aggregate_types;
+use common_datavalues::prelude::*;
+use common_exception::Result;
+
+use crate::scalars::function_factory::FunctionDescription;
+use crate::scalars::function_factory::FunctionFeatures;
+use crate::scalars::Function;
+
+#[derive(Clone)]
+pub struct NullIfFunction {
+    _display_name: String,
+}
+
+impl NullIfFunction {
+    pub fn try_create_func(display_name: &str) -> Result<Box<dyn Function>> {
+        Ok(Box::new(NullIfFunction {
+            _display_name: display_name.to_string(),
+        }))
+    }
+
+    pub fn desc() -> FunctionDescription {
+        FunctionDescription::creator(Box::new(Self::try_create_func))
+            .features(FunctionFeatures::default().deterministic())
+    }
+}
+
+impl Function for NullIfFunction {
+    fn name(&self) -> &str {
+        "NullIfFunction"
+    }
+
+    fn num_arguments(&self) -> usize {
+        2
+    }
+
+    fn return_type(&self, args: &[DataType]) -> Result<DataType> {
+        aggregate_types(args)
+    }
+
+    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
+        Ok(true)
+    }
+
+    // NULL if the arguments are equal, otherwise the first argument. The arguments are
+    // compared in their common type.
+    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+        let data_types = [
+            columns[0].data_type().clone(),
+            columns[1].data_type().clone(),
+        ];
+        let data_type = self.return_type(&data_types)?;
+        if data_type == DataType::Null {
+            return Ok(DataColumn::Constant(DataValue::Null, input_rows));
+        }
+
+        let mut values = columns[0]
+            .column()
+            .cast_with_type(&data_type)?
+            .to_values()?;
+        let others = columns[1]
+            .column()
+            .cast_with_type(&data_type)?
+            .to_values()?;
+        for (value, other) in values.iter_mut().zip(others) {
+            if !value.is_null() && *value == other {
+                *value = DataValue::from(&data_type);
+            }
+        }
+
+        let result = DataValue::try_into_data_array(&values, &data_type)?;
+        Ok(result.into())
+    }
+}
+
+impl fmt::Display for NullIfFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NULLIF")
+    }
+}
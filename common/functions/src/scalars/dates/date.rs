@@ -24,17 +24,21 @@ use super::ToDayOfMonthFunction;
 use super::ToDayOfWeekFunction;
 use super::ToDayOfYearFunction;
 use super::ToHourFunction;
+use super::ToISOWeekFunction;
 use super::ToMinuteFunction;
 use super::ToMonthFunction;
+use super::ToQuarterFunction;
 use super::ToSecondFunction;
 use super::ToStartOfISOYearFunction;
 use super::ToStartOfMonthFunction;
 use super::ToStartOfQuarterFunction;
 use super::ToStartOfWeekFunction;
 use super::ToStartOfYearFunction;
+use super::ToUnixTimestampFunction;
 use super::ToYYYYMMDDFunction;
 use super::ToYYYYMMDDhhmmssFunction;
 use super::ToYYYYMMFunction;
+use super::ToYearFunction;
 use super::TodayFunction;
 use super::TomorrowFunction;
 use super::YesterdayFunction;
@@ -116,13 +120,17 @@ impl DateFunction {
         factory.register("toStartOfQuarter", ToStartOfQuarterFunction::desc());
         factory.register("toStartOfWeek", ToStartOfWeekFunction::desc());
         factory.register("toStartOfMonth", ToStartOfMonthFunction::desc());
+        factory.register("toYear", ToYearFunction::desc());
+        factory.register("toQuarter", ToQuarterFunction::desc());
         factory.register("toMonth", ToMonthFunction::desc());
+        factory.register("toISOWeek", ToISOWeekFunction::desc());
         factory.register("toDayOfYear", ToDayOfYearFunction::desc());
         factory.register("toDayOfMonth", ToDayOfMonthFunction::desc());
         factory.register("toDayOfWeek", ToDayOfWeekFunction::desc());
         factory.register("toHour", ToHourFunction::desc());
         factory.register("toMinute", ToMinuteFunction::desc());
         factory.register("toSecond", ToSecondFunction::desc());
+        factory.register("toUnixTimestamp", ToUnixTimestampFunction::desc());
         factory.register("toMonday", ToMondayFunction::desc());
 
         // rounders
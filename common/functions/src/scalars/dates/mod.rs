@@ -29,17 +29,21 @@ pub use number_function::ToDayOfMonthFunction;
 pub use number_function::ToDayOfWeekFunction;
 pub use number_function::ToDayOfYearFunction;
 pub use number_function::ToHourFunction;
+pub use number_function::ToISOWeekFunction;
 pub use number_function::ToMinuteFunction;
 pub use number_function::ToMondayFunction;
 pub use number_function::ToMonthFunction;
+pub use number_function::ToQuarterFunction;
 pub use number_function::ToSecondFunction;
 pub use number_function::ToStartOfISOYearFunction;
 pub use number_function::ToStartOfMonthFunction;
 pub use number_function::ToStartOfQuarterFunction;
 pub use number_function::ToStartOfYearFunction;
+pub use number_function::ToUnixTimestampFunction;
 pub use number_function::ToYYYYMMDDFunction;
 pub use number_function::ToYYYYMMDDhhmmssFunction;
 pub use number_function::ToYYYYMMFunction;
+pub use number_function::ToYearFunction;
 pub use round_function::RoundFunction;
 pub use simple_date::TodayFunction;
 pub use simple_date::TomorrowFunction;
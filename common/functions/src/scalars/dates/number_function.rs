@@ -193,6 +193,44 @@ impl NumberResultFunction<u16> for ToStartOfMonth {
     }
 }
 
+#[derive(Clone)]
+pub struct ToYear;
+
+impl NumberResultFunction<u16> for ToYear {
+    const IS_DETERMINISTIC: bool = true;
+    const MAYBE_MONOTONIC: bool = true;
+
+    fn return_type() -> Result<DataType> {
+        Ok(DataType::UInt16)
+    }
+    fn to_number(value: DateTime<Utc>) -> u16 {
+        value.year() as u16
+    }
+
+    fn to_constant_value(value: DateTime<Utc>) -> DataValue {
+        DataValue::UInt16(Some(Self::to_number(value)))
+    }
+}
+
+#[derive(Clone)]
+pub struct ToQuarter;
+
+impl NumberResultFunction<u8> for ToQuarter {
+    const IS_DETERMINISTIC: bool = true;
+    const MAYBE_MONOTONIC: bool = false;
+
+    fn return_type() -> Result<DataType> {
+        Ok(DataType::UInt8)
+    }
+    fn to_number(value: DateTime<Utc>) -> u8 {
+        (value.month0() / 3 + 1) as u8
+    }
+
+    fn to_constant_value(value: DateTime<Utc>) -> DataValue {
+        DataValue::UInt8(Some(Self::to_number(value)))
+    }
+}
+
 #[derive(Clone)]
 pub struct ToMonth;
 
@@ -212,6 +250,25 @@ impl NumberResultFunction<u8> for ToMonth {
     }
 }
 
+#[derive(Clone)]
+pub struct ToISOWeek;
+
+impl NumberResultFunction<u8> for ToISOWeek {
+    const IS_DETERMINISTIC: bool = true;
+    const MAYBE_MONOTONIC: bool = false;
+
+    fn return_type() -> Result<DataType> {
+        Ok(DataType::UInt8)
+    }
+    fn to_number(value: DateTime<Utc>) -> u8 {
+        value.iso_week().week() as u8
+    }
+
+    fn to_constant_value(value: DateTime<Utc>) -> DataValue {
+        DataValue::UInt8(Some(Self::to_number(value)))
+    }
+}
+
 #[derive(Clone)]
 pub struct ToDayOfYear;
 
@@ -326,6 +383,25 @@ impl NumberResultFunction<u8> for ToSecond {
     }
 }
 
+#[derive(Clone)]
+pub struct ToUnixTimestamp;
+
+impl NumberResultFunction<u32> for ToUnixTimestamp {
+    const IS_DETERMINISTIC: bool = true;
+    const MAYBE_MONOTONIC: bool = true;
+
+    fn return_type() -> Result<DataType> {
+        Ok(DataType::UInt32)
+    }
+    fn to_number(value: DateTime<Utc>) -> u32 {
+        value.timestamp() as u32
+    }
+
+    fn to_constant_value(value: DateTime<Utc>) -> DataValue {
+        DataValue::UInt32(Some(Self::to_number(value)))
+    }
+}
+
 #[derive(Clone)]
 pub struct ToMonday;
 
@@ -481,7 +557,10 @@ pub type ToStartOfYearFunction = NumberFunction<ToStartOfYear, u16>;
 pub type ToStartOfQuarterFunction = NumberFunction<ToStartOfQuarter, u16>;
 pub type ToStartOfMonthFunction = NumberFunction<ToStartOfMonth, u16>;
 
+pub type ToYearFunction = NumberFunction<ToYear, u16>;
+pub type ToQuarterFunction = NumberFunction<ToQuarter, u8>;
 pub type ToMonthFunction = NumberFunction<ToMonth, u8>;
+pub type ToISOWeekFunction = NumberFunction<ToISOWeek, u8>;
 pub type ToDayOfYearFunction = NumberFunction<ToDayOfYear, u16>;
 pub type ToDayOfMonthFunction = NumberFunction<ToDayOfMonth, u8>;
 pub type ToDayOfWeekFunction = NumberFunction<ToDayOfWeek, u8>;
@@ -489,5 +568,6 @@ pub type ToDayOfWeekFunction = NumberFunction<ToDayOfWeek, u8>;
 pub type ToHourFunction = NumberFunction<ToHour, u8>;
 pub type ToMinuteFunction = NumberFunction<ToMinute, u8>;
 pub type ToSecondFunction = NumberFunction<ToSecond, u8>;
+pub type ToUnixTimestampFunction = NumberFunction<ToUnixTimestamp, u32>;
 
 pub type ToMondayFunction = NumberFunction<ToMonday, u16>;
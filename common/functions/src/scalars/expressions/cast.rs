@@ -22,8 +22,10 @@ use common_datavalues::chrono::NaiveDateTime;
 use common_datavalues::chrono::TimeZone;
 use common_datavalues::chrono::Utc;
 use common_datavalues::columns::DataColumn;
+use common_datavalues::is_numeric;
 use common_datavalues::prelude::ArrayApply;
 use common_datavalues::prelude::DFInt32Array;
+use common_datavalues::prelude::DFPrimitiveArray;
 use common_datavalues::prelude::DFStringArray;
 use common_datavalues::prelude::DFUInt16Array;
 use common_datavalues::prelude::DFUInt32Array;
@@ -42,6 +44,8 @@ pub struct CastFunction {
     _display_name: String,
     /// The data type to cast to
     cast_type: DataType,
+    /// TRY_CAST returns NULL instead of an error if the cast fails
+    try_cast: bool,
 }
 
 impl CastFunction {
@@ -49,25 +53,19 @@ impl CastFunction {
         Ok(Box::new(Self {
             _display_name: display_name,
             cast_type,
+            try_cast: false,
         }))
     }
-}
-
-impl Function for CastFunction {
-    fn name(&self) -> &str {
-        "CastFunction"
-    }
 
-    fn return_type(&self, _args: &[DataType]) -> Result<DataType> {
-        Ok(self.cast_type.clone())
-    }
-
-    // TODO
-    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
-        Ok(false)
+    pub fn create_try(display_name: String, cast_type: DataType) -> Result<Box<dyn Function>> {
+        Ok(Box::new(Self {
+            _display_name: display_name,
+            cast_type,
+            try_cast: true,
+        }))
     }
 
-    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+    fn cast(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
         if columns[0].data_type() == &self.cast_type {
             return Ok(columns[0].column().clone());
         }
@@ -119,6 +117,18 @@ impl Function for CastFunction {
                }
             }),
 
+            // A string is parsed as a whole, the value fails to convert unless it is a number.
+            (DataType::String, _) if is_numeric(&self.cast_type) => {
+                with_match_primitive_type!(&self.cast_type, |$T| {
+                    let it = series.string()?.into_iter().map(|v| {
+                        v.and_then(|v| std::str::from_utf8(v).ok()?.trim().parse::<$T>().ok())
+                    });
+                    Ok(DFPrimitiveArray::<$T>::from_iter(it).into_series())
+                }, {
+                    Err(error)
+                })
+            }
+
             // others to Date/DateTime
             (_, DataType::Date16) => with_match_primitive_type!(columns[0].data_type(), |$T| {
                 series.cast_with_type(&self.cast_type)
@@ -167,9 +177,43 @@ impl Function for CastFunction {
             _ => series.cast_with_type(&self.cast_type),
         }?;
 
+        // The values failing to convert are NULL, only TRY_CAST keeps them.
+        if !self.try_cast && array.null_count() > series.null_count() {
+            if let Some(row) =
+                (0..series.len()).find(|row| !series.is_null(*row) && array.is_null(*row))
+            {
+                return Err(ErrorCode::BadDataValueType(format!(
+                    "Cannot cast value {} into data_type: {:?}",
+                    series.try_get(row)?,
+                    self.cast_type
+                )));
+            }
+        }
+
         let column: DataColumn = array.into();
         Ok(column.resize_constant(input_rows))
     }
+}
+
+impl Function for CastFunction {
+    fn name(&self) -> &str {
+        "CastFunction"
+    }
+
+    fn return_type(&self, _args: &[DataType]) -> Result<DataType> {
+        Ok(self.cast_type.clone())
+    }
+
+    // TODO: CAST of a nullable argument is nullable too, the argument isn't known here.
+    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
+        Ok(self.try_cast)
+    }
+
+    // The unsupported type pairs are errors even for TRY_CAST, only the values failing to
+    // convert are NULL.
+    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+        self.cast(columns, input_rows)
+    }
 
     fn num_arguments(&self) -> usize {
         1
@@ -178,7 +222,10 @@ impl Function for CastFunction {
 
 impl fmt::Display for CastFunction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "CAST")
+        match self.try_cast {
+            true => write!(f, "TRY_CAST"),
+            false => write!(f, "CAST"),
+        }
     }
 }
 
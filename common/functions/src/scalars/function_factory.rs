@@ -43,6 +43,8 @@ pub struct FunctionFeatures {
     pub is_bool_func: bool,
     pub is_context_func: bool,
     pub maybe_monotonic: bool,
+    // The parameter names that the named arguments bind to, None if not declared.
+    pub parameter_names: Option<Vec<String>>,
 }
 
 impl FunctionFeatures {
@@ -53,6 +55,7 @@ impl FunctionFeatures {
             is_bool_func: false,
             is_context_func: false,
             maybe_monotonic: false,
+            parameter_names: None,
         }
     }
 
@@ -80,6 +83,11 @@ impl FunctionFeatures {
         self.maybe_monotonic = true;
         self
     }
+
+    pub fn parameter_names(mut self, names: &[&str]) -> FunctionFeatures {
+        self.parameter_names = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
 }
 
 pub struct FunctionDescription {
@@ -105,6 +113,8 @@ impl FunctionDescription {
 
 pub struct FunctionFactory {
     case_insensitive_desc: HashMap<String, FunctionDescription>,
+    // The deprecated or alternative names of the functions, to their canonical names.
+    case_insensitive_aliases: HashMap<String, String>,
 }
 
 lazy_static! {
@@ -132,6 +142,7 @@ impl FunctionFactory {
     pub(in crate::scalars::function_factory) fn create() -> FunctionFactory {
         FunctionFactory {
             case_insensitive_desc: Default::default(),
+            case_insensitive_aliases: Default::default(),
         }
     }
 
@@ -144,10 +155,45 @@ impl FunctionFactory {
         case_insensitive_desc.insert(name.to_lowercase(), desc);
     }
 
+    /// Register `alias` as another name of the function `canonical_name`, such as `substr`
+    /// of `substring`. The alias is resolved to the canonical name when analyzing the query.
+    /// The canonical function must be registered before its aliases.
+    pub fn register_alias(&mut self, alias: &str, canonical_name: &str) {
+        debug_assert!(
+            self.case_insensitive_desc
+                .contains_key(&canonical_name.to_lowercase()),
+            "Function alias {} refers to the unregistered function {}",
+            alias,
+            canonical_name
+        );
+
+        let case_insensitive_aliases = &mut self.case_insensitive_aliases;
+        case_insensitive_aliases.insert(alias.to_lowercase(), canonical_name.to_string());
+    }
+
+    /// The canonical name of the function if the name is a registered alias.
+    pub fn get_canonical_name(&self, name: impl AsRef<str>) -> Option<&str> {
+        let lowercase_name = name.as_ref().to_lowercase();
+        self.case_insensitive_aliases
+            .get(&lowercase_name)
+            .map(String::as_str)
+    }
+
+    fn get_desc(&self, lowercase_name: &str) -> Option<&FunctionDescription> {
+        match self.case_insensitive_desc.get(lowercase_name) {
+            Some(desc) => Some(desc),
+            None => {
+                let canonical_name = self.case_insensitive_aliases.get(lowercase_name)?;
+                self.case_insensitive_desc
+                    .get(&canonical_name.to_lowercase())
+            }
+        }
+    }
+
     pub fn get(&self, name: impl AsRef<str>) -> Result<Box<dyn Function>> {
         let origin_name = name.as_ref();
         let lowercase_name = origin_name.to_lowercase();
-        match self.case_insensitive_desc.get(&lowercase_name) {
+        match self.get_desc(&lowercase_name) {
             // TODO(Winter): we should write similar function names into error message if function name is not found.
             None => Err(ErrorCode::UnknownFunction(format!(
                 "Unsupported Function: {}",
@@ -160,7 +206,7 @@ impl FunctionFactory {
     pub fn get_features(&self, name: impl AsRef<str>) -> Result<FunctionFeatures> {
         let origin_name = name.as_ref();
         let lowercase_name = origin_name.to_lowercase();
-        match self.case_insensitive_desc.get(&lowercase_name) {
+        match self.get_desc(&lowercase_name) {
             // TODO(Winter): we should write similar function names into error message if function name is not found.
             None => Err(ErrorCode::UnknownFunction(format!(
                 "Unsupported Function: {}",
@@ -173,7 +219,7 @@ impl FunctionFactory {
     pub fn check(&self, name: impl AsRef<str>) -> bool {
         let origin_name = name.as_ref();
         let lowercase_name = origin_name.to_lowercase();
-        self.case_insensitive_desc.contains_key(&lowercase_name)
+        self.get_desc(&lowercase_name).is_some()
     }
 
     pub fn registered_names(&self) -> Vec<String> {
@@ -0,0 +1,100 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use common_datavalues::prelude::*;
+use common_exception::Result;
+
+use crate::scalars::function_factory::FunctionDescription;
+use crate::scalars::function_factory::FunctionFeatures;
+use crate::scalars::Function;
+
+#[derive(Clone)]
+pub struct ConcatFunction {
+    _display_name: String,
+}
+
+impl ConcatFunction {
+    pub fn try_create(display_name: &str) -> Result<Box<dyn Function>> {
+        Ok(Box::new(ConcatFunction {
+            _display_name: display_name.to_string(),
+        }))
+    }
+
+    pub fn desc() -> FunctionDescription {
+        FunctionDescription::creator(Box::new(Self::try_create))
+            .features(FunctionFeatures::default().deterministic())
+    }
+}
+
+impl Function for ConcatFunction {
+    fn name(&self) -> &str {
+        "concat"
+    }
+
+    fn return_type(&self, _args: &[DataType]) -> Result<DataType> {
+        Ok(DataType::String)
+    }
+
+    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
+        Ok(true)
+    }
+
+    // The row is NULL if any of the arguments is NULL, as `a || b` is.
+    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+        let mut arrays = Vec::with_capacity(columns.len());
+        for column in columns {
+            arrays.push(
+                column
+                    .column()
+                    .to_array()?
+                    .cast_with_type(&DataType::String)?,
+            );
+        }
+
+        let mut iters = Vec::with_capacity(arrays.len());
+        for array in &arrays {
+            iters.push(array.string()?.into_iter());
+        }
+
+        let opt_iter = (0..input_rows).map(|_| {
+            let mut value: Option<Vec<u8>> = Some(vec![]);
+            for iter in iters.iter_mut() {
+                value = match (value, iter.next().flatten()) {
+                    (Some(mut value), Some(v)) => {
+                        value.extend_from_slice(v);
+                        Some(value)
+                    }
+                    _ => None,
+                };
+            }
+            value
+        });
+
+        let result = DFStringArray::new_from_opt_iter(opt_iter);
+        Ok(result.into())
+    }
+
+    // concat(str, ...)
+    fn variadic_arguments(&self) -> Option<(usize, usize)> {
+        Some((1, usize::MAX))
+    }
+}
+
+impl fmt::Display for ConcatFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CONCAT")
+    }
+}
@@ -12,8 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod concat;
 mod string;
 mod substring;
+mod trim;
 
+pub use concat::ConcatFunction;
 pub use string::StringFunction;
 pub use substring::SubstringFunction;
+pub use trim::TrimFunction;
+pub use trim::TrimSide;
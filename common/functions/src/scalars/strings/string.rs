@@ -13,13 +13,22 @@
 // limitations under the License.
 
 use crate::scalars::function_factory::FunctionFactory;
+use crate::scalars::ConcatFunction;
 use crate::scalars::SubstringFunction;
+use crate::scalars::TrimFunction;
+use crate::scalars::TrimSide;
 
 #[derive(Clone)]
 pub struct StringFunction;
 
 impl StringFunction {
     pub fn register(factory: &mut FunctionFactory) {
-        factory.register("substring", SubstringFunction::desc())
+        factory.register("substring", SubstringFunction::desc());
+        factory.register_alias("substr", "substring");
+        factory.register("concat", ConcatFunction::desc());
+        factory.register("trim", TrimFunction::desc(TrimSide::Both));
+        factory.register("trim_both", TrimFunction::desc(TrimSide::Both));
+        factory.register("trim_leading", TrimFunction::desc(TrimSide::Leading));
+        factory.register("trim_trailing", TrimFunction::desc(TrimSide::Trailing));
     }
 }
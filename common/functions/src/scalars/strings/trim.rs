@@ -0,0 +1,121 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use common_datavalues::prelude::*;
+use common_exception::Result;
+
+use crate::scalars::function_factory::FactoryCreator;
+use crate::scalars::function_factory::FunctionDescription;
+use crate::scalars::function_factory::FunctionFeatures;
+use crate::scalars::Function;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrimSide {
+    Both,
+    Leading,
+    Trailing,
+}
+
+#[derive(Clone)]
+pub struct TrimFunction {
+    display_name: String,
+    side: TrimSide,
+}
+
+impl TrimFunction {
+    pub fn try_create(display_name: &str, side: TrimSide) -> Result<Box<dyn Function>> {
+        Ok(Box::new(TrimFunction {
+            display_name: display_name.to_string(),
+            side,
+        }))
+    }
+
+    pub fn desc(side: TrimSide) -> FunctionDescription {
+        let creator: FactoryCreator =
+            Box::new(move |display_name| TrimFunction::try_create(display_name, side));
+
+        FunctionDescription::creator(creator).features(FunctionFeatures::default().deterministic())
+    }
+
+    // The characters are a set, as in `TRIM(BOTH 'xy' FROM 'xyaxy')` = 'a'.
+    fn trim(&self, source: &[u8], chars: &[u8]) -> Vec<u8> {
+        let source = String::from_utf8_lossy(source);
+        let chars: Vec<char> = String::from_utf8_lossy(chars).chars().collect();
+        let is_trimmed = |c: char| chars.contains(&c);
+
+        let trimmed = match self.side {
+            TrimSide::Both => source.trim_matches(is_trimmed),
+            TrimSide::Leading => source.trim_start_matches(is_trimmed),
+            TrimSide::Trailing => source.trim_end_matches(is_trimmed),
+        };
+        trimmed.as_bytes().to_vec()
+    }
+}
+
+impl Function for TrimFunction {
+    fn name(&self) -> &str {
+        self.display_name.as_str()
+    }
+
+    fn return_type(&self, _args: &[DataType]) -> Result<DataType> {
+        Ok(DataType::String)
+    }
+
+    fn nullable(&self, _input_schema: &DataSchema) -> Result<bool> {
+        Ok(true)
+    }
+
+    // The row is NULL if the source or the characters are NULL.
+    fn eval(&self, columns: &DataColumnsWithField, input_rows: usize) -> Result<DataColumn> {
+        let (chars, source) = match columns.len() {
+            1 => {
+                let spaces = DataValue::String(Some(b" ".to_vec()));
+                (DataColumn::Constant(spaces, input_rows), &columns[0])
+            }
+            _ => (columns[0].column().clone(), &columns[1]),
+        };
+
+        let chars = chars.to_array()?.cast_with_type(&DataType::String)?;
+        let source = source
+            .column()
+            .to_array()?
+            .cast_with_type(&DataType::String)?;
+
+        let opt_iter = chars
+            .string()?
+            .into_iter()
+            .zip(source.string()?.into_iter())
+            .map(|(chars, source)| match (chars, source) {
+                (Some(chars), Some(source)) => Some(self.trim(source, chars)),
+                _ => None,
+            });
+
+        let result = DFStringArray::new_from_opt_iter(opt_iter);
+        Ok(result.into())
+    }
+
+    // trim_both(str)
+    // trim_both(chars, str)
+    fn variadic_arguments(&self) -> Option<(usize, usize)> {
+        Some((1, 2))
+    }
+}
+
+impl fmt::Display for TrimFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.display_name.to_uppercase())
+    }
+}
@@ -42,7 +42,7 @@ impl SleepFunction {
 
     pub fn desc() -> FunctionDescription {
         FunctionDescription::creator(Box::new(Self::try_create))
-            .features(FunctionFeatures::default())
+            .features(FunctionFeatures::default().parameter_names(&["seconds"]))
     }
 }
 
@@ -87,3 +87,76 @@ fn test_if_function() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_coalesce_function() -> Result<()> {
+    struct Test {
+        name: &'static str,
+        columns: Vec<DataColumn>,
+        expect: DataColumn,
+    }
+
+    let tests = vec![
+        Test {
+            name: "coalesce-first-non-null-passed",
+            columns: vec![
+                Series::new(vec![Some(1i32), None, None]).into(),
+                Series::new(vec![Some(2i32), Some(3), None]).into(),
+                DataColumn::Constant(DataValue::Int32(Some(4)), 3),
+            ],
+            expect: Series::new(vec![1i32, 3, 4]).into(),
+        },
+        Test {
+            name: "coalesce-common-type-passed",
+            columns: vec![
+                Series::new(vec![Some(1i32), None]).into(),
+                DataColumn::Constant(DataValue::Float64(Some(2.5)), 2),
+            ],
+            expect: Series::new(vec![1f64, 2.5]).into(),
+        },
+        Test {
+            name: "coalesce-null-literal-passed",
+            columns: vec![
+                DataColumn::Constant(DataValue::Null, 2),
+                Series::new(vec![Some("a"), None]).into(),
+            ],
+            expect: Series::new(vec![Some("a"), None]).into(),
+        },
+    ];
+
+    let func = CoalesceFunction::try_create_func("coalesce")?;
+    for t in tests {
+        let rows = t.columns[0].len();
+        let columns: Vec<DataColumnWithField> = t
+            .columns
+            .iter()
+            .map(|c| DataColumnWithField::new(c.clone(), DataField::new("a", c.data_type(), true)))
+            .collect();
+
+        assert_eq!("COALESCE", format!("{}", func));
+        assert_eq!(func.eval(&columns, rows)?, t.expect, "{}", t.name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_nullif_function() -> Result<()> {
+    let columns = vec![
+        DataColumnWithField::new(
+            Series::new(vec![Some(1i32), Some(2), None]).into(),
+            DataField::new("a", DataType::Int32, true),
+        ),
+        DataColumnWithField::new(
+            DataColumn::Constant(DataValue::Int64(Some(2)), 3),
+            DataField::new("b", DataType::Int64, false),
+        ),
+    ];
+
+    let func = NullIfFunction::try_create_func("nullif")?;
+    let expect: DataColumn = Series::new(vec![Some(1i64), None, None]).into();
+    assert_eq!("NULLIF", format!("{}", func));
+    assert_eq!(func.eval(&columns, 3)?, expect);
+
+    Ok(())
+}
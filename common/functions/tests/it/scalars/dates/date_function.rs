@@ -764,6 +764,118 @@ fn test_tosecond_function() -> Result<()> {
     do_test(tests)
 }
 
+#[test]
+fn test_toyear_function() -> Result<()> {
+    let tests = vec![
+        Test {
+            name: "test_toyear_date16",
+            display: "c()",
+            arg_names: vec!["c"],
+            func: ToYearFunction::try_create("c")?,
+            columns: vec![Series::new(vec![18902u16]).into()],
+            nullable: false,
+            expect: Series::new(vec![2021u16]).into(),
+            error: "",
+        },
+        Test {
+            name: "test_toyear_datetime",
+            display: "a()",
+            arg_names: vec!["a"],
+            func: ToYearFunction::try_create("a")?,
+            columns: vec![Series::new(vec![1633173324u32]).into()],
+            nullable: false,
+            expect: Series::new(vec![2021u16]).into(),
+            error: "",
+        },
+    ];
+
+    do_test(tests)
+}
+
+#[test]
+fn test_toquarter_function() -> Result<()> {
+    let tests = vec![
+        Test {
+            name: "test_toquarter_date16",
+            display: "c()",
+            arg_names: vec!["c"],
+            func: ToQuarterFunction::try_create("c")?,
+            columns: vec![Series::new(vec![18902u16]).into()],
+            nullable: false,
+            expect: Series::new(vec![4u8]).into(),
+            error: "",
+        },
+        Test {
+            name: "test_toquarter_datetime",
+            display: "a()",
+            arg_names: vec!["a"],
+            func: ToQuarterFunction::try_create("a")?,
+            columns: vec![Series::new(vec![1633173324u32]).into()],
+            nullable: false,
+            expect: Series::new(vec![4u8]).into(),
+            error: "",
+        },
+    ];
+
+    do_test(tests)
+}
+
+#[test]
+fn test_toisoweek_function() -> Result<()> {
+    let tests = vec![
+        Test {
+            name: "test_toisoweek_date16",
+            display: "c()",
+            arg_names: vec!["c"],
+            func: ToISOWeekFunction::try_create("c")?,
+            columns: vec![Series::new(vec![18902u16]).into()],
+            nullable: false,
+            expect: Series::new(vec![39u8]).into(),
+            error: "",
+        },
+        Test {
+            name: "test_toisoweek_datetime",
+            display: "a()",
+            arg_names: vec!["a"],
+            func: ToISOWeekFunction::try_create("a")?,
+            columns: vec![Series::new(vec![1633173324u32]).into()],
+            nullable: false,
+            expect: Series::new(vec![39u8]).into(),
+            error: "",
+        },
+    ];
+
+    do_test(tests)
+}
+
+#[test]
+fn test_tounixtimestamp_function() -> Result<()> {
+    let tests = vec![
+        Test {
+            name: "test_tounixtimestamp_date16",
+            display: "c()",
+            arg_names: vec!["c"],
+            func: ToUnixTimestampFunction::try_create("c")?,
+            columns: vec![Series::new(vec![18902u16]).into()],
+            nullable: false,
+            expect: Series::new(vec![1633132800u32]).into(),
+            error: "",
+        },
+        Test {
+            name: "test_tounixtimestamp_datetime",
+            display: "a()",
+            arg_names: vec!["a"],
+            func: ToUnixTimestampFunction::try_create("a")?,
+            columns: vec![Series::new(vec![1633173324u32]).into()],
+            nullable: false,
+            expect: Series::new(vec![1633173324u32]).into(),
+            error: "",
+        },
+    ];
+
+    do_test(tests)
+}
+
 #[test]
 fn test_tomonday_function() -> Result<()> {
     let tests = vec![
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use common_datavalues::prelude::*;
+use common_exception::ErrorCode;
 use common_exception::Result;
 use common_functions::scalars::*;
 use pretty_assertions::assert_eq;
@@ -167,3 +168,87 @@ fn test_cast_function() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_try_cast_function() -> Result<()> {
+    struct Test {
+        name: &'static str,
+        column: DataColumn,
+        column_type: DataType,
+        cast_type: DataType,
+        expect: Result<DataColumn>,
+        cast_failed: bool,
+    }
+
+    let tests = vec![
+        Test {
+            name: "try-cast-int64-to-int8-passed",
+            column: Series::new(vec![4i64, 3, 2, 4]).into(),
+            column_type: DataType::Int64,
+            cast_type: DataType::Int8,
+            expect: Ok(Series::new(vec![4i8, 3, 2, 4]).into()),
+            cast_failed: false,
+        },
+        Test {
+            name: "try-cast-string-to-int32-partial-null",
+            column: Series::new(vec!["1", "x", "3"]).into(),
+            column_type: DataType::String,
+            cast_type: DataType::Int32,
+            expect: Ok(Series::new(vec![Some(1i32), None, Some(3)]).into()),
+            cast_failed: true,
+        },
+        Test {
+            name: "try-cast-nullable-string-to-int32-passed",
+            column: Series::new(vec![Some("1"), None, Some("3")]).into(),
+            column_type: DataType::String,
+            cast_type: DataType::Int32,
+            expect: Ok(Series::new(vec![Some(1i32), None, Some(3)]).into()),
+            cast_failed: false,
+        },
+        Test {
+            name: "try-cast-date16-to-boolean-failed",
+            column: Series::new(vec![18691u16, 18924]).into(),
+            column_type: DataType::Date16,
+            cast_type: DataType::Boolean,
+            expect: Err(ErrorCode::BadDataValueType(
+                "Unsupported cast_with_type from Date16 into Boolean",
+            )),
+            cast_failed: true,
+        },
+    ];
+
+    for t in tests {
+        let rows = t.column.len();
+        let dummy = DataField::new("dummy", t.column_type.clone(), false);
+        let columns = vec![DataColumnWithField::new(t.column.clone(), dummy)];
+
+        let func = CastFunction::create_try("try_cast".to_string(), t.cast_type.clone())?;
+        assert_eq!("TRY_CAST", format!("{}", func), "{}", t.name);
+        assert!(func.nullable(&DataSchema::empty())?, "{}", t.name);
+        match t.expect {
+            Ok(expected) => {
+                assert_eq!(func.eval(&columns, rows)?, expected, "{}", t.name);
+            }
+            Err(expected_err) => {
+                let got = func.eval(&columns, rows).unwrap_err();
+                assert_eq!(got.code(), expected_err.code(), "{}", t.name);
+            }
+        }
+
+        // CAST fails on the values TRY_CAST turns into NULL.
+        let func = CastFunction::create("cast".to_string(), t.cast_type.clone())?;
+        match func.eval(&columns, rows) {
+            Ok(_) => assert!(!t.cast_failed, "{}", t.name),
+            Err(cause) => {
+                assert!(t.cast_failed, "{}", t.name);
+                assert_eq!(
+                    cause.code(),
+                    ErrorCode::BadDataValueTypeCode(),
+                    "{}",
+                    t.name
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -16,8 +16,11 @@ use common_datavalues::prelude::*;
 use common_exception::Result;
 use pretty_assertions::assert_eq;
 
+use common_functions::scalars::ConcatFunction;
 use common_functions::scalars::Function;
 use common_functions::scalars::SubstringFunction;
+use common_functions::scalars::TrimFunction;
+use common_functions::scalars::TrimSide;
 
 #[test]
 fn test_substring_function() -> Result<()> {
@@ -142,3 +145,131 @@ fn test_substring_function() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_concat_function() -> Result<()> {
+    struct Test {
+        name: &'static str,
+        columns: Vec<DataColumn>,
+        expect: DataColumn,
+    }
+
+    let tests = vec![
+        Test {
+            name: "concat-strings-passed",
+            columns: vec![
+                Series::new(vec!["ab", "c"]).into(),
+                Series::new(vec!["cd", ""]).into(),
+            ],
+            expect: Series::new(vec!["abcd", "c"]).into(),
+        },
+        Test {
+            name: "concat-constant-passed",
+            columns: vec![
+                Series::new(vec!["a", "b"]).into(),
+                DataColumn::Constant(DataValue::String(Some(b"-".to_vec())), 2),
+                Series::new(vec![1_u8, 2]).into(),
+            ],
+            expect: Series::new(vec!["a-1", "b-2"]).into(),
+        },
+        Test {
+            name: "concat-null-passed",
+            columns: vec![
+                Series::new(vec![Some("a"), None]).into(),
+                Series::new(vec!["b", "c"]).into(),
+            ],
+            expect: Series::new(vec![Some("ab"), None]).into(),
+        },
+    ];
+
+    let func = ConcatFunction::try_create("concat")?;
+    for t in tests {
+        let rows = t.columns[0].len();
+        let columns: Vec<DataColumnWithField> = t
+            .columns
+            .iter()
+            .map(|c| {
+                let field = DataField::new("arg", c.data_type(), true);
+                DataColumnWithField::new(c.clone(), field)
+            })
+            .collect();
+
+        assert_eq!("CONCAT", format!("{}", func));
+        assert_eq!(DataType::String, func.return_type(&[])?);
+        assert!(func.nullable(&DataSchema::empty())?);
+        assert_eq!(func.eval(&columns, rows)?, t.expect, "{}", t.name);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_trim_function() -> Result<()> {
+    struct Test {
+        name: &'static str,
+        side: TrimSide,
+        columns: Vec<DataColumn>,
+        expect: DataColumn,
+    }
+
+    let tests = vec![
+        Test {
+            name: "trim-spaces-passed",
+            side: TrimSide::Both,
+            columns: vec![Series::new(vec!["  ab c  ", "d"]).into()],
+            expect: Series::new(vec!["ab c", "d"]).into(),
+        },
+        Test {
+            name: "trim-both-chars-passed",
+            side: TrimSide::Both,
+            columns: vec![
+                DataColumn::Constant(DataValue::String(Some(b"xy".to_vec())), 2),
+                Series::new(vec!["xyaxy", "yxb"]).into(),
+            ],
+            expect: Series::new(vec!["a", "b"]).into(),
+        },
+        Test {
+            name: "trim-leading-passed",
+            side: TrimSide::Leading,
+            columns: vec![
+                Series::new(vec!["x", "-"]).into(),
+                Series::new(vec!["xxax", "--b-"]).into(),
+            ],
+            expect: Series::new(vec!["ax", "b-"]).into(),
+        },
+        Test {
+            name: "trim-trailing-passed",
+            side: TrimSide::Trailing,
+            columns: vec![
+                DataColumn::Constant(DataValue::String(Some(b"x".to_vec())), 1),
+                Series::new(vec!["xaxx"]).into(),
+            ],
+            expect: Series::new(vec!["xa"]).into(),
+        },
+        Test {
+            name: "trim-null-passed",
+            side: TrimSide::Both,
+            columns: vec![
+                Series::new(vec![Some("x"), None]).into(),
+                Series::new(vec![None, Some("xa")]).into(),
+            ],
+            expect: Series::new(vec![None::<&str>, None]).into(),
+        },
+    ];
+
+    for t in tests {
+        let func = TrimFunction::try_create("trim_both", t.side)?;
+        let rows = t.columns[0].len();
+        let columns: Vec<DataColumnWithField> = t
+            .columns
+            .iter()
+            .map(|c| {
+                let field = DataField::new("arg", c.data_type(), true);
+                DataColumnWithField::new(c.clone(), field)
+            })
+            .collect();
+
+        assert_eq!(DataType::String, func.return_type(&[])?);
+        assert_eq!(func.eval(&columns, rows)?, t.expect, "{}", t.name);
+    }
+    Ok(())
+}
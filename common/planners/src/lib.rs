@@ -33,8 +33,10 @@ mod plan_expression_function;
 mod plan_expression_literal;
 mod plan_expression_rewriter;
 mod plan_expression_sort;
+mod plan_expression_sql;
 mod plan_expression_validator;
 mod plan_expression_visitor;
+mod plan_expression_window;
 mod plan_extras;
 mod plan_filter;
 mod plan_grant_privilege;
@@ -77,6 +79,7 @@ pub use plan_describe_table::DescribeTablePlan;
 pub use plan_empty::EmptyPlan;
 pub use plan_explain::ExplainPlan;
 pub use plan_explain::ExplainType;
+pub use plan_expression::CastOnError;
 pub use plan_expression::Expression;
 pub use plan_expression::ExpressionPlan;
 pub use plan_expression::Expressions;
@@ -89,7 +92,12 @@ pub use plan_expression_common::expr_as_column_expr;
 pub use plan_expression_common::extract_aliases;
 pub use plan_expression_common::find_aggregate_exprs;
 pub use plan_expression_common::find_aggregate_exprs_in_expr;
+pub use plan_expression_common::find_column_exprs;
+pub use plan_expression_common::find_columns_not_in_group_by;
 pub use plan_expression_common::find_columns_not_satisfy_exprs;
+pub use plan_expression_common::find_grouping_exprs_in_expr;
+pub use plan_expression_common::find_window_exprs;
+pub use plan_expression_common::find_window_exprs_in_expr;
 pub use plan_expression_common::rebase_expr;
 pub use plan_expression_common::rebase_expr_from_input;
 pub use plan_expression_common::resolve_aliases_to_exprs;
@@ -107,6 +115,10 @@ pub use plan_expression_sort::sort;
 pub use plan_expression_validator::validate_expression;
 pub use plan_expression_visitor::ExpressionVisitor;
 pub use plan_expression_visitor::Recursion;
+pub use plan_expression_window::NullTreatment;
+pub use plan_expression_window::WindowFrame;
+pub use plan_expression_window::WindowFrameBound;
+pub use plan_expression_window::WindowFrameUnits;
 pub use plan_extras::Extras;
 pub use plan_filter::FilterPlan;
 pub use plan_grant_privilege::GrantPrivilegePlan;
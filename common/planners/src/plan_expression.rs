@@ -16,10 +16,12 @@ use std::collections::HashSet;
 use std::fmt;
 use std::sync::Arc;
 
+use common_datavalues::merge_types;
 use common_datavalues::DataField;
 use common_datavalues::DataSchemaRef;
 use common_datavalues::DataType;
 use common_datavalues::DataValue;
+use common_datavalues::IntervalUnit;
 use common_exception::ErrorCode;
 use common_exception::Result;
 use common_functions::aggregates::AggregateFunctionFactory;
@@ -27,7 +29,9 @@ use common_functions::aggregates::AggregateFunctionRef;
 use common_functions::scalars::FunctionFactory;
 use lazy_static::lazy_static;
 
+use crate::NullTreatment;
 use crate::PlanNode;
+use crate::WindowFrame;
 
 lazy_static! {
     static ref OP_SET: HashSet<&'static str> = ["database", "version", "current_user"]
@@ -63,6 +67,9 @@ pub enum Expression {
     Column(String),
     /// Qualified column name.
     QualifiedColumn(Vec<String>),
+    /// Column of an enclosing query referenced by a correlated subquery, `depth` is the
+    /// number of the query levels out it's resolved in(1 is the immediately enclosing query).
+    OuterColumn { name: String, depth: usize },
 
     /// Constant value.
     /// Note: When literal represents a column, its column_name will not be None
@@ -94,6 +101,23 @@ pub enum Expression {
         distinct: bool,
         params: Vec<DataValue>,
         args: Vec<Expression>,
+        /// The `WITHIN GROUP (ORDER BY ...)` sort keys of an ordered-set aggregate,
+        /// such as `PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY latency)`.
+        within_group: Vec<Expression>,
+    },
+
+    /// WindowFunction with a set of arguments and its window specification,
+    /// such as `ROW_NUMBER() OVER (PARTITION BY a ORDER BY b)`.
+    WindowFunction {
+        op: String,
+        params: Vec<DataValue>,
+        args: Vec<Expression>,
+        partition_by: Vec<Expression>,
+        /// The window order, a list of `Expression::Sort`
+        order_by: Vec<Expression>,
+        window_frame: Option<WindowFrame>,
+        /// The `RESPECT NULLS` or `IGNORE NULLS` of the value functions, such as `LAST_VALUE`.
+        null_treatment: Option<NullTreatment>,
     },
 
     /// A sort expression, that can be used to sort values.
@@ -113,6 +137,17 @@ pub enum Expression {
     /// All fields(*) in a schema.
     Wildcard,
 
+    /// All fields of a table, such as `t.*`, with the BigQuery style column lists of the table
+    /// `t.* EXCEPT (id)` and `t.* RENAME (old AS new)`.
+    QualifiedWildcard {
+        /// The table name parts before `.*`
+        qualifier: Vec<String>,
+        /// The short names of the excluded columns
+        except: Vec<String>,
+        /// The (old, new) names of the renamed columns
+        rename: Vec<(String, String)>,
+    },
+
     /// Casts the expression to a given type and will return a runtime error if the expression cannot be cast.
     /// This expression is guaranteed to have a fixed type.
     Cast {
@@ -122,6 +157,70 @@ pub enum Expression {
         data_type: DataType,
     },
 
+    /// Same as `Cast`, but yields NULL or the default value instead of a runtime error
+    /// if the expression cannot be cast.
+    TryCast {
+        /// The expression being cast
+        expr: Box<Expression>,
+        /// The `DataType` the expression will yield
+        data_type: DataType,
+        /// The value yielded if the expression cannot be cast
+        on_error: CastOnError,
+    },
+
+    /// Interval such as `INTERVAL '7' DAY`, the unit is validated in analyze.
+    Interval {
+        /// The number of units, may be fractional or negative
+        value: Box<Expression>,
+        /// The unit name, such as `DAY`
+        unit: String,
+    },
+
+    /// A string expression with an explicit collation, such as `name COLLATE "C"`.
+    Collate {
+        /// The string expression being collated
+        expr: Box<Expression>,
+        /// The collation name, validated in analyze
+        collation: String,
+    },
+
+    /// A row constructor of two or more elements, such as `(a, b)` in `(a, b) IN (SELECT x, y FROM t)`.
+    Tuple(Vec<Expression>),
+
+    /// An array constructor, such as `[a, b]`, the elements are unified to a common type.
+    Array(Vec<Expression>),
+
+    /// A struct constructor, such as `{'k': v}`, with the unique field names in order.
+    Struct(Vec<(String, Expression)>),
+
+    /// `expr [NOT] LIKE pattern ESCAPE 'c'` or the ILIKE of it, the escape character is validated in analyze.
+    Like {
+        /// The normalized operator, one of `LIKE`, `NOT LIKE`, `ILIKE` and `NOT ILIKE`
+        op: String,
+        /// The expression being matched
+        expr: Box<Expression>,
+        /// The pattern to match
+        pattern: Box<Expression>,
+        /// The single character escape literal
+        escape: Option<Box<Expression>>,
+    },
+
+    /// A named argument of a scalar function, such as `timeout => 30` in `my_func(timeout => 30)`.
+    NamedArgument {
+        /// The parameter name, bound by the function
+        name: String,
+        /// The argument value
+        expr: Box<Expression>,
+    },
+
+    /// Subscript access on an array or map value, such as `tags[0]` or `props['key']`.
+    Subscript {
+        /// The array or map expression being accessed
+        base: Box<Expression>,
+        /// The element index or map key
+        index: Box<Expression>,
+    },
+
     /// Scalar sub query. such as `SELECT (SELECT 1)`
     ScalarSubquery {
         name: String,
@@ -132,6 +231,21 @@ pub enum Expression {
         name: String,
         query_plan: Arc<PlanNode>,
     },
+
+    /// `expr [NOT] IN (subquery)`, the subquery is an `Expression::Subquery`.
+    InSubquery {
+        expr: Box<Expression>,
+        subquery: Box<Expression>,
+        negated: bool,
+    },
+
+    /// `left op ANY|ALL (subquery)`, `SOME` is normalized to `ANY`.
+    QuantifiedComparison {
+        op: String,
+        quantifier: String,
+        left: Box<Expression>,
+        subquery: Box<Expression>,
+    },
 }
 
 impl Expression {
@@ -156,6 +270,7 @@ impl Expression {
         match self {
             Expression::Alias(name, _expr) => name.clone(),
             Expression::Column(name) => name.clone(),
+            Expression::OuterColumn { name, .. } => name.clone(),
             Expression::Literal {
                 value, column_name, ..
             } => match column_name {
@@ -193,6 +308,7 @@ impl Expression {
                 distinct,
                 params,
                 args,
+                within_group,
             } => {
                 let args_column_name = args.iter().map(Expression::column_name).collect::<Vec<_>>();
                 let params_name = params
@@ -206,17 +322,124 @@ impl Expression {
                     format!("{}({})", op, params_name.join(", "))
                 };
 
-                match distinct {
+                let mut name = match distinct {
                     true => format!("{}(distinct {})", prefix, args_column_name.join(", ")),
                     false => format!("{}({})", prefix, args_column_name.join(", ")),
+                };
+
+                if !within_group.is_empty() {
+                    let keys = within_group.iter().map(Expression::column_name);
+                    let keys = keys.collect::<Vec<_>>();
+                    name = format!("{} within group (order by {})", name, keys.join(", "));
                 }
+
+                name
+            }
+            Expression::WindowFunction {
+                op,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+                ..
+            } => {
+                let args_column_name = args.iter().map(Expression::column_name).collect::<Vec<_>>();
+                format!(
+                    "{}({}){} over ({})",
+                    op,
+                    args_column_name.join(", "),
+                    Self::null_treatment_name(null_treatment),
+                    Self::window_spec_name(partition_by, order_by, window_frame)
+                )
             }
             Expression::Sort { expr, .. } => expr.column_name(),
             Expression::Cast { expr, data_type } => {
                 format!("cast({} as {:?})", expr.column_name(), data_type)
             }
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => match on_error {
+                CastOnError::Null => format!("try_cast({} as {:?})", expr.column_name(), data_type),
+                CastOnError::Default(default) => format!(
+                    "cast({} as {:?} default {} on error)",
+                    expr.column_name(),
+                    data_type,
+                    default.column_name()
+                ),
+            },
+            Expression::Subscript { base, index } => {
+                format!("{}[{}]", base.column_name(), index.column_name())
+            }
+            Expression::Collate { expr, collation } => {
+                format!("{} collate {}", expr.column_name(), collation)
+            }
+            Expression::NamedArgument { name, expr } => {
+                format!("{} => {}", name, expr.column_name())
+            }
+            Expression::Tuple(exprs) => {
+                let names = exprs
+                    .iter()
+                    .map(Expression::column_name)
+                    .collect::<Vec<_>>();
+                format!("tuple({})", names.join(", "))
+            }
+            Expression::Array(exprs) => {
+                let names = exprs
+                    .iter()
+                    .map(Expression::column_name)
+                    .collect::<Vec<_>>();
+                format!("[{}]", names.join(", "))
+            }
+            Expression::Struct(fields) => {
+                let names = fields
+                    .iter()
+                    .map(|(name, expr)| format!("'{}': {}", name, expr.column_name()))
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", names.join(", "))
+            }
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => match escape {
+                None => format!("({} {} {})", expr.column_name(), op, pattern.column_name()),
+                Some(escape) => format!(
+                    "({} {} {} ESCAPE {})",
+                    expr.column_name(),
+                    op,
+                    pattern.column_name(),
+                    escape.column_name()
+                ),
+            },
+            Expression::Interval { value, unit } => {
+                format!("INTERVAL {} {}", value.column_name(), unit)
+            }
             Expression::Subquery { name, .. } => name.clone(),
             Expression::ScalarSubquery { name, .. } => name.clone(),
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => match negated {
+                true => format!("({} NOT IN {})", expr.column_name(), subquery.column_name()),
+                false => format!("({} IN {})", expr.column_name(), subquery.column_name()),
+            },
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => format!(
+                "({} {} {} {})",
+                left.column_name(),
+                op,
+                quantifier,
+                subquery.column_name()
+            ),
             _ => format!("{:?}", self),
         }
     }
@@ -231,7 +454,14 @@ impl Expression {
 
     // TODO
     pub fn nullable(&self, _input_schema: &DataSchemaRef) -> Result<bool> {
-        Ok(false)
+        match self {
+            // The value functions are NULL out of the window, such as LAG of the first row.
+            Expression::WindowFunction { op, .. } => Ok(matches!(
+                op.to_lowercase().as_str(),
+                "lag" | "lead" | "first_value" | "last_value" | "nth_value"
+            )),
+            _ => Ok(false),
+        }
     }
 
     pub fn to_subquery_type(subquery_plan: &PlanNode) -> DataType {
@@ -272,11 +502,17 @@ impl Expression {
             Expression::QualifiedColumn(_) => Err(ErrorCode::LogicalError(
                 "QualifiedColumn should be resolve in analyze.",
             )),
+            Expression::OuterColumn { name, .. } => Err(ErrorCode::LogicalError(format!(
+                "OuterColumn {} is not in the input schema, it should be decorrelated.",
+                name
+            ))),
             Expression::Literal { data_type, .. } => Ok(data_type.clone()),
             Expression::Subquery { query_plan, .. } => Ok(Self::to_subquery_type(query_plan)),
             Expression::ScalarSubquery { query_plan, .. } => {
                 Ok(Self::to_scalar_subquery_type(query_plan))
             }
+            Expression::InSubquery { .. } => Ok(DataType::Boolean),
+            Expression::QuantifiedComparison { .. } => Ok(DataType::Boolean),
             Expression::BinaryExpression { op, left, right } => {
                 let arg_types = vec![
                     left.to_data_type(input_schema)?,
@@ -304,10 +540,97 @@ impl Expression {
                 let func = self.to_aggregate_function(input_schema)?;
                 func.return_type()
             }
-            Expression::Wildcard => Result::Err(ErrorCode::IllegalDataType(
-                "Wildcard expressions are not valid to get return type",
-            )),
+            Expression::WindowFunction {
+                op, params, args, ..
+            } => match op.to_lowercase().as_str() {
+                "row_number" | "rank" | "dense_rank" | "ntile" => Ok(DataType::UInt64),
+                "percent_rank" | "cume_dist" => Ok(DataType::Float64),
+                "lag" | "lead" | "first_value" | "last_value" | "nth_value" => match args.first() {
+                    Some(arg) => arg.to_data_type(input_schema),
+                    None => Result::Err(ErrorCode::NumberArgumentsNotMatch(format!(
+                        "Window function {} expects at least one argument",
+                        op
+                    ))),
+                },
+                _ => {
+                    let mut fields = Vec::with_capacity(args.len());
+                    for arg in args.iter() {
+                        fields.push(arg.to_data_field(input_schema)?);
+                    }
+                    let func =
+                        AggregateFunctionFactory::instance().get(op, params.clone(), fields)?;
+                    func.return_type()
+                }
+            },
+            Expression::Wildcard | Expression::QualifiedWildcard { .. } => Result::Err(
+                ErrorCode::IllegalDataType("Wildcard expressions are not valid to get return type"),
+            ),
             Expression::Cast { data_type, .. } => Ok(data_type.clone()),
+            Expression::TryCast { data_type, .. } => Ok(data_type.clone()),
+            Expression::Interval { unit, .. } => match unit.to_uppercase().as_str() {
+                "YEAR" | "QUARTER" | "MONTH" => Ok(DataType::Interval(IntervalUnit::YearMonth)),
+                _ => Ok(DataType::Interval(IntervalUnit::DayTime)),
+            },
+            Expression::Collate { expr, .. } => expr.to_data_type(input_schema),
+            Expression::NamedArgument { expr, .. } => expr.to_data_type(input_schema),
+            Expression::Tuple(exprs) => {
+                let arg_types = exprs
+                    .iter()
+                    .map(|expr| expr.to_data_type(input_schema))
+                    .collect::<Result<Vec<_>>>()?;
+                let func = FunctionFactory::instance().get("tuple")?;
+                func.return_type(&arg_types)
+            }
+            Expression::Array(exprs) => {
+                let mut element_type = DataType::Null;
+                for expr in exprs {
+                    element_type = merge_types(&element_type, &expr.to_data_type(input_schema)?)?;
+                }
+
+                let element = DataField::new("item", element_type, true);
+                Ok(DataType::List(Box::new(element)))
+            }
+            Expression::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, expr)| {
+                        let data_type = expr.to_data_type(input_schema)?;
+                        Ok(DataField::new(name, data_type, true))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(DataType::Struct(fields))
+            }
+            Expression::Like { .. } => Ok(DataType::Boolean),
+            Expression::Subscript { base, index } => match base.to_data_type(input_schema)? {
+                DataType::String => Ok(DataType::String),
+                DataType::List(inner) => Ok(inner.data_type().clone()),
+                DataType::Struct(fields) => {
+                    let key = match index.as_ref() {
+                        Expression::Literal {
+                            value: DataValue::String(Some(key)),
+                            ..
+                        } => String::from_utf8(key.clone())?,
+                        other => {
+                            return Result::Err(ErrorCode::IllegalDataType(format!(
+                                "Struct field access requires a constant string key, but got {:?}",
+                                other
+                            )))
+                        }
+                    };
+
+                    match fields.iter().find(|field| field.name() == &key) {
+                        Some(field) => Ok(field.data_type().clone()),
+                        None => Result::Err(ErrorCode::IllegalDataType(format!(
+                            "Unknown struct field {}",
+                            key
+                        ))),
+                    }
+                }
+                other => Result::Err(ErrorCode::IllegalDataType(format!(
+                    "Subscript access is not supported on type {:?}",
+                    other
+                ))),
+            },
             Expression::Sort { expr, .. } => expr.to_data_type(input_schema),
         }
     }
@@ -319,6 +642,7 @@ impl Expression {
                 distinct,
                 params,
                 args,
+                ..
             } => {
                 let mut func_name = op.clone();
                 if *distinct {
@@ -352,6 +676,37 @@ impl Expression {
         }
     }
 
+    fn window_spec_name(
+        partition_by: &[Expression],
+        order_by: &[Expression],
+        window_frame: &Option<WindowFrame>,
+    ) -> String {
+        let mut spec = vec![];
+        if !partition_by.is_empty() {
+            let names = partition_by.iter().map(|e| format!("{:?}", e));
+            spec.push(format!(
+                "partition by {}",
+                names.collect::<Vec<_>>().join(", ")
+            ));
+        }
+        if !order_by.is_empty() {
+            let names = order_by.iter().map(|e| format!("{:?}", e));
+            spec.push(format!("order by {}", names.collect::<Vec<_>>().join(", ")));
+        }
+        if let Some(window_frame) = window_frame {
+            spec.push(format!("{:?}", window_frame));
+        }
+        spec.join(" ")
+    }
+
+    fn null_treatment_name(null_treatment: &Option<NullTreatment>) -> &'static str {
+        match null_treatment {
+            None => "",
+            Some(NullTreatment::RespectNulls) => " respect nulls",
+            Some(NullTreatment::IgnoreNulls) => " ignore nulls",
+        }
+    }
+
     pub fn create_scalar_function(op: &str, args: Expressions) -> Expression {
         let op = op.to_string();
         Expression::ScalarFunction { op, args }
@@ -378,9 +733,24 @@ impl fmt::Debug for Expression {
             Expression::Alias(alias, v) => write!(f, "{:?} as {:#}", v, alias),
             Expression::Column(ref v) => write!(f, "{:#}", v),
             Expression::QualifiedColumn(v) => write!(f, "{:?}", v.join(".")),
+            Expression::OuterColumn { name, depth } => write!(f, "outer({}, {})", name, depth),
             Expression::Literal { ref value, .. } => write!(f, "{:#}", value),
             Expression::Subquery { name, .. } => write!(f, "subquery({})", name),
             Expression::ScalarSubquery { name, .. } => write!(f, "scalar subquery({})", name),
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => match negated {
+                true => write!(f, "({:?} NOT IN {:?})", expr, subquery),
+                false => write!(f, "({:?} IN {:?})", expr, subquery),
+            },
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => write!(f, "({:?} {} {} {:?})", left, op, quantifier, subquery),
             Expression::BinaryExpression { op, left, right } => {
                 write!(f, "({:?} {} {:?})", left, op, right,)
             }
@@ -406,6 +776,7 @@ impl fmt::Debug for Expression {
                 distinct,
                 params,
                 args,
+                within_group,
             } => {
                 let args_column_name = args.iter().map(Expression::column_name).collect::<Vec<_>>();
                 let params_name = params
@@ -423,16 +794,136 @@ impl fmt::Debug for Expression {
                     true => write!(f, "(distinct {})", args_column_name.join(", "))?,
                     false => write!(f, "({})", args_column_name.join(", "))?,
                 }
+
+                if !within_group.is_empty() {
+                    let keys = within_group.iter().map(|key| format!("{:?}", key));
+                    let keys = keys.collect::<Vec<_>>();
+                    write!(f, " within group (order by {})", keys.join(", "))?;
+                }
                 Ok(())
             }
 
+            Expression::WindowFunction {
+                op,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+                ..
+            } => {
+                let args_column_name = args.iter().map(Expression::column_name).collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{}({}){} over ({})",
+                    op,
+                    args_column_name.join(", "),
+                    Self::null_treatment_name(null_treatment),
+                    Self::window_spec_name(partition_by, order_by, window_frame)
+                )
+            }
             Expression::Sort { expr, .. } => write!(f, "{:?}", expr),
             Expression::Wildcard => write!(f, "*"),
+            Expression::QualifiedWildcard {
+                qualifier,
+                except,
+                rename,
+            } => {
+                write!(f, "{}.*", qualifier.join("."))?;
+                if !except.is_empty() {
+                    write!(f, " EXCEPT ({})", except.join(", "))?;
+                }
+                if !rename.is_empty() {
+                    let rename = rename
+                        .iter()
+                        .map(|(old, new)| format!("{} AS {}", old, new))
+                        .collect::<Vec<_>>();
+                    write!(f, " RENAME ({})", rename.join(", "))?;
+                }
+                Ok(())
+            }
             Expression::Cast { expr, data_type } => {
                 write!(f, "cast({:?} as {:?})", expr, data_type)
             }
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => match on_error {
+                CastOnError::Null => write!(f, "try_cast({:?} as {:?})", expr, data_type),
+                CastOnError::Default(default) => write!(
+                    f,
+                    "cast({:?} as {:?} default {:?} on error)",
+                    expr, data_type, default
+                ),
+            },
+            Expression::Subscript { base, index } => write!(f, "{:?}[{:?}]", base, index),
+            Expression::Collate { expr, collation } => {
+                write!(f, "{:?} collate {}", expr, collation)
+            }
+            Expression::NamedArgument { name, expr } => write!(f, "{} => {:?}", name, expr),
+            Expression::Tuple(exprs) => {
+                write!(f, "(")?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", expr)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Array(exprs) => {
+                write!(f, "[")?;
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", expr)?;
+                }
+                write!(f, "]")
+            }
+            Expression::Struct(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, expr)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "'{}': {:?}", name, expr)?;
+                }
+                write!(f, "}}")
+            }
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => match escape {
+                None => write!(f, "({:?} {} {:?})", expr, op, pattern),
+                Some(escape) => write!(f, "({:?} {} {:?} ESCAPE {:?})", expr, op, pattern, escape),
+            },
+            Expression::Interval { value, unit } => write!(f, "INTERVAL {:?} {}", value, unit),
         }
     }
 }
 
 pub type Expressions = Vec<Expression>;
+
+/// The ON ERROR clause of a cast, such as `CAST(x AS INT DEFAULT 0 ON ERROR)`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub enum CastOnError {
+    /// `NULL ON ERROR`, same as `TRY_CAST`.
+    Null,
+    /// `DEFAULT expr ON ERROR`.
+    Default(Box<Expression>),
+}
+
+impl CastOnError {
+    /// Rewrite the default expression, if any.
+    pub fn try_map<F>(&self, f: F) -> Result<CastOnError>
+    where F: FnOnce(&Expression) -> Result<Expression> {
+        match self {
+            CastOnError::Null => Ok(CastOnError::Null),
+            CastOnError::Default(default) => Ok(CastOnError::Default(Box::new(f(default)?))),
+        }
+    }
+}
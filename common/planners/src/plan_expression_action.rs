@@ -91,6 +91,9 @@ impl ActionFunction {
 
         match self.func_name.as_str() {
             "cast" => CastFunction::create(self.func_name.clone(), self.return_type.clone()),
+            "try_cast" => {
+                CastFunction::create_try(self.func_name.clone(), self.return_type.clone())
+            }
             _ => FunctionFactory::instance().get(&self.func_name),
         }
     }
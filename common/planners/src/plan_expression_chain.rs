@@ -21,6 +21,7 @@ use crate::ActionAlias;
 use crate::ActionConstant;
 use crate::ActionFunction;
 use crate::ActionInput;
+use crate::CastOnError;
 use crate::Expression;
 use crate::ExpressionAction;
 
@@ -146,30 +147,25 @@ impl ExpressionChain {
                 self.actions.push(ExpressionAction::Function(function));
             }
 
-            Expression::ScalarFunction { op, args } => {
-                for expr in args.iter() {
-                    self.add_expr(expr)?;
-                }
-
-                let func = FunctionFactory::instance().get(op)?;
-                let arg_types = args
-                    .iter()
-                    .map(|action| action.to_data_type(&self.schema))
-                    .collect::<Result<Vec<_>>>()?;
-
-                let function = ActionFunction {
-                    name: expr.column_name(),
-                    func_name: op.clone(),
-                    is_aggregated: false,
-                    params: vec![],
-                    arg_names: args.iter().map(|action| action.column_name()).collect(),
-                    arg_types: arg_types.clone(),
-                    arg_fields: vec![],
-                    is_nullable: func.nullable(self.schema.as_ref())?,
-                    return_type: func.return_type(&arg_types)?,
-                };
-
-                self.actions.push(ExpressionAction::Function(function));
+            Expression::ScalarFunction { op, args } => self.add_function(expr, op, args)?,
+            // A row constructor is evaluated by the `tuple` function.
+            Expression::Tuple(args) => self.add_function(expr, "tuple", args)?,
+            Expression::Like {
+                escape: Some(_), ..
+            } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "LIKE with ESCAPE is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::Like {
+                op,
+                expr: sub_expr,
+                pattern,
+                ..
+            } => {
+                let args = [sub_expr.as_ref().clone(), pattern.as_ref().clone()];
+                self.add_function(expr, op, &args)?
             }
 
             Expression::AggregateFunction {
@@ -199,7 +195,13 @@ impl ExpressionChain {
                 self.add_expr(expr)?;
             }
 
-            Expression::Wildcard => {}
+            Expression::Wildcard | Expression::QualifiedWildcard { .. } => {}
+            Expression::OuterColumn { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Correlated column {:?} is not supported in expression chain",
+                    expr
+                )));
+            }
             Expression::Cast {
                 expr: sub_expr,
                 data_type,
@@ -219,7 +221,111 @@ impl ExpressionChain {
 
                 self.actions.push(ExpressionAction::Function(function));
             }
+            Expression::TryCast {
+                on_error: CastOnError::Default(_),
+                ..
+            } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "DEFAULT ON ERROR cast is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::TryCast {
+                expr: sub_expr,
+                data_type,
+                ..
+            } => {
+                self.add_expr(sub_expr)?;
+                let function = ActionFunction {
+                    name: expr.column_name(),
+                    func_name: "try_cast".to_string(),
+                    is_aggregated: false,
+                    arg_names: vec![sub_expr.column_name()],
+                    arg_types: vec![sub_expr.to_data_type(&self.schema)?],
+                    params: vec![],
+                    arg_fields: vec![],
+                    is_nullable: true,
+                    return_type: data_type.clone(),
+                };
+
+                self.actions.push(ExpressionAction::Function(function));
+            }
+            Expression::WindowFunction { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Window function is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::InSubquery { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "IN subquery is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::QuantifiedComparison { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Quantified comparison is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::Subscript { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Subscript access is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::Array(_) | Expression::Struct(_) => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Array and struct constructors are not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::Interval { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Interval is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::Collate { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Collation is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+            Expression::NamedArgument { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Named argument is not supported in expression chain: {:?}",
+                    expr
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn add_function(&mut self, expr: &Expression, op: &str, args: &[Expression]) -> Result<()> {
+        for arg in args {
+            self.add_expr(arg)?;
         }
+
+        let func = FunctionFactory::instance().get(op)?;
+        let arg_types = args
+            .iter()
+            .map(|action| action.to_data_type(&self.schema))
+            .collect::<Result<Vec<_>>>()?;
+
+        let function = ActionFunction {
+            name: expr.column_name(),
+            func_name: op.to_string(),
+            is_aggregated: false,
+            params: vec![],
+            arg_names: args.iter().map(|action| action.column_name()).collect(),
+            arg_types: arg_types.clone(),
+            arg_fields: vec![],
+            is_nullable: func.nullable(self.schema.as_ref())?,
+            return_type: func.return_type(&arg_types)?,
+        };
+
+        self.actions.push(ExpressionAction::Function(function));
         Ok(())
     }
 }
@@ -49,6 +49,45 @@ pub fn find_aggregate_exprs_in_expr(expr: &Expression) -> Vec<Expression> {
     })
 }
 
+/// Collect all deeply nested `Expression::WindowFunction`. They are returned in order of
+/// occurrence (depth first), with duplicates omitted.
+pub fn find_window_exprs(exprs: &[Expression]) -> Vec<Expression> {
+    find_exprs_in_exprs(exprs, &|nest_exprs| {
+        matches!(nest_exprs, Expression::WindowFunction { .. })
+    })
+}
+
+pub fn find_window_exprs_in_expr(expr: &Expression) -> Vec<Expression> {
+    find_exprs_in_expr(expr, &|nest_exprs| {
+        matches!(nest_exprs, Expression::WindowFunction { .. })
+    })
+}
+
+/// Collect all deeply nested `GROUPING(...)` pseudo-function calls.
+pub fn find_grouping_exprs_in_expr(expr: &Expression) -> Vec<Expression> {
+    find_exprs_in_expr(expr, &|nest_exprs| match nest_exprs {
+        Expression::ScalarFunction { op, .. } => op.eq_ignore_ascii_case("grouping"),
+        _ => false,
+    })
+}
+
+/// Collect the columns not under an aggregate function nor inside any of the `group_by`
+/// expressions, which are matched by structural equality.
+pub fn find_columns_not_in_group_by(expr: &Expression, group_by: &[Expression]) -> Vec<Expression> {
+    let exprs = find_exprs_in_expr(expr, &|nest_exprs| {
+        group_by.contains(nest_exprs)
+            || matches!(
+                nest_exprs,
+                Expression::Column(_) | Expression::AggregateFunction { .. }
+            )
+    });
+
+    exprs
+        .into_iter()
+        .filter(|expr| matches!(expr, Expression::Column(_)) && !group_by.contains(expr))
+        .collect()
+}
+
 /// Collect all arguments from aggregation function and append to this exprs
 /// [ColumnExpr(b), Aggr(sum(a, b))] ---> [ColumnExpr(b), ColumnExpr(a)]
 
@@ -261,6 +300,7 @@ where F: Fn(&Expression) -> Result<Option<Expression>> {
         // clone_with_replacement() on any nested Expressionessions.
         None => match expr {
             Expression::Wildcard => Ok(Expression::Wildcard),
+            Expression::QualifiedWildcard { .. } => Ok(expr.clone()),
             Expression::Alias(alias_name, nested_expr) => Ok(Expression::Alias(
                 alias_name.clone(),
                 Box::new(clone_with_replacement(&**nested_expr, replacement_fn)?),
@@ -293,6 +333,7 @@ where F: Fn(&Expression) -> Result<Option<Expression>> {
                 distinct,
                 params,
                 args,
+                within_group,
             } => Ok(Expression::AggregateFunction {
                 op: op.clone(),
                 distinct: *distinct,
@@ -301,6 +342,28 @@ where F: Fn(&Expression) -> Result<Option<Expression>> {
                     .iter()
                     .map(|e| clone_with_replacement(e, replacement_fn))
                     .collect::<Result<Vec<Expression>>>()?,
+                within_group: within_group
+                    .iter()
+                    .map(|e| clone_with_replacement(e, replacement_fn))
+                    .collect::<Result<Vec<Expression>>>()?,
+            }),
+
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => Ok(Expression::WindowFunction {
+                op: op.clone(),
+                params: params.clone(),
+                args: clone_exprs_with_replacement(args, replacement_fn)?,
+                partition_by: clone_exprs_with_replacement(partition_by, replacement_fn)?,
+                order_by: clone_exprs_with_replacement(order_by, replacement_fn)?,
+                window_frame: window_frame.clone(),
+                null_treatment: *null_treatment,
             }),
 
             Expression::Sort {
@@ -321,9 +384,90 @@ where F: Fn(&Expression) -> Result<Option<Expression>> {
                 expr: Box::new(clone_with_replacement(&**nested_expr, replacement_fn)?),
                 data_type: data_type.clone(),
             }),
+            Expression::TryCast {
+                expr: nested_expr,
+                data_type,
+                on_error,
+            } => Ok(Expression::TryCast {
+                expr: Box::new(clone_with_replacement(&**nested_expr, replacement_fn)?),
+                data_type: data_type.clone(),
+                on_error: on_error
+                    .try_map(|default| clone_with_replacement(default, replacement_fn))?,
+            }),
+            Expression::InSubquery {
+                expr: nested_expr,
+                subquery,
+                negated,
+            } => Ok(Expression::InSubquery {
+                expr: Box::new(clone_with_replacement(&**nested_expr, replacement_fn)?),
+                subquery: subquery.clone(),
+                negated: *negated,
+            }),
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => Ok(Expression::QuantifiedComparison {
+                op: op.clone(),
+                quantifier: quantifier.clone(),
+                left: Box::new(clone_with_replacement(&**left, replacement_fn)?),
+                subquery: subquery.clone(),
+            }),
+            Expression::Subscript { base, index } => Ok(Expression::Subscript {
+                base: Box::new(clone_with_replacement(&**base, replacement_fn)?),
+                index: Box::new(clone_with_replacement(&**index, replacement_fn)?),
+            }),
+            Expression::Interval { value, unit } => Ok(Expression::Interval {
+                value: Box::new(clone_with_replacement(&**value, replacement_fn)?),
+                unit: unit.clone(),
+            }),
+            Expression::Collate { expr, collation } => Ok(Expression::Collate {
+                expr: Box::new(clone_with_replacement(&**expr, replacement_fn)?),
+                collation: collation.clone(),
+            }),
+            Expression::NamedArgument { name, expr } => Ok(Expression::NamedArgument {
+                name: name.clone(),
+                expr: Box::new(clone_with_replacement(&**expr, replacement_fn)?),
+            }),
+            Expression::Array(exprs) => Ok(Expression::Array(
+                exprs
+                    .iter()
+                    .map(|e| clone_with_replacement(e, replacement_fn))
+                    .collect::<Result<Vec<Expression>>>()?,
+            )),
+            Expression::Struct(fields) => Ok(Expression::Struct(
+                fields
+                    .iter()
+                    .map(|(name, e)| Ok((name.clone(), clone_with_replacement(e, replacement_fn)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            )),
+            Expression::Tuple(exprs) => Ok(Expression::Tuple(
+                exprs
+                    .iter()
+                    .map(|e| clone_with_replacement(e, replacement_fn))
+                    .collect::<Result<Vec<Expression>>>()?,
+            )),
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => Ok(Expression::Like {
+                op: op.clone(),
+                expr: Box::new(clone_with_replacement(&**expr, replacement_fn)?),
+                pattern: Box::new(clone_with_replacement(&**pattern, replacement_fn)?),
+                escape: match escape {
+                    None => None,
+                    Some(escape) => {
+                        Some(Box::new(clone_with_replacement(&**escape, replacement_fn)?))
+                    }
+                },
+            }),
 
             Expression::Column(_)
             | Expression::QualifiedColumn(_)
+            | Expression::OuterColumn { .. }
             | Expression::Literal { .. }
             | Expression::Subquery { .. }
             | Expression::ScalarSubquery { .. } => Ok(expr.clone()),
@@ -331,6 +475,19 @@ where F: Fn(&Expression) -> Result<Option<Expression>> {
     }
 }
 
+fn clone_exprs_with_replacement<F>(
+    exprs: &[Expression],
+    replacement_fn: &F,
+) -> Result<Vec<Expression>>
+where
+    F: Fn(&Expression) -> Result<Option<Expression>>,
+{
+    exprs
+        .iter()
+        .map(|e| clone_with_replacement(e, replacement_fn))
+        .collect::<Result<Vec<Expression>>>()
+}
+
 /// Returns mapping of each alias (`String`) to the exprs (`Expression`) it is
 /// aliasing.
 pub fn extract_aliases(exprs: &[Expression]) -> HashMap<String, Expression> {
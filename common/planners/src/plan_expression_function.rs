@@ -56,6 +56,7 @@ pub fn sum(other: Expression) -> Expression {
         distinct: false,
         params: vec![],
         args: vec![other],
+        within_group: vec![],
     }
 }
 
@@ -66,6 +67,7 @@ pub fn avg(other: Expression) -> Expression {
         distinct: false,
         params: vec![],
         args: vec![other],
+        within_group: vec![],
     }
 }
 
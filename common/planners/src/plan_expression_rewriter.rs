@@ -77,6 +77,10 @@ impl Expression {
                 let expr = expr.rewrite(rewriter)?;
                 Expression::Alias(name, Box::new(expr))
             }
+            Expression::UnaryExpression { op, expr } => Expression::UnaryExpression {
+                op,
+                expr: Box::new(expr.rewrite(rewriter)?),
+            },
             Expression::BinaryExpression { op, left, right } => Expression::BinaryExpression {
                 op,
                 left: Box::new(left.rewrite(rewriter)?),
@@ -94,16 +98,22 @@ impl Expression {
                 distinct,
                 params,
                 args,
+                within_group,
             } => {
                 let mut new_args = Vec::with_capacity(args.len());
                 for arg in args {
                     new_args.push(arg.rewrite(rewriter)?);
                 }
+                let mut new_within_group = Vec::with_capacity(within_group.len());
+                for key in within_group {
+                    new_within_group.push(key.rewrite(rewriter)?);
+                }
                 Expression::AggregateFunction {
                     op,
                     distinct,
                     params,
                     args: new_args,
+                    within_group: new_within_group,
                 }
             }
             Expression::Cast { expr, data_type } => {
@@ -113,6 +123,99 @@ impl Expression {
                     data_type,
                 }
             }
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => {
+                let expr = expr.rewrite(rewriter)?;
+                let on_error = on_error.try_map(|default| default.clone().rewrite(rewriter))?;
+                Expression::TryCast {
+                    expr: Box::new(expr),
+                    data_type,
+                    on_error,
+                }
+            }
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => Expression::WindowFunction {
+                op,
+                params,
+                args: Self::rewrite_exprs(args, rewriter)?,
+                partition_by: Self::rewrite_exprs(partition_by, rewriter)?,
+                order_by: Self::rewrite_exprs(order_by, rewriter)?,
+                window_frame,
+                null_treatment,
+            },
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => Expression::InSubquery {
+                expr: Box::new(expr.rewrite(rewriter)?),
+                subquery: Box::new(subquery.rewrite(rewriter)?),
+                negated,
+            },
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left: Box::new(left.rewrite(rewriter)?),
+                subquery: Box::new(subquery.rewrite(rewriter)?),
+            },
+            Expression::Subscript { base, index } => Expression::Subscript {
+                base: Box::new(base.rewrite(rewriter)?),
+                index: Box::new(index.rewrite(rewriter)?),
+            },
+            Expression::Interval { value, unit } => Expression::Interval {
+                value: Box::new(value.rewrite(rewriter)?),
+                unit,
+            },
+            Expression::Collate { expr, collation } => Expression::Collate {
+                expr: Box::new(expr.rewrite(rewriter)?),
+                collation,
+            },
+            Expression::NamedArgument { name, expr } => Expression::NamedArgument {
+                name,
+                expr: Box::new(expr.rewrite(rewriter)?),
+            },
+            Expression::Tuple(exprs) => Expression::Tuple(Self::rewrite_exprs(exprs, rewriter)?),
+            Expression::Array(exprs) => Expression::Array(Self::rewrite_exprs(exprs, rewriter)?),
+            Expression::Struct(fields) => Expression::Struct(
+                fields
+                    .into_iter()
+                    .map(|(name, expr)| Ok((name, expr.rewrite(rewriter)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => {
+                let expr = expr.rewrite(rewriter)?;
+                let pattern = pattern.rewrite(rewriter)?;
+                let escape = match escape {
+                    None => None,
+                    Some(escape) => Some(Box::new(escape.rewrite(rewriter)?)),
+                };
+                Expression::Like {
+                    op,
+                    expr: Box::new(expr),
+                    pattern: Box::new(pattern),
+                    escape,
+                }
+            }
             Expression::Sort {
                 expr,
                 asc,
@@ -133,4 +236,12 @@ impl Expression {
         // now rewrite this expression itself
         rewriter.mutate(expr)
     }
+
+    fn rewrite_exprs<R>(exprs: Vec<Expression>, rewriter: &mut R) -> Result<Vec<Expression>>
+    where R: ExprRewriter {
+        exprs
+            .into_iter()
+            .map(|expr| expr.rewrite(rewriter))
+            .collect::<Result<Vec<_>>>()
+    }
 }
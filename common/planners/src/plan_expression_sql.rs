@@ -0,0 +1,444 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use common_datavalues::DataType;
+use common_datavalues::DataValue;
+use common_exception::ErrorCode;
+use common_exception::Result;
+
+use crate::CastOnError;
+use crate::Expression;
+use crate::NullTreatment;
+use crate::WindowFrame;
+use crate::WindowFrameBound;
+use crate::WindowFrameUnits;
+
+// The words that cannot be used as a bare identifier in an expression.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "cast",
+    "collate",
+    "desc",
+    "distinct",
+    "else",
+    "end",
+    "except",
+    "exists",
+    "extract",
+    "false",
+    "filter",
+    "from",
+    "group",
+    "having",
+    "in",
+    "interval",
+    "is",
+    "join",
+    "like",
+    "limit",
+    "not",
+    "null",
+    "on",
+    "or",
+    "order",
+    "over",
+    "select",
+    "some",
+    "substring",
+    "then",
+    "trim",
+    "true",
+    "union",
+    "when",
+    "where",
+    "with",
+];
+
+impl Expression {
+    /// The canonical SQL text of the expression, which parses and analyzes back to the same
+    /// expression. The operators are fully parenthesized and the identifiers are quoted if
+    /// needed. A resolved column `t.name`, which is ambiguous without its qualifier, is written
+    /// as the qualified name. The subqueries have no SQL text, they are reported as errors.
+    pub fn to_sql(&self) -> Result<String> {
+        Ok(match self {
+            Expression::Alias(alias, expr) => {
+                format!("{} AS {}", expr.to_sql()?, quote_ident(alias))
+            }
+            Expression::Column(name) | Expression::OuterColumn { name, .. } => {
+                let name_parts = name.split('.').map(quote_ident).collect::<Vec<_>>();
+                name_parts.join(".")
+            }
+            Expression::QualifiedColumn(name_parts) => {
+                let name_parts = name_parts.iter().map(|part| quote_ident(part));
+                name_parts.collect::<Vec<_>>().join(".")
+            }
+            Expression::Literal { value, .. } => literal_sql(value)?,
+            Expression::UnaryExpression { op, expr } => format!("({} {})", op, expr.to_sql()?),
+            Expression::BinaryExpression { left, op, right } => {
+                format!("({} {} {})", left.to_sql()?, op, right.to_sql()?)
+            }
+            Expression::ScalarFunction { op, args } => scalar_function_sql(op, args)?,
+            Expression::AggregateFunction {
+                op,
+                distinct,
+                params,
+                args,
+                within_group,
+            } => {
+                let mut sql = op.clone();
+                if !params.is_empty() {
+                    let params = params.iter().map(literal_sql).collect::<Result<Vec<_>>>()?;
+                    sql.push_str(&format!("({})", params.join(", ")));
+                }
+
+                let args = exprs_sql(args)?;
+                match distinct {
+                    true => sql.push_str(&format!("(DISTINCT {})", args)),
+                    false => sql.push_str(&format!("({})", args)),
+                }
+
+                if !within_group.is_empty() {
+                    let keys = exprs_sql(within_group)?;
+                    sql.push_str(&format!(" WITHIN GROUP (ORDER BY {})", keys));
+                }
+                sql
+            }
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => {
+                let mut sql = op.clone();
+                if !params.is_empty() {
+                    let params = params.iter().map(literal_sql).collect::<Result<Vec<_>>>()?;
+                    sql.push_str(&format!("({})", params.join(", ")));
+                }
+
+                sql.push_str(&format!("({})", exprs_sql(args)?));
+                match null_treatment {
+                    None => {}
+                    Some(NullTreatment::RespectNulls) => sql.push_str(" RESPECT NULLS"),
+                    Some(NullTreatment::IgnoreNulls) => sql.push_str(" IGNORE NULLS"),
+                }
+
+                let mut spec = vec![];
+                if !partition_by.is_empty() {
+                    spec.push(format!("PARTITION BY {}", exprs_sql(partition_by)?));
+                }
+                if !order_by.is_empty() {
+                    spec.push(format!("ORDER BY {}", exprs_sql(order_by)?));
+                }
+                if let Some(window_frame) = window_frame {
+                    spec.push(window_frame_sql(window_frame)?);
+                }
+                sql.push_str(&format!(" OVER ({})", spec.join(" ")));
+                sql
+            }
+            Expression::Sort {
+                expr,
+                asc,
+                nulls_first,
+                ..
+            } => format!(
+                "{} {} {}",
+                expr.to_sql()?,
+                if *asc { "ASC" } else { "DESC" },
+                if *nulls_first {
+                    "NULLS FIRST"
+                } else {
+                    "NULLS LAST"
+                }
+            ),
+            Expression::Wildcard => String::from("*"),
+            Expression::QualifiedWildcard {
+                qualifier,
+                except,
+                rename,
+            } => {
+                let qualifier = qualifier.iter().map(|part| quote_ident(part));
+                let mut sql = format!("{}.*", qualifier.collect::<Vec<_>>().join("."));
+                if !except.is_empty() {
+                    let except = except.iter().map(|name| quote_ident(name));
+                    sql.push_str(&format!(
+                        " EXCEPT ({})",
+                        except.collect::<Vec<_>>().join(", ")
+                    ));
+                }
+                if !rename.is_empty() {
+                    let rename = rename
+                        .iter()
+                        .map(|(old, new)| format!("{} AS {}", quote_ident(old), quote_ident(new)));
+                    sql.push_str(&format!(
+                        " RENAME ({})",
+                        rename.collect::<Vec<_>>().join(", ")
+                    ));
+                }
+                sql
+            }
+            Expression::Cast { expr, data_type } => {
+                format!("CAST({} AS {})", expr.to_sql()?, data_type_sql(data_type)?)
+            }
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => match on_error {
+                CastOnError::Null => {
+                    format!(
+                        "TRY_CAST({} AS {})",
+                        expr.to_sql()?,
+                        data_type_sql(data_type)?
+                    )
+                }
+                CastOnError::Default(default) => format!(
+                    "CAST({} AS {} DEFAULT {} ON ERROR)",
+                    expr.to_sql()?,
+                    data_type_sql(data_type)?,
+                    default.to_sql()?
+                ),
+            },
+            Expression::Interval { value, unit } => match value.as_ref() {
+                Expression::Literal { value, .. } if !value.is_null() => {
+                    format!("INTERVAL '{}' {}", literal_sql(value)?, unit)
+                }
+                other => {
+                    return Err(ErrorCode::UnImplement(format!(
+                        "Interval of non-literal value {:?} cannot be converted to SQL",
+                        other
+                    )))
+                }
+            },
+            Expression::Collate { expr, collation } => {
+                format!("({} COLLATE {})", expr.to_sql()?, quote_ident(collation))
+            }
+            Expression::NamedArgument { name, expr } => {
+                format!("{} => {}", quote_ident(name), expr.to_sql()?)
+            }
+            Expression::Subscript { base, index } => {
+                format!("{}[{}]", base.to_sql()?, index.to_sql()?)
+            }
+            Expression::Tuple(exprs) => format!("({})", exprs_sql(exprs)?),
+            Expression::Array(exprs) => format!("[{}]", exprs_sql(exprs)?),
+            Expression::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, expr)| {
+                        let name = literal_sql(&DataValue::String(Some(name.as_bytes().to_vec())))?;
+                        Ok(format!("{}: {}", name, expr.to_sql()?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                format!("{{{}}}", fields.join(", "))
+            }
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => match escape {
+                None => format!("({} {} {})", expr.to_sql()?, op, pattern.to_sql()?),
+                Some(escape) => format!(
+                    "({} {} {} ESCAPE {})",
+                    expr.to_sql()?,
+                    op,
+                    pattern.to_sql()?,
+                    escape.to_sql()?
+                ),
+            },
+            Expression::ScalarSubquery { .. }
+            | Expression::Subquery { .. }
+            | Expression::InSubquery { .. }
+            | Expression::QuantifiedComparison { .. } => {
+                return Err(ErrorCode::UnImplement(format!(
+                    "Subquery expression {:?} cannot be converted to SQL",
+                    self
+                )))
+            }
+        })
+    }
+}
+
+fn exprs_sql(exprs: &[Expression]) -> Result<String> {
+    let exprs = exprs
+        .iter()
+        .map(Expression::to_sql)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(exprs.join(", "))
+}
+
+// The keyword forms are written back as they were, since the keywords can't be called
+// like a function, such as `EXTRACT(YEAR FROM ts)`.
+fn scalar_function_sql(op: &str, args: &[Expression]) -> Result<String> {
+    match (op.to_lowercase().as_str(), args) {
+        (
+            "extract",
+            [Expression::Literal {
+                value: DataValue::String(Some(field)),
+                ..
+            }, source],
+        ) => {
+            let field = String::from_utf8_lossy(field);
+            return Ok(format!("EXTRACT({} FROM {})", field, source.to_sql()?));
+        }
+        ("substring", [source, from]) => {
+            return Ok(format!(
+                "SUBSTRING({} FROM {})",
+                source.to_sql()?,
+                from.to_sql()?
+            ));
+        }
+        ("substring", [source, from, length]) => {
+            return Ok(format!(
+                "SUBSTRING({} FROM {} FOR {})",
+                source.to_sql()?,
+                from.to_sql()?,
+                length.to_sql()?
+            ));
+        }
+        ("trim_both", [chars, source]) => {
+            return Ok(format!(
+                "TRIM(BOTH {} FROM {})",
+                chars.to_sql()?,
+                source.to_sql()?
+            ));
+        }
+        ("trim_leading", [chars, source]) => {
+            return Ok(format!(
+                "TRIM(LEADING {} FROM {})",
+                chars.to_sql()?,
+                source.to_sql()?
+            ));
+        }
+        ("trim_trailing", [chars, source]) => {
+            return Ok(format!(
+                "TRIM(TRAILING {} FROM {})",
+                chars.to_sql()?,
+                source.to_sql()?
+            ));
+        }
+        _ => {}
+    }
+
+    Ok(format!("{}({})", op, exprs_sql(args)?))
+}
+
+fn window_frame_sql(window_frame: &WindowFrame) -> Result<String> {
+    let units = match window_frame.units {
+        WindowFrameUnits::Rows => "ROWS",
+        WindowFrameUnits::Range => "RANGE",
+        WindowFrameUnits::Groups => "GROUPS",
+    };
+
+    Ok(format!(
+        "{} BETWEEN {} AND {}",
+        units,
+        window_frame_bound_sql(&window_frame.start_bound)?,
+        window_frame_bound_sql(&window_frame.end_bound)?
+    ))
+}
+
+fn window_frame_bound_sql(bound: &WindowFrameBound) -> Result<String> {
+    Ok(match bound {
+        WindowFrameBound::CurrentRow => String::from("CURRENT ROW"),
+        WindowFrameBound::Preceding(None) => String::from("UNBOUNDED PRECEDING"),
+        WindowFrameBound::Preceding(Some(n)) => format!("{} PRECEDING", n),
+        WindowFrameBound::Following(None) => String::from("UNBOUNDED FOLLOWING"),
+        WindowFrameBound::Following(Some(n)) => format!("{} FOLLOWING", n),
+        WindowFrameBound::IntervalPreceding(interval) => {
+            format!("{} PRECEDING", interval.to_sql()?)
+        }
+        WindowFrameBound::IntervalFollowing(interval) => {
+            format!("{} FOLLOWING", interval.to_sql()?)
+        }
+    })
+}
+
+fn literal_sql(value: &DataValue) -> Result<String> {
+    if value.is_null() {
+        return Ok(String::from("NULL"));
+    }
+
+    Ok(match value {
+        DataValue::Boolean(Some(v)) => String::from(if *v { "TRUE" } else { "FALSE" }),
+        DataValue::Int8(Some(v)) => v.to_string(),
+        DataValue::Int16(Some(v)) => v.to_string(),
+        DataValue::Int32(Some(v)) => v.to_string(),
+        DataValue::Int64(Some(v)) => v.to_string(),
+        DataValue::UInt8(Some(v)) => v.to_string(),
+        DataValue::UInt16(Some(v)) => v.to_string(),
+        DataValue::UInt32(Some(v)) => v.to_string(),
+        DataValue::UInt64(Some(v)) => v.to_string(),
+        // The debug format keeps the fraction of a whole number, `1.0` is still a float.
+        DataValue::Float32(Some(v)) if v.is_finite() => format!("{:?}", v),
+        DataValue::Float64(Some(v)) if v.is_finite() => format!("{:?}", v),
+        DataValue::String(Some(v)) => {
+            format!("'{}'", String::from_utf8_lossy(v).replace('\'', "''"))
+        }
+        other => {
+            return Err(ErrorCode::UnImplement(format!(
+                "Literal {:?} cannot be converted to SQL",
+                other
+            )))
+        }
+    })
+}
+
+fn data_type_sql(data_type: &DataType) -> Result<&'static str> {
+    Ok(match data_type {
+        DataType::Boolean => "BOOLEAN",
+        DataType::UInt8 => "UINT8",
+        DataType::UInt16 => "UINT16",
+        DataType::UInt32 => "UINT32",
+        DataType::UInt64 => "UINT64",
+        DataType::Int8 => "INT8",
+        DataType::Int16 => "INT16",
+        DataType::Int32 => "INT32",
+        DataType::Int64 => "INT64",
+        DataType::Float32 => "FLOAT32",
+        DataType::Float64 => "FLOAT64",
+        DataType::Date16 => "DATE16",
+        DataType::Date32 => "DATE32",
+        DataType::DateTime32(None) => "DATETIME32",
+        DataType::String => "STRING",
+        other => {
+            return Err(ErrorCode::UnImplement(format!(
+                "Data type {:?} cannot be converted to SQL",
+                other
+            )))
+        }
+    })
+}
+
+// A lower case identifier that is not a keyword is written as is, the others are quoted.
+fn quote_ident(ident: &str) -> String {
+    let is_bare = ident
+        .chars()
+        .enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_lowercase() || (i > 0 && c.is_ascii_digit()));
+
+    match !ident.is_empty() && is_bare && !RESERVED_KEYWORDS.contains(&ident) {
+        true => ident.to_string(),
+        false => format!("\"{}\"", ident.replace('"', "\"\"")),
+    }
+}
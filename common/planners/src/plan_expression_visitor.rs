@@ -14,6 +14,7 @@
 
 use common_exception::Result;
 
+use crate::CastOnError;
 use crate::Expression;
 
 /// Controls how the visitor recursion should proceed.
@@ -33,39 +34,14 @@ pub trait ExpressionVisitor: Sized {
     /// Invoked before any children of `expr` are visisted.
     fn pre_visit(self, expr: &Expression) -> Result<Recursion<Self>>;
 
+    /// Invoked to visit the children of `expr`. The default implementation
+    /// accepts each of [`Expression::children`] in order.
     fn visit(self, predecessor_expr: &Expression) -> Result<Self> {
-        // recursively visit (and cover all expression types)
-        match predecessor_expr {
-            Expression::Alias(_, expr) => expr.accept(self),
-            Expression::BinaryExpression { left, right, .. } => {
-                let mut visitor = self;
-                visitor = left.accept(visitor)?;
-                visitor = right.accept(visitor)?;
-                Ok(visitor)
-            }
-            Expression::UnaryExpression { expr, .. } => {
-                let mut visitor = self;
-                visitor = expr.accept(visitor)?;
-                Ok(visitor)
-            }
-            Expression::ScalarFunction { args, .. } => {
-                let mut visitor = self;
-                for arg in args {
-                    visitor = arg.accept(visitor)?;
-                }
-                Ok(visitor)
-            }
-            Expression::AggregateFunction { args, .. } => {
-                let mut visitor = self;
-                for arg in args {
-                    visitor = arg.accept(visitor)?;
-                }
-                Ok(visitor)
-            }
-            Expression::Cast { expr, .. } => expr.accept(self),
-            Expression::Sort { expr, .. } => expr.accept(self),
-            _ => Ok(self),
+        let mut visitor = self;
+        for child in predecessor_expr.children() {
+            visitor = child.accept(visitor)?;
         }
+        Ok(visitor)
     }
 
     /// Invoked after all children of `expr` are visited. Default
@@ -73,9 +49,61 @@ pub trait ExpressionVisitor: Sized {
     fn post_visit(self, _expr: &Expression) -> Result<Self> {
         Ok(self)
     }
+
+    /// Checked before visiting each expression, once it returns true the walk
+    /// ends without invoking any more hooks. Default implementation returns false.
+    fn is_finished(&self) -> bool {
+        false
+    }
 }
 
 impl Expression {
+    /// The direct children of the expression, in the order they are visited.
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Alias(_, expr) => vec![expr.as_ref()],
+            Expression::BinaryExpression { left, right, .. } => vec![left.as_ref(), right.as_ref()],
+            Expression::UnaryExpression { expr, .. } => vec![expr.as_ref()],
+            Expression::ScalarFunction { args, .. } => args.iter().collect(),
+            Expression::Tuple(exprs) | Expression::Array(exprs) => exprs.iter().collect(),
+            Expression::Struct(fields) => fields.iter().map(|(_, expr)| expr).collect(),
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut children = vec![expr.as_ref(), pattern.as_ref()];
+                children.extend(escape.as_deref());
+                children
+            }
+            Expression::AggregateFunction {
+                args, within_group, ..
+            } => args.iter().chain(within_group).collect(),
+            Expression::WindowFunction {
+                args,
+                partition_by,
+                order_by,
+                ..
+            } => args.iter().chain(partition_by).chain(order_by).collect(),
+            Expression::Cast { expr, .. } => vec![expr.as_ref()],
+            Expression::TryCast { expr, on_error, .. } => match on_error {
+                CastOnError::Null => vec![expr.as_ref()],
+                CastOnError::Default(default) => vec![expr.as_ref(), default.as_ref()],
+            },
+            Expression::Interval { value, .. } => vec![value.as_ref()],
+            Expression::Collate { expr, .. } => vec![expr.as_ref()],
+            Expression::NamedArgument { expr, .. } => vec![expr.as_ref()],
+            Expression::InSubquery { expr, subquery, .. } => vec![expr.as_ref(), subquery.as_ref()],
+            Expression::QuantifiedComparison { left, subquery, .. } => {
+                vec![left.as_ref(), subquery.as_ref()]
+            }
+            Expression::Subscript { base, index } => vec![base.as_ref(), index.as_ref()],
+            Expression::Sort { expr, .. } => vec![expr.as_ref()],
+            _ => vec![],
+        }
+    }
+
     /// Performs a depth first walk of an expression and
     /// its children, calling [`ExpressionVisitor::pre_visit`] and
     /// `visitor.post_visit`.
@@ -104,7 +132,14 @@ impl Expression {
     ///
     /// If an Err result is returned, recursion is stopped immediately
     ///
+    /// If [`ExpressionVisitor::is_finished`] returns true, no more expressions are visited
+    /// and the visitor is returned as is
+    ///
     pub fn accept<V: ExpressionVisitor>(&self, visitor: V) -> Result<V> {
+        if visitor.is_finished() {
+            return Ok(visitor);
+        }
+
         let visitor = match visitor.pre_visit(self)? {
             Recursion::Continue(visitor) => visitor,
             // If the recursion should stop, do not visit children
@@ -112,6 +147,10 @@ impl Expression {
         };
 
         let visitor = visitor.visit(self)?;
+        if visitor.is_finished() {
+            return Ok(visitor);
+        }
+
         visitor.post_visit(self)
     }
 }
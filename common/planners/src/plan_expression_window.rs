@@ -0,0 +1,112 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+
+use crate::Expression;
+
+/// The frame of a window function, such as `ROWS BETWEEN 1 PRECEDING AND CURRENT ROW`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct WindowFrame {
+    pub units: WindowFrameUnits,
+    pub start_bound: WindowFrameBound,
+    pub end_bound: WindowFrameBound,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq)]
+pub enum WindowFrameUnits {
+    Rows,
+    Range,
+    Groups,
+}
+
+/// The `RESPECT NULLS` or `IGNORE NULLS` of a window function, such as
+/// `LAST_VALUE(x) IGNORE NULLS OVER (ORDER BY t)`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq)]
+pub enum NullTreatment {
+    RespectNulls,
+    IgnoreNulls,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub enum WindowFrameBound {
+    /// `CURRENT ROW`
+    CurrentRow,
+    /// `<N> PRECEDING` or `UNBOUNDED PRECEDING`
+    Preceding(Option<u64>),
+    /// `<N> FOLLOWING` or `UNBOUNDED FOLLOWING`
+    Following(Option<u64>),
+    /// `INTERVAL '<N>' <unit> PRECEDING`, only in RANGE frames ordered by a date or datetime
+    IntervalPreceding(Box<Expression>),
+    /// `INTERVAL '<N>' <unit> FOLLOWING`, only in RANGE frames ordered by a date or datetime
+    IntervalFollowing(Box<Expression>),
+}
+
+impl WindowFrame {
+    pub fn has_interval_bound(&self) -> bool {
+        self.start_bound.is_interval() || self.end_bound.is_interval()
+    }
+}
+
+impl WindowFrameBound {
+    pub fn is_interval(&self) -> bool {
+        matches!(
+            self,
+            WindowFrameBound::IntervalPreceding(_) | WindowFrameBound::IntervalFollowing(_)
+        )
+    }
+}
+
+impl fmt::Debug for WindowFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} BETWEEN {:?} AND {:?}",
+            self.units, self.start_bound, self.end_bound
+        )
+    }
+}
+
+impl fmt::Debug for WindowFrameUnits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowFrameUnits::Rows => write!(f, "ROWS"),
+            WindowFrameUnits::Range => write!(f, "RANGE"),
+            WindowFrameUnits::Groups => write!(f, "GROUPS"),
+        }
+    }
+}
+
+impl fmt::Debug for NullTreatment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NullTreatment::RespectNulls => write!(f, "RESPECT NULLS"),
+            NullTreatment::IgnoreNulls => write!(f, "IGNORE NULLS"),
+        }
+    }
+}
+
+impl fmt::Debug for WindowFrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WindowFrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            WindowFrameBound::Preceding(None) => write!(f, "UNBOUNDED PRECEDING"),
+            WindowFrameBound::Preceding(Some(n)) => write!(f, "{} PRECEDING", n),
+            WindowFrameBound::Following(None) => write!(f, "UNBOUNDED FOLLOWING"),
+            WindowFrameBound::Following(Some(n)) => write!(f, "{} FOLLOWING", n),
+            WindowFrameBound::IntervalPreceding(v) => write!(f, "{:?} PRECEDING", v),
+            WindowFrameBound::IntervalFollowing(v) => write!(f, "{:?} FOLLOWING", v),
+        }
+    }
+}
@@ -26,6 +26,7 @@ use crate::plan_subqueries_set::SubQueriesSetPlan;
 use crate::AggregatorFinalPlan;
 use crate::AggregatorPartialPlan;
 use crate::AlterUserPlan;
+use crate::CastOnError;
 use crate::CopyPlan;
 use crate::CreateDatabasePlan;
 use crate::CreateTablePlan;
@@ -146,11 +147,30 @@ pub trait PlanRewriter {
                 distinct,
                 params,
                 args,
+                within_group,
             } => Ok(Expression::AggregateFunction {
                 op: op.clone(),
                 distinct: *distinct,
                 params: params.clone(),
                 args: self.rewrite_exprs(schema, args)?,
+                within_group: self.rewrite_exprs(schema, within_group)?,
+            }),
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => Ok(Expression::WindowFunction {
+                op: op.clone(),
+                params: params.clone(),
+                args: self.rewrite_exprs(schema, args)?,
+                partition_by: self.rewrite_exprs(schema, partition_by)?,
+                order_by: self.rewrite_exprs(schema, order_by)?,
+                window_frame: window_frame.clone(),
+                null_treatment: *null_treatment,
             }),
             Expression::Sort {
                 expr,
@@ -167,9 +187,78 @@ pub trait PlanRewriter {
                 expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
                 data_type: data_type.clone(),
             }),
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => Ok(Expression::TryCast {
+                expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
+                data_type: data_type.clone(),
+                on_error: on_error.try_map(|default| self.rewrite_expr(schema, default))?,
+            }),
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => Ok(Expression::InSubquery {
+                expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
+                subquery: Box::new(self.rewrite_expr(schema, subquery.as_ref())?),
+                negated: *negated,
+            }),
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => Ok(Expression::QuantifiedComparison {
+                op: op.clone(),
+                quantifier: quantifier.clone(),
+                left: Box::new(self.rewrite_expr(schema, left.as_ref())?),
+                subquery: Box::new(self.rewrite_expr(schema, subquery.as_ref())?),
+            }),
+            Expression::Subscript { base, index } => Ok(Expression::Subscript {
+                base: Box::new(self.rewrite_expr(schema, base.as_ref())?),
+                index: Box::new(self.rewrite_expr(schema, index.as_ref())?),
+            }),
+            Expression::Interval { value, unit } => Ok(Expression::Interval {
+                value: Box::new(self.rewrite_expr(schema, value.as_ref())?),
+                unit: unit.clone(),
+            }),
+            Expression::Collate { expr, collation } => Ok(Expression::Collate {
+                expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
+                collation: collation.clone(),
+            }),
+            Expression::NamedArgument { name, expr } => Ok(Expression::NamedArgument {
+                name: name.clone(),
+                expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
+            }),
+            Expression::Tuple(exprs) => Ok(Expression::Tuple(self.rewrite_exprs(schema, exprs)?)),
+            Expression::Array(exprs) => Ok(Expression::Array(self.rewrite_exprs(schema, exprs)?)),
+            Expression::Struct(fields) => Ok(Expression::Struct(
+                fields
+                    .iter()
+                    .map(|(name, expr)| Ok((name.clone(), self.rewrite_expr(schema, expr)?)))
+                    .collect::<Result<Vec<_>>>()?,
+            )),
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => Ok(Expression::Like {
+                op: op.clone(),
+                expr: Box::new(self.rewrite_expr(schema, expr.as_ref())?),
+                pattern: Box::new(self.rewrite_expr(schema, pattern.as_ref())?),
+                escape: match escape {
+                    None => None,
+                    Some(escape) => Some(Box::new(self.rewrite_expr(schema, escape.as_ref())?)),
+                },
+            }),
             Expression::Wildcard => Ok(Expression::Wildcard),
+            Expression::QualifiedWildcard { .. } => Ok(expr.clone()),
             Expression::Column(column_name) => Ok(Expression::Column(column_name.clone())),
             Expression::QualifiedColumn(v) => Ok(Expression::QualifiedColumn(v.clone())),
+            Expression::OuterColumn { .. } => Ok(expr.clone()),
             Expression::Literal {
                 value,
                 column_name,
@@ -494,23 +583,57 @@ impl RewriteHelper {
                 distinct,
                 params,
                 args,
+                within_group,
             } => {
                 let new_args: Result<Vec<Expression>> = args
                     .iter()
                     .map(|v| RewriteHelper::expr_rewrite_alias(v, data))
                     .collect();
 
+                let within_group = within_group
+                    .iter()
+                    .map(|v| RewriteHelper::expr_rewrite_alias(v, data))
+                    .collect::<Result<Vec<_>>>()?;
+
                 match new_args {
                     Ok(v) => Ok(Expression::AggregateFunction {
                         op: op.clone(),
                         distinct: *distinct,
                         params: params.clone(),
                         args: v,
+                        within_group,
                     }),
                     Err(v) => Err(v),
                 }
             }
 
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => {
+                let rewrite_exprs = |exprs: &[Expression], data: &mut QueryAliasData| {
+                    exprs
+                        .iter()
+                        .map(|v| RewriteHelper::expr_rewrite_alias(v, data))
+                        .collect::<Result<Vec<_>>>()
+                };
+
+                Ok(Expression::WindowFunction {
+                    op: op.clone(),
+                    params: params.clone(),
+                    args: rewrite_exprs(args, data)?,
+                    partition_by: rewrite_exprs(partition_by, data)?,
+                    order_by: rewrite_exprs(order_by, data)?,
+                    window_frame: window_frame.clone(),
+                    null_treatment: *null_treatment,
+                })
+            }
+
             Expression::Alias(alias, plan) => {
                 if data.inside_aliases.contains(alias) {
                     return Result::Err(ErrorCode::SyntaxException(format!(
@@ -535,8 +658,122 @@ impl RewriteHelper {
                     data_type: data_type.clone(),
                 })
             }
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => {
+                let new_expr = RewriteHelper::expr_rewrite_alias(expr, data)?;
+                Ok(Expression::TryCast {
+                    expr: Box::new(new_expr),
+                    data_type: data_type.clone(),
+                    on_error: on_error
+                        .try_map(|default| RewriteHelper::expr_rewrite_alias(default, data))?,
+                })
+            }
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => {
+                let new_expr = RewriteHelper::expr_rewrite_alias(expr, data)?;
+                Ok(Expression::InSubquery {
+                    expr: Box::new(new_expr),
+                    subquery: subquery.clone(),
+                    negated: *negated,
+                })
+            }
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => {
+                let new_left = RewriteHelper::expr_rewrite_alias(left, data)?;
+                Ok(Expression::QuantifiedComparison {
+                    op: op.clone(),
+                    quantifier: quantifier.clone(),
+                    left: Box::new(new_left),
+                    subquery: subquery.clone(),
+                })
+            }
+            Expression::Subscript { base, index } => {
+                let new_base = RewriteHelper::expr_rewrite_alias(base, data)?;
+                let new_index = RewriteHelper::expr_rewrite_alias(index, data)?;
+                Ok(Expression::Subscript {
+                    base: Box::new(new_base),
+                    index: Box::new(new_index),
+                })
+            }
+            Expression::Interval { value, unit } => {
+                let new_value = RewriteHelper::expr_rewrite_alias(value, data)?;
+                Ok(Expression::Interval {
+                    value: Box::new(new_value),
+                    unit: unit.clone(),
+                })
+            }
+            Expression::Collate { expr, collation } => {
+                let new_expr = RewriteHelper::expr_rewrite_alias(expr, data)?;
+                Ok(Expression::Collate {
+                    expr: Box::new(new_expr),
+                    collation: collation.clone(),
+                })
+            }
+            Expression::NamedArgument { name, expr } => {
+                let new_expr = RewriteHelper::expr_rewrite_alias(expr, data)?;
+                Ok(Expression::NamedArgument {
+                    name: name.clone(),
+                    expr: Box::new(new_expr),
+                })
+            }
+            Expression::Tuple(exprs) => {
+                let new_exprs = exprs
+                    .iter()
+                    .map(|v| RewriteHelper::expr_rewrite_alias(v, data))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Expression::Tuple(new_exprs))
+            }
+            Expression::Array(exprs) => {
+                let new_exprs = exprs
+                    .iter()
+                    .map(|v| RewriteHelper::expr_rewrite_alias(v, data))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Expression::Array(new_exprs))
+            }
+            Expression::Struct(fields) => {
+                let new_fields = fields
+                    .iter()
+                    .map(|(name, v)| {
+                        Ok((name.clone(), RewriteHelper::expr_rewrite_alias(v, data)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Expression::Struct(new_fields))
+            }
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => {
+                let new_expr = RewriteHelper::expr_rewrite_alias(expr, data)?;
+                let new_pattern = RewriteHelper::expr_rewrite_alias(pattern, data)?;
+                let new_escape = match escape {
+                    None => None,
+                    Some(escape) => {
+                        Some(Box::new(RewriteHelper::expr_rewrite_alias(escape, data)?))
+                    }
+                };
+                Ok(Expression::Like {
+                    op: op.clone(),
+                    expr: Box::new(new_expr),
+                    pattern: Box::new(new_pattern),
+                    escape: new_escape,
+                })
+            }
             Expression::Wildcard
+            | Expression::QualifiedWildcard { .. }
             | Expression::QualifiedColumn(_)
+            | Expression::OuterColumn { .. }
             | Expression::Literal { .. }
             | Expression::Subquery { .. }
             | Expression::ScalarSubquery { .. }
@@ -591,6 +828,7 @@ impl RewriteHelper {
             Expression::Alias(_, expr) => vec![expr.as_ref().clone()],
             Expression::Column(_) => vec![],
             Expression::QualifiedColumn(_) => vec![],
+            Expression::OuterColumn { .. } => vec![],
             Expression::Literal { .. } => vec![],
             Expression::Subquery { .. } => vec![],
             Expression::ScalarSubquery { .. } => vec![],
@@ -601,10 +839,57 @@ impl RewriteHelper {
                 vec![left.as_ref().clone(), right.as_ref().clone()]
             }
             Expression::ScalarFunction { args, .. } => args.clone(),
-            Expression::AggregateFunction { args, .. } => args.clone(),
-            Expression::Wildcard => vec![],
+            Expression::Tuple(exprs) | Expression::Array(exprs) => exprs.clone(),
+            Expression::Struct(fields) => fields.iter().map(|(_, expr)| expr.clone()).collect(),
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut children = vec![expr.as_ref().clone(), pattern.as_ref().clone()];
+                children.extend(escape.as_deref().cloned());
+                children
+            }
+            Expression::AggregateFunction {
+                args, within_group, ..
+            } => {
+                let mut v = args.clone();
+                v.extend(within_group.iter().cloned());
+                v
+            }
+            Expression::WindowFunction {
+                args,
+                partition_by,
+                order_by,
+                ..
+            } => {
+                let mut v = args.clone();
+                v.extend(partition_by.iter().cloned());
+                v.extend(order_by.iter().cloned());
+                v
+            }
+            Expression::Wildcard | Expression::QualifiedWildcard { .. } => vec![],
             Expression::Sort { expr, .. } => vec![expr.as_ref().clone()],
             Expression::Cast { expr, .. } => vec![expr.as_ref().clone()],
+            Expression::TryCast { expr, on_error, .. } => match on_error {
+                CastOnError::Null => vec![expr.as_ref().clone()],
+                CastOnError::Default(default) => {
+                    vec![expr.as_ref().clone(), default.as_ref().clone()]
+                }
+            },
+            Expression::Subscript { base, index } => {
+                vec![base.as_ref().clone(), index.as_ref().clone()]
+            }
+            Expression::Interval { value, .. } => vec![value.as_ref().clone()],
+            Expression::Collate { expr, .. } => vec![expr.as_ref().clone()],
+            Expression::NamedArgument { expr, .. } => vec![expr.as_ref().clone()],
+            Expression::InSubquery { expr, subquery, .. } => {
+                vec![expr.as_ref().clone(), subquery.as_ref().clone()]
+            }
+            Expression::QuantifiedComparison { left, subquery, .. } => {
+                vec![left.as_ref().clone(), subquery.as_ref().clone()]
+            }
         })
     }
 
@@ -614,6 +899,8 @@ impl RewriteHelper {
             Expression::Alias(_, expr) => Self::expression_plan_columns(expr)?,
             Expression::Column(_) => vec![expr.clone()],
             Expression::QualifiedColumn(_) => vec![expr.clone()],
+            // The columns of the enclosing queries are constant in the subquery.
+            Expression::OuterColumn { .. } => vec![],
             Expression::Literal { .. } => vec![],
             Expression::Subquery { .. } => vec![],
             Expression::ScalarSubquery { .. } => vec![],
@@ -624,7 +911,9 @@ impl RewriteHelper {
                 l.append(&mut r);
                 l
             }
-            Expression::ScalarFunction { args, .. } => {
+            Expression::ScalarFunction { args, .. }
+            | Expression::Tuple(args)
+            | Expression::Array(args) => {
                 let mut v = vec![];
                 for arg in args {
                     let mut col = Self::expression_plan_columns(arg)?;
@@ -632,17 +921,71 @@ impl RewriteHelper {
                 }
                 v
             }
-            Expression::AggregateFunction { args, .. } => {
+            Expression::Struct(fields) => {
                 let mut v = vec![];
-                for arg in args {
+                for (_, expr) in fields {
+                    let mut col = Self::expression_plan_columns(expr)?;
+                    v.append(&mut col);
+                }
+                v
+            }
+            Expression::AggregateFunction {
+                args, within_group, ..
+            } => {
+                let mut v = vec![];
+                for arg in args.iter().chain(within_group) {
+                    let mut col = Self::expression_plan_columns(arg)?;
+                    v.append(&mut col);
+                }
+                v
+            }
+            Expression::WindowFunction {
+                args,
+                partition_by,
+                order_by,
+                ..
+            } => {
+                let mut v = vec![];
+                for arg in args.iter().chain(partition_by).chain(order_by) {
                     let mut col = Self::expression_plan_columns(arg)?;
                     v.append(&mut col);
                 }
                 v
             }
-            Expression::Wildcard => vec![],
+            Expression::Wildcard | Expression::QualifiedWildcard { .. } => vec![],
             Expression::Sort { expr, .. } => Self::expression_plan_columns(expr)?,
             Expression::Cast { expr, .. } => Self::expression_plan_columns(expr)?,
+            Expression::TryCast { expr, on_error, .. } => {
+                let mut v = Self::expression_plan_columns(expr)?;
+                if let CastOnError::Default(default) = on_error {
+                    v.append(&mut Self::expression_plan_columns(default)?);
+                }
+                v
+            }
+            Expression::InSubquery { expr, .. } => Self::expression_plan_columns(expr)?,
+            Expression::QuantifiedComparison { left, .. } => Self::expression_plan_columns(left)?,
+            Expression::Subscript { base, index } => {
+                let mut b = Self::expression_plan_columns(base)?;
+                let mut i = Self::expression_plan_columns(index)?;
+                b.append(&mut i);
+                b
+            }
+            Expression::Like {
+                expr,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut v = Self::expression_plan_columns(expr)?;
+                v.append(&mut Self::expression_plan_columns(pattern)?);
+                if let Some(escape) = escape {
+                    v.append(&mut Self::expression_plan_columns(escape)?);
+                }
+                v
+            }
+            Expression::Interval { value, .. } => Self::expression_plan_columns(value)?,
+            Expression::Collate { expr, .. } => Self::expression_plan_columns(expr)?,
+            Expression::NamedArgument { expr, .. } => Self::expression_plan_columns(expr)?,
         })
     }
 
@@ -701,16 +1044,97 @@ impl RewriteHelper {
                 op: op.clone(),
                 args: expressions.to_vec(),
             },
+            Expression::Tuple(_) => Expression::Tuple(expressions.to_vec()),
+            Expression::Array(_) => Expression::Array(expressions.to_vec()),
+            Expression::Struct(fields) => Expression::Struct(
+                fields
+                    .iter()
+                    .zip(expressions)
+                    .map(|((name, _), expr)| (name.clone(), expr.clone()))
+                    .collect(),
+            ),
+            Expression::Like { op, .. } => Expression::Like {
+                op: op.clone(),
+                expr: Box::new(expressions[0].clone()),
+                pattern: Box::new(expressions[1].clone()),
+                escape: expressions.get(2).cloned().map(Box::new),
+            },
             Expression::AggregateFunction {
                 op,
                 distinct,
                 params,
+                args,
                 ..
-            } => Expression::AggregateFunction {
-                op: op.clone(),
-                distinct: *distinct,
-                params: params.clone(),
-                args: expressions.to_vec(),
+            } => {
+                let (args_exprs, within_group_exprs) = expressions.split_at(args.len());
+                Expression::AggregateFunction {
+                    op: op.clone(),
+                    distinct: *distinct,
+                    params: params.clone(),
+                    args: args_exprs.to_vec(),
+                    within_group: within_group_exprs.to_vec(),
+                }
+            }
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                window_frame,
+                null_treatment,
+                ..
+            } => {
+                let (args_exprs, others) = expressions.split_at(args.len());
+                let (partition_exprs, order_exprs) = others.split_at(partition_by.len());
+                Expression::WindowFunction {
+                    op: op.clone(),
+                    params: params.clone(),
+                    args: args_exprs.to_vec(),
+                    partition_by: partition_exprs.to_vec(),
+                    order_by: order_exprs.to_vec(),
+                    window_frame: window_frame.clone(),
+                    null_treatment: *null_treatment,
+                }
+            }
+            Expression::InSubquery { negated, .. } => Expression::InSubquery {
+                expr: Box::new(expressions[0].clone()),
+                subquery: Box::new(expressions[1].clone()),
+                negated: *negated,
+            },
+            Expression::QuantifiedComparison { op, quantifier, .. } => {
+                Expression::QuantifiedComparison {
+                    op: op.clone(),
+                    quantifier: quantifier.clone(),
+                    left: Box::new(expressions[0].clone()),
+                    subquery: Box::new(expressions[1].clone()),
+                }
+            }
+            Expression::Subscript { .. } => Expression::Subscript {
+                base: Box::new(expressions[0].clone()),
+                index: Box::new(expressions[1].clone()),
+            },
+            Expression::Interval { unit, .. } => Expression::Interval {
+                value: Box::new(expressions[0].clone()),
+                unit: unit.clone(),
+            },
+            Expression::Collate { collation, .. } => Expression::Collate {
+                expr: Box::new(expressions[0].clone()),
+                collation: collation.clone(),
+            },
+            Expression::NamedArgument { name, .. } => Expression::NamedArgument {
+                name: name.clone(),
+                expr: Box::new(expressions[0].clone()),
+            },
+            Expression::Sort {
+                asc,
+                nulls_first,
+                origin_expr,
+                ..
+            } => Expression::Sort {
+                expr: Box::new(expressions[0].clone()),
+                asc: *asc,
+                nulls_first: *nulls_first,
+                origin_expr: origin_expr.clone(),
             },
             other => other.clone(),
         }
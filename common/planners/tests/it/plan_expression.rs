@@ -121,3 +121,160 @@ fn test_expression_validate() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn test_expression_visitor() -> Result<()> {
+    struct ColumnCollector {
+        limit: usize,
+        columns: Vec<String>,
+    }
+
+    impl ExpressionVisitor for ColumnCollector {
+        fn pre_visit(self, _expr: &Expression) -> Result<Recursion<Self>> {
+            Ok(Recursion::Continue(self))
+        }
+
+        fn post_visit(mut self, expr: &Expression) -> Result<Self> {
+            if let Expression::Column(name) = expr {
+                self.columns.push(name.clone());
+            }
+            Ok(self)
+        }
+
+        fn is_finished(&self) -> bool {
+            self.columns.len() >= self.limit
+        }
+    }
+
+    // not((a + 1) = b) and (c > 1)
+    let expression = not(add(col("a"), lit(1)).eq(col("b"))).and(col("c").gt(lit(1)));
+
+    let visitor = expression.accept(ColumnCollector {
+        limit: usize::MAX,
+        columns: vec![],
+    })?;
+    assert_eq!(vec!["a", "b", "c"], visitor.columns);
+
+    let visitor = expression.accept(ColumnCollector {
+        limit: 2,
+        columns: vec![],
+    })?;
+    assert_eq!(vec!["a", "b"], visitor.columns);
+
+    Ok(())
+}
+
+#[test]
+fn test_expression_rewrite_unary() -> Result<()> {
+    struct ColumnRenamer;
+
+    impl ExprRewriter for ColumnRenamer {
+        fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+            match expr {
+                Expression::Column(name) => Ok(col(&format!("t.{}", name))),
+                expr => Ok(expr),
+            }
+        }
+    }
+
+    let expression = not(col("a").eq(lit(1)));
+    let expression = expression.rewrite(&mut ColumnRenamer)?;
+    assert_eq!("(not (t.a = 1))", format!("{:?}", expression));
+
+    Ok(())
+}
+
+#[test]
+fn test_expression_to_sql() -> Result<()> {
+    let count_distinct = Expression::AggregateFunction {
+        op: "count".to_string(),
+        distinct: true,
+        params: vec![],
+        args: vec![col("t.number")],
+        within_group: vec![],
+    };
+
+    let tests = vec![
+        (col("number"), "number"),
+        (col("t.number"), "t.number"),
+        (col("Number"), "\"Number\""),
+        (col("order"), "\"order\""),
+        (col("a\"b"), "\"a\"\"b\""),
+        (lit(1u8), "1"),
+        (lit(1.0f64), "1.0"),
+        (lit("it's".as_bytes()), "'it''s'"),
+        (not(col("a").eq(lit(1))), "(not (a = 1))"),
+        (
+            Expression::Alias("Total".to_string(), Box::new(sum(col("a")))),
+            "sum(a) AS \"Total\"",
+        ),
+        (
+            Expression::Cast {
+                expr: Box::new(col("a")),
+                data_type: DataType::Int32,
+            },
+            "CAST(a AS INT32)",
+        ),
+        (count_distinct, "count(DISTINCT t.number)"),
+        (
+            Expression::Like {
+                op: "LIKE".to_string(),
+                expr: Box::new(col("a")),
+                pattern: Box::new(lit("a!_%".as_bytes())),
+                escape: Some(Box::new(lit("!".as_bytes()))),
+            },
+            "(a LIKE 'a!_%' ESCAPE '!')",
+        ),
+    ];
+
+    for (expression, expect) in tests {
+        assert_eq!(expect, expression.to_sql()?);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_window_function_data_field() -> Result<()> {
+    let schema = DataSchemaRefExt::create(vec![DataField::new("a", DataType::Int32, false)]);
+    let window = |op: &str, args: Vec<Expression>| Expression::WindowFunction {
+        op: op.to_string(),
+        params: vec![],
+        args,
+        partition_by: vec![],
+        order_by: vec![],
+        window_frame: None,
+        null_treatment: None,
+    };
+
+    let tests = vec![
+        (window("row_number", vec![]), DataType::UInt64, false),
+        (window("ntile", vec![lit(4u64)]), DataType::UInt64, false),
+        (window("percent_rank", vec![]), DataType::Float64, false),
+        (window("cume_dist", vec![]), DataType::Float64, false),
+        (
+            window("lag", vec![col("a"), lit(1u64)]),
+            DataType::Int32,
+            true,
+        ),
+        (window("LEAD", vec![col("a")]), DataType::Int32, true),
+        (window("first_value", vec![col("a")]), DataType::Int32, true),
+        (window("last_value", vec![col("a")]), DataType::Int32, true),
+        (
+            window("nth_value", vec![col("a"), lit(2u64)]),
+            DataType::Int32,
+            true,
+        ),
+    ];
+
+    for (expression, data_type, nullable) in tests {
+        let field = expression.to_data_field(&schema)?;
+        assert_eq!(&data_type, field.data_type(), "{:?}", expression);
+        assert_eq!(nullable, field.is_nullable(), "{:?}", expression);
+    }
+
+    let err = window("lag", vec![]).to_data_type(&schema).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::NumberArgumentsNotMatchCode());
+
+    Ok(())
+}
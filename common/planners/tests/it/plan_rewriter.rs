@@ -177,6 +177,40 @@ fn test_rewrite_expressions_plan() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_rewrite_within_group_expressions() -> Result<()> {
+    use pretty_assertions::assert_eq;
+
+    // The sort keys remember the expression they are written as.
+    let aggregate = |arg: Expression, key: Expression| Expression::AggregateFunction {
+        op: "listagg".to_string(),
+        distinct: false,
+        params: vec![],
+        args: vec![arg],
+        within_group: vec![Expression::Sort {
+            expr: Box::new(key),
+            asc: true,
+            nulls_first: true,
+            origin_expr: Box::new(col("y")),
+        }],
+    };
+
+    let columns = RewriteHelper::expression_plan_columns(&aggregate(col("x"), col("y")))?;
+    assert_eq!(vec![col("x"), col("y")], columns);
+
+    let mut aliases = HashMap::new();
+    aliases.insert("x".to_string(), col("a"));
+    aliases.insert("y".to_string(), col("b"));
+    let exprs = vec![aggregate(col("x"), col("y"))];
+    let actual = RewriteHelper::rewrite_alias_exprs(&aliases, &exprs)?;
+    assert_eq!(vec![aggregate(col("a"), col("b"))], actual);
+
+    let actual = RewriteHelper::rewrite_column_expr(&exprs[0], "y", "z")?;
+    assert_eq!(aggregate(col("x"), col("z")), actual);
+
+    Ok(())
+}
+
 struct DefaultRewriter;
 
 impl PlanRewriter for DefaultRewriter {
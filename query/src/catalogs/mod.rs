@@ -28,6 +28,7 @@ pub use database::Database;
 pub use table::Table;
 pub use table::TablePtr;
 pub use table::ToReadDataSourcePlan;
+pub use table::PSEUDO_COLUMN_NAMES;
 pub use table_function::TableFunction;
 pub use table_id_ranges::*;
 pub use table_memory_meta::InMemoryMetas;
@@ -16,6 +16,7 @@ use std::any::Any;
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
+use common_datavalues::DataField;
 use common_datavalues::DataSchemaRef;
 use common_exception::ErrorCode;
 use common_exception::Result;
@@ -33,6 +34,9 @@ use common_streams::SendableDataBlockStream;
 
 use crate::sessions::QueryContext;
 
+/// The names of all the pseudo columns that a table may provide.
+pub const PSEUDO_COLUMN_NAMES: &[&str] = &["_row_id", "_segment"];
+
 #[async_trait::async_trait]
 pub trait Table: Sync + Send {
     fn name(&self) -> &str {
@@ -59,6 +63,12 @@ pub trait Table: Sync + Send {
 
     fn get_table_info(&self) -> &TableInfo;
 
+    /// The pseudo columns, such as `_row_id`, which are only resolved if referenced by name,
+    /// `SELECT *` never expands them. Each one must be named in `PSEUDO_COLUMN_NAMES`.
+    fn pseudo_columns(&self) -> Vec<DataField> {
+        vec![]
+    }
+
     /// whether column prune(projection) can help in table read
     fn benefit_column_prune(&self) -> bool {
         false
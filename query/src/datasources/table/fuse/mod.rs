@@ -28,7 +28,7 @@ mod read_plan_test;
 #[cfg(test)]
 mod table_test;
 #[cfg(test)]
-mod table_test_fixture;
+pub(crate) mod table_test_fixture;
 
 pub(crate) use io::*;
 pub(crate) use meta::*;
@@ -17,6 +17,8 @@ use std::any::Any;
 use std::sync::Arc;
 
 use common_dal::read_obj;
+use common_datavalues::DataField;
+use common_datavalues::DataType;
 use common_exception::Result;
 use common_meta_types::TableInfo;
 use common_planners::Extras;
@@ -57,6 +59,13 @@ impl Table for FuseTable {
         &self.table_info
     }
 
+    fn pseudo_columns(&self) -> Vec<DataField> {
+        vec![
+            DataField::new("_row_id", DataType::UInt64, false),
+            DataField::new("_segment", DataType::String, false),
+        ]
+    }
+
     fn benefit_column_prune(&self) -> bool {
         true
     }
@@ -78,3 +78,58 @@ async fn test_select_interpreter() -> Result<()> {
     }
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_select_interpreter_trim() -> Result<()> {
+    common_tracing::init_default_ut_tracing();
+    let ctx = crate::tests::try_create_context()?;
+
+    static TEST_QUERY: &str = "select trim('  a  ') as a, trim(both 'x' from 'xbx') as b, \
+        trim(leading 'x' from 'xcx') as c, trim(trailing 'x' from 'xdx') as d";
+    if let PlanNode::Select(plan) = PlanParser::parse(TEST_QUERY, ctx.clone()).await? {
+        let executor = SelectInterpreter::try_create(ctx.clone(), plan)?;
+        let stream = executor.execute(None).await?;
+        let result = stream.try_collect::<Vec<_>>().await?;
+
+        let expected = vec![
+            "+---+---+----+----+",
+            "| a | b | c  | d  |",
+            "+---+---+----+----+",
+            "| a | b | cx | xd |",
+            "+---+---+----+----+",
+        ];
+        common_datablocks::assert_blocks_sorted_eq(expected, result.as_slice());
+    } else {
+        panic!()
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+async fn test_select_interpreter_extract() -> Result<()> {
+    common_tracing::init_default_ut_tracing();
+    let ctx = crate::tests::try_create_context()?;
+
+    // The fields the parser has no keyword for are passed as strings.
+    static TEST_QUERY: &str = "select extract(year from toDate(18902)) as y, \
+        extract('quarter', toDate(18902)) as q, extract('week', toDate(18902)) as w, \
+        extract('dow', toDate(18902)) as d, extract(hour from toDateTime(1633173324)) as h, \
+        extract('epoch', toDateTime(1633173324)) as e";
+    if let PlanNode::Select(plan) = PlanParser::parse(TEST_QUERY, ctx.clone()).await? {
+        let executor = SelectInterpreter::try_create(ctx.clone(), plan)?;
+        let stream = executor.execute(None).await?;
+        let result = stream.try_collect::<Vec<_>>().await?;
+
+        let expected = vec![
+            "+------+---+----+---+----+------------+",
+            "| y    | q | w  | d | h  | e          |",
+            "+------+---+----+---+----+------------+",
+            "| 2021 | 4 | 39 | 6 | 11 | 1633173324 |",
+            "+------+---+----+---+----+------------+",
+        ];
+        common_datablocks::assert_blocks_sorted_eq(expected, result.as_slice());
+    } else {
+        panic!()
+    }
+    Ok(())
+}
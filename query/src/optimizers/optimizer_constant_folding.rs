@@ -297,11 +297,16 @@ impl PlanRewriter for ConstantFoldingImpl {
                 distinct,
                 params,
                 args,
+                within_group,
             } => {
                 let args = args
                     .iter()
                     .map(|expr| Self::rewrite_expr(self, schema, expr))
                     .collect::<Result<Vec<_>>>()?;
+                let within_group = within_group
+                    .iter()
+                    .map(|expr| Self::rewrite_expr(self, schema, expr))
+                    .collect::<Result<Vec<_>>>()?;
 
                 let op = op.clone();
                 let distinct = *distinct;
@@ -311,6 +316,7 @@ impl PlanRewriter for ConstantFoldingImpl {
                     distinct,
                     params,
                     args,
+                    within_group,
                 })
             }
             _ => Ok(origin.clone()),
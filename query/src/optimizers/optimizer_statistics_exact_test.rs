@@ -61,6 +61,7 @@ mod tests {
             distinct: false,
             params: vec![],
             args: vec![Expression::create_literal(DataValue::UInt64(Some(0)))],
+            within_group: vec![],
         };
 
         let plan = PlanBuilder::from(&source_plan)
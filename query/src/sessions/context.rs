@@ -50,6 +50,7 @@ use crate::configs::AzureStorageBlobConfig;
 use crate::configs::Config;
 use crate::servers::http::v1::query::HttpQueryHandle;
 use crate::sessions::QueryContextShared;
+use crate::sessions::RewritePhaseTiming;
 use crate::sessions::SessionManager;
 use crate::sessions::Settings;
 
@@ -171,6 +172,24 @@ impl QueryContext {
         self.shared.attach_query_plan(query_plan);
     }
 
+    /// Collect a non-fatal diagnostic raised while analyzing the query.
+    pub fn push_warning(&self, warning: String) {
+        self.shared.push_warning(warning);
+    }
+
+    pub fn get_warnings(&self) -> Vec<String> {
+        self.shared.get_warnings()
+    }
+
+    /// Collect the duration of a rewrite phase, the phases of the subqueries are nested by depth.
+    pub fn push_rewrite_timing(&self, timing: RewritePhaseTiming) {
+        self.shared.push_rewrite_timing(timing);
+    }
+
+    pub fn get_rewrite_timings(&self) -> Vec<RewritePhaseTiming> {
+        self.shared.get_rewrite_timings()
+    }
+
     pub fn get_cluster(&self) -> Arc<Cluster> {
         self.shared.get_cluster()
     }
@@ -34,6 +34,7 @@ use crate::catalogs::Table;
 use crate::clusters::Cluster;
 use crate::configs::Config;
 use crate::servers::http::v1::query::HttpQueryHandle;
+use crate::sessions::RewritePhaseTiming;
 use crate::sessions::Session;
 use crate::sessions::Settings;
 
@@ -63,6 +64,8 @@ pub struct QueryContextShared {
     pub(in crate::sessions) running_plan: Arc<RwLock<Option<PlanNode>>>,
     pub(in crate::sessions) tables_refs: Arc<Mutex<HashMap<DatabaseAndTable, Arc<dyn Table>>>>,
     pub(in crate::sessions) dal_ctx: Arc<DalContext>,
+    pub(in crate::sessions) warnings: Arc<RwLock<Vec<String>>>,
+    pub(in crate::sessions) rewrite_timings: Arc<RwLock<Vec<RewritePhaseTiming>>>,
 }
 
 impl QueryContextShared {
@@ -86,6 +89,8 @@ impl QueryContextShared {
             running_plan: Arc::new(RwLock::new(None)),
             tables_refs: Arc::new(Mutex::new(HashMap::new())),
             dal_ctx: Arc::new(Default::default()),
+            warnings: Arc::new(RwLock::new(Vec::new())),
+            rewrite_timings: Arc::new(RwLock::new(Vec::new())),
         })
     }
 
@@ -192,6 +197,22 @@ impl QueryContextShared {
         *running_plan = Some(plan.clone());
     }
 
+    pub fn push_warning(&self, warning: String) {
+        self.warnings.write().push(warning);
+    }
+
+    pub fn get_warnings(&self) -> Vec<String> {
+        self.warnings.read().clone()
+    }
+
+    pub fn push_rewrite_timing(&self, timing: RewritePhaseTiming) {
+        self.rewrite_timings.write().push(timing);
+    }
+
+    pub fn get_rewrite_timings(&self) -> Vec<RewritePhaseTiming> {
+        self.rewrite_timings.read().clone()
+    }
+
     pub fn add_source_abort_handle(&self, handle: AbortHandle) {
         let mut sources_abort_handle = self.sources_abort_handle.write();
         sources_abort_handle.push(handle);
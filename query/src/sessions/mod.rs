@@ -21,6 +21,7 @@ mod macros;
 mod context;
 mod context_shared;
 mod metrics;
+mod rewrite_timing;
 mod session;
 mod session_info;
 mod session_ref;
@@ -33,6 +34,7 @@ mod settings;
 
 pub use context::QueryContext;
 pub use context_shared::QueryContextShared;
+pub use rewrite_timing::RewritePhaseTiming;
 pub use session::Session;
 pub use session_info::ProcessInfo;
 pub use session_ref::SessionRef;
@@ -0,0 +1,38 @@
+// Copyright 2021 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fmt;
+use std::time::Duration;
+
+/// The duration of a rewrite phase, recorded if `enable_analyze_profiling` is set.
+/// `depth` is the nesting level of the rewritten query, 0 for the outermost query.
+#[derive(Clone, Debug)]
+pub struct RewritePhaseTiming {
+    pub depth: usize,
+    pub phase: &'static str,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for RewritePhaseTiming {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}{} rewrite: {:.1}ms",
+            "",
+            self.phase,
+            self.elapsed.as_secs_f64() * 1000.0,
+            indent = self.depth * 2
+        )
+    }
+}
@@ -31,7 +31,25 @@ impl Settings {
         ("max_threads", u64, 16, "The maximum number of threads to execute the request. By default, it is determined automatically."),
         ("flight_client_timeout", u64, 60, "Max duration the flight client request is allowed to take in seconds. By default, it is 60 seconds"),
         ("min_distributed_rows", u64, 100000000, "Minimum distributed read rows. In cluster mode, when read rows exceeds this value, the local table converted to distributed query."),
-        ("min_distributed_bytes", u64, 500 * 1024 * 1024, "Minimum distributed read bytes. In cluster mode, when read bytes exceeds this value, the local table converted to distributed query.")
+        ("min_distributed_bytes", u64, 500 * 1024 * 1024, "Minimum distributed read bytes. In cluster mode, when read bytes exceeds this value, the local table converted to distributed query."),
+        ("enable_lateral_column_aliases", u64, 0, "Allow a projection item to reference the aliases of the items before it, such as SELECT a + 1 AS x, x * 2 AS y. An alias shadows the column of the same name. By default, it is 0 (disabled)."),
+        ("strict_mode", u64, 0, "Turn query analysis warnings into errors. By default, it is 0 (disabled)."),
+        ("enable_semi_join_hint", u64, 0, "Annotate the uncorrelated single-column IN subqueries as semi-join candidates. By default, it is 0 (disabled)."),
+        ("dedup_projection", u64, 0, "Remove the later duplicates of the same projection expression. By default, it is 0 (disabled)."),
+        ("merge_identical_subqueries", u64, 0, "Tag the structurally identical uncorrelated subqueries of a query with a shared id. By default, it is 0 (disabled)."),
+        ("forbid_implicit_cross_join", u64, 0, "Reject the tables in a comma separated FROM list that are not connected by any predicate. By default, it is 0 (disabled)."),
+        ("enable_constant_folding", u64, 0, "Fold the unary and binary expressions of literals into a single literal. By default, it is 0 (disabled)."),
+        ("enable_analyze_profiling", u64, 0, "Record the duration of each rewrite phase of the query analysis. By default, it is 0 (disabled)."),
+        ("rewrite_null_comparison", u64, 0, "Rewrite `expr = NULL` and `expr <> NULL` to IS NULL and IS NOT NULL for the MySQL compatibility. By default, it is 0 (disabled)."),
+        ("analyze_error_mode", u64, 0, "How the query analysis reports the errors of the clauses: 0 fails on the first one, 1 reports the earliest clause in the query text, 2 reports all of them. By default, it is 0."),
+        ("strict_type_comparison", u64, 0, "Disable the implicit casts of the comparison operands, the comparisons of mismatched types are reported instead. By default, it is 0 (disabled)."),
+        ("enable_order_by_positions", u64, 1, "Resolve the unsigned integer literals of ORDER BY, including the ORDER BY of the window functions, as the positions in the select list. By default, it is 1 (enabled)."),
+        ("max_expression_depth", u64, 1000, "The maximum nesting depth of the expressions and the subqueries in the query analysis, a deeper query is rejected. By default, it is 1000."),
+        ("max_subquery_depth", u64, 64, "The maximum nesting depth of the subqueries in the query analysis, a deeper query is rejected. By default, it is 64."),
+        ("max_joined_tables", u64, 256, "The maximum number of tables joined in one query. By default, it is 256."),
+        ("enable_pseudo_columns", u64, 0, "Resolve the pseudo columns of the tables, such as _row_id and _segment, if they are referenced by name. SELECT * never expands them. By default, it is 0 (disabled)."),
+        ("always_qualify_columns", u64, 0, "Qualify every resolved column with its table, even if its name is not ambiguous. By default, it is 0 (disabled), only the ambiguous columns are qualified."),
+        ("warn_unused_aliases", u64, 0, "Warn about the projection and table aliases nothing in the query references, the query is resolved as usual. By default, it is 0 (disabled).")
     }
 
     pub fn try_create() -> Result<Arc<Settings>> {
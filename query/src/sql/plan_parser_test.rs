@@ -178,10 +178,28 @@ fn test_plan_parser() -> Result<()> {
             error: "",
         },
         Test {
-            name: "unimplemented-cte",
-            sql: "with t as ( select sum(number) n from numbers_mt(1000) )select * from t",
+            name: "unimplemented-recursive-cte",
+            sql: "with recursive t as ( select sum(number) n from numbers_mt(1000) )select * from t",
             expect: "",
-            error: "Code: 5, displayText = sql parser error: CTE is not yet implement.",
+            error: "Code: 5, displayText = sql parser error: Recursive CTE is not yet implement.",
+        },
+        Test {
+            name: "top-conflicts-with-limit",
+            sql: "select top 2 number from numbers(10) limit 3",
+            expect: "",
+            error: "Code: 5, displayText = sql parser error: TOP conflicts with LIMIT.",
+        },
+        Test {
+            name: "fetch-with-ties-without-order-by",
+            sql: "select number from numbers(10) fetch first 2 rows with ties",
+            expect: "",
+            error: "Code: 5, displayText = sql parser error: WITH TIES requires ORDER BY.",
+        },
+        Test {
+            name: "unimplemented-fetch-with-ties",
+            sql: "select number from numbers(10) order by number fetch first 2 rows with ties",
+            expect: "",
+            error: "Code: 2, displayText = WITH TIES is not yet supported in query plan.",
         },
         Test {
             name: "kleene-logic-null",
@@ -37,10 +37,12 @@ impl SQLCommon {
             SQLDataType::Boolean => Ok(DataType::Boolean),
             SQLDataType::Date => Ok(DataType::Date16),
             SQLDataType::Timestamp => Ok(DataType::DateTime32(None)),
+            SQLDataType::Uuid => Ok(DataType::String),
 
             //custom types for databend
             // Custom(ObjectName([Ident { value: "uint8", quote_style: None }])
-            SQLDataType::Custom(obj) if !obj.0.is_empty() => {
+            // A qualified name such as `t.uint8` is a column reference, not a type.
+            SQLDataType::Custom(obj) if obj.0.len() == 1 => {
                 match obj.0[0].value.to_uppercase().as_str() {
                     "UINT8" => Ok(DataType::UInt8),
                     "UINT16" => Ok(DataType::UInt16),
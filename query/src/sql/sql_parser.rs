@@ -104,6 +104,7 @@ impl<'a> DfParser<'a> {
     pub fn parse_sql(sql: &str) -> Result<(Vec<DfStatement>, Vec<DfHint>), ErrorCode> {
         let dialect = &GenericDialect {};
         let start = Instant::now();
+        DfParser::check_null_treatment(sql, dialect)?;
         let result = DfParser::parse_sql_with_dialect(sql, dialect)?;
         histogram!(super::metrics::METRIC_PARSER_USEDTIME, start.elapsed());
         Ok(result)
@@ -152,6 +153,40 @@ impl<'a> DfParser<'a> {
         Ok((stmts, hints))
     }
 
+    /// The sqlparser doesn't parse the RESPECT NULLS and IGNORE NULLS of the window functions,
+    /// report them as unimplemented instead of the syntax error they end up with.
+    fn check_null_treatment(sql: &str, dialect: &dyn Dialect) -> Result<(), ErrorCode> {
+        let tokens = match Tokenizer::new(dialect, sql).tokenize() {
+            Ok(tokens) => tokens,
+            // The parser reports it
+            Err(_) => return Ok(()),
+        };
+
+        let words = tokens
+            .iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect::<Vec<_>>();
+
+        for pair in words.windows(2) {
+            if let (Token::Word(first), Token::Word(second)) = (pair[0], pair[1]) {
+                let treatment = first.quote_style.is_none()
+                    && second.quote_style.is_none()
+                    && second.value.eq_ignore_ascii_case("NULLS")
+                    && (first.value.eq_ignore_ascii_case("RESPECT")
+                        || first.value.eq_ignore_ascii_case("IGNORE"));
+
+                if treatment {
+                    return Err(ErrorCode::UnImplement(format!(
+                        "{} NULLS of window functions is not yet supported",
+                        first.value.to_uppercase()
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Report unexpected token
     fn expected<T>(&self, expected: &str, found: Token) -> Result<T, ParserError> {
         parser_err!(format!("Expected {}, found: {}", expected, found))
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use common_exception::ErrorCode;
 use common_exception::Result;
 use common_meta_types::AuthType;
 use common_meta_types::UserPrivilege;
@@ -344,6 +345,27 @@ fn hint_test() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn null_treatment_test() -> Result<()> {
+    expect_parse_err(
+        "SELECT LAST_VALUE(number) IGNORE NULLS OVER (ORDER BY number) FROM numbers(10)",
+        String::from("IGNORE NULLS of window functions is not yet supported"),
+    )?;
+
+    expect_parse_err(
+        "SELECT lag(number) respect nulls OVER (ORDER BY number) FROM numbers(10)",
+        String::from("RESPECT NULLS of window functions is not yet supported"),
+    )?;
+
+    let err = DfParser::parse_sql("SELECT first_value(a) IGNORE NULLS OVER () FROM t").unwrap_err();
+    assert_eq!(err.code(), ErrorCode::UnImplementCode());
+
+    // NULLS of the ORDER BY is not the null treatment.
+    DfParser::parse_sql("SELECT number FROM numbers(10) ORDER BY number DESC NULLS FIRST")?;
+
+    Ok(())
+}
+
 #[test]
 fn copy_test() -> Result<()> {
     let ident = Ident::new("test_csv");
@@ -15,23 +15,37 @@
 use std::convert::TryFrom;
 use std::sync::Arc;
 
+use common_datavalues::DataValue;
 use common_exception::ErrorCode;
 use common_exception::Result;
 use common_functions::aggregates::AggregateFunctionFactory;
+use common_planners::CastOnError;
 use common_planners::Expression;
+use common_planners::WindowFrame;
+use common_planners::WindowFrameBound;
+use common_planners::WindowFrameUnits;
 use sqlparser::ast::BinaryOperator;
 use sqlparser::ast::DataType;
+use sqlparser::ast::DateTimeField;
 use sqlparser::ast::Expr;
 use sqlparser::ast::Function;
 use sqlparser::ast::FunctionArg;
 use sqlparser::ast::Ident;
+use sqlparser::ast::ListAgg;
+use sqlparser::ast::ObjectName;
 use sqlparser::ast::Query;
+use sqlparser::ast::TrimWhereField;
 use sqlparser::ast::UnaryOperator;
 use sqlparser::ast::Value;
+use sqlparser::ast::WindowFrame as SqlparserWindowFrame;
+use sqlparser::ast::WindowFrameBound as SqlparserWindowFrameBound;
+use sqlparser::ast::WindowFrameUnits as SqlparserWindowFrameUnits;
+use sqlparser::ast::WindowSpec;
 
 use crate::functions::ContextFunction;
 use crate::sessions::QueryContext;
 use crate::sql::statements::analyzer_value_expr::ValueExprAnalyzer;
+use crate::sql::statements::query::JoinedSchema;
 use crate::sql::statements::AnalyzableStatement;
 use crate::sql::statements::AnalyzedResult;
 use crate::sql::statements::DfQueryStatement;
@@ -40,11 +54,32 @@ use crate::sql::SQLCommon;
 
 pub struct ExpressionAnalyzer {
     context: Arc<QueryContext>,
+    // Scopes of the enclosing queries, visible to the subqueries.
+    outer_scopes: Vec<JoinedSchema>,
 }
 
 impl ExpressionAnalyzer {
     pub fn create(context: Arc<QueryContext>) -> ExpressionAnalyzer {
-        ExpressionAnalyzer { context }
+        Self::create_with_outer_scopes(context, vec![])
+    }
+
+    pub fn create_with_outer_scopes(
+        context: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+    ) -> ExpressionAnalyzer {
+        ExpressionAnalyzer {
+            context,
+            outer_scopes,
+        }
+    }
+
+    /// The functions that the query analysis resolves itself instead of the function factory,
+    /// `extract` is lowered to the date functions and `grouping` is checked against the
+    /// grouping keys.
+    pub fn is_analyzer_function(name: &str) -> bool {
+        ["extract", "grouping"]
+            .iter()
+            .any(|function| function.eq_ignore_ascii_case(name))
     }
 
     pub async fn analyze(&self, expr: &Expr) -> Result<Expression> {
@@ -57,11 +92,28 @@ impl ExpressionAnalyzer {
                 ExprRPNItem::Identifier(v) => self.analyze_identifier(v, &mut stack)?,
                 ExprRPNItem::QualifiedIdentifier(v) => self.analyze_identifiers(v, &mut stack)?,
                 ExprRPNItem::Function(v) => self.analyze_function(v, &mut stack)?,
+                ExprRPNItem::WindowFunction(v) => self.analyze_window_function(v, &mut stack)?,
+                ExprRPNItem::ListAgg(v) => self.analyze_list_agg(v, &mut stack)?,
                 ExprRPNItem::Wildcard => self.analyze_wildcard(&mut stack)?,
                 ExprRPNItem::Exists(v) => self.analyze_exists(v, &mut stack).await?,
                 ExprRPNItem::Subquery(v) => self.analyze_scalar_subquery(v, &mut stack).await?,
-                ExprRPNItem::Cast(v) => self.analyze_cast(v, &mut stack)?,
+                ExprRPNItem::InSubquery(v, negated) => {
+                    self.analyze_in_subquery(v, *negated, &mut stack).await?
+                }
+                ExprRPNItem::Cast(v, try_cast, constraint) => {
+                    self.analyze_cast(v, *try_cast, constraint, &mut stack)?
+                }
                 ExprRPNItem::Between(negated) => self.analyze_between(*negated, &mut stack)?,
+                ExprRPNItem::Subscript => self.analyze_subscript(&mut stack)?,
+                ExprRPNItem::Collate(collation) => self.analyze_collate(collation, &mut stack)?,
+                ExprRPNItem::Tuple(len) => self.analyze_tuple(*len, &mut stack)?,
+                ExprRPNItem::InList(len, negated) => {
+                    self.analyze_in_list(*len, *negated, &mut stack)?
+                }
+                ExprRPNItem::NamedArgument(name) => {
+                    self.analyze_named_argument(name, &mut stack)?
+                }
+                ExprRPNItem::Case(v) => self.analyze_case(v, &mut stack)?,
             }
         }
 
@@ -104,6 +156,120 @@ impl ExpressionAnalyzer {
         Ok(())
     }
 
+    fn analyze_window_function(
+        &self,
+        info: &WindowFunctionExprInfo,
+        args: &mut Vec<Expression>,
+    ) -> Result<()> {
+        let mut order_by = Vec::with_capacity(info.order_by.len());
+        for (asc, nulls_first) in info.order_by.iter().rev() {
+            match args.pop() {
+                None => {
+                    return Err(ErrorCode::LogicalError("It's a bug."));
+                }
+                Some(expr) => order_by.insert(0, Expression::Sort {
+                    expr: Box::new(expr.clone()),
+                    asc: *asc,
+                    nulls_first: *nulls_first,
+                    origin_expr: Box::new(expr),
+                }),
+            }
+        }
+
+        let mut partition_by = Vec::with_capacity(info.partition_by_count);
+        for _index in 0..info.partition_by_count {
+            match args.pop() {
+                None => {
+                    return Err(ErrorCode::LogicalError("It's a bug."));
+                }
+                Some(expr) => partition_by.insert(0, expr),
+            }
+        }
+
+        let mut arguments = Vec::with_capacity(info.args_count);
+        for _index in 0..info.args_count {
+            match args.pop() {
+                None => {
+                    return Err(ErrorCode::LogicalError("It's a bug."));
+                }
+                Some(arg) => arguments.insert(0, arg),
+            }
+        }
+
+        let mut parameters = Vec::with_capacity(info.parameters.len());
+        for parameter in &info.parameters {
+            match ValueExprAnalyzer::analyze(parameter)? {
+                Expression::Literal { value, .. } => parameters.push(value),
+                expr => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Unsupported value expression: {:?}, must be datavalue",
+                        expr
+                    )));
+                }
+            };
+        }
+
+        args.push(Expression::WindowFunction {
+            op: info.name.clone(),
+            params: parameters,
+            args: arguments,
+            partition_by,
+            order_by,
+            window_frame: info.window_frame.clone(),
+            // RESPECT NULLS and IGNORE NULLS are rejected by the DfParser
+            null_treatment: None,
+        });
+        Ok(())
+    }
+
+    // LISTAGG is lowered into an aggregate function, whose parameter is the separator,
+    // whose argument is the value and whose WITHIN GROUP are the sort keys.
+    fn analyze_list_agg(&self, info: &ListAggExprInfo, args: &mut Vec<Expression>) -> Result<()> {
+        let mut within_group = Vec::with_capacity(info.order_by.len());
+        for (asc, nulls_first) in info.order_by.iter().rev() {
+            match args.pop() {
+                None => {
+                    return Err(ErrorCode::LogicalError("It's a bug."));
+                }
+                Some(expr) => within_group.insert(0, Expression::Sort {
+                    expr: Box::new(expr.clone()),
+                    asc: *asc,
+                    nulls_first: *nulls_first,
+                    origin_expr: Box::new(expr),
+                }),
+            }
+        }
+
+        let arguments = match args.pop() {
+            None => {
+                return Err(ErrorCode::LogicalError("It's a bug."));
+            }
+            Some(expr) => vec![expr],
+        };
+
+        let mut parameters = Vec::with_capacity(1);
+        if let Some(separator) = &info.separator {
+            match ValueExprAnalyzer::analyze(separator)? {
+                Expression::Literal { value, .. } => parameters.push(value),
+                expr => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Unsupported value expression: {:?}, must be datavalue",
+                        expr
+                    )));
+                }
+            };
+        }
+
+        args.push(Expression::AggregateFunction {
+            op: String::from("listagg"),
+            distinct: info.distinct,
+            params: parameters,
+            args: arguments,
+            within_group,
+        });
+        Ok(())
+    }
+
     fn unary_function(info: &FunctionExprInfo, args: &[Expression]) -> Result<Expression> {
         match args.is_empty() {
             true => Err(ErrorCode::LogicalError(
@@ -131,6 +297,13 @@ impl ExpressionAnalyzer {
     }
 
     fn function(&self, info: &FunctionExprInfo, args: &[Expression]) -> Result<Expression> {
+        if Self::is_analyzer_function(&info.name) {
+            return Ok(Expression::ScalarFunction {
+                op: info.name.clone(),
+                args: args.to_owned(),
+            });
+        }
+
         let query_context = self.context.clone();
         let context_args = ContextFunction::build_args_from_ctx(&info.name, query_context)?;
 
@@ -179,6 +352,7 @@ impl ExpressionAnalyzer {
                 distinct: info.distinct,
                 args: vec![common_planners::lit(0i64)],
                 params: parameters,
+                within_group: vec![],
             })
         } else {
             Ok(Expression::AggregateFunction {
@@ -186,6 +360,7 @@ impl ExpressionAnalyzer {
                 distinct: info.distinct,
                 args: args.to_owned(),
                 params: parameters,
+                within_group: vec![],
             })
         }
     }
@@ -216,13 +391,36 @@ impl ExpressionAnalyzer {
         Ok(())
     }
 
+    async fn analyze_in_subquery(
+        &self,
+        subquery: &Query,
+        negated: bool,
+        args: &mut Vec<Expression>,
+    ) -> Result<()> {
+        match args.pop() {
+            None => Err(ErrorCode::LogicalError(
+                "In subquery operator must be one children.",
+            )),
+            Some(expr) => {
+                let subquery = self.analyze_subquery(subquery).await?;
+                args.push(Expression::InSubquery {
+                    expr: Box::new(expr),
+                    subquery: Box::new(subquery),
+                    negated,
+                });
+                Ok(())
+            }
+        }
+    }
+
     async fn analyze_subquery(&self, subquery: &Query) -> Result<Expression> {
         let statement = DfQueryStatement::try_from(subquery.clone())?;
 
         let query_context = self.context.clone();
         let subquery_context = QueryContext::new(query_context.clone());
 
-        let analyze_subquery = statement.analyze(subquery_context);
+        let outer_scopes = self.outer_scopes.clone();
+        let analyze_subquery = statement.analyze_with_outer_scopes(subquery_context, outer_scopes);
         if let AnalyzedResult::SelectQuery(analyze_data) = analyze_subquery.await? {
             let subquery_plan = PlanParser::build_query_plan(&analyze_data)?;
             return Ok(Expression::Subquery {
@@ -247,7 +445,8 @@ impl ExpressionAnalyzer {
         let query_context = self.context.clone();
         let subquery_context = QueryContext::new(query_context.clone());
 
-        let analyze_subquery = statement.analyze(subquery_context);
+        let outer_scopes = self.outer_scopes.clone();
+        let analyze_subquery = statement.analyze_with_outer_scopes(subquery_context, outer_scopes);
         if let AnalyzedResult::SelectQuery(analyze_data) = analyze_subquery.await? {
             let subquery_plan = PlanParser::build_query_plan(&analyze_data)?;
             args.push(Expression::ScalarSubquery {
@@ -272,6 +471,8 @@ impl ExpressionAnalyzer {
     fn analyze_cast(
         &self,
         data_type: &common_datavalues::DataType,
+        try_cast: bool,
+        constraint: &Option<CastConstraint>,
         args: &mut Vec<Expression>,
     ) -> Result<()> {
         match args.pop() {
@@ -279,15 +480,205 @@ impl ExpressionAnalyzer {
                 "Cast operator must be one children.",
             )),
             Some(inner_expr) => {
-                args.push(Expression::Cast {
-                    expr: Box::new(inner_expr),
-                    data_type: data_type.clone(),
+                match constraint {
+                    None => {}
+                    Some(CastConstraint::FixedLength(length)) => {
+                        self.check_fixed_length(&inner_expr, *length)?
+                    }
+                    // TRY_CAST yields NULL for the malformed literal at runtime.
+                    Some(CastConstraint::Uuid) if try_cast => {}
+                    Some(CastConstraint::Uuid) => Self::check_uuid_literal(&inner_expr)?,
+                }
+
+                let expr = Box::new(inner_expr);
+                let data_type = data_type.clone();
+                args.push(match try_cast {
+                    true => Expression::TryCast {
+                        expr,
+                        data_type,
+                        on_error: CastOnError::Null,
+                    },
+                    false => Expression::Cast { expr, data_type },
                 });
                 Ok(())
             }
         }
     }
 
+    // The length of CHAR(n) is lost once mapped to String, so it is validated here.
+    fn check_fixed_length(&self, expr: &Expression, length: u64) -> Result<()> {
+        if length == 0 {
+            return Err(ErrorCode::BadDataValueType(
+                "Fixed-length string length must be positive, but got 0",
+            ));
+        }
+
+        if let Expression::Literal {
+            value: DataValue::String(Some(bytes)),
+            ..
+        } = expr
+        {
+            let chars = String::from_utf8_lossy(bytes).chars().count() as u64;
+            if chars > length {
+                let message = format!(
+                    "String literal {:?} of length {} exceeds the fixed length {}",
+                    String::from_utf8_lossy(bytes),
+                    chars,
+                    length
+                );
+
+                return match self.context.get_settings().get_strict_mode()? {
+                    0 => {
+                        self.context.push_warning(message);
+                        Ok(())
+                    }
+                    _ => Err(ErrorCode::SyntaxException(message)),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    // Only a literal is validated, the other operands are validated at runtime.
+    fn check_uuid_literal(expr: &Expression) -> Result<()> {
+        if let Expression::Literal {
+            value: DataValue::String(Some(bytes)),
+            ..
+        } = expr
+        {
+            let well_formed = bytes.len() == 36
+                && bytes.iter().enumerate().all(|(index, byte)| match index {
+                    8 | 13 | 18 | 23 => *byte == b'-',
+                    _ => byte.is_ascii_hexdigit(),
+                });
+
+            if !well_formed {
+                return Err(ErrorCode::BadArguments(format!(
+                    "Malformed UUID literal {:?}, expect the form xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx",
+                    String::from_utf8_lossy(bytes)
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn analyze_subscript(&self, args: &mut Vec<Expression>) -> Result<()> {
+        if args.len() < 2 {
+            return Err(ErrorCode::LogicalError(
+                "Subscript operator must be two children.",
+            ));
+        }
+
+        let index = args.pop().unwrap();
+        let base = args.pop().unwrap();
+        args.push(Expression::Subscript {
+            base: Box::new(base),
+            index: Box::new(index),
+        });
+        Ok(())
+    }
+
+    fn analyze_collate(&self, collation: &str, args: &mut Vec<Expression>) -> Result<()> {
+        match args.pop() {
+            None => Err(ErrorCode::LogicalError(
+                "Collate operator must be one children.",
+            )),
+            Some(expr) => {
+                args.push(Expression::Collate {
+                    expr: Box::new(expr),
+                    collation: collation.to_string(),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn analyze_tuple(&self, len: usize, args: &mut Vec<Expression>) -> Result<()> {
+        match args.len() < len {
+            true => Err(ErrorCode::LogicalError(format!(
+                "Tuple must be {} children.",
+                len
+            ))),
+            false => {
+                let elements = args.split_off(args.len() - len);
+                args.push(Expression::Tuple(elements));
+                Ok(())
+            }
+        }
+    }
+
+    // `expr [NOT] IN (v1, v2, ...)` is the function `in` or `not_in` of `(expr, v1, v2, ...)`,
+    // it's validated and expanded into the comparisons in the rewriter.
+    fn analyze_in_list(&self, len: usize, negated: bool, args: &mut Vec<Expression>) -> Result<()> {
+        match args.len() < len + 1 {
+            true => Err(ErrorCode::LogicalError(format!(
+                "In list operator must be {} children.",
+                len + 1
+            ))),
+            false => {
+                let op = match negated {
+                    true => "not_in",
+                    false => "in",
+                };
+
+                args.push(Expression::ScalarFunction {
+                    op: op.to_string(),
+                    args: args.split_off(args.len() - len - 1),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn analyze_named_argument(&self, name: &str, args: &mut Vec<Expression>) -> Result<()> {
+        match args.pop() {
+            None => Err(ErrorCode::LogicalError(
+                "Named argument must be one children.",
+            )),
+            Some(expr) => {
+                args.push(Expression::NamedArgument {
+                    name: name.to_string(),
+                    expr: Box::new(expr),
+                });
+                Ok(())
+            }
+        }
+    }
+
+    // CASE is evaluated as the nested `if(condition, result, else_result)`.
+    fn analyze_case(&self, info: &CaseExprInfo, args: &mut Vec<Expression>) -> Result<()> {
+        let args_count = info.branches_count * 2 + 1 + info.has_operand as usize;
+        if args.len() < args_count {
+            return Err(ErrorCode::LogicalError(
+                "Case operator must have all the branches.",
+            ));
+        }
+
+        let mut arguments = args.split_off(args.len() - args_count);
+        let mut expression = arguments.pop().unwrap();
+        let operand = match info.has_operand {
+            true => Some(arguments.remove(0)),
+            false => None,
+        };
+
+        for branch in arguments.chunks(2).rev() {
+            let condition = match &operand {
+                Some(operand) => operand.eq(branch[0].clone()),
+                None => branch[0].clone(),
+            };
+
+            expression = Expression::ScalarFunction {
+                op: String::from("if"),
+                args: vec![condition, branch[1].clone(), expression],
+            };
+        }
+
+        args.push(expression);
+        Ok(())
+    }
+
     fn analyze_between(&self, negated: bool, args: &mut Vec<Expression>) -> Result<()> {
         if args.len() < 3 {
             return Err(ErrorCode::SyntaxException(
@@ -325,16 +716,60 @@ struct FunctionExprInfo {
     parameters: Vec<Value>,
 }
 
+struct WindowFunctionExprInfo {
+    name: String,
+    args_count: usize,
+    parameters: Vec<Value>,
+    partition_by_count: usize,
+    // (asc, nulls_first) of each window order key
+    order_by: Vec<(bool, bool)>,
+    window_frame: Option<WindowFrame>,
+}
+
+struct CaseExprInfo {
+    has_operand: bool,
+    branches_count: usize,
+}
+
+struct ListAggExprInfo {
+    distinct: bool,
+    separator: Option<Value>,
+    // (asc, nulls_first) of each WITHIN GROUP order key
+    order_by: Vec<(bool, bool)>,
+}
+
+// The constraint of a SQL cast target type lost in the mapped `DataType`,
+// it is validated on the literal operands.
+enum CastConstraint {
+    // The length of `CHAR(n)`.
+    FixedLength(u64),
+    Uuid,
+}
+
 enum ExprRPNItem {
     Value(Value),
     Identifier(Ident),
     QualifiedIdentifier(Vec<Ident>),
     Function(FunctionExprInfo),
+    WindowFunction(WindowFunctionExprInfo),
+    ListAgg(ListAggExprInfo),
     Wildcard,
     Exists(Box<Query>),
     Subquery(Box<Query>),
-    Cast(common_datavalues::DataType),
+    InSubquery(Box<Query>, bool),
+    // The target type, whether it is a TRY_CAST and the constraint of the SQL type.
+    Cast(common_datavalues::DataType, bool, Option<CastConstraint>),
     Between(bool),
+    Subscript,
+    Case(CaseExprInfo),
+    // The collation name of `expr COLLATE collation`.
+    Collate(String),
+    // The parameter name of `name => expr` in the function arguments.
+    NamedArgument(String),
+    // The number of the elements of a row constructor `(a, b, ...)`.
+    Tuple(usize),
+    // The number of the values of `expr [NOT] IN (v1, v2, ...)` and whether it is negated.
+    InList(usize, bool),
 }
 
 impl ExprRPNItem {
@@ -396,8 +831,21 @@ impl ExprRPNBuilder {
             Expr::Wildcard => self.visit_wildcard(),
             Expr::Exists(subquery) => self.visit_exists(subquery),
             Expr::Subquery(subquery) => self.visit_subquery(subquery),
+            Expr::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => self.visit_in_subquery(expr, subquery, *negated),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => self.visit_in_list(expr, list, *negated),
             Expr::Function(function) => self.visit_function(function),
-            Expr::Cast { expr, data_type } => self.visit_cast(expr, data_type),
+            Expr::ListAgg(list_agg) => self.visit_list_agg(list_agg),
+            Expr::Cast { expr, data_type } => self.visit_cast(expr, data_type, false),
+            // The sqlparser has no shorthand of TRY_CAST, `x::type` is always a CAST.
+            Expr::TryCast { expr, data_type } => self.visit_cast(expr, data_type, true),
             Expr::TypedString { data_type, value } => self.visit_typed_string(data_type, value),
             Expr::Substring {
                 expr,
@@ -411,6 +859,16 @@ impl ExprRPNBuilder {
                 high,
             } => self.visit_between(expr, negated, low, high),
             Expr::Tuple(exprs) => self.visit_tuple(exprs),
+            Expr::MapAccess { column, key } => self.visit_map_access(column, key),
+            Expr::Collate { expr, collation } => self.visit_collate(expr, collation),
+            Expr::Trim { expr, trim_where } => self.visit_trim(expr, trim_where),
+            Expr::Extract { field, expr } => self.visit_extract(field, expr),
+            Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => self.visit_case(operand, conditions, results, else_result),
             other => Result::Err(ErrorCode::SyntaxException(format!(
                 "Unsupported expression: {}, type: {:?}",
                 expr, other
@@ -429,13 +887,35 @@ impl ExprRPNBuilder {
                     self.visit(expr)?;
                 }
 
-                self.rpn
-                    .push(ExprRPNItem::function(String::from("tuple"), len));
+                self.rpn.push(ExprRPNItem::Tuple(len));
                 Ok(())
             }
         }
     }
 
+    fn visit_collate(&mut self, expr: &Expr, collation: &ObjectName) -> Result<()> {
+        self.visit(expr)?;
+        let collation = collation.0.iter().map(|ident| ident.value.clone());
+        self.rpn.push(ExprRPNItem::Collate(
+            collation.collect::<Vec<_>>().join("."),
+        ));
+        Ok(())
+    }
+
+    fn visit_map_access(&mut self, column: &Expr, key: &str) -> Result<()> {
+        self.visit(column)?;
+        match key.parse::<u64>() {
+            Ok(_) => self
+                .rpn
+                .push(ExprRPNItem::Value(Value::Number(key.to_string(), false))),
+            Err(_) => self.rpn.push(ExprRPNItem::Value(Value::SingleQuotedString(
+                key.to_string(),
+            ))),
+        };
+        self.rpn.push(ExprRPNItem::Subscript);
+        Ok(())
+    }
+
     fn visit_wildcard(&mut self) -> Result<()> {
         self.rpn.push(ExprRPNItem::Wildcard);
         Ok(())
@@ -469,15 +949,46 @@ impl ExprRPNBuilder {
         Ok(())
     }
 
+    fn visit_in_list(&mut self, expr: &Expr, list: &[Expr], negated: bool) -> Result<()> {
+        if list.is_empty() {
+            return Err(ErrorCode::SyntaxException(
+                "IN list must have at least one value",
+            ));
+        }
+
+        self.visit(expr)?;
+        for value in list {
+            self.visit(value)?;
+        }
+
+        self.rpn.push(ExprRPNItem::InList(list.len(), negated));
+        Ok(())
+    }
+
+    fn visit_in_subquery(&mut self, expr: &Expr, subquery: &Query, negated: bool) -> Result<()> {
+        self.visit(expr)?;
+        self.rpn
+            .push(ExprRPNItem::InSubquery(Box::new(subquery.clone()), negated));
+        Ok(())
+    }
+
     fn visit_function(&mut self, function: &Function) -> Result<()> {
         // TODO: context function.
         for function_arg in &function.args {
             match function_arg {
-                FunctionArg::Named { arg, .. } => self.visit(arg)?,
+                FunctionArg::Named { name, arg } => {
+                    self.visit(arg)?;
+                    self.rpn
+                        .push(ExprRPNItem::NamedArgument(name.value.clone()));
+                }
                 FunctionArg::Unnamed(expr) => self.visit(expr)?,
             };
         }
 
+        if let Some(window_spec) = &function.over {
+            return self.visit_window_spec(function, window_spec);
+        }
+
         self.rpn.push(ExprRPNItem::Function(FunctionExprInfo {
             name: function.name.to_string(),
             distinct: function.distinct,
@@ -489,19 +1000,142 @@ impl ExprRPNBuilder {
         Ok(())
     }
 
-    fn visit_cast(&mut self, expr: &Expr, data_type: &DataType) -> Result<()> {
+    fn visit_list_agg(&mut self, list_agg: &ListAgg) -> Result<()> {
+        if list_agg.on_overflow.is_some() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "ON OVERFLOW is not supported in LISTAGG: {}",
+                list_agg
+            )));
+        }
+
+        let separator = match &list_agg.separator {
+            None => None,
+            Some(separator) => match separator.as_ref() {
+                Expr::Value(value @ Value::SingleQuotedString(_)) => Some(value.clone()),
+                other => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "LISTAGG separator must be a string literal, but got {}",
+                        other
+                    )));
+                }
+            },
+        };
+
+        self.visit(&list_agg.expr)?;
+
+        let mut order_by = Vec::with_capacity(list_agg.within_group.len());
+        for order_by_expr in &list_agg.within_group {
+            self.visit(&order_by_expr.expr)?;
+            let asc = order_by_expr.asc.unwrap_or(true);
+            order_by.push((asc, order_by_expr.nulls_first.unwrap_or(asc)));
+        }
+
+        self.rpn.push(ExprRPNItem::ListAgg(ListAggExprInfo {
+            distinct: list_agg.distinct,
+            separator,
+            order_by,
+        }));
+        Ok(())
+    }
+
+    fn visit_window_spec(&mut self, function: &Function, window_spec: &WindowSpec) -> Result<()> {
+        if function.distinct {
+            return Err(ErrorCode::SyntaxException(format!(
+                "DISTINCT is not supported in window function: {}",
+                function
+            )));
+        }
+
+        for partition_by_expr in &window_spec.partition_by {
+            self.visit(partition_by_expr)?;
+        }
+
+        let mut order_by = Vec::with_capacity(window_spec.order_by.len());
+        for order_by_expr in &window_spec.order_by {
+            self.visit(&order_by_expr.expr)?;
+            let asc = order_by_expr.asc.unwrap_or(true);
+            order_by.push((asc, order_by_expr.nulls_first.unwrap_or(asc)));
+        }
+
+        let window_frame = match &window_spec.window_frame {
+            None => None,
+            Some(window_frame) => Some(Self::window_frame(window_frame)?),
+        };
+
+        self.rpn
+            .push(ExprRPNItem::WindowFunction(WindowFunctionExprInfo {
+                name: function.name.to_string(),
+                args_count: function.args.len(),
+                parameters: function.params.to_owned(),
+                partition_by_count: window_spec.partition_by.len(),
+                order_by,
+                window_frame,
+            }));
+        Ok(())
+    }
+
+    fn window_frame(window_frame: &SqlparserWindowFrame) -> Result<WindowFrame> {
+        let units = match window_frame.units {
+            SqlparserWindowFrameUnits::Rows => WindowFrameUnits::Rows,
+            SqlparserWindowFrameUnits::Range => WindowFrameUnits::Range,
+            SqlparserWindowFrameUnits::Groups => WindowFrameUnits::Groups,
+        };
+
+        let start_bound = Self::window_frame_bound(&window_frame.start_bound);
+        let end_bound = match &window_frame.end_bound {
+            None => WindowFrameBound::CurrentRow,
+            Some(end_bound) => Self::window_frame_bound(end_bound),
+        };
+
+        Ok(WindowFrame {
+            units,
+            start_bound,
+            end_bound,
+        })
+    }
+
+    // The sqlparser only parses numeric offsets, the IntervalPreceding and IntervalFollowing
+    // bounds aren't built from SQL until it parses the intervals.
+    fn window_frame_bound(bound: &SqlparserWindowFrameBound) -> WindowFrameBound {
+        match bound {
+            SqlparserWindowFrameBound::CurrentRow => WindowFrameBound::CurrentRow,
+            SqlparserWindowFrameBound::Preceding(n) => WindowFrameBound::Preceding(*n),
+            SqlparserWindowFrameBound::Following(n) => WindowFrameBound::Following(*n),
+        }
+    }
+
+    fn visit_cast(&mut self, expr: &Expr, data_type: &DataType, try_cast: bool) -> Result<()> {
         self.visit(expr)?;
+        let constraint = Self::cast_constraint(data_type);
+        let data_type = Self::cast_data_type(data_type)?;
         self.rpn
-            .push(ExprRPNItem::Cast(SQLCommon::make_data_type(data_type)?));
+            .push(ExprRPNItem::Cast(data_type, try_cast, constraint));
         Ok(())
     }
 
+    // TODO: FixedString(n) once the parser supports it.
+    fn cast_constraint(data_type: &DataType) -> Option<CastConstraint> {
+        match data_type {
+            DataType::Char(length) => length.map(CastConstraint::FixedLength),
+            DataType::Uuid => Some(CastConstraint::Uuid),
+            _ => None,
+        }
+    }
+
+    fn cast_data_type(data_type: &DataType) -> Result<common_datavalues::DataType> {
+        SQLCommon::make_data_type(data_type).map_err(|_| {
+            ErrorCode::BadDataValueType(format!("Unknown cast target type: {}", data_type))
+        })
+    }
+
     fn visit_typed_string(&mut self, data_type: &DataType, value: &str) -> Result<()> {
         self.rpn.push(ExprRPNItem::Value(Value::SingleQuotedString(
             value.to_string(),
         )));
+        let constraint = Self::cast_constraint(data_type);
+        let data_type = Self::cast_data_type(data_type)?;
         self.rpn
-            .push(ExprRPNItem::Cast(SQLCommon::make_data_type(data_type)?));
+            .push(ExprRPNItem::Cast(data_type, false, constraint));
         Ok(())
     }
 
@@ -524,6 +1158,40 @@ impl ExprRPNBuilder {
         Ok(())
     }
 
+    fn visit_case(
+        &mut self,
+        operand: &Option<Box<Expr>>,
+        conditions: &[Expr],
+        results: &[Expr],
+        else_result: &Option<Box<Expr>>,
+    ) -> Result<()> {
+        if conditions.is_empty() || conditions.len() != results.len() {
+            return Err(ErrorCode::SyntaxException(
+                "Case must have at least one WHEN ... THEN branch.",
+            ));
+        }
+
+        if let Some(operand) = operand {
+            self.visit(operand)?;
+        }
+
+        for (condition, result) in conditions.iter().zip(results) {
+            self.visit(condition)?;
+            self.visit(result)?;
+        }
+
+        match else_result {
+            Some(else_result) => self.visit(else_result)?,
+            None => self.rpn.push(ExprRPNItem::Value(Value::Null)),
+        };
+
+        self.rpn.push(ExprRPNItem::Case(CaseExprInfo {
+            has_operand: operand.is_some(),
+            branches_count: conditions.len(),
+        }));
+        Ok(())
+    }
+
     fn visit_between(
         &mut self,
         expr: &Expr,
@@ -538,6 +1206,41 @@ impl ExprRPNBuilder {
         Ok(())
     }
 
+    // The special form is analyzed into `trim_both`, `trim_leading` or `trim_trailing` of
+    // `(chars, source)`, and the plain `TRIM(source)` into `trim(source)`.
+    fn visit_trim(
+        &mut self,
+        expr: &Expr,
+        trim_where: &Option<(TrimWhereField, Box<Expr>)>,
+    ) -> Result<()> {
+        let (name, trim_what) = match trim_where {
+            None => {
+                self.visit(expr)?;
+                self.rpn
+                    .push(ExprRPNItem::function(String::from("trim"), 1));
+                return Ok(());
+            }
+            Some((TrimWhereField::Both, trim_what)) => ("trim_both", trim_what),
+            Some((TrimWhereField::Leading, trim_what)) => ("trim_leading", trim_what),
+            Some((TrimWhereField::Trailing, trim_what)) => ("trim_trailing", trim_what),
+        };
+
+        self.visit(trim_what)?;
+        self.visit(expr)?;
+        self.rpn.push(ExprRPNItem::function(String::from(name), 2));
+        Ok(())
+    }
+
+    // `EXTRACT(field FROM source)` is analyzed into `extract('field', source)`.
+    fn visit_extract(&mut self, field: &DateTimeField, expr: &Expr) -> Result<()> {
+        let field = Value::SingleQuotedString(field.to_string());
+        self.rpn.push(ExprRPNItem::Value(field));
+        self.visit(expr)?;
+        self.rpn
+            .push(ExprRPNItem::function(String::from("extract"), 2));
+        Ok(())
+    }
+
     fn visit_substring(
         &mut self,
         expr: &Expr,
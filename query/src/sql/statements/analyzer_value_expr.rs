@@ -81,34 +81,60 @@ impl ValueExprAnalyzer {
     }
 
     fn analyze_interval(value: &str, unit: &Option<DateTimeField>) -> Result<Expression> {
-        // We only accept i32 for number in "interval [num] [year|month|day|hour|minute|second]"
-        let num = value.parse::<i32>()?;
-
         //TODO: support default unit for interval
         match unit {
             None => Err(ErrorCode::SyntaxException(
                 "Interval must have unit, e.g: '1 HOUR'",
             )),
-            Some(DateTimeField::Year) => Self::year_month_interval(num * 12),
-            Some(DateTimeField::Month) => Self::year_month_interval(num),
-            Some(DateTimeField::Day) => Self::day_time_interval(num, 0),
-            Some(DateTimeField::Hour) => Self::day_time_interval(0, num * 3600 * 1000),
-            Some(DateTimeField::Minute) => Self::day_time_interval(0, num * 60 * 1000),
-            Some(DateTimeField::Second) => Self::day_time_interval(0, num * 1000),
+            Some(unit) => Ok(Expression::Interval {
+                value: Box::new(Self::analyze_number_value(value)?),
+                unit: unit.to_string(),
+            }),
         }
     }
 
-    fn year_month_interval(months: i32) -> Result<Expression> {
+    /// Convert the interval into the literal known by the planner, which is months
+    /// for YEAR, QUARTER and MONTH, or milliseconds for the others.
+    pub fn interval_literal(value: &DataValue, unit: &str) -> Result<Expression> {
+        let num = match value {
+            DataValue::Float64(Some(value)) => *value,
+            value => value.as_i64()? as f64,
+        };
+
+        match unit.to_uppercase().as_str() {
+            "YEAR" => Self::year_month_interval(num * 12.0, unit),
+            "QUARTER" => Self::year_month_interval(num * 3.0, unit),
+            "MONTH" => Self::year_month_interval(num, unit),
+            "WEEK" => Self::day_time_interval(num * 7.0 * MILLISECONDS_PER_DAY),
+            "DAY" => Self::day_time_interval(num * MILLISECONDS_PER_DAY),
+            "HOUR" => Self::day_time_interval(num * 3600.0 * 1000.0),
+            "MINUTE" => Self::day_time_interval(num * 60.0 * 1000.0),
+            "SECOND" => Self::day_time_interval(num * 1000.0),
+            _ => Err(ErrorCode::SyntaxException(format!(
+                "Unsupported interval unit: {}",
+                unit
+            ))),
+        }
+    }
+
+    fn year_month_interval(months: f64, unit: &str) -> Result<Expression> {
+        if months.fract() != 0.0 {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Interval of {} must be a whole number of months",
+                unit
+            )));
+        }
+
+        let months = months as i64;
         Ok(Expression::Literal {
-            value: DataValue::Int64(Some(months as i64)),
+            value: DataValue::Int64(Some(months)),
             column_name: Some(months.to_string()),
             data_type: DataType::Interval(IntervalUnit::YearMonth),
         })
     }
 
-    fn day_time_interval(days: i32, ms: i32) -> Result<Expression> {
-        static MILLISECONDS_PER_DAY: i64 = 24 * 3600 * 1000;
-        let total_ms = days as i64 * MILLISECONDS_PER_DAY + ms as i64;
+    fn day_time_interval(ms: f64) -> Result<Expression> {
+        let total_ms = ms.round() as i64;
 
         Ok(Expression::Literal {
             value: DataValue::Int64(Some(total_ms)),
@@ -117,3 +143,5 @@ impl ValueExprAnalyzer {
         })
     }
 }
+
+const MILLISECONDS_PER_DAY: f64 = 24.0 * 3600.0 * 1000.0;
@@ -66,6 +66,7 @@ pub use statement_grant::DfGrantStatement;
 pub use statement_insert::DfInsertStatement;
 pub use statement_kill::DfKillStatement;
 pub use statement_select::DfQueryStatement;
+pub use statement_select::DfSetOperation;
 pub use statement_set_variable::DfSetVariable;
 pub use statement_show_create_table::DfShowCreateTable;
 pub use statement_show_databases::DfShowDatabases;
@@ -26,10 +26,22 @@ mod query_qualified_rewriter;
 mod query_schema_joined;
 mod query_schema_joined_analyzer;
 
+pub use query_normalizer::GroupingSpec;
 pub use query_normalizer::QueryASTIR;
+pub use query_normalizer::QueryHint;
 pub use query_normalizer::QueryNormalizer;
+pub use query_normalizer::SetOperation;
+pub use query_qualified_rewriter::is_deterministic;
+pub use query_qualified_rewriter::ExpandedColumn;
 pub use query_qualified_rewriter::QualifiedRewriter;
+pub use query_qualified_rewriter::ResolvedColumn;
+pub use query_schema_joined::JoinCondition;
 pub use query_schema_joined::JoinedColumnDesc;
 pub use query_schema_joined::JoinedSchema;
 pub use query_schema_joined::JoinedTableDesc;
+pub use query_schema_joined::RelationId;
+pub use query_schema_joined::TablePivot;
+pub use query_schema_joined::TableSample;
+pub use query_schema_joined::TableSampleMethod;
+pub use query_schema_joined::TableSampleSize;
 pub use query_schema_joined_analyzer::JoinedSchemaAnalyzer;
@@ -17,54 +17,139 @@ use std::fmt::Debug;
 use std::sync::Arc;
 
 use common_arrow::arrow_format::ipc::flatbuffers::bitflags::_core::fmt::Formatter;
+use common_datavalues::DataType;
 use common_exception::ErrorCode;
 use common_exception::Result;
 use common_planners::extract_aliases;
 use common_planners::find_aggregate_exprs_in_expr;
+use common_planners::find_window_exprs_in_expr;
 use common_planners::resolve_aliases_to_exprs;
 use common_planners::Expression;
 use sqlparser::ast::Expr;
+use sqlparser::ast::Function;
+use sqlparser::ast::FunctionArg;
 use sqlparser::ast::OffsetRows;
 use sqlparser::ast::SelectItem;
 
 use crate::sessions::QueryContext;
 use crate::sql::statements::analyzer_expr::ExpressionAnalyzer;
+use crate::sql::statements::query::JoinedSchema;
 use crate::sql::statements::DfQueryStatement;
 
 // Intermediate representation for query AST(after normalize)
+#[derive(serde::Serialize)]
 pub struct QueryASTIR {
+    // The ON conditions of the joins, filled in by the rewriter.
+    pub join_conditions: Vec<Expression>,
     pub filter_predicate: Option<Expression>,
     pub group_by_expressions: Vec<Expression>,
+    pub grouping: GroupingSpec,
     pub having_predicate: Option<Expression>,
+    pub qualify_predicate: Option<Expression>,
     pub aggregate_expressions: Vec<Expression>,
+    pub window_expressions: Vec<Expression>,
     pub order_by_expressions: Vec<Expression>,
+    // The DISTINCT ON keys, the first row of each of them by ORDER BY is kept.
+    pub distinct_on_expressions: Vec<Expression>,
     pub projection_expressions: Vec<Expression>,
-    pub limit: Option<usize>,
-    pub offset: Option<usize>,
+    pub limit: Option<Expression>,
+    // The rows tied with the last one of the limit are kept, it requires ORDER BY.
+    pub limit_with_ties: bool,
+    pub offset: Option<Expression>,
+    // Columns resolved in the outer queries, with the depth of the scope they were found in.
+    pub outer_references: Vec<(Expression, usize)>,
+    // Names of the IN subqueries the planner can convert into semi-joins.
+    pub semi_join_subqueries: Vec<String>,
+    // Names of the subqueries with the id shared by the structurally identical ones.
+    pub subquery_ids: Vec<(String, usize)>,
+    // Optimizer hints for the planner, the invalid ones are dropped with a warning.
+    pub hints: Vec<QueryHint>,
+    // The UNION, INTERSECT and EXCEPT operands, the query itself is the first one.
+    pub set_operations: Vec<SetOperation>,
+    // The output columns of the set operations, named by the first SELECT and typed by the
+    // common type of all the operands. ORDER BY is resolved to them.
+    pub set_output_columns: Vec<(String, DataType)>,
+}
+
+/// The structure of GROUP BY. Except for `Plain`, `group_by_expressions` holds
+/// the distinct keys of all grouping sets.
+#[derive(serde::Serialize, Clone, PartialEq)]
+pub enum GroupingSpec {
+    /// `GROUP BY a, b`
+    Plain,
+    /// `GROUP BY ROLLUP(a, b)`
+    Rollup(Vec<Expression>),
+    /// `GROUP BY CUBE(a, b)`
+    Cube(Vec<Expression>),
+    /// `GROUP BY GROUPING SETS ((a, b), (a), ())`
+    Sets(Vec<Vec<Expression>>),
+    /// `GROUP BY ALL`, grouped by the projection items without aggregate functions,
+    /// it's resolved to `Plain` with those keys by the rewriter.
+    All,
+}
+
+/// An optimizer hint of `/*+ ... */`, a hint with several arguments is split into one
+/// hint per argument.
+#[derive(serde::Serialize, Clone, PartialEq)]
+pub enum QueryHint {
+    /// `BROADCAST(t)`, the table to broadcast to the join, by alias or name.
+    Broadcast(Vec<String>),
+    /// `SKEW(t.column)`, the skewed join key, resolved by the rewriter.
+    Skew(Expression),
+}
+
+/// A UNION, INTERSECT or EXCEPT operand, resolved in its own scope.
+#[derive(serde::Serialize)]
+pub struct SetOperation {
+    pub op: String,
+    pub all: bool,
+    pub query: QueryASTIR,
 }
 
 pub struct QueryNormalizer {
     query_ast_ir: QueryASTIR,
     expression_analyzer: ExpressionAnalyzer,
     aliases_map: HashMap<String, Expression>,
+    ctx: Arc<QueryContext>,
 }
 
 /// Replace alias in query and collect aggregate functions
 impl QueryNormalizer {
     pub fn create(ctx: Arc<QueryContext>) -> QueryNormalizer {
+        Self::create_with_scopes(ctx, vec![])
+    }
+
+    /// The scopes are visible to the subqueries in expressions, the current query is the last one.
+    pub fn create_with_scopes(
+        ctx: Arc<QueryContext>,
+        scopes: Vec<JoinedSchema>,
+    ) -> QueryNormalizer {
         QueryNormalizer {
-            expression_analyzer: ExpressionAnalyzer::create(ctx),
+            expression_analyzer: ExpressionAnalyzer::create_with_outer_scopes(ctx.clone(), scopes),
             aliases_map: HashMap::new(),
             query_ast_ir: QueryASTIR {
+                join_conditions: vec![],
                 filter_predicate: None,
                 group_by_expressions: vec![],
+                grouping: GroupingSpec::Plain,
                 having_predicate: None,
+                qualify_predicate: None,
                 aggregate_expressions: vec![],
+                window_expressions: vec![],
                 order_by_expressions: vec![],
+                distinct_on_expressions: vec![],
                 projection_expressions: vec![],
                 limit: None,
+                limit_with_ties: false,
                 offset: None,
+                outer_references: vec![],
+                semi_join_subqueries: vec![],
+                subquery_ids: vec![],
+                hints: vec![],
+                set_operations: vec![],
+                set_output_columns: vec![],
             },
+            ctx,
         }
     }
 
@@ -85,14 +170,26 @@ impl QueryNormalizer {
             return Err(cause.add_message_back(" (while in analyze select having)"));
         }
 
+        if let Err(cause) = self.analyze_qualify(query).await {
+            return Err(cause.add_message_back(" (while in analyze select qualify)"));
+        }
+
         if let Err(cause) = self.analyze_order_by(query).await {
             return Err(cause.add_message_back(" (while in analyze select order by)"));
         }
 
+        if let Err(cause) = self.analyze_distinct_on(query).await {
+            return Err(cause.add_message_back(" (while in analyze select distinct on)"));
+        }
+
         if let Err(cause) = self.analyze_limit(query).await {
             return Err(cause.add_message_back(" (while in analyze select limit)"));
         }
 
+        if let Err(cause) = self.analyze_hints(query).await {
+            return Err(cause.add_message_back(" (while in analyze select hints)"));
+        }
+
         Ok(self.query_ast_ir)
     }
 
@@ -111,6 +208,7 @@ impl QueryNormalizer {
 
         for projection_expression in &projection_expressions {
             self.add_aggregate_function(projection_expression)?;
+            self.add_window_function(projection_expression)?;
         }
 
         self.query_ast_ir.projection_expressions = projection_expressions;
@@ -118,7 +216,46 @@ impl QueryNormalizer {
     }
 
     async fn analyze_group_by(&mut self, query: &DfQueryStatement) -> Result<()> {
+        if !query.grouping_sets.is_empty() {
+            return self.analyze_grouping_sets(query).await;
+        }
+
+        // The parser takes the unquoted ALL as an identifier, a column named `all`
+        // must be quoted.
+        if let [Expr::Identifier(ident)] = query.group_by.as_slice() {
+            if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("all") {
+                self.query_ast_ir.grouping = GroupingSpec::All;
+                return Ok(());
+            }
+        }
+
+        if let [Expr::Function(function)] = query.group_by.as_slice() {
+            match function.name.to_string().to_lowercase().as_str() {
+                "rollup" => {
+                    let keys = self.grouping_keys(function).await?;
+                    self.query_ast_ir.grouping = GroupingSpec::Rollup(keys.clone());
+                    return self.add_group_by_keys(&keys);
+                }
+                "cube" => {
+                    let keys = self.grouping_keys(function).await?;
+                    self.query_ast_ir.grouping = GroupingSpec::Cube(keys.clone());
+                    return self.add_group_by_keys(&keys);
+                }
+                _ => {}
+            }
+        }
+
         for group_by_expr in &query.group_by {
+            if let Expr::Function(function) = group_by_expr {
+                let name = function.name.to_string().to_lowercase();
+                if name == "rollup" || name == "cube" {
+                    return Err(ErrorCode::UnImplement(format!(
+                        "{} must be the only GROUP BY item",
+                        name.to_uppercase()
+                    )));
+                }
+            }
+
             let expression = self.resolve_aliases(group_by_expr).await?;
             self.query_ast_ir.group_by_expressions.push(expression);
         }
@@ -126,6 +263,57 @@ impl QueryNormalizer {
         Ok(())
     }
 
+    async fn analyze_grouping_sets(&mut self, query: &DfQueryStatement) -> Result<()> {
+        if !query.group_by.is_empty() {
+            return Err(ErrorCode::UnImplement(
+                "GROUPING SETS must be the only GROUP BY item",
+            ));
+        }
+
+        let mut grouping_sets = Vec::with_capacity(query.grouping_sets.len());
+        for grouping_set in &query.grouping_sets {
+            let mut keys = Vec::with_capacity(grouping_set.len());
+            for key in grouping_set {
+                keys.push(self.resolve_aliases(key).await?);
+            }
+
+            self.add_group_by_keys(&keys)?;
+            grouping_sets.push(keys);
+        }
+
+        self.query_ast_ir.grouping = GroupingSpec::Sets(grouping_sets);
+        Ok(())
+    }
+
+    async fn grouping_keys(&self, function: &Function) -> Result<Vec<Expression>> {
+        let mut keys = Vec::with_capacity(function.args.len());
+        for arg in &function.args {
+            match arg {
+                FunctionArg::Unnamed(expr) => keys.push(self.resolve_aliases(expr).await?),
+                FunctionArg::Named { .. } => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Named argument is not allowed in {}",
+                        function
+                    )));
+                }
+            }
+        }
+
+        Ok(keys)
+    }
+
+    fn add_group_by_keys(&mut self, keys: &[Expression]) -> Result<()> {
+        for key in keys {
+            if !self.query_ast_ir.group_by_expressions.contains(key) {
+                self.query_ast_ir.group_by_expressions.push(key.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    // A projection alias takes precedence over a base column of the same name in HAVING,
+    // as in ORDER BY, while WHERE only sees the base columns.
     async fn analyze_having(&mut self, query: &DfQueryStatement) -> Result<()> {
         if let Some(predicate) = &query.having {
             let expression = self.resolve_aliases(predicate).await?;
@@ -136,6 +324,68 @@ impl QueryNormalizer {
         Ok(())
     }
 
+    async fn analyze_qualify(&mut self, query: &DfQueryStatement) -> Result<()> {
+        if let Some(predicate) = &query.qualify {
+            let expression = self.resolve_aliases(predicate).await?;
+
+            self.add_aggregate_function(&expression)?;
+            self.add_window_function(&expression)?;
+            self.query_ast_ir.qualify_predicate = Some(expression);
+        }
+        Ok(())
+    }
+
+    // Unlike a clause, a malformed or unknown hint is ignored with a warning, so that
+    // the hints of a newer version don't fail the query.
+    async fn analyze_hints(&mut self, query: &DfQueryStatement) -> Result<()> {
+        for hint in &query.hints {
+            let function = match hint {
+                Expr::Function(function) if !function.args.is_empty() => function,
+                _ => {
+                    self.ctx
+                        .push_warning(format!("Ignored malformed hint {}", hint));
+                    continue;
+                }
+            };
+
+            let name = function.name.to_string().to_uppercase();
+            if name != "BROADCAST" && name != "SKEW" {
+                self.ctx
+                    .push_warning(format!("Ignored unknown hint {}", hint));
+                continue;
+            }
+
+            for arg in &function.args {
+                let query_hint = match (name.as_str(), arg) {
+                    ("BROADCAST", FunctionArg::Unnamed(Expr::Identifier(ident))) => {
+                        Some(QueryHint::Broadcast(vec![ident.value.clone()]))
+                    }
+                    ("BROADCAST", FunctionArg::Unnamed(Expr::CompoundIdentifier(idents))) => {
+                        let name_parts = idents.iter().map(|ident| ident.value.clone());
+                        Some(QueryHint::Broadcast(name_parts.collect()))
+                    }
+                    ("SKEW", FunctionArg::Unnamed(expr)) => {
+                        match self.expression_analyzer.analyze(expr).await {
+                            Ok(expression) => Some(QueryHint::Skew(expression)),
+                            Err(_) => None,
+                        }
+                    }
+                    _ => None,
+                };
+
+                match query_hint {
+                    Some(query_hint) => self.query_ast_ir.hints.push(query_hint),
+                    None => self.ctx.push_warning(format!(
+                        "Ignored malformed argument {} of hint {}",
+                        arg, hint
+                    )),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn analyze_order_by(&mut self, query: &DfQueryStatement) -> Result<()> {
         for order_by_expr in &query.order_by {
             let expression = self.resolve_aliases(&order_by_expr.expr).await?;
@@ -154,17 +404,22 @@ impl QueryNormalizer {
         Ok(())
     }
 
+    async fn analyze_distinct_on(&mut self, query: &DfQueryStatement) -> Result<()> {
+        for distinct_on_expr in &query.distinct_on {
+            let expression = self.resolve_aliases(distinct_on_expr).await?;
+
+            self.add_aggregate_function(&expression)?;
+            self.query_ast_ir.distinct_on_expressions.push(expression);
+        }
+
+        Ok(())
+    }
+
     async fn analyze_limit(&mut self, query: &DfQueryStatement) -> Result<()> {
         if let Some(limit) = &query.limit {
             let expression_analyzer = &self.expression_analyzer;
-            let limit_literal = match expression_analyzer.analyze(limit).await? {
-                Expression::Literal { value, .. } => Ok(value.as_u64()? as usize),
-                _ => Err(ErrorCode::SyntaxException(format!(
-                    "Unexpected expression for LIMIT clause: {:?}",
-                    limit
-                ))),
-            }?;
-            self.query_ast_ir.limit = Some(limit_literal);
+            self.query_ast_ir.limit = Some(expression_analyzer.analyze(limit).await?);
+            self.query_ast_ir.limit_with_ties = query.limit_with_ties;
         }
 
         if let Some(offset) = &query.offset {
@@ -173,14 +428,7 @@ impl QueryNormalizer {
             }
 
             let expression_analyzer = &self.expression_analyzer;
-            let offset_literal = match expression_analyzer.analyze(&offset.value).await? {
-                Expression::Literal { value, .. } => Ok(value.as_u64()? as usize),
-                _ => Err(ErrorCode::SyntaxException(format!(
-                    "Unexpected expression for LIMIT clause: {:?}",
-                    offset
-                ))),
-            }?;
-            self.query_ast_ir.offset = Some(offset_literal);
+            self.query_ast_ir.offset = Some(expression_analyzer.analyze(&offset.value).await?);
         }
 
         Ok(())
@@ -195,6 +443,14 @@ impl QueryNormalizer {
                 SelectItem::Wildcard => {
                     output_columns.push(Expression::Wildcard);
                 }
+                // TODO: the sqlparser doesn't produce `t.* EXCEPT (...)` and `t.* RENAME (...)` yet
+                SelectItem::QualifiedWildcard(name) => {
+                    output_columns.push(Expression::QualifiedWildcard {
+                        qualifier: name.0.iter().map(|ident| ident.value.clone()).collect(),
+                        except: vec![],
+                        rename: vec![],
+                    });
+                }
                 SelectItem::UnnamedExpr(expr) => {
                     output_columns.push(expr_analyzer.analyze(expr).await?);
                 }
@@ -234,12 +490,26 @@ impl QueryNormalizer {
 
         Ok(())
     }
+
+    fn add_window_function(&mut self, expr: &Expression) -> Result<()> {
+        for window_expr in find_window_exprs_in_expr(expr) {
+            if !self.query_ast_ir.window_expressions.contains(&window_expr) {
+                self.query_ast_ir.window_expressions.push(window_expr);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Debug for QueryASTIR {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let mut debug_struct = f.debug_struct("NormalQuery");
 
+        if !self.join_conditions.is_empty() {
+            debug_struct.field("join conditions", &self.join_conditions);
+        }
+
         if let Some(predicate) = &self.filter_predicate {
             debug_struct.field("filter", predicate);
         }
@@ -248,22 +518,115 @@ impl Debug for QueryASTIR {
             debug_struct.field("group by", &self.group_by_expressions);
         }
 
+        if self.grouping != GroupingSpec::Plain {
+            debug_struct.field("grouping", &self.grouping);
+        }
+
         if let Some(predicate) = &self.having_predicate {
             debug_struct.field("having", predicate);
         }
 
+        if let Some(predicate) = &self.qualify_predicate {
+            debug_struct.field("qualify", predicate);
+        }
+
         if !self.aggregate_expressions.is_empty() {
             debug_struct.field("aggregate", &self.aggregate_expressions);
         }
 
+        if !self.window_expressions.is_empty() {
+            debug_struct.field("window", &self.window_expressions);
+        }
+
         if !self.order_by_expressions.is_empty() {
             debug_struct.field("order by", &self.order_by_expressions);
         }
 
+        if !self.distinct_on_expressions.is_empty() {
+            debug_struct.field("distinct on", &self.distinct_on_expressions);
+        }
+
         if !self.projection_expressions.is_empty() {
             debug_struct.field("projection", &self.projection_expressions);
         }
 
+        if let Some(limit) = &self.limit {
+            debug_struct.field("limit", limit);
+        }
+
+        if self.limit_with_ties {
+            debug_struct.field("limit with ties", &self.limit_with_ties);
+        }
+
+        if let Some(offset) = &self.offset {
+            debug_struct.field("offset", offset);
+        }
+
+        if !self.outer_references.is_empty() {
+            debug_struct.field("outer references", &self.outer_references);
+        }
+
+        if !self.semi_join_subqueries.is_empty() {
+            debug_struct.field("semi join", &self.semi_join_subqueries);
+        }
+
+        if !self.subquery_ids.is_empty() {
+            debug_struct.field("subquery ids", &self.subquery_ids);
+        }
+
+        if !self.hints.is_empty() {
+            debug_struct.field("hints", &self.hints);
+        }
+
+        if !self.set_operations.is_empty() {
+            debug_struct.field("set operations", &self.set_operations);
+        }
+
+        if !self.set_output_columns.is_empty() {
+            let columns = self.set_output_columns.iter();
+            let columns = columns.map(|(name, data_type)| format!("{} {:?}", name, data_type));
+            let columns = columns.collect::<Vec<_>>().join(", ");
+            debug_struct.field("set output", &format_args!("[{}]", columns));
+        }
+
         debug_struct.finish()
     }
 }
+
+impl Debug for GroupingSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let keys_name = |keys: &[Expression]| {
+            let keys = keys.iter().map(|key| format!("{:?}", key));
+            keys.collect::<Vec<_>>().join(", ")
+        };
+
+        match self {
+            GroupingSpec::Plain => write!(f, "PLAIN"),
+            GroupingSpec::Rollup(keys) => write!(f, "ROLLUP({})", keys_name(keys)),
+            GroupingSpec::Cube(keys) => write!(f, "CUBE({})", keys_name(keys)),
+            GroupingSpec::Sets(sets) => {
+                let sets = sets.iter().map(|keys| format!("({})", keys_name(keys)));
+                write!(f, "GROUPING SETS ({})", sets.collect::<Vec<_>>().join(", "))
+            }
+            GroupingSpec::All => write!(f, "ALL"),
+        }
+    }
+}
+
+impl Debug for SetOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self.all {
+            true => write!(f, "{} ALL {:?}", self.op, self.query),
+            false => write!(f, "{} {:?}", self.op, self.query),
+        }
+    }
+}
+
+impl Debug for QueryHint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            QueryHint::Broadcast(name_parts) => write!(f, "BROADCAST({})", name_parts.join(".")),
+            QueryHint::Skew(column) => write!(f, "SKEW({:?})", column),
+        }
+    }
+}
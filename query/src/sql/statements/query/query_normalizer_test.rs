@@ -115,6 +115,21 @@ async fn test_query_normalizer() -> Result<()> {
             query: "SELECT SUM(number) AS number1 FROM numbers(100) GROUP BY number ORDER BY number1",
             expect: "NormalQuery { group by: [number], aggregate: [SUM(number)], order by: [SUM(number)], projection: [SUM(number) as number1] }",
         },
+        TestCase {
+            name: "Alias with the same name as a function",
+            query: "SELECT count AS count FROM t GROUP BY count ORDER BY count",
+            expect: "NormalQuery { group by: [count], order by: [count], projection: [count as count] }",
+        },
+        TestCase {
+            name: "Quoted reserved word column with alias",
+            query: "SELECT \"count\" AS c FROM t ORDER BY c",
+            expect: "NormalQuery { order by: [count], projection: [count as c] }",
+        },
+        TestCase {
+            name: "Same alias on the same expression",
+            query: "SELECT number AS a, number AS a FROM numbers(100)",
+            expect: "NormalQuery { projection: [number as a, number as a] }",
+        },
     ];
 
     for test_case in &tests {
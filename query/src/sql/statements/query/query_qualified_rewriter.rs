@@ -12,37 +12,278 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Instant;
 
+use common_datablocks::DataBlock;
+use common_datavalues::aggregate_types;
+use common_datavalues::columns::DataColumn;
+use common_datavalues::is_date_or_date_time;
+use common_datavalues::is_integer;
+use common_datavalues::is_numeric;
+use common_datavalues::is_signed_numeric;
+use common_datavalues::merge_types;
+use common_datavalues::DataField;
+use common_datavalues::DataSchema;
+use common_datavalues::DataSchemaRef;
+use common_datavalues::DataSchemaRefExt;
+use common_datavalues::DataType;
+use common_datavalues::DataValue;
 use common_exception::ErrorCode;
 use common_exception::Result;
+use common_functions::scalars::FunctionFactory;
+use common_infallible::Mutex;
+use common_meta_types::TableInfo;
+use common_planners::find_aggregate_exprs_in_expr;
+use common_planners::find_column_exprs;
+use common_planners::find_columns_not_in_group_by;
+use common_planners::find_grouping_exprs_in_expr;
+use common_planners::find_window_exprs_in_expr;
+use common_planners::not;
+use common_planners::CastOnError;
+use common_planners::ExprRewriter;
 use common_planners::Expression;
+use common_planners::ExpressionVisitor;
+use common_planners::NullTreatment;
+use common_planners::PlanNode;
+use common_planners::PlanVisitor;
+use common_planners::ReadDataSourcePlan;
+use common_planners::Recursion;
+use common_planners::WindowFrame;
+use common_planners::WindowFrameBound;
+use common_planners::WindowFrameUnits;
 
+use crate::catalogs::PSEUDO_COLUMN_NAMES;
+use crate::pipelines::transforms::ExpressionExecutor;
 use crate::sessions::QueryContext;
+use crate::sessions::RewritePhaseTiming;
+use crate::sql::statements::analyzer_expr::ExpressionAnalyzer;
 use crate::sql::statements::query::query_schema_joined::JoinedTableDesc;
+use crate::sql::statements::query::GroupingSpec;
+use crate::sql::statements::query::JoinCondition;
 use crate::sql::statements::query::JoinedSchema;
+use crate::sql::statements::query::QueryHint;
+use crate::sql::statements::query::RelationId;
 use crate::sql::statements::QueryASTIR;
+use crate::sql::statements::QueryAnalyzeState;
+use crate::sql::statements::QueryRelation;
+
+/// A column expanded from `*`, `qualified` is set if the short name is ambiguous,
+/// so it is emitted as `relation.short_name`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExpandedColumn {
+    pub relation: String,
+    pub short_name: String,
+    pub qualified: bool,
+    pub data_type: DataType,
+}
+
+/// A column resolved to a table of the query, `ambiguity_prefixed` is set if the short name
+/// is ambiguous among the joined tables, so the column is named `relation.short_name`.
+#[derive(Clone)]
+pub struct ResolvedColumn {
+    pub column: Expression,
+    pub table_desc: JoinedTableDesc,
+    pub ambiguity_prefixed: bool,
+}
 
 pub struct QualifiedRewriter {
-    tables_schema: JoinedSchema,
+    // Scope stack of the nested queries, the innermost(current) query is the last one.
+    scopes: Vec<JoinedSchema>,
+    // Columns resolved in an outer scope and the depth of that scope.
+    outer_references: Mutex<Vec<(Expression, usize)>>,
+    // IN subqueries eligible for semi-join, collected if `enable_semi_join_hint` is set.
+    semi_join_subqueries: Mutex<Vec<String>>,
+    // Outer scopes that are not visible to the query, e.g. the enclosing query of a
+    // non-lateral subquery in FROM. Only used to report a more helpful error.
+    hidden_scopes: Vec<JoinedSchema>,
+    // The query is the body of a view, whose column names must be stable.
+    view_body: bool,
+    // The table of the current scope that the ambiguous bare columns are resolved to.
+    preferred_table: Option<usize>,
+    // Depth of the `rewrite_expr` recursion, bounded by `max_expression_depth`.
+    expr_depth: Cell<usize>,
+    max_expression_depth: u64,
+    // Columns resolved to the tables of the query, in the order they are first resolved.
+    resolved_columns: Mutex<Vec<ResolvedColumn>>,
+    // Qualify every resolved column with its table, set by `always_qualify_columns`.
+    always_qualify: bool,
     ctx: Arc<QueryContext>,
 }
 
 impl QualifiedRewriter {
     pub fn create(tables_schema: JoinedSchema, ctx: Arc<QueryContext>) -> QualifiedRewriter {
-        QualifiedRewriter { tables_schema, ctx }
+        Self::create_with_outer_scopes(vec![], tables_schema, ctx)
+    }
+
+    pub fn create_with_outer_scopes(
+        mut outer_scopes: Vec<JoinedSchema>,
+        tables_schema: JoinedSchema,
+        ctx: Arc<QueryContext>,
+    ) -> QualifiedRewriter {
+        outer_scopes.push(tables_schema);
+        let always_qualify = match ctx.get_settings().get_always_qualify_columns() {
+            Ok(value) => value != 0,
+            Err(_) => false,
+        };
+        let max_expression_depth = match ctx.get_settings().get_max_expression_depth() {
+            Ok(value) => value,
+            Err(_) => 1000,
+        };
+
+        QualifiedRewriter {
+            scopes: outer_scopes,
+            outer_references: Mutex::new(vec![]),
+            semi_join_subqueries: Mutex::new(vec![]),
+            hidden_scopes: vec![],
+            view_body: false,
+            preferred_table: None,
+            expr_depth: Cell::new(0),
+            resolved_columns: Mutex::new(vec![]),
+            always_qualify,
+            max_expression_depth,
+            ctx,
+        }
+    }
+
+    pub fn with_hidden_scopes(mut self, hidden_scopes: Vec<JoinedSchema>) -> QualifiedRewriter {
+        self.hidden_scopes = hidden_scopes;
+        self
+    }
+
+    /// Analyze the body of `CREATE VIEW`, under `strict_mode` its computed projection
+    /// expressions must be aliased explicitly.
+    pub fn for_view_body(mut self) -> QualifiedRewriter {
+        self.view_body = true;
+        self
+    }
+
+    /// Resolve the ambiguous bare columns to the table `name_parts`, such as the fact table
+    /// of a query joining the dimension tables. It must be one of the joined tables.
+    pub fn with_preferred_table(mut self, name_parts: &[String]) -> Result<QualifiedRewriter> {
+        let tables_desc = self.current_scope().get_tables_desc();
+        let matched = tables_desc
+            .iter()
+            .enumerate()
+            .filter(|(_, table_desc)| table_desc.get_name_parts().ends_with(name_parts))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+
+        match matched.as_slice() {
+            [index] => {
+                self.preferred_table = Some(*index);
+                Ok(self)
+            }
+            _ => Err(ErrorCode::UnknownTable(format!(
+                "Preferred table {} is not one of the joined tables",
+                name_parts.join(".")
+            ))),
+        }
+    }
+
+    pub fn rewrite(&self, mut ir: QueryASTIR) -> Result<QueryASTIR> {
+        let profiling = self.ctx.get_settings().get_enable_analyze_profiling()? != 0;
+        let mode = self.ctx.get_settings().get_analyze_error_mode()?;
+        let mut errors = ClauseErrors::create(mode);
+        self.check_nesting_limits()?;
+
+        errors.collect(
+            Clause::Projection,
+            self.resolve_window_order_positions(&mut ir),
+        )?;
+        let res = self.timed_phase(
+            profiling,
+            "join conditions",
+            &mut ir,
+            Self::rewrite_join_conditions,
+        );
+        errors.collect(Clause::From, res)?;
+        let res = self.timed_phase(profiling, "group", &mut ir, Self::rewrite_group);
+        errors.collect(Clause::GroupBy, res)?;
+        let res = self.timed_phase(profiling, "order", &mut ir, Self::rewrite_order);
+        errors.collect(Clause::OrderBy, res)?;
+        let res = self.timed_phase(profiling, "aggregate", &mut ir, Self::rewrite_aggregate);
+        errors.collect(Clause::Projection, res)?;
+        let res = self.timed_phase(profiling, "window", &mut ir, Self::rewrite_window);
+        errors.collect(Clause::Projection, res)?;
+        let res = self.timed_phase(profiling, "projection", &mut ir, Self::rewrite_projection);
+        errors.collect(Clause::Projection, res)?;
+        errors.collect(Clause::Projection, self.check_view_projection_aliases(&ir))?;
+        errors.collect(Clause::OrderBy, self.resolve_order_positions(&mut ir))?;
+        errors.collect(Clause::Projection, self.rewrite_distinct(&mut ir))?;
+        errors.collect(Clause::Projection, self.dedup_projection(&mut ir))?;
+        errors.collect(Clause::Projection, self.check_output_names(&ir))?;
+        let res = self.timed_phase(profiling, "predicates", &mut ir, Self::rewrite_predicates);
+        errors.collect(Clause::Where, res)?;
+        errors.collect(Clause::From, self.check_implicit_cross_joins(&ir))?;
+        let res = self.timed_phase(profiling, "limit", &mut ir, Self::rewrite_limit);
+        errors.collect(Clause::Limit, res)?;
+
+        ir.outer_references = self.outer_references.lock().clone();
+        errors.collect(Clause::Projection, Self::check_aggregate_projection(&ir))?;
+        errors.finish()?;
+        self.rewrite_hints(&mut ir);
+
+        ir.semi_join_subqueries = self.semi_join_subqueries.lock().clone();
+        self.tag_identical_subqueries(&mut ir)?;
+        Ok(ir)
     }
 
-    pub async fn rewrite(&self, mut ir: QueryASTIR) -> Result<QueryASTIR> {
-        self.rewrite_group(&mut ir)?;
-        self.rewrite_order(&mut ir)?;
-        self.rewrite_aggregate(&mut ir)?;
-        self.rewrite_projection(&mut ir)?;
+    // The nesting of the subquery scopes and the number of joined tables are bounded,
+    // a huge query is rejected instead of exhausting the stack.
+    fn check_nesting_limits(&self) -> Result<()> {
+        let settings = self.ctx.get_settings();
+        let max_subquery_depth = settings.get_max_subquery_depth()?;
+        // The outer scopes are the enclosing queries of the subquery.
+        let subquery_depth = self.scopes.len() - 1;
+        if subquery_depth as u64 > max_subquery_depth {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Too deeply nested subqueries: {}, the limit is max_subquery_depth = {}",
+                subquery_depth, max_subquery_depth
+            )));
+        }
+
+        let max_joined_tables = settings.get_max_joined_tables()?;
+        let joined_tables = self.current_scope().get_tables_desc().len();
+        if joined_tables as u64 > max_joined_tables {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Too many joined tables: {}, the limit is max_joined_tables = {}",
+                joined_tables, max_joined_tables
+            )));
+        }
+
+        Ok(())
+    }
+
+    // Run a rewrite phase, its duration is recorded into the context if profiling.
+    fn timed_phase(
+        &self,
+        profiling: bool,
+        phase: &'static str,
+        ir: &mut QueryASTIR,
+        rewrite: fn(&Self, &mut QueryASTIR) -> Result<()>,
+    ) -> Result<()> {
+        if !profiling {
+            return rewrite(self, ir);
+        }
+
+        let start = Instant::now();
+        let res = rewrite(self, ir);
+        self.ctx.push_rewrite_timing(RewritePhaseTiming {
+            depth: self.scopes.len() - 1,
+            phase,
+            elapsed: start.elapsed(),
+        });
+        res
+    }
 
+    fn rewrite_predicates(&self, ir: &mut QueryASTIR) -> Result<()> {
         if let Some(predicate) = &ir.filter_predicate {
             match self.rewrite_expr(predicate) {
                 Ok(predicate) => {
-                    ir.filter_predicate = Some(predicate);
+                    ir.filter_predicate = Some(self.simplify_boolean_predicate(predicate)?);
                 }
                 Err(cause) => {
                     return Err(cause.add_message_back(format!(
@@ -54,7 +295,12 @@ impl QualifiedRewriter {
         }
 
         if let Some(predicate) = &ir.having_predicate {
-            match self.rewrite_expr(predicate) {
+            let rewritten = self.rewrite_expr(predicate).and_then(|rewritten| {
+                Self::check_aggregate_having(ir, &rewritten)?;
+                Ok(rewritten)
+            });
+
+            match rewritten {
                 Ok(predicate) => {
                     ir.having_predicate = Some(predicate);
                 }
@@ -67,266 +313,3601 @@ impl QualifiedRewriter {
             }
         }
 
-        Ok(ir)
-    }
-
-    fn rewrite_group(&self, mut ir: &mut QueryASTIR) -> Result<()> {
-        let mut group_expressions = Vec::with_capacity(ir.group_by_expressions.len());
-
-        for group_by_expression in &ir.group_by_expressions {
-            match self.rewrite_expr(group_by_expression) {
-                Ok(expr) => {
-                    group_expressions.push(expr);
+        if let Some(predicate) = &ir.qualify_predicate {
+            match self.rewrite_qualify(predicate) {
+                Ok(predicate) => {
+                    ir.qualify_predicate = Some(predicate);
                 }
                 Err(cause) => {
                     return Err(cause.add_message_back(format!(
-                        " (while in analyze group expr: {:?})",
-                        group_by_expression
+                        " (while in analyze qualify predicate {:?})",
+                        predicate
                     )));
                 }
             }
         }
 
-        ir.group_by_expressions = group_expressions;
         Ok(())
     }
 
-    fn rewrite_aggregate(&self, mut ir: &mut QueryASTIR) -> Result<()> {
-        let mut aggregate_expressions = Vec::with_capacity(ir.aggregate_expressions.len());
+    // A correlated subquery fails in its own analysis for now, so the subqueries with the
+    // same plan return the same result, the planner only needs to evaluate one of them.
+    fn tag_identical_subqueries(&self, ir: &mut QueryASTIR) -> Result<()> {
+        if self.ctx.get_settings().get_merge_identical_subqueries()? == 0 {
+            return Ok(());
+        }
 
-        for aggregate_expression in &ir.aggregate_expressions {
-            match self.rewrite_expr(aggregate_expression) {
-                Ok(expr) => {
-                    aggregate_expressions.push(expr);
-                }
-                Err(cause) => {
-                    return Err(cause.add_message_back(format!(
-                        " (while in analyze aggregate expr: {:?})",
-                        aggregate_expression
-                    )));
+        let mut collector = SubqueryCollector { subqueries: vec![] };
+        for expression in &Self::clause_expressions(ir) {
+            collector = expression.accept(collector)?;
+        }
+
+        let mut distinct_plans: Vec<(bool, Arc<PlanNode>)> = vec![];
+        for (name, scalar, query_plan) in collector.subqueries {
+            let id = match distinct_plans
+                .iter()
+                .position(|(s, plan)| *s == scalar && plan == &query_plan)
+            {
+                Some(id) => id,
+                None => {
+                    distinct_plans.push((scalar, query_plan));
+                    distinct_plans.len() - 1
                 }
+            };
+
+            if !ir.subquery_ids.iter().any(|(tagged, _)| tagged == &name) {
+                ir.subquery_ids.push((name, id));
             }
         }
 
-        ir.aggregate_expressions = aggregate_expressions;
         Ok(())
     }
 
-    fn rewrite_order(&self, mut ir: &mut QueryASTIR) -> Result<()> {
-        let mut order_expressions = Vec::with_capacity(ir.order_by_expressions.len());
+    // The expressions of the clauses, the aggregate and window functions are in them.
+    fn clause_expressions(ir: &QueryASTIR) -> Vec<Expression> {
+        let mut expressions = ir.join_conditions.clone();
+        expressions.extend(ir.filter_predicate.iter().cloned());
+        expressions.extend(ir.projection_expressions.iter().cloned());
+        expressions.extend(ir.group_by_expressions.iter().cloned());
+        expressions.extend(ir.having_predicate.iter().cloned());
+        expressions.extend(ir.qualify_predicate.iter().cloned());
+        expressions.extend(ir.order_by_expressions.iter().cloned());
+        expressions.extend(ir.distinct_on_expressions.iter().cloned());
+        expressions
+    }
 
-        for order_by_expression in &ir.order_by_expressions {
-            match self.rewrite_expr(order_by_expression) {
-                Ok(expr) => {
-                    order_expressions.push(expr);
+    /// The base tables read by the rewritten query as `[database, table]`, in order of
+    /// appearance and without duplicates. The CTEs, subqueries and VALUES in FROM and the
+    /// subqueries in the expressions are reported by the base tables they read.
+    pub fn referenced_tables(&self, ir: &QueryASTIR) -> Result<Vec<Vec<String>>> {
+        let mut collector = ReferencedTablesCollector { tables: vec![] };
+        for table_desc in self.current_scope().get_tables_desc() {
+            match table_desc {
+                JoinedTableDesc::Table { table, .. } => {
+                    collector.add_table(table.get_table_info());
                 }
-                Err(cause) => {
-                    return Err(cause.add_message_back(format!(
-                        " (while in analyze order expr: {:?})",
-                        order_by_expression
-                    )));
+                JoinedTableDesc::Subquery { state, .. } => {
+                    collector.visit_analyze_state(state)?;
+                }
+                JoinedTableDesc::Values { rows, .. } => {
+                    for row in rows {
+                        collector.visit_exprs(row)?;
+                    }
                 }
             }
         }
 
-        ir.order_by_expressions = order_expressions;
-        Ok(())
+        collector.visit_exprs(&Self::clause_expressions(ir))?;
+        Ok(collector.tables)
     }
 
-    fn rewrite_projection(&self, mut ir: &mut QueryASTIR) -> Result<()> {
-        let mut projection_expressions = Vec::with_capacity(ir.projection_expressions.len());
+    // `FROM a, b` is a cross join unless a predicate of the filter or the join conditions
+    // references the both sides, the explicit joins are connected by their syntax.
+    fn check_implicit_cross_joins(&self, ir: &QueryASTIR) -> Result<()> {
+        if self.ctx.get_settings().get_forbid_implicit_cross_join()? == 0 {
+            return Ok(());
+        }
 
-        // TODO: alias.*
-        for projection_expression in &ir.projection_expressions {
-            if let Expression::Alias(_, x) = projection_expression {
-                if let Expression::Wildcard = x.as_ref() {
-                    return Err(ErrorCode::SyntaxException("* AS alias is wrong syntax"));
-                }
+        let scope = self.current_scope();
+        let relations = scope.get_implicit_joined();
+        let mut roots = (0..scope.get_tables_desc().len()).collect::<Vec<_>>();
+
+        for relation in relations {
+            for table in relation.clone() {
+                let root = Self::find_root(&roots, table);
+                roots[root] = Self::find_root(&roots, relation.start);
+            }
+        }
+
+        let mut predicates = vec![];
+        for join_condition in &ir.join_conditions {
+            Self::split_conjunctions(join_condition, &mut predicates);
+        }
+        if let Some(predicate) = &ir.filter_predicate {
+            Self::split_conjunctions(predicate, &mut predicates);
+        }
+
+        for predicate in predicates {
+            let columns = find_column_exprs(std::slice::from_ref(predicate));
+            let tables = columns.iter().filter_map(|column| match column {
+                Expression::Column(name) => scope.find_column_table(name),
+                _ => None,
+            });
+
+            let tables = tables.map(|(table, _)| table).collect::<Vec<_>>();
+            for table in tables.iter().skip(1) {
+                let root = Self::find_root(&roots, *table);
+                roots[root] = Self::find_root(&roots, tables[0]);
             }
+        }
 
-            match projection_expression {
-                Expression::Wildcard => self.expand_wildcard(&mut projection_expressions),
-                _ => match self.rewrite_expr(projection_expression) {
-                    Ok(expr) => {
-                        projection_expressions.push(expr);
-                    }
-                    Err(cause) => {
-                        return Err(cause.add_message_back(format!(
-                            " (while in analyze projection expr: {:?})",
-                            projection_expression
-                        )));
-                    }
-                },
+        let first = match relations.first() {
+            None => return Ok(()),
+            Some(first) => first.start,
+        };
+
+        for relation in relations.iter().skip(1) {
+            if Self::find_root(&roots, relation.start) != Self::find_root(&roots, first) {
+                let tables = scope.get_tables_desc();
+                let table_name = |table: usize| {
+                    let name_parts = tables[table].get_name_parts();
+                    name_parts.last().cloned().unwrap_or_default()
+                };
+
+                return Err(ErrorCode::SyntaxException(format!(
+                    "implicit cross join between {} and {}; add a join condition or use CROSS JOIN",
+                    table_name(first),
+                    table_name(relation.start)
+                )));
             }
         }
 
-        ir.projection_expressions = projection_expressions;
         Ok(())
     }
 
-    fn expand_wildcard(&self, columns_expression: &mut Vec<Expression>) {
-        for table_desc in self.tables_schema.get_tables_desc() {
-            for column_desc in table_desc.get_columns_desc() {
-                let name = column_desc.short_name.clone();
-                match column_desc.is_ambiguity {
-                    true => {
-                        let prefix = table_desc.get_name_parts().join(".");
-                        columns_expression.push(Expression::Column(format!("{}.{}", prefix, name)));
-                    }
-                    false => columns_expression.push(Expression::Column(name)),
-                }
-            }
+    // The disjoint sets of the tables connected to each other, `roots[table]` is the parent.
+    fn find_root(roots: &[usize], mut table: usize) -> usize {
+        while roots[table] != table {
+            table = roots[table];
         }
+        table
     }
 
-    fn rewrite_expr(&self, expr: &Expression) -> Result<Expression> {
+    fn split_conjunctions<'a>(expr: &'a Expression, conjunctions: &mut Vec<&'a Expression>) {
         match expr {
-            Expression::Column(v) => match self.tables_schema.contains_column(v) {
-                true => Ok(Expression::Column(v.clone())),
-                false => Err(ErrorCode::UnknownColumn(format!("Unknown column {}", v))),
-            },
-            Expression::QualifiedColumn(names) => self.rewrite_qualified_column(names),
-            Expression::Alias(alias, expr) => Ok(Expression::Alias(
-                alias.clone(),
-                Box::new(self.rewrite_expr(expr)?),
-            )),
-            Expression::UnaryExpression { op, expr } => Ok(Expression::UnaryExpression {
-                op: op.clone(),
-                expr: Box::new(self.rewrite_expr(expr)?),
-            }),
-            Expression::BinaryExpression { left, op, right } => Ok(Expression::BinaryExpression {
-                op: op.clone(),
-                left: Box::new(self.rewrite_expr(left)?),
-                right: Box::new(self.rewrite_expr(right)?),
-            }),
-            Expression::ScalarFunction { op, args } => {
-                let mut new_args = Vec::with_capacity(args.len());
+            Expression::BinaryExpression { left, op, right } if op.eq_ignore_ascii_case("and") => {
+                Self::split_conjunctions(left, conjunctions);
+                Self::split_conjunctions(right, conjunctions);
+            }
+            _ => conjunctions.push(expr),
+        }
+    }
 
-                for arg in args {
-                    new_args.push(self.rewrite_expr(arg)?);
+    // Without GROUP BY, an aggregate query returns a single row, so the projection
+    // cannot reference a column outside the aggregate functions. With ROLLUP, CUBE or
+    // GROUPING SETS, the columns must be inside the grouping keys, which may be any
+    // expression, such as `GROUPING SETS ((SUBSTRING(name, 1, 2)), ())`.
+    fn check_aggregate_projection(ir: &QueryASTIR) -> Result<()> {
+        let group_by = match &ir.grouping {
+            GroupingSpec::Plain if ir.group_by_expressions.is_empty() => {
+                if ir.aggregate_expressions.is_empty() {
+                    return Ok(());
                 }
-
-                Ok(Expression::ScalarFunction {
-                    op: op.clone(),
-                    args: new_args,
-                })
+                &[]
             }
-            Expression::AggregateFunction {
-                op,
-                distinct,
-                params,
-                args,
-            } => {
-                let mut new_args = Vec::with_capacity(args.len());
+            GroupingSpec::Plain => return Ok(()),
+            _ => ir.group_by_expressions.as_slice(),
+        };
 
-                for arg in args {
-                    new_args.push(self.rewrite_expr(arg)?);
+        for projection_expression in &ir.projection_expressions {
+            for column in find_columns_not_in_group_by(projection_expression, group_by) {
+                // The columns of the outer queries are constant in a subquery.
+                if ir
+                    .outer_references
+                    .iter()
+                    .any(|(outer, _)| outer == &column)
+                {
+                    continue;
                 }
 
-                Ok(Expression::AggregateFunction {
-                    op: op.clone(),
-                    distinct: *distinct,
-                    params: params.clone(),
-                    args: new_args,
-                })
+                return Err(ErrorCode::SyntaxException(format!(
+                    "column '{}' must appear in GROUP BY or be used in an aggregate",
+                    column.column_name()
+                )));
             }
-            Expression::Sort {
-                expr,
-                asc,
-                nulls_first,
-                origin_expr,
-            } => Ok(Expression::Sort {
-                expr: Box::new(self.rewrite_expr(expr)?),
-                asc: *asc,
-                nulls_first: *nulls_first,
-                origin_expr: Box::new(self.rewrite_expr(origin_expr)?),
-            }),
-            Expression::Cast { expr, data_type } => Ok(Expression::Cast {
-                expr: Box::new(self.rewrite_expr(expr)?),
-                data_type: data_type.clone(),
-            }),
-            Expression::Wildcard
-            | Expression::Literal { .. }
-            | Expression::Subquery { .. }
-            | Expression::ScalarSubquery { .. } => Ok(expr.clone()),
         }
+
+        Ok(())
     }
 
-    fn rewrite_qualified_column(&self, ref_names: &[String]) -> Result<Expression> {
-        match self.best_match_table(ref_names) {
-            None => Err(ErrorCode::UnknownColumn(format!(
-                "Unknown column {}",
-                ref_names.join(".")
-            ))),
-            Some((pos, table_ref)) => {
-                let column_name = &ref_names[pos..];
-                match column_name.len() {
-                    1 => Self::find_column(&table_ref, &column_name[0]),
-                    // TODO: column.field_a.field_b => GetField(field_b, GetField(field_a, column))
-                    _ => Err(ErrorCode::SyntaxException(
-                        "Unsupported complex type field access",
-                    )),
+    fn current_scope(&self) -> &JoinedSchema {
+        &self.scopes[self.scopes.len() - 1]
+    }
+
+    fn rewrite_join_conditions(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        let join_conditions = self.current_scope().get_join_conditions();
+        let mut rewritten_conditions = Vec::with_capacity(join_conditions.len());
+
+        for join_condition in join_conditions {
+            match self.rewrite_join_condition(join_condition) {
+                Ok(condition) => rewritten_conditions.push(condition),
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze join condition {:?})",
+                        join_condition.expr
+                    )));
                 }
             }
         }
+
+        ir.join_conditions = rewritten_conditions;
+        Ok(())
     }
 
-    fn find_column(table_desc: &JoinedTableDesc, name: &str) -> Result<Expression> {
-        let name_parts = table_desc.get_name_parts();
-        for column_desc in table_desc.get_columns_desc() {
-            if column_desc.short_name == name {
-                return match column_desc.is_ambiguity {
-                    true => Ok(Expression::Column(format!(
-                        "{}.{}",
-                        name_parts.join("."),
-                        name
-                    ))),
-                    false => Ok(Expression::Column(name.to_string())),
-                };
-            }
+    // The columns are first resolved against the tables visible to the join, so that
+    // a table joined later cannot be referenced, then rewritten in the whole FROM clause.
+    // The output-invisible side of a semi-join is visible to the join condition.
+    fn rewrite_join_condition(&self, join_condition: &JoinCondition) -> Result<Expression> {
+        let outer_scopes = self.scopes[..self.scopes.len() - 1].to_vec();
+        let scope = self.current_scope().to_output_visible();
+        let join_scope = scope.sub_schema(&join_condition.tables)?;
+        let join_rewriter = QualifiedRewriter::create_with_outer_scopes(
+            outer_scopes.clone(),
+            join_scope,
+            self.ctx.clone(),
+        );
+
+        join_condition.expr.accept(JoinScopeVisitor {
+            rewriter: &join_rewriter,
+        })?;
+
+        if !self.current_scope().has_output_invisible() {
+            return self.rewrite_expr(&join_condition.expr);
         }
 
-        Err(ErrorCode::UnknownColumn(format!(
-            "Unknown column: {}.{}",
-            name_parts.join("."),
-            name
-        )))
+        let rewriter =
+            QualifiedRewriter::create_with_outer_scopes(outer_scopes, scope, self.ctx.clone());
+        let condition = rewriter.rewrite_expr(&join_condition.expr)?;
+        for (column, depth) in rewriter.outer_references.lock().iter() {
+            self.add_outer_reference(column, *depth);
+        }
+
+        Ok(condition)
     }
 
-    fn first_diff_pos(left: &[String], right: &[String]) -> usize {
-        let min_len = std::cmp::min(left.len(), right.len());
+    fn rewrite_qualify(&self, predicate: &Expression) -> Result<Expression> {
+        if find_window_exprs_in_expr(predicate).is_empty() {
+            return Err(ErrorCode::SyntaxException(
+                "QUALIFY clause must contain at least one window function",
+            ));
+        }
 
-        for index in 0..min_len {
-            if left[index] != right[index] {
-                return index;
+        self.rewrite_expr(predicate)
+    }
+
+    // A hint referencing an unknown table or column is dropped with a warning instead of
+    // failing the query, even under strict mode.
+    fn rewrite_hints(&self, ir: &mut QueryASTIR) {
+        let mut hints = Vec::with_capacity(ir.hints.len());
+        for hint in &ir.hints {
+            match hint {
+                QueryHint::Broadcast(name_parts) => {
+                    let tables_desc = self.current_scope().get_tables_desc();
+                    match tables_desc.iter().any(|table_desc| {
+                        let table_name_parts = table_desc.get_name_parts();
+                        !table_name_parts.is_empty() && table_name_parts.ends_with(name_parts)
+                    }) {
+                        true => hints.push(hint.clone()),
+                        false => self.ctx.push_warning(format!(
+                            "Ignored hint {:?}, unknown table {}",
+                            hint,
+                            name_parts.join(".")
+                        )),
+                    }
+                }
+                QueryHint::Skew(column) => match self.rewrite_expr(column) {
+                    Ok(column) => hints.push(QueryHint::Skew(column)),
+                    Err(cause) => self.ctx.push_warning(format!(
+                        "Ignored hint {:?}, {}",
+                        hint,
+                        cause.message()
+                    )),
+                },
             }
         }
 
-        min_len
+        ir.hints = hints;
     }
 
-    fn best_match_table(&self, ref_names: &[String]) -> Option<(usize, JoinedTableDesc)> {
-        if ref_names.len() <= 1 {
-            return None;
+    fn rewrite_limit(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        if let Some(limit) = &ir.limit {
+            match self.rewrite_limit_expr(limit) {
+                Ok(limit) => {
+                    ir.limit = Some(limit);
+                }
+                Err(cause) => {
+                    return Err(
+                        cause.add_message_back(format!(" (while in analyze limit {:?})", limit))
+                    );
+                }
+            }
         }
 
-        let current_database = self.ctx.get_current_database();
-        for table_desc in self.tables_schema.get_tables_desc() {
-            let name_parts = table_desc.get_name_parts();
-            if Self::first_diff_pos(ref_names, name_parts) == name_parts.len() {
-                // alias.column or database.table.column
-                return Some((name_parts.len(), table_desc.clone()));
+        if let Some(offset) = &ir.offset {
+            match self.rewrite_limit_expr(offset) {
+                Ok(offset) => {
+                    ir.offset = Some(offset);
+                }
+                Err(cause) => {
+                    return Err(
+                        cause.add_message_back(format!(" (while in analyze offset {:?})", offset))
+                    );
+                }
             }
+        }
 
-            if name_parts.len() > 1
-                && Self::first_diff_pos(ref_names, &name_parts[1..]) == 1
-                && current_database == name_parts[0]
+        Ok(())
+    }
+
+    fn rewrite_limit_expr(&self, expr: &Expression) -> Result<Expression> {
+        if !Self::is_constant_expr(expr) {
+            return Err(ErrorCode::SyntaxException(
+                "LIMIT must be a constant expression",
+            ));
+        }
+
+        // The literal operands are checked before the evaluation even if the constant
+        // folding is disabled, the executor panics on `10 % 0` and wraps on overflow.
+        let count = match self.rewrite_expr(expr)?.rewrite(&mut ConstantFolder)? {
+            literal @ Expression::Literal { .. } => literal,
+            expr => Self::evaluate_constant_expr(expr)?,
+        };
+
+        match &count {
+            Expression::Literal { value, .. }
+                if value.is_null() || !is_integer(&value.data_type()) =>
             {
-                // use current_database; table.column
-                return Some((1, table_desc.clone()));
+                Err(ErrorCode::SyntaxException(
+                    "LIMIT must be a constant expression",
+                ))
+            }
+            Expression::Literal { value, .. }
+                if is_integer(&value.data_type())
+                    && is_signed_numeric(&value.data_type())
+                    && value.as_i64()? < 0 =>
+            {
+                Err(ErrorCode::SyntaxException(format!(
+                    "LIMIT must be a non-negative constant, but got {:?}",
+                    count
+                )))
             }
+            _ => Ok(count),
         }
+    }
 
-        None
+    fn rewrite_group(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        if ir.grouping == GroupingSpec::All {
+            ir.group_by_expressions = self.group_by_all_keys(ir)?;
+            ir.grouping = GroupingSpec::Plain;
+            return Ok(());
+        }
+
+        let mut group_expressions = Vec::with_capacity(ir.group_by_expressions.len());
+
+        for group_by_expression in &ir.group_by_expressions {
+            match self.rewrite_expr(group_by_expression) {
+                Ok(expr) => {
+                    group_expressions.push(expr);
+                }
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze group expr: {:?})",
+                        group_by_expression
+                    )));
+                }
+            }
+        }
+
+        ir.group_by_expressions = group_expressions;
+        ir.grouping = match &ir.grouping {
+            GroupingSpec::Plain => GroupingSpec::Plain,
+            GroupingSpec::Rollup(keys) => GroupingSpec::Rollup(self.rewrite_group_keys(keys)?),
+            GroupingSpec::Cube(keys) => GroupingSpec::Cube(self.rewrite_group_keys(keys)?),
+            GroupingSpec::Sets(sets) => {
+                let mut grouping_sets = Vec::with_capacity(sets.len());
+                for keys in sets {
+                    grouping_sets.push(self.rewrite_group_keys(keys)?);
+                }
+                GroupingSpec::Sets(grouping_sets)
+            }
+        };
+
+        // Qualified and unqualified keys may resolve to the same column
+        if ir.grouping != GroupingSpec::Plain {
+            let mut group_keys: Vec<Expression> = Vec::with_capacity(ir.group_by_expressions.len());
+            for group_key in &ir.group_by_expressions {
+                if !group_keys.contains(group_key) {
+                    group_keys.push(group_key.clone());
+                }
+            }
+            ir.group_by_expressions = group_keys;
+        }
+
+        Ok(())
+    }
+
+    // The keys of `GROUP BY ALL` are the projection items without aggregate or window
+    // functions, the wildcards are expanded and the aliases are unwrapped, as if they
+    // were written out in GROUP BY.
+    fn group_by_all_keys(&self, ir: &QueryASTIR) -> Result<Vec<Expression>> {
+        let mut group_keys: Vec<Expression> = Vec::with_capacity(ir.projection_expressions.len());
+
+        for projection_expression in &ir.projection_expressions {
+            let mut keys = Vec::new();
+            match projection_expression {
+                Expression::Wildcard => self.expand_wildcard(&mut keys),
+                Expression::QualifiedWildcard {
+                    qualifier,
+                    except,
+                    rename,
+                } => self.expand_qualified_wildcard(qualifier, except, rename, &mut keys)?,
+                expr if !find_aggregate_exprs_in_expr(expr).is_empty()
+                    || !find_window_exprs_in_expr(expr).is_empty() => {}
+                expr => match self.rewrite_expr(expr) {
+                    Ok(key) => keys.push(key),
+                    Err(cause) => {
+                        return Err(cause.add_message_back(format!(
+                            " (while in analyze group expr: {:?})",
+                            expr
+                        )));
+                    }
+                },
+            }
+
+            for key in keys {
+                let key = match key {
+                    Expression::Alias(_, expr) => *expr,
+                    key => key,
+                };
+
+                if !group_keys.contains(&key) {
+                    group_keys.push(key);
+                }
+            }
+        }
+
+        Ok(group_keys)
+    }
+
+    fn rewrite_group_keys(&self, keys: &[Expression]) -> Result<Vec<Expression>> {
+        let mut group_keys = Vec::with_capacity(keys.len());
+
+        for key in keys {
+            match self.rewrite_expr(key) {
+                Ok(expr) => group_keys.push(expr),
+                Err(cause) => {
+                    return Err(cause
+                        .add_message_back(format!(" (while in analyze group expr: {:?})", key)));
+                }
+            }
+        }
+
+        Ok(group_keys)
+    }
+
+    // Each argument of GROUPING(a, b, ...) must be one of the grouping keys, which are the
+    // distinct keys of all the grouping sets after `rewrite_group`.
+    fn check_grouping_function(expr: &Expression, group_by: &[Expression]) -> Result<()> {
+        for grouping_expr in find_grouping_exprs_in_expr(expr) {
+            if let Expression::ScalarFunction { args, .. } = &grouping_expr {
+                if args.is_empty() {
+                    return Err(ErrorCode::BadArguments(
+                        "GROUPING requires at least one argument",
+                    ));
+                }
+
+                if args.iter().any(|arg| !group_by.contains(arg)) {
+                    return Err(ErrorCode::SyntaxException(
+                        "GROUPING argument must be a grouping column",
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rewrite_aggregate(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        let mut aggregate_expressions = Vec::with_capacity(ir.aggregate_expressions.len());
+
+        for aggregate_expression in &ir.aggregate_expressions {
+            match self.rewrite_expr(aggregate_expression) {
+                Ok(expr) => {
+                    aggregate_expressions.push(expr);
+                }
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze aggregate expr: {:?})",
+                        aggregate_expression
+                    )));
+                }
+            }
+        }
+
+        ir.aggregate_expressions = aggregate_expressions;
+        Ok(())
+    }
+
+    // The ordered-set aggregates compute over the input sorted by WITHIN GROUP, e.g. the
+    // percentile of the sorted values, DISTINCT would change the value they are computed on.
+    fn check_ordered_set_aggregate(
+        op: &str,
+        distinct: bool,
+        within_group: &[Expression],
+    ) -> Result<()> {
+        let ordered_set = ["percentile_cont", "percentile_disc", "mode"]
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(op));
+
+        if ordered_set && within_group.is_empty() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Ordered-set aggregate {} requires WITHIN GROUP (ORDER BY ...)",
+                op
+            )));
+        }
+
+        if ordered_set && distinct {
+            return Err(ErrorCode::SyntaxException(format!(
+                "DISTINCT is not supported for the ordered-set aggregate {}",
+                op
+            )));
+        }
+
+        Ok(())
+    }
+
+    // The null treatment decides whether the NULL values are skipped when picking a value
+    // of the window, it means nothing to the other window functions.
+    fn check_null_treatment(op: &str, null_treatment: &Option<NullTreatment>) -> Result<()> {
+        match (null_treatment, op.to_lowercase().as_str()) {
+            (None, _) => Ok(()),
+            (Some(_), "lead" | "lag" | "first_value" | "last_value" | "nth_value") => Ok(()),
+            (Some(null_treatment), _) => Err(ErrorCode::SyntaxException(format!(
+                "{:?} is only supported for LEAD, LAG, FIRST_VALUE, LAST_VALUE and NTH_VALUE, but got {}",
+                null_treatment, op
+            ))),
+        }
+    }
+
+    fn rewrite_window(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        let mut window_expressions = Vec::with_capacity(ir.window_expressions.len());
+
+        for window_expression in &ir.window_expressions {
+            match self.rewrite_expr(window_expression) {
+                // Qualified and unqualified references may resolve to the same window
+                Ok(expr) if window_expressions.contains(&expr) => {}
+                Ok(expr) => {
+                    window_expressions.push(expr);
+                }
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze window expr: {:?})",
+                        window_expression
+                    )));
+                }
+            }
+        }
+
+        ir.window_expressions = window_expressions;
+        Ok(())
+    }
+
+    fn rewrite_order(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        let mut order_expressions = Vec::with_capacity(ir.order_by_expressions.len());
+
+        for order_by_expression in &ir.order_by_expressions {
+            match self.rewrite_expr(order_by_expression) {
+                Ok(expr) => {
+                    order_expressions.push(expr);
+                }
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze order expr: {:?})",
+                        order_by_expression
+                    )));
+                }
+            }
+        }
+
+        ir.order_by_expressions = order_expressions;
+        self.check_aggregate_order(ir)
+    }
+
+    // HAVING filters the groups, so like ORDER BY it can only use the group keys, the
+    // aggregates and the expressions built from them. The projection aliases in it are
+    // already replaced by the normalizer, such as `c` of `COUNT(*) AS c`.
+    fn check_aggregate_having(ir: &QueryASTIR, predicate: &Expression) -> Result<()> {
+        if ir.group_by_expressions.is_empty() && ir.aggregate_expressions.is_empty() {
+            return Ok(());
+        }
+
+        match find_columns_not_in_group_by(predicate, &ir.group_by_expressions).first() {
+            None => Ok(()),
+            Some(column) => Err(ErrorCode::SyntaxException(format!(
+                "HAVING column '{}' must appear in GROUP BY or be used in an aggregate",
+                column.column_name()
+            ))),
+        }
+    }
+
+    // The rows of an aggregated query are the groups, so ORDER BY can only use
+    // the group keys, the aggregates and the expressions built from them.
+    fn check_aggregate_order(&self, ir: &QueryASTIR) -> Result<()> {
+        if ir.group_by_expressions.is_empty() && ir.aggregate_expressions.is_empty() {
+            return Ok(());
+        }
+
+        let outer_references = self.outer_references.lock();
+        for order_by_expression in &ir.order_by_expressions {
+            let group_by = &ir.group_by_expressions;
+            for column in find_columns_not_in_group_by(order_by_expression, group_by) {
+                // The columns of the outer queries are constant in a subquery.
+                if outer_references.iter().any(|(outer, _)| outer == &column) {
+                    continue;
+                }
+
+                return Err(ErrorCode::SyntaxException(format!(
+                    "ORDER BY column '{}' must appear in GROUP BY or be used in an aggregate",
+                    column.column_name()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // `OVER (ORDER BY 2)` refers to the second item of the projection as written. Unlike the
+    // top level ORDER BY, it's resolved before the columns, so that the projection item is
+    // rewritten along with the window. Only a bare position is resolved, `ORDER BY 2 + 1`
+    // is an ordinary expression.
+    fn resolve_window_order_positions(&self, ir: &mut QueryASTIR) -> Result<()> {
+        if ir.window_expressions.is_empty()
+            || self.ctx.get_settings().get_enable_order_by_positions()? == 0
+        {
+            return Ok(());
+        }
+
+        let mut resolver = WindowOrderPositionResolver {
+            projection: ir.projection_expressions.clone(),
+        };
+
+        let mut resolve = |exprs: &[Expression]| {
+            exprs
+                .iter()
+                .map(|expr| expr.clone().rewrite(&mut resolver))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        ir.window_expressions = resolve(&ir.window_expressions)?;
+        ir.projection_expressions = resolve(&ir.projection_expressions)?;
+        ir.order_by_expressions = resolve(&ir.order_by_expressions)?;
+        if let Some(predicate) = ir.qualify_predicate.take() {
+            ir.qualify_predicate = Some(predicate.rewrite(&mut resolver)?);
+        }
+
+        Ok(())
+    }
+
+    // `ORDER BY 1` refers to the first item of the rewritten(wildcard expanded) projection.
+    fn resolve_order_positions(&self, ir: &mut QueryASTIR) -> Result<()> {
+        if self.ctx.get_settings().get_enable_order_by_positions()? == 0 {
+            return Ok(());
+        }
+
+        for order_by_expression in ir.order_by_expressions.iter_mut() {
+            if let Expression::Sort {
+                expr, origin_expr, ..
+            } = order_by_expression
+            {
+                if let Some(position) = Self::order_position(origin_expr) {
+                    let projection_expression = Self::projection_at_position(
+                        &ir.projection_expressions,
+                        "ORDER BY",
+                        position,
+                    )?;
+                    *expr = Box::new(projection_expression.clone());
+                    *origin_expr = Box::new(projection_expression);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // `DISTINCT ON (1)` refers to the first item of the rewritten projection like ORDER BY.
+    // The leading ORDER BY expressions, which pick the row kept, must be the DISTINCT ON
+    // keys in any order, compared after the positions of both are resolved.
+    fn rewrite_distinct(&self, ir: &mut QueryASTIR) -> Result<()> {
+        let positions = self.ctx.get_settings().get_enable_order_by_positions()? != 0;
+        let mut distinct_on_expressions = Vec::with_capacity(ir.distinct_on_expressions.len());
+
+        for distinct_on_expression in &ir.distinct_on_expressions {
+            let expr = match Self::order_position(distinct_on_expression) {
+                Some(position) if positions => Self::projection_at_position(
+                    &ir.projection_expressions,
+                    "DISTINCT ON",
+                    position,
+                )?,
+                _ => self.rewrite_expr(distinct_on_expression)?,
+            };
+
+            distinct_on_expressions.push(expr);
+        }
+
+        let leading_order_by = ir
+            .order_by_expressions
+            .iter()
+            .take(distinct_on_expressions.len());
+
+        for order_by_expression in leading_order_by {
+            let expr = match order_by_expression {
+                Expression::Sort { expr, .. } => expr.as_ref(),
+                other => other,
+            };
+
+            if !distinct_on_expressions.contains(expr) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "DISTINCT ON expressions must match the leading ORDER BY expressions, but got {:?}",
+                    expr
+                )));
+            }
+        }
+
+        ir.distinct_on_expressions = distinct_on_expressions;
+        Ok(())
+    }
+
+    fn projection_at_position(
+        projection_expressions: &[Expression],
+        clause: &str,
+        position: usize,
+    ) -> Result<Expression> {
+        if position == 0 || position > projection_expressions.len() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "{} position {} is not in select list",
+                clause, position
+            )));
+        }
+
+        match &projection_expressions[position - 1] {
+            Expression::Alias(_, inner) => Ok(inner.as_ref().clone()),
+            other => Ok(other.clone()),
+        }
+    }
+
+    /// Resolve the ORDER BY of UNION, INTERSECT or EXCEPT to the output columns of the set
+    /// operations, which are named by the first SELECT. A position refers to them as well.
+    pub fn rewrite_set_order_by(
+        &self,
+        order_by: Vec<Expression>,
+        output_names: &[String],
+    ) -> Result<Vec<Expression>> {
+        let positions = self.ctx.get_settings().get_enable_order_by_positions()? != 0;
+        let mut resolver = SetOutputColumnResolver { output_names };
+        let mut order_by_expressions = Vec::with_capacity(order_by.len());
+        for order_by_expression in order_by {
+            if let Expression::Sort {
+                expr,
+                asc,
+                nulls_first,
+                ..
+            } = order_by_expression
+            {
+                let expr = match Self::order_position(&expr) {
+                    Some(position) if positions => {
+                        if position == 0 || position > output_names.len() {
+                            return Err(ErrorCode::SyntaxException(format!(
+                                "ORDER BY position {} is not in select list",
+                                position
+                            )));
+                        }
+
+                        Expression::Column(output_names[position - 1].clone())
+                    }
+                    _ => (*expr).rewrite(&mut resolver)?,
+                };
+
+                order_by_expressions.push(Expression::Sort {
+                    expr: Box::new(expr.clone()),
+                    asc,
+                    nulls_first,
+                    origin_expr: Box::new(expr),
+                });
+            }
+        }
+
+        Ok(order_by_expressions)
+    }
+
+    // The positions are the unsigned integer literals, which may be of any width.
+    fn order_position(expr: &Expression) -> Option<usize> {
+        match expr {
+            Expression::Literal { value, .. } => {
+                let data_type = value.data_type();
+                match is_integer(&data_type) && !is_signed_numeric(&data_type) {
+                    true => value.as_u64().ok().map(|position| position as usize),
+                    false => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Runs after the ORDER BY positions are resolved, which refer to the projection
+    // before the duplicates are removed. The first occurrence is kept with its alias,
+    // a later one with a different alias is not a duplicate.
+    fn dedup_projection(&self, ir: &mut QueryASTIR) -> Result<()> {
+        if self.ctx.get_settings().get_dedup_projection()? == 0 {
+            return Ok(());
+        }
+
+        let mut projection_expressions: Vec<Expression> =
+            Vec::with_capacity(ir.projection_expressions.len());
+
+        for projection_expression in &ir.projection_expressions {
+            let is_duplicate = projection_expressions.iter().any(|kept| {
+                kept == projection_expression
+                    || matches!(kept, Expression::Alias(_, kept) if kept.as_ref() == projection_expression)
+            });
+
+            if !is_duplicate {
+                projection_expressions.push(projection_expression.clone());
+            }
+        }
+
+        ir.projection_expressions = projection_expressions;
+        Ok(())
+    }
+
+    // The output names are the aliases or the expression names, compared case-sensitively
+    // like the column resolution. The same expression repeated is not a collision, e.g.
+    // `SELECT a, a` or `SELECT a AS x, a AS x`, it's the same column in the result.
+    fn check_output_names(&self, ir: &QueryASTIR) -> Result<()> {
+        let mut outputs: Vec<(String, &Expression)> =
+            Vec::with_capacity(ir.projection_expressions.len());
+
+        for projection_expression in &ir.projection_expressions {
+            let (name, expr) = match projection_expression {
+                Expression::Alias(alias, expr) => (alias.clone(), expr.as_ref()),
+                expr => (expr.column_name(), expr),
+            };
+
+            if let Some((_, previous)) = outputs.iter().find(|(output, _)| output == &name) {
+                if *previous != expr {
+                    let mut message = format!("duplicate output column name '{}'", name);
+                    let sources = [*previous, expr]
+                        .iter()
+                        .filter_map(|expr| self.column_source(expr))
+                        .collect::<Vec<_>>();
+
+                    if !sources.is_empty() {
+                        message.push_str(&format!(" (from {})", sources.join(" and ")));
+                    }
+
+                    return Err(ErrorCode::SyntaxException(message));
+                }
+            }
+
+            outputs.push((name, expr));
+        }
+
+        Ok(())
+    }
+
+    // The source `table.column` of a column output, e.g. a column expanded from a wildcard.
+    fn column_source(&self, expr: &Expression) -> Option<String> {
+        let scope = self.current_scope();
+        match expr {
+            Expression::Column(name) => scope.find_column_table(name).map(|(index, desc)| {
+                let table_desc = &scope.get_tables_desc()[index];
+                format!(
+                    "{}.{}",
+                    table_desc.get_name_parts().join("."),
+                    desc.short_name
+                )
+            }),
+            _ => None,
+        }
+    }
+
+    // The bare columns are named by themselves, the others are named by the expression text
+    // which changes with the formatting, so they must be aliased to keep the view columns stable.
+    fn check_view_projection_aliases(&self, ir: &QueryASTIR) -> Result<()> {
+        if !self.view_body || self.ctx.get_settings().get_strict_mode()? == 0 {
+            return Ok(());
+        }
+
+        for projection_expression in &ir.projection_expressions {
+            if !matches!(
+                projection_expression,
+                Expression::Column(_) | Expression::Alias(_, _)
+            ) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Computed column {:?} of a view must have an explicit alias",
+                    projection_expression
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rewrite_projection(&self, mut ir: &mut QueryASTIR) -> Result<()> {
+        let mut projection_expressions = Vec::with_capacity(ir.projection_expressions.len());
+        let mut lateral_aliases = match self
+            .ctx
+            .get_settings()
+            .get_enable_lateral_column_aliases()?
+        {
+            0 => None,
+            _ => Some(LateralAliasResolver { aliases: vec![] }),
+        };
+
+        // TODO: alias.*
+        for projection_expression in &ir.projection_expressions {
+            if let Expression::Alias(_, x) = projection_expression {
+                if let Expression::Wildcard = x.as_ref() {
+                    return Err(ErrorCode::SyntaxException("* AS alias is wrong syntax"));
+                }
+            }
+
+            match projection_expression {
+                Expression::Wildcard => self.expand_wildcard(&mut projection_expressions),
+                Expression::QualifiedWildcard {
+                    qualifier,
+                    except,
+                    rename,
+                } => {
+                    if let Err(cause) = self.expand_qualified_wildcard(
+                        qualifier,
+                        except,
+                        rename,
+                        &mut projection_expressions,
+                    ) {
+                        return Err(cause.add_message_back(format!(
+                            " (while in analyze projection expr: {:?})",
+                            projection_expression
+                        )));
+                    }
+                }
+                Expression::BinaryExpression { left, op, right }
+                    if matches!(left.as_ref(), Expression::Wildcard)
+                        && op.eq_ignore_ascii_case("ilike") =>
+                {
+                    if let Err(cause) =
+                        self.expand_wildcard_ilike(right, &mut projection_expressions)
+                    {
+                        return Err(cause.add_message_back(format!(
+                            " (while in analyze projection expr: {:?})",
+                            projection_expression
+                        )));
+                    }
+                }
+                _ => {
+                    let resolved = match lateral_aliases.as_mut() {
+                        Some(resolver) => resolver.resolve(projection_expression),
+                        None => Ok(projection_expression.clone()),
+                    };
+
+                    match resolved
+                        .and_then(|expr| self.rewrite_expr(&expr))
+                        .and_then(|expr| {
+                            Self::check_grouping_function(&expr, &ir.group_by_expressions)?;
+                            Ok(expr)
+                        }) {
+                        Ok(expr) => {
+                            projection_expressions.push(expr);
+                        }
+                        Err(cause) => {
+                            return Err(cause.add_message_back(format!(
+                                " (while in analyze projection expr: {:?})",
+                                projection_expression
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+
+        ir.projection_expressions = projection_expressions;
+        Ok(())
+    }
+
+    fn expand_wildcard(&self, columns_expression: &mut Vec<Expression>) {
+        let (expressions, _) = self.expand_wildcard_with_metadata();
+        columns_expression.extend(expressions);
+    }
+
+    // `t.*`, the wildcard expansion of the columns of the table `t`. Unlike a bare wildcard,
+    // its EXCEPT and RENAME lists only refer to the columns of that table.
+    fn expand_qualified_wildcard(
+        &self,
+        qualifier: &[String],
+        except: &[String],
+        rename: &[(String, String)],
+        columns_expression: &mut Vec<Expression>,
+    ) -> Result<()> {
+        let mut ref_names = qualifier.to_vec();
+        ref_names.push(String::from("*"));
+        let table_desc = match self.best_match_table(self.current_scope(), &ref_names) {
+            Some((pos, table_desc)) if pos == qualifier.len() && table_desc.is_output_visible() => {
+                table_desc
+            }
+            _ => {
+                return Err(ErrorCode::UnknownTable(format!(
+                    "Unknown table {} of qualified wildcard",
+                    qualifier.join(".")
+                )));
+            }
+        };
+
+        let relation = table_desc.get_name_parts().join(".");
+        let columns_desc = table_desc.get_columns_desc();
+        let renamed = rename.iter().map(|(old, _)| ("RENAME", old));
+        for (clause, name) in except.iter().map(|name| ("EXCEPT", name)).chain(renamed) {
+            if !columns_desc.iter().any(|c| &c.short_name == name) {
+                return Err(ErrorCode::UnknownColumn(format!(
+                    "Unknown column {} in {} of table {}",
+                    name, clause, relation
+                )));
+            }
+        }
+
+        // The NATURAL JOIN key is expanded once, by the column of the other side.
+        for column_desc in columns_desc.iter().filter(|c| !c.is_merged) {
+            let name = &column_desc.short_name;
+            if except.contains(name) {
+                continue;
+            }
+
+            let column = match column_desc.is_ambiguity || self.always_qualify {
+                true => Expression::Column(format!("{}.{}", relation, name)),
+                false => Expression::Column(name.clone()),
+            };
+
+            match rename.iter().find(|(old, _)| old == name) {
+                None => columns_expression.push(column),
+                Some((_, new)) => {
+                    columns_expression.push(Expression::Alias(new.clone(), Box::new(column)))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // `* ILIKE 'pattern'`, the wildcard expansion of the columns whose short name matches
+    // the pattern case-insensitively.
+    fn expand_wildcard_ilike(
+        &self,
+        pattern: &Expression,
+        columns_expression: &mut Vec<Expression>,
+    ) -> Result<()> {
+        let pattern = match pattern {
+            Expression::Literal {
+                value: DataValue::String(Some(pattern)),
+                ..
+            } => String::from_utf8(pattern.clone())?,
+            _ => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Wildcard ILIKE pattern must be a constant string, but got {:?}",
+                    pattern
+                )))
+            }
+        };
+
+        let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+        let escapes = pattern.iter().rev().take_while(|c| **c == '\\').count();
+        if escapes % 2 == 1 {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Malformed wildcard ILIKE pattern {}, it ends with an escape character",
+                pattern.iter().collect::<String>()
+            )));
+        }
+
+        let (expressions, expanded_columns) = self.expand_wildcard_with_metadata();
+        for (expression, expanded_column) in expressions.into_iter().zip(expanded_columns) {
+            let short_name = expanded_column.short_name.to_lowercase();
+            if Self::ilike_matches(&pattern, &short_name.chars().collect::<Vec<_>>()) {
+                columns_expression.push(expression);
+            }
+        }
+
+        Ok(())
+    }
+
+    // `%` matches any sequence of characters and `_` any single character, `\` escapes the
+    // next character. Both the pattern and the name are already in lowercase.
+    fn ilike_matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some((&'%', rest)) => {
+                (0..=name.len()).any(|pos| Self::ilike_matches(rest, &name[pos..]))
+            }
+            Some((&'_', rest)) => !name.is_empty() && Self::ilike_matches(rest, &name[1..]),
+            Some((c, rest)) => {
+                let (c, rest) = match (c, rest.split_first()) {
+                    (&'\\', Some((escaped, rest))) => (escaped, rest),
+                    _ => (c, rest),
+                };
+
+                match name.split_first() {
+                    Some((n, name)) => c == n && Self::ilike_matches(rest, name),
+                    None => false,
+                }
+            }
+        }
+    }
+
+    /// Same as the wildcard expansion of the projection, with the source of each column.
+    pub fn expand_wildcard_with_metadata(&self) -> (Vec<Expression>, Vec<ExpandedColumn>) {
+        let mut expressions = Vec::new();
+        let mut expanded_columns = Vec::new();
+
+        for table_desc in self.current_scope().get_tables_desc() {
+            if !table_desc.is_output_visible() {
+                continue;
+            }
+
+            let relation = table_desc.get_name_parts().join(".");
+            // The NATURAL JOIN key is expanded once, by the column of the other side.
+            let columns_desc = table_desc.get_columns_desc().iter();
+            for column_desc in columns_desc.filter(|column_desc| !column_desc.is_merged) {
+                let name = column_desc.short_name.clone();
+                let qualified = column_desc.is_ambiguity || self.always_qualify;
+                match qualified {
+                    true => expressions.push(Expression::Column(format!("{}.{}", relation, name))),
+                    false => expressions.push(Expression::Column(name.clone())),
+                }
+
+                expanded_columns.push(ExpandedColumn {
+                    relation: relation.clone(),
+                    short_name: name,
+                    qualified,
+                    data_type: column_desc.data_type.clone(),
+                });
+            }
+        }
+
+        (expressions, expanded_columns)
+    }
+
+    fn rewrite_expr(&self, expr: &Expression) -> Result<Expression> {
+        let depth = self.expr_depth.get() + 1;
+        self.expr_depth.set(depth);
+
+        let res = match depth as u64 > self.max_expression_depth {
+            true => Err(ErrorCode::SyntaxException("expression nesting too deep")),
+            false => self.rewrite_expr_inner(expr),
+        };
+
+        self.expr_depth.set(depth - 1);
+        res
+    }
+
+    fn rewrite_expr_inner(&self, expr: &Expression) -> Result<Expression> {
+        match expr {
+            Expression::Column(v) => {
+                let (column, depth) = self.resolve_column(v)?;
+                self.check_outer_grouping(&column, depth)?;
+                self.add_outer_reference(&column, depth);
+                Ok(Self::correlated_column(column, depth))
+            }
+            Expression::QualifiedColumn(names) => {
+                let (column, depth) = self.resolve_qualified_column(names)?;
+                self.check_outer_grouping(&column, depth)?;
+                self.add_outer_reference(&column, depth);
+                Ok(Self::correlated_column(column, depth))
+            }
+            Expression::OuterColumn { .. } => Ok(expr.clone()),
+            Expression::Alias(alias, expr) => Ok(Expression::Alias(
+                alias.clone(),
+                Box::new(self.rewrite_expr(expr)?),
+            )),
+            Expression::UnaryExpression { op, expr } => {
+                self.fold_constant_expr(Expression::UnaryExpression {
+                    op: op.clone(),
+                    expr: Box::new(self.rewrite_expr(expr)?),
+                })
+            }
+            Expression::BinaryExpression { left, op, right } => {
+                Self::check_ilike_op(op)?;
+                let left = self.rewrite_expr(left)?;
+                let right = self.rewrite_expr(right)?;
+                self.check_interval_arithmetic(&left, &right)?;
+                if op == "||" {
+                    return Ok(self.rewrite_string_concat(left, right));
+                }
+
+                if let Some(expr) = self.rewrite_null_comparison(op, &left, &right)? {
+                    return Ok(expr);
+                }
+
+                let (left, right) = self.coerce_comparison_operands(op, left, right)?;
+                self.fold_constant_expr(Expression::BinaryExpression {
+                    op: Self::normalize_binary_op(op),
+                    left: Box::new(left),
+                    right: Box::new(right),
+                })
+            }
+            Expression::ScalarFunction { op, args } if op == "in" || op == "not_in" => {
+                self.rewrite_in_list(args, op == "not_in")
+            }
+            Expression::ScalarFunction { op, args } => {
+                Self::check_conditional_function_arity(op, args.len())?;
+                let mut new_args = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    new_args.push(match arg {
+                        Expression::NamedArgument { name, expr } => Expression::NamedArgument {
+                            name: name.clone(),
+                            expr: Box::new(self.rewrite_expr(expr)?),
+                        },
+                        arg => self.rewrite_expr(arg)?,
+                    });
+                }
+
+                let op = Self::canonical_function_name(op);
+                let new_args = Self::bind_named_arguments(&op, new_args)?;
+                let expr = if Self::is_trim_function(&op) {
+                    self.rewrite_trim_function(&op, new_args)?
+                } else if op.eq_ignore_ascii_case("extract") {
+                    self.rewrite_extract_function(new_args)?
+                } else {
+                    if op.eq_ignore_ascii_case("coalesce") {
+                        self.check_coalesce_args(&new_args)?;
+                    }
+
+                    if op.eq_ignore_ascii_case("if") && new_args.len() == 3 {
+                        self.check_if_branches(&new_args[1], &new_args[2])?;
+                    }
+
+                    Expression::ScalarFunction { op, args: new_args }
+                };
+
+                // After the lowerings above, which replace the functions of the special forms.
+                if let Expression::ScalarFunction { op, .. } = &expr {
+                    Self::check_function_exists(op)?;
+                }
+                Ok(expr)
+            }
+            Expression::AggregateFunction {
+                op,
+                distinct,
+                params,
+                args,
+                within_group,
+            } => {
+                Self::check_ordered_set_aggregate(op, *distinct, within_group)?;
+                let mut new_args = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    new_args.push(self.rewrite_expr(arg)?);
+                }
+
+                Ok(Expression::AggregateFunction {
+                    op: op.clone(),
+                    distinct: *distinct,
+                    params: params.clone(),
+                    args: new_args,
+                    within_group: self.rewrite_exprs(within_group)?,
+                })
+            }
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => {
+                Self::check_null_treatment(op, null_treatment)?;
+                let order_by = self.rewrite_exprs(order_by)?;
+                let window_frame = match window_frame {
+                    None => None,
+                    Some(frame) => Some(self.rewrite_window_frame(frame, &order_by)?),
+                };
+
+                Ok(Expression::WindowFunction {
+                    op: op.clone(),
+                    params: params.clone(),
+                    args: self.rewrite_exprs(args)?,
+                    partition_by: self.rewrite_exprs(partition_by)?,
+                    order_by,
+                    window_frame,
+                    null_treatment: *null_treatment,
+                })
+            }
+            Expression::Sort {
+                expr,
+                asc,
+                nulls_first,
+                origin_expr,
+            } => {
+                let expr = self.rewrite_expr(expr)?;
+                let origin_expr = self.rewrite_expr(origin_expr)?;
+                Self::check_sort_columns(&expr, &origin_expr)?;
+                Ok(Expression::Sort {
+                    expr: Box::new(expr),
+                    asc: *asc,
+                    nulls_first: *nulls_first,
+                    origin_expr: Box::new(origin_expr),
+                })
+            }
+            Expression::Cast { expr, data_type } => {
+                Self::validate_cast_type(data_type)?;
+                let expr = self.rewrite_expr(expr)?;
+                self.check_cast_source(&expr, data_type)?;
+                let is_untyped_null = Self::is_untyped_null(&expr);
+                let cast = Expression::Cast {
+                    expr: Box::new(expr),
+                    data_type: data_type.clone(),
+                };
+
+                // `CAST(NULL AS type)` is the typed null, the later passes and the UNION
+                // type unification don't have to guess its type.
+                let typed_null = DataValue::from(data_type);
+                if !is_untyped_null || !typed_null.is_null() {
+                    return Ok(cast);
+                }
+
+                Ok(Expression::Literal {
+                    value: typed_null,
+                    column_name: Some(cast.column_name()),
+                    data_type: data_type.clone(),
+                })
+            }
+            // TRY_CAST only suppresses the errors at runtime, the types are validated as CAST.
+            Expression::TryCast {
+                expr,
+                data_type,
+                on_error,
+            } => {
+                Self::validate_cast_type(data_type)?;
+                let expr = self.rewrite_expr(expr)?;
+                self.check_cast_source(&expr, data_type)?;
+                Ok(Expression::TryCast {
+                    expr: Box::new(expr),
+                    data_type: data_type.clone(),
+                    on_error: on_error.try_map(|default| self.rewrite_expr(default))?,
+                })
+            }
+            Expression::InSubquery {
+                expr,
+                subquery,
+                negated,
+            } => {
+                Self::check_in_subquery_arity(expr, subquery)?;
+                match *negated {
+                    true => self.check_not_in_subquery(subquery)?,
+                    false => self.add_semi_join_hint(subquery)?,
+                }
+
+                Ok(Expression::InSubquery {
+                    expr: Box::new(self.rewrite_expr(expr)?),
+                    subquery: subquery.clone(),
+                    negated: *negated,
+                })
+            }
+            Expression::QuantifiedComparison {
+                op,
+                quantifier,
+                left,
+                subquery,
+            } => self.rewrite_quantified_comparison(op, quantifier, left, subquery),
+            Expression::Tuple(exprs) => Ok(Expression::Tuple(self.rewrite_exprs(exprs)?)),
+            Expression::Array(exprs) => {
+                let exprs = self.rewrite_exprs(exprs)?;
+                self.check_array_elements(&exprs)?;
+                Ok(Expression::Array(exprs))
+            }
+            Expression::Struct(fields) => {
+                let mut rewritten_fields: Vec<(String, Expression)> =
+                    Vec::with_capacity(fields.len());
+                for (name, expr) in fields {
+                    if rewritten_fields.iter().any(|(field, _)| field == name) {
+                        return Err(ErrorCode::SyntaxException(format!(
+                            "Duplicate struct field {}",
+                            name
+                        )));
+                    }
+
+                    rewritten_fields.push((name.clone(), self.rewrite_expr(expr)?));
+                }
+
+                Ok(Expression::Struct(rewritten_fields))
+            }
+            Expression::Like {
+                op,
+                expr,
+                pattern,
+                escape,
+            } => self.rewrite_like(op, expr, pattern, escape.as_deref()),
+            Expression::Interval { value, unit } => {
+                Self::validate_interval_unit(unit)?;
+                Ok(Expression::Interval {
+                    value: Box::new(self.rewrite_expr(value)?),
+                    unit: unit.clone(),
+                })
+            }
+            Expression::Subscript { base, index } => {
+                let base = self.rewrite_expr(base)?;
+                let index = self.rewrite_expr(index)?;
+                self.check_subscript(&base, &index)?;
+                Ok(Expression::Subscript {
+                    base: Box::new(base),
+                    index: Box::new(index),
+                })
+            }
+            Expression::Collate { expr, collation } => {
+                Self::check_collation(collation)?;
+                let expr = self.rewrite_expr(expr)?;
+                match self.static_data_type(&expr) {
+                    Some(data_type) if data_type != DataType::String => {
+                        Err(ErrorCode::IllegalDataType(format!(
+                            "COLLATE requires a string expression, but {:?} is {:?}",
+                            expr, data_type
+                        )))
+                    }
+                    _ => Ok(Expression::Collate {
+                        expr: Box::new(expr),
+                        collation: collation.clone(),
+                    }),
+                }
+            }
+            Expression::NamedArgument { name, .. } => Err(ErrorCode::SyntaxException(format!(
+                "Named argument {} is only supported in scalar functions",
+                name
+            ))),
+            Expression::Wildcard
+            | Expression::QualifiedWildcard { .. }
+            | Expression::Literal { .. }
+            | Expression::Subquery { .. }
+            | Expression::ScalarSubquery { .. } => Ok(expr.clone()),
+        }
+    }
+
+    // A bare NULL is kept untyped, the planner infers its type from the context.
+    fn is_untyped_null(expr: &Expression) -> bool {
+        matches!(expr, Expression::Literal {
+            value: DataValue::Null,
+            data_type: DataType::Null,
+            ..
+        })
+    }
+
+    // `expr = NULL` is always NULL, it's likely meant to be `expr IS NULL`. It's rewritten
+    // under `rewrite_null_comparison` for the MySQL compatibility, or reported as a warning.
+    fn rewrite_null_comparison(
+        &self,
+        op: &str,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Option<Expression>> {
+        let (function, keyword) = match op {
+            "=" => ("isnull", "IS NULL"),
+            "<>" | "!=" => ("isnotnull", "IS NOT NULL"),
+            _ => return Ok(None),
+        };
+
+        let expr = match (Self::is_untyped_null(left), Self::is_untyped_null(right)) {
+            (false, true) => left,
+            (true, false) => right,
+            _ => return Ok(None),
+        };
+
+        if self.ctx.get_settings().get_rewrite_null_comparison()? == 0 {
+            self.warning(format!(
+                "Comparison ({:?} {} NULL) is always NULL, use {} instead",
+                expr, op, keyword
+            ))?;
+            return Ok(None);
+        }
+
+        Ok(Some(Expression::ScalarFunction {
+            op: function.to_string(),
+            args: vec![expr.clone()],
+        }))
+    }
+
+    // The operands of a comparison are coerced by the first matching rule of:
+    //   1. the same types, an unknown type or NULL on either side: kept as is;
+    //   2. a List or Struct on either side: the comparison is invalid;
+    //   3. numbers of different types: kept as is, the planner widens them;
+    //   4. a string literal with a number, date, datetime or boolean: the literal is
+    //      cast to the type of the other operand;
+    //   5. anything else: kept as is, left to the planner.
+    // Under `strict_type_comparison`, the rule 4 is an error instead.
+    fn coerce_comparison_operands(
+        &self,
+        op: &str,
+        left: Expression,
+        right: Expression,
+    ) -> Result<(Expression, Expression)> {
+        if !matches!(
+            Self::normalize_binary_op(op).as_str(),
+            "=" | "<>"
+                | "!="
+                | "<"
+                | "<="
+                | ">"
+                | ">="
+                | "IS DISTINCT FROM"
+                | "IS NOT DISTINCT FROM"
+        ) {
+            return Ok((left, right));
+        }
+
+        let (left_type, right_type) =
+            match (self.static_data_type(&left), self.static_data_type(&right)) {
+                (Some(left_type), Some(right_type)) => (left_type, right_type),
+                _ => return Ok((left, right)),
+            };
+
+        if left_type == right_type || left_type == DataType::Null || right_type == DataType::Null {
+            return Ok((left, right));
+        }
+
+        let is_nested =
+            |data_type: &DataType| matches!(data_type, DataType::List(_) | DataType::Struct(_));
+
+        if is_nested(&left_type) || is_nested(&right_type) {
+            return Err(ErrorCode::IllegalTypeComparison(format!(
+                "Cannot compare {:?} of type {:?} with {:?} of type {:?}",
+                left, left_type, right, right_type
+            )));
+        }
+
+        if is_numeric(&left_type) && is_numeric(&right_type) {
+            return Ok((left, right));
+        }
+
+        let is_string_literal = |expr: &Expression| {
+            matches!(expr, Expression::Literal {
+                data_type: DataType::String,
+                ..
+            })
+        };
+
+        let is_castable = |data_type: &DataType| {
+            is_numeric(data_type)
+                || is_date_or_date_time(data_type)
+                || data_type == &DataType::Boolean
+        };
+
+        let cast_left = match (is_string_literal(&left), is_string_literal(&right)) {
+            (true, false) if is_castable(&right_type) => true,
+            (false, true) if is_castable(&left_type) => false,
+            _ => return Ok((left, right)),
+        };
+
+        if self.ctx.get_settings().get_strict_type_comparison()? != 0 {
+            return Err(ErrorCode::IllegalTypeComparison(format!(
+                "Cannot compare {:?} of type {:?} with {:?} of type {:?}, the implicit cast is disabled by strict_type_comparison",
+                left, left_type, right, right_type
+            )));
+        }
+
+        Ok(match cast_left {
+            true => (
+                Expression::Cast {
+                    expr: Box::new(left),
+                    data_type: right_type,
+                },
+                right,
+            ),
+            false => (left, Expression::Cast {
+                expr: Box::new(right),
+                data_type: left_type,
+            }),
+        })
+    }
+
+    // `a || b || c` is the `concat` of all the operands, so that the planner only sees `concat`.
+    // A numeric operand is cast to string implicitly, as Postgres does.
+    fn rewrite_string_concat(&self, left: Expression, right: Expression) -> Expression {
+        let mut args = Vec::new();
+        for operand in vec![left, right] {
+            match operand {
+                Expression::ScalarFunction { op, args: operands } if op == "concat" => {
+                    args.extend(operands)
+                }
+                operand => match self.static_data_type(&operand) {
+                    Some(data_type) if is_numeric(&data_type) => args.push(Expression::Cast {
+                        expr: Box::new(operand),
+                        data_type: DataType::String,
+                    }),
+                    _ => args.push(operand),
+                },
+            }
+        }
+
+        Expression::ScalarFunction {
+            op: String::from("concat"),
+            args,
+        }
+    }
+
+    // `IS [NOT] DISTINCT FROM` is the null-safe comparison, keep it in the canonical form
+    // so that the planner can tell it from `=` and `<>`.
+    fn normalize_binary_op(op: &str) -> String {
+        let words = op.split_whitespace().collect::<Vec<_>>();
+        let normalized = words.join(" ").to_uppercase();
+        match normalized.as_str() {
+            "IS DISTINCT FROM" | "IS NOT DISTINCT FROM" => normalized,
+            _ if Self::is_like_op(&normalized) => normalized,
+            _ => op.to_string(),
+        }
+    }
+
+    // ILIKE is kept apart from LIKE, it matches case-insensitively.
+    fn is_like_op(normalized_op: &str) -> bool {
+        matches!(normalized_op, "LIKE" | "NOT LIKE" | "ILIKE" | "NOT ILIKE")
+    }
+
+    // There are no `ilike` and `not ilike` functions to execute the case-insensitive match,
+    // reject it here instead of failing in the expression chain.
+    fn check_ilike_op(op: &str) -> Result<()> {
+        let normalized = Self::normalize_binary_op(op);
+        match normalized.as_str() {
+            "ILIKE" | "NOT ILIKE" => Err(ErrorCode::UnImplement(format!(
+                "{} is not supported yet, use LOWER(expr) LIKE LOWER(pattern) instead",
+                normalized
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // `expr [NOT] {LIKE | ILIKE} pattern ESCAPE 'c'`, the escape must be a single character
+    // literal. The `like` functions only know the backslash escape, so `ESCAPE '\'` is the
+    // plain binary LIKE and any other escape character is rejected.
+    fn rewrite_like(
+        &self,
+        op: &str,
+        expr: &Expression,
+        pattern: &Expression,
+        escape: Option<&Expression>,
+    ) -> Result<Expression> {
+        let op = Self::normalize_binary_op(op);
+        let binary_like = Expression::BinaryExpression {
+            op: op.clone(),
+            left: Box::new(expr.clone()),
+            right: Box::new(pattern.clone()),
+        };
+        let escape = match escape {
+            None => return self.rewrite_expr(&binary_like),
+            Some(escape) => escape,
+        };
+
+        if !Self::is_like_op(&op) {
+            return Err(ErrorCode::SyntaxException(format!(
+                "ESCAPE is only supported by LIKE and ILIKE, but got {}",
+                op
+            )));
+        }
+
+        let escape_char = match escape {
+            Expression::Literal {
+                value: DataValue::String(Some(escape)),
+                ..
+            } => match std::str::from_utf8(escape) {
+                Ok(escape) if escape.chars().count() == 1 => escape.chars().next(),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match escape_char {
+            None => Err(ErrorCode::SyntaxException(format!(
+                "ESCAPE must be a single character literal, but got {:?}",
+                escape
+            ))),
+            Some('\\') => self.rewrite_expr(&binary_like),
+            Some(_) => {
+                Self::check_ilike_op(&op)?;
+                self.rewrite_expr(expr)?;
+                self.rewrite_expr(pattern)?;
+                Err(ErrorCode::UnImplement(format!(
+                    "{} with ESCAPE other than backslash is not supported yet",
+                    op
+                )))
+            }
+        }
+    }
+
+    fn is_trim_function(op: &str) -> bool {
+        ["trim", "trim_both", "trim_leading", "trim_trailing"]
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(op))
+    }
+
+    // `TRIM([BOTH | LEADING | TRAILING] chars FROM source)` is analyzed into `trim_both`,
+    // `trim_leading` or `trim_trailing` of `(chars, source)`. A plain `TRIM(source)` trims
+    // the spaces from both sides, as the SQL standard defaults to.
+    fn rewrite_trim_function(&self, op: &str, mut args: Vec<Expression>) -> Result<Expression> {
+        let op = op.to_lowercase();
+        let (op, args) = match (op.as_str(), args.len()) {
+            ("trim", 1) => {
+                let spaces = DataValue::String(Some(b" ".to_vec()));
+                let source = args.remove(0);
+                (String::from("trim_both"), vec![
+                    Expression::create_literal(spaces),
+                    source,
+                ])
+            }
+            ("trim", _) => {
+                return Err(ErrorCode::NumberArgumentsNotMatch(format!(
+                    "TRIM requires exactly 1 argument, but got {}",
+                    args.len()
+                )))
+            }
+            (_, 2) => (op, args),
+            (_, _) => {
+                return Err(ErrorCode::NumberArgumentsNotMatch(format!(
+                    "{} requires exactly 2 arguments, but got {}",
+                    op,
+                    args.len()
+                )))
+            }
+        };
+
+        match self.static_data_type(&args[0]) {
+            Some(data_type) if data_type != DataType::String && data_type != DataType::Null => {
+                Err(ErrorCode::IllegalDataType(format!(
+                    "TRIM characters must be a string, but {:?} is {:?}",
+                    args[0], data_type
+                )))
+            }
+            _ => Ok(Expression::ScalarFunction { op, args }),
+        }
+    }
+
+    // `EXTRACT(field FROM source)` is analyzed into `extract('field', source)`, which is lowered
+    // to the date function of the field, DOW is numbered from Monday = 1 as in `toDayOfWeek`.
+    // The time fields can only be extracted from a datetime.
+    fn rewrite_extract_function(&self, args: Vec<Expression>) -> Result<Expression> {
+        if args.len() != 2 {
+            return Err(ErrorCode::NumberArgumentsNotMatch(format!(
+                "EXTRACT requires exactly 2 arguments, but got {}",
+                args.len()
+            )));
+        }
+
+        let field = match &args[0] {
+            Expression::Literal {
+                value: DataValue::String(Some(field)),
+                ..
+            } => String::from_utf8_lossy(field).to_uppercase(),
+            other => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "EXTRACT field must be a keyword, such as YEAR, but got {:?}",
+                    other
+                )))
+            }
+        };
+
+        let (function, is_time_field) = match field.as_str() {
+            "YEAR" => ("toYear", false),
+            "QUARTER" => ("toQuarter", false),
+            "MONTH" => ("toMonth", false),
+            "WEEK" => ("toISOWeek", false),
+            "DAY" => ("toDayOfMonth", false),
+            "DOY" => ("toDayOfYear", false),
+            "DOW" => ("toDayOfWeek", false),
+            "HOUR" => ("toHour", true),
+            "MINUTE" => ("toMinute", true),
+            "SECOND" => ("toSecond", true),
+            "EPOCH" => ("toUnixTimestamp", true),
+            _ => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Unsupported EXTRACT field {}, expected one of YEAR, QUARTER, MONTH, WEEK, DAY, DOY, DOW, HOUR, MINUTE, SECOND or EPOCH",
+                    field
+                )))
+            }
+        };
+
+        let source = &args[1];
+        match self.static_data_type(source) {
+            None | Some(DataType::Null) | Some(DataType::DateTime32(_)) => {}
+            Some(data_type) if is_date_or_date_time(&data_type) && !is_time_field => {}
+            Some(data_type) => {
+                return Err(ErrorCode::IllegalDataType(format!(
+                    "Cannot extract {} from {:?} of type {:?}",
+                    field, source, data_type
+                )))
+            }
+        }
+
+        Ok(Expression::ScalarFunction {
+            op: function.to_string(),
+            args: vec![source.clone()],
+        })
+    }
+
+    // The named arguments follow the positional ones, each parameter is specified at most once.
+    // They are bound to the parameter names declared by the function and passed by position,
+    // a parameter before the last one bound can't be left out as there are no defaults.
+    fn bind_named_arguments(op: &str, args: Vec<Expression>) -> Result<Vec<Expression>> {
+        let mut positional_count = 0;
+        let mut names: Vec<&String> = Vec::new();
+        for arg in &args {
+            match arg {
+                Expression::NamedArgument { name, .. } => {
+                    if names.iter().any(|other| other.eq_ignore_ascii_case(name)) {
+                        return Err(ErrorCode::SyntaxException(format!(
+                            "Duplicate argument {} of function {}",
+                            name, op
+                        )));
+                    }
+                    names.push(name);
+                }
+                _ if !names.is_empty() => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Positional argument {:?} of function {} cannot follow the named arguments",
+                        arg, op
+                    )));
+                }
+                _ => positional_count += 1,
+            }
+        }
+
+        if names.is_empty() {
+            return Ok(args);
+        }
+
+        let features = FunctionFactory::instance().get_features(op).ok();
+        let parameter_names = match features.and_then(|features| features.parameter_names) {
+            None => {
+                return Err(ErrorCode::BadArguments(format!(
+                    "Function {} doesn't accept named arguments",
+                    op
+                )))
+            }
+            Some(parameter_names) => parameter_names,
+        };
+
+        let mut bound_args: Vec<Option<Expression>> = vec![None; parameter_names.len()];
+        for (position, arg) in args.into_iter().enumerate() {
+            let (name, expr) = match arg {
+                Expression::NamedArgument { name, expr } => (name, *expr),
+                arg => match bound_args.get_mut(position) {
+                    Some(bound_arg) => {
+                        *bound_arg = Some(arg);
+                        continue;
+                    }
+                    None => {
+                        return Err(ErrorCode::BadArguments(format!(
+                            "Function {} expects at most {} arguments",
+                            op,
+                            parameter_names.len()
+                        )))
+                    }
+                },
+            };
+
+            match parameter_names
+                .iter()
+                .position(|parameter| parameter.eq_ignore_ascii_case(&name))
+            {
+                None => {
+                    return Err(ErrorCode::BadArguments(format!(
+                        "Unknown parameter {} of function {}, expected one of {}",
+                        name,
+                        op,
+                        parameter_names.join(", ")
+                    )));
+                }
+                Some(position) if position < positional_count => {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Parameter {} of function {} is specified both by position and by name",
+                        name, op
+                    )));
+                }
+                Some(position) => bound_args[position] = Some(expr),
+            }
+        }
+
+        let bound_count = bound_args
+            .iter()
+            .rposition(Option::is_some)
+            .map_or(0, |p| p + 1);
+        let mut bound = Vec::with_capacity(bound_count);
+        for (parameter, arg) in parameter_names.iter().zip(bound_args).take(bound_count) {
+            match arg {
+                Some(arg) => bound.push(arg),
+                None => {
+                    return Err(ErrorCode::BadArguments(format!(
+                        "Missing parameter {} of function {}",
+                        parameter, op
+                    )))
+                }
+            }
+        }
+
+        Ok(bound)
+    }
+
+    // The sort key and the expression it was written as may differ, e.g. after rebasing
+    // on the aggregate, but they must be built from the same columns.
+    fn check_sort_columns(expr: &Expression, origin_expr: &Expression) -> Result<()> {
+        let column_names = |expr: &Expression| {
+            let columns = find_column_exprs(std::slice::from_ref(expr));
+            let mut names = columns
+                .iter()
+                .map(Expression::column_name)
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        match column_names(expr) == column_names(origin_expr) {
+            true => Ok(()),
+            false => Err(ErrorCode::LogicalError(format!(
+                "Sort expression {:?} and its origin expression {:?} reference different columns",
+                expr, origin_expr
+            ))),
+        }
+    }
+
+    /// The relations of the current scope referenced by the projection of the rewritten query,
+    /// the filter and the group by are not considered. The outer references are not included.
+    pub fn relations_used_by_projection(&self, ir: &QueryASTIR) -> HashSet<RelationId> {
+        let scope = self.current_scope();
+        find_column_exprs(&ir.projection_expressions)
+            .iter()
+            .filter(|column| {
+                !ir.outer_references
+                    .iter()
+                    .any(|(outer, _)| outer == *column)
+            })
+            .filter_map(|column| match column {
+                Expression::Column(name) => scope.find_column_table(name).map(|(index, _)| index),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Resolve the columns of the expressions, without rewriting a query.
+    pub fn resolve_exprs(&self, exprs: &[Expression]) -> Result<Vec<Expression>> {
+        self.rewrite_exprs(exprs)
+    }
+
+    fn rewrite_exprs(&self, exprs: &[Expression]) -> Result<Vec<Expression>> {
+        exprs
+            .iter()
+            .map(|expr| self.rewrite_expr(expr))
+            .collect::<Result<Vec<_>>>()
+    }
+
+    // The elements of an array constructor must have a common type, the elements of an
+    // unknown type are left to the planner.
+    fn check_array_elements(&self, exprs: &[Expression]) -> Result<()> {
+        let mut element_type = DataType::Null;
+        for expr in exprs {
+            if let Some(data_type) = self.static_data_type(expr) {
+                element_type = match merge_types(&element_type, &data_type) {
+                    Ok(merged_type) => merged_type,
+                    Err(_) => {
+                        return Err(ErrorCode::IllegalDataType(format!(
+                            "Array element {:?} of type {:?} cannot be unified with the elements of type {:?}",
+                            expr, data_type, element_type
+                        )))
+                    }
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    // `x = ANY (subquery)` is `x IN (subquery)` and `x <> ALL (subquery)` is `x NOT IN (subquery)`,
+    // they are rewritten as IN subquery so that the NOT IN checks and semi join hint apply.
+    // The other comparisons are kept with the operator for the planner.
+    fn rewrite_quantified_comparison(
+        &self,
+        op: &str,
+        quantifier: &str,
+        left: &Expression,
+        subquery: &Expression,
+    ) -> Result<Expression> {
+        let quantifier = match quantifier.to_uppercase().as_str() {
+            "ANY" | "SOME" => "ANY",
+            "ALL" => "ALL",
+            _ => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Unsupported quantifier {}, expected ANY, SOME or ALL",
+                    quantifier
+                )))
+            }
+        };
+
+        if !matches!(op, "=" | "<>" | "!=" | "<" | "<=" | ">" | ">=") {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Unsupported operator {} in {} comparison",
+                op, quantifier
+            )));
+        }
+
+        let (name, query_plan) = match subquery {
+            Expression::Subquery { name, query_plan } => (name, query_plan),
+            _ => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "{} comparison requires a subquery, but got {:?}",
+                    quantifier, subquery
+                )))
+            }
+        };
+
+        let columns = query_plan.schema().fields().len();
+        if columns != 1 {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Subquery {} of {} comparison must return exactly one column, but got {}",
+                name, quantifier, columns
+            )));
+        }
+
+        match (op, quantifier) {
+            ("=", "ANY") | ("<>", "ALL") | ("!=", "ALL") => {
+                self.rewrite_expr(&Expression::InSubquery {
+                    expr: Box::new(left.clone()),
+                    subquery: Box::new(subquery.clone()),
+                    negated: quantifier == "ALL",
+                })
+            }
+            _ => Ok(Expression::QuantifiedComparison {
+                op: op.to_string(),
+                quantifier: quantifier.to_string(),
+                left: Box::new(self.rewrite_expr(left)?),
+                subquery: Box::new(self.rewrite_expr(subquery)?),
+            }),
+        }
+    }
+
+    // A correlated subquery fails in its own analysis for now, so every subquery here is
+    // uncorrelated, it only has to produce a single column.
+    fn add_semi_join_hint(&self, subquery: &Expression) -> Result<()> {
+        if self.ctx.get_settings().get_enable_semi_join_hint()? == 0 {
+            return Ok(());
+        }
+
+        if let Expression::Subquery { name, query_plan } = subquery {
+            let mut semi_join_subqueries = self.semi_join_subqueries.lock();
+            if query_plan.schema().fields().len() == 1 && !semi_join_subqueries.contains(name) {
+                semi_join_subqueries.push(name.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    // The number of the columns of a row, `(a, b)` has two and any other expression has one.
+    fn row_arity(expr: &Expression) -> usize {
+        match expr {
+            Expression::Tuple(exprs) => exprs.len(),
+            _ => 1,
+        }
+    }
+
+    // `left [NOT] IN (v1, v2, ...)` is expanded into `[NOT] (left = v1 OR left = v2 ...)`,
+    // a row constructor on the left is compared element by element with each row of the list.
+    fn rewrite_in_list(&self, args: &[Expression], negated: bool) -> Result<Expression> {
+        let (left, list) = match args.split_first() {
+            Some((left, list)) if !list.is_empty() => (left, list),
+            _ => {
+                return Err(ErrorCode::SyntaxException(
+                    "IN list must have at least one value",
+                ))
+            }
+        };
+
+        let arity = Self::row_arity(left);
+        let mut expansion: Option<Expression> = None;
+        for (index, item) in list.iter().enumerate() {
+            if Self::row_arity(item) != arity {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "IN list item {} {:?} has {} columns, but the left side {:?} has {}",
+                    index + 1,
+                    item,
+                    Self::row_arity(item),
+                    left,
+                    arity
+                )));
+            }
+
+            let equality = match (left, item) {
+                (Expression::Tuple(lefts), Expression::Tuple(rights)) => lefts
+                    .iter()
+                    .zip(rights.iter())
+                    .map(|(left, right)| left.eq(right.clone()))
+                    .reduce(|conjunction, equality| conjunction.and(equality))
+                    .ok_or_else(|| ErrorCode::SyntaxException("Tuple must not be empty"))?,
+                _ => left.eq(item.clone()),
+            };
+
+            expansion = Some(match expansion {
+                None => equality,
+                Some(disjunction) => disjunction.or(equality),
+            });
+        }
+
+        // The list is not empty, the expansion is always present here.
+        let expansion = expansion.unwrap();
+        self.rewrite_expr(&match negated {
+            true => not(expansion),
+            false => expansion,
+        })
+    }
+
+    fn check_in_subquery_arity(expr: &Expression, subquery: &Expression) -> Result<()> {
+        if let Expression::Subquery { name, query_plan } = subquery {
+            let columns = query_plan.schema().fields().len();
+            if columns != Self::row_arity(expr) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "IN subquery {} returns {} columns, but the left side {:?} has {}",
+                    name,
+                    columns,
+                    expr,
+                    Self::row_arity(expr)
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    // `x NOT IN (SELECT y ...)` is never true once `y` yields a NULL
+    fn check_not_in_subquery(&self, subquery: &Expression) -> Result<()> {
+        if let Expression::Subquery { query_plan, .. } = subquery {
+            if Self::is_nullable_subquery(query_plan)? {
+                let schema = query_plan.schema();
+                return self.warning(format!(
+                    "NOT IN subquery column '{}' is nullable, consider NOT EXISTS instead",
+                    schema.field(0).name()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_nullable_subquery(plan: &PlanNode) -> Result<bool> {
+        match plan {
+            PlanNode::Projection(projection) => match projection.expr.first() {
+                None => Ok(false),
+                Some(expr) => Self::is_nullable_expr(expr, &projection.input.schema()),
+            },
+            other => match other.inputs().first() {
+                None => Ok(other.schema().field(0).is_nullable()),
+                Some(input) => Self::is_nullable_subquery(input),
+            },
+        }
+    }
+
+    fn is_nullable_expr(expr: &Expression, schema: &DataSchemaRef) -> Result<bool> {
+        match expr {
+            Expression::Literal { value, .. } => Ok(value.is_null()),
+            Expression::Column(name) => Ok(schema.field_with_name(name)?.is_nullable()),
+            _ => {
+                for column in find_column_exprs(&[expr.clone()]) {
+                    if Self::is_nullable_expr(&column, schema)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    // The aliases registered in the function factory are replaced by the canonical names,
+    // such as `substr` by `substring`, so that the planner only sees one name of a function.
+    fn canonical_function_name(op: &str) -> String {
+        let factory = FunctionFactory::instance();
+        factory.get_canonical_name(op).unwrap_or(op).to_string()
+    }
+
+    // A function must be registered in the function factory, unless the query analysis
+    // resolves it itself, so that an unknown function fails here instead of in the planner.
+    fn check_function_exists(op: &str) -> Result<()> {
+        match FunctionFactory::instance().check(op) || ExpressionAnalyzer::is_analyzer_function(op)
+        {
+            true => Ok(()),
+            false => Err(ErrorCode::UnknownFunction(format!(
+                "Unknown function {}",
+                op
+            ))),
+        }
+    }
+
+    // The conditional functions are resolved at runtime, check the number of
+    // arguments here so that `NULLIF(a)` fails in analysis.
+    fn check_conditional_function_arity(op: &str, args: usize) -> Result<()> {
+        let name = op.to_uppercase();
+        let expected = match name.as_str() {
+            "COALESCE" if args < 1 => "at least 1 argument",
+            "NULLIF" | "IFNULL" if args != 2 => "exactly 2 arguments",
+            _ => return Ok(()),
+        };
+
+        Err(ErrorCode::SyntaxException(format!(
+            "{} requires {}, but got {}",
+            name, expected, args
+        )))
+    }
+
+    // The arguments of COALESCE are unified to a single type, only the statically known
+    // types are checked here, the others are left to the function at runtime.
+    fn check_coalesce_args(&self, args: &[Expression]) -> Result<()> {
+        let (typed_args, data_types): (Vec<_>, Vec<_>) = args
+            .iter()
+            .filter_map(|arg| Some((arg.column_name(), self.static_data_type(arg)?)))
+            .unzip();
+
+        if data_types.len() < 2 || aggregate_types(&data_types).is_ok() {
+            return Ok(());
+        }
+
+        self.warning(format!(
+            "COALESCE arguments {} cannot be unified to a common type",
+            typed_args.join(", ")
+        ))
+    }
+
+    // CASE is analyzed as the nested IF, so the else branch is the rest of the CASE.
+    fn check_if_branches(&self, result: &Expression, else_result: &Expression) -> Result<()> {
+        let data_types = match (
+            self.static_data_type(result),
+            self.static_data_type(else_result),
+        ) {
+            (Some(result_type), Some(else_type)) => vec![result_type, else_type],
+            _ => return Ok(()),
+        };
+
+        match aggregate_types(&data_types) {
+            Ok(_) => Ok(()),
+            Err(_) => self.warning(format!(
+                "IF or CASE results {} and {} cannot be unified to a common type",
+                result.column_name(),
+                else_result.column_name()
+            )),
+        }
+    }
+
+    fn static_data_type(&self, expr: &Expression) -> Option<DataType> {
+        match expr {
+            Expression::Literal { data_type, .. } => Some(data_type.clone()),
+            Expression::Column(name) => self.column_data_type(name),
+            Expression::Alias(_, expr) => self.static_data_type(expr),
+            Expression::Cast { data_type, .. } => Some(data_type.clone()),
+            Expression::TryCast { data_type, .. } => Some(data_type.clone()),
+            Expression::Collate { expr, .. } => self.static_data_type(expr),
+            Expression::Array(exprs) => {
+                let mut element_type = DataType::Null;
+                for expr in exprs {
+                    element_type =
+                        merge_types(&element_type, &self.static_data_type(expr)?).ok()?;
+                }
+
+                let element = DataField::new("item", element_type, true);
+                Some(DataType::List(Box::new(element)))
+            }
+            Expression::Struct(fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, expr)| {
+                        Some(DataField::new(name, self.static_data_type(expr)?, true))
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(DataType::Struct(fields))
+            }
+            Expression::Subscript { base, index } => match self.static_data_type(base)? {
+                DataType::String => Some(DataType::String),
+                DataType::List(inner) => Some(inner.data_type().clone()),
+                DataType::Struct(fields) => {
+                    let key = Self::subscript_key(index)?;
+                    let field = fields.iter().find(|field| field.name() == &key)?;
+                    Some(field.data_type().clone())
+                }
+                _ => None,
+            },
+            Expression::ScalarFunction { op, args }
+                if op.eq_ignore_ascii_case("if") && args.len() == 3 =>
+            {
+                let result_type = self.static_data_type(&args[1])?;
+                let else_type = self.static_data_type(&args[2])?;
+                aggregate_types(&[result_type, else_type]).ok()
+            }
+            _ => None,
+        }
+    }
+
+    // The strings are compared byte by byte, only the binary collations are supported.
+    fn check_collation(collation: &str) -> Result<()> {
+        match ["C", "POSIX", "binary"]
+            .iter()
+            .any(|supported| supported.eq_ignore_ascii_case(collation))
+        {
+            true => Ok(()),
+            false => Err(ErrorCode::SyntaxException(format!(
+                "Unsupported collation {:?}, only C, POSIX and binary are supported",
+                collation
+            ))),
+        }
+    }
+
+    // Arrays are accessed by an integer index and structs by a constant field name, strings
+    // hold semi-structured documents that can be accessed by either. Access on an unknown
+    // type is left to the planner.
+    fn check_subscript(&self, base: &Expression, index: &Expression) -> Result<()> {
+        let base_type = match self.static_data_type(base) {
+            None => return Ok(()),
+            Some(base_type) => base_type,
+        };
+
+        let mismatch = |index_type: Option<DataType>| {
+            ErrorCode::IllegalDataType(format!(
+                "Subscript index {:?} of type {:?} mismatches {:?} of type {:?}",
+                index, index_type, base, base_type
+            ))
+        };
+
+        match &base_type {
+            DataType::String => Ok(()),
+            DataType::List(_) => match self.static_data_type(index) {
+                Some(index_type) if !is_integer(&index_type) => Err(mismatch(Some(index_type))),
+                _ => Ok(()),
+            },
+            DataType::Struct(fields) => match Self::subscript_key(index) {
+                None => Err(mismatch(self.static_data_type(index))),
+                Some(key) if fields.iter().any(|field| field.name() == &key) => Ok(()),
+                Some(key) => Err(ErrorCode::IllegalDataType(format!(
+                    "Unknown field {} of {:?} of type {:?}",
+                    key, base, base_type
+                ))),
+            },
+            _ => Err(ErrorCode::IllegalDataType(format!(
+                "Subscript access is not supported on {:?} of type {:?}",
+                base, base_type
+            ))),
+        }
+    }
+
+    fn subscript_key(index: &Expression) -> Option<String> {
+        match index {
+            Expression::Literal {
+                value: DataValue::String(Some(key)),
+                ..
+            } => String::from_utf8(key.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    // Collected in the query warnings, or raised as an error under strict mode
+    fn warning(&self, message: String) -> Result<()> {
+        match self.ctx.get_settings().get_strict_mode()? {
+            0 => {
+                self.ctx.push_warning(message);
+                Ok(())
+            }
+            _ => Err(ErrorCode::SyntaxException(message)),
+        }
+    }
+
+    fn is_constant_expr(expr: &Expression) -> bool {
+        match expr {
+            Expression::Literal { .. } => true,
+            Expression::Alias(_, expr) => Self::is_constant_expr(expr),
+            Expression::Cast { expr, .. } => Self::is_constant_expr(expr),
+            Expression::TryCast {
+                expr,
+                on_error: CastOnError::Null,
+                ..
+            } => Self::is_constant_expr(expr),
+            Expression::UnaryExpression { expr, .. } => Self::is_constant_expr(expr),
+            Expression::BinaryExpression { left, right, .. } => {
+                Self::is_constant_expr(left) && Self::is_constant_expr(right)
+            }
+            Expression::ScalarFunction { op, args } => {
+                let factory = FunctionFactory::instance();
+                let deterministic = match factory.get_features(op) {
+                    Ok(features) => features.is_deterministic,
+                    Err(_) => false,
+                };
+
+                deterministic && args.iter().all(Self::is_constant_expr)
+            }
+            _ => false,
+        }
+    }
+
+    // Fold the unary or binary expression into a literal when all of its operands are
+    // non-null literals. The operands are rewritten first, so nested expressions fold bottom-up.
+    // An operator that isn't a deterministic function, such as IS DISTINCT FROM, is kept.
+    fn fold_constant_expr(&self, expr: Expression) -> Result<Expression> {
+        if self.ctx.get_settings().get_enable_constant_folding()? == 0 {
+            return Ok(expr);
+        }
+
+        Self::fold_literal_operands(expr)
+    }
+
+    fn fold_literal_operands(expr: Expression) -> Result<Expression> {
+        let (op, operands) = match &expr {
+            Expression::UnaryExpression { op, expr } => (op, vec![expr.as_ref()]),
+            Expression::BinaryExpression { left, op, right } => {
+                (op, vec![left.as_ref(), right.as_ref()])
+            }
+            _ => return Ok(expr),
+        };
+
+        let factory = FunctionFactory::instance();
+        if !factory.check(op) || !factory.get_features(op)?.is_deterministic {
+            return Ok(expr);
+        }
+
+        let mut values = Vec::with_capacity(operands.len());
+        for operand in operands {
+            match operand {
+                Expression::Literal { value, .. } if !value.is_null() => values.push(value),
+                _ => return Ok(expr),
+            }
+        }
+
+        Self::check_constant_arithmetic(op, &values, &expr)?;
+        Self::evaluate_constant_expr(expr)
+    }
+
+    // `TRUE AND p` is `p` and `FALSE AND p` is `FALSE`, OR is the same with the roles of the
+    // literals swapped. The absorbed operand is kept if it has a subquery, which may raise an error.
+    fn simplify_boolean_predicate(&self, expr: Expression) -> Result<Expression> {
+        if self.ctx.get_settings().get_enable_constant_folding()? == 0 {
+            return Ok(expr);
+        }
+
+        Ok(Self::simplify_boolean_expr(expr))
+    }
+
+    fn simplify_boolean_expr(expr: Expression) -> Expression {
+        let (left, op, right) = match expr {
+            Expression::BinaryExpression { left, op, right }
+                if op.eq_ignore_ascii_case("and") || op.eq_ignore_ascii_case("or") =>
+            {
+                let left = Self::simplify_boolean_expr(*left);
+                let right = Self::simplify_boolean_expr(*right);
+                (left, op, right)
+            }
+            expr => return expr,
+        };
+
+        let identity = op.eq_ignore_ascii_case("and");
+        let boolean_literal = |expr: &Expression| match expr {
+            Expression::Literal {
+                value: DataValue::Boolean(Some(v)),
+                ..
+            } => Some(*v),
+            _ => None,
+        };
+
+        match (boolean_literal(&left), boolean_literal(&right)) {
+            (Some(v), _) if v == identity => right,
+            (_, Some(v)) if v == identity => left,
+            (Some(_), _) if !Self::contains_subquery(&right) => left,
+            (_, Some(_)) if !Self::contains_subquery(&left) => right,
+            _ => Expression::BinaryExpression {
+                left: Box::new(left),
+                op,
+                right: Box::new(right),
+            },
+        }
+    }
+
+    fn contains_subquery(expr: &Expression) -> bool {
+        match expr.accept(SubqueryCollector { subqueries: vec![] }) {
+            Ok(collector) => !collector.subqueries.is_empty(),
+            Err(_) => true,
+        }
+    }
+
+    // The executor wraps or panics on integer overflow and division by zero,
+    // so the literals are checked before the evaluation.
+    fn check_constant_arithmetic(op: &str, values: &[&DataValue], expr: &Expression) -> Result<()> {
+        let is_zero = |value: &DataValue| match value {
+            DataValue::Float32(Some(v)) => *v == 0.0,
+            DataValue::Float64(Some(v)) => *v == 0.0,
+            value => Self::integer_value(value) == Some(0),
+        };
+
+        if let ("/" | "divide" | "%" | "modulo", [_, divisor]) = (op, values) {
+            if is_zero(*divisor) {
+                return Err(ErrorCode::BadArguments(format!(
+                    "Division by zero in constant expression {:?}",
+                    expr
+                )));
+            }
+        }
+
+        let integers = values
+            .iter()
+            .map(|value| Self::integer_value(value))
+            .collect::<Option<Vec<_>>>();
+
+        let exact = match (op, integers.as_deref()) {
+            ("+" | "plus", Some([left, right])) => left.checked_add(*right),
+            ("-" | "minus", Some([left, right])) => left.checked_sub(*right),
+            ("*" | "multiply", Some([left, right])) => left.checked_mul(*right),
+            ("-" | "negate", Some([value])) => value.checked_neg(),
+            _ => return Ok(()),
+        };
+
+        let input_schema = DataSchemaRefExt::create(vec![]);
+        let data_type = expr.to_data_type(&input_schema)?;
+        match (exact, Self::integer_bounds(&data_type)) {
+            (Some(exact), Some((min, max))) if exact >= min && exact <= max => Ok(()),
+            _ => Err(ErrorCode::Overflow(format!(
+                "Constant expression {:?} overflows {:?}",
+                expr, data_type
+            ))),
+        }
+    }
+
+    fn integer_value(value: &DataValue) -> Option<i128> {
+        match value {
+            DataValue::UInt64(Some(v)) => Some(*v as i128),
+            value if is_integer(&value.data_type()) => value.as_i64().ok().map(|v| v as i128),
+            _ => None,
+        }
+    }
+
+    fn integer_bounds(data_type: &DataType) -> Option<(i128, i128)> {
+        match data_type {
+            DataType::Int8 => Some((i8::MIN as i128, i8::MAX as i128)),
+            DataType::Int16 => Some((i16::MIN as i128, i16::MAX as i128)),
+            DataType::Int32 => Some((i32::MIN as i128, i32::MAX as i128)),
+            DataType::Int64 => Some((i64::MIN as i128, i64::MAX as i128)),
+            DataType::UInt8 => Some((0, u8::MAX as i128)),
+            DataType::UInt16 => Some((0, u16::MAX as i128)),
+            DataType::UInt32 => Some((0, u32::MAX as i128)),
+            DataType::UInt64 => Some((0, u64::MAX as i128)),
+            _ => None,
+        }
+    }
+
+    // Evaluate the constant expression with a dummy block, the expression must be constant.
+    fn evaluate_constant_expr(expr: Expression) -> Result<Expression> {
+        let input_fields = vec![DataField::new("_dummy", DataType::UInt8, false)];
+        let input_schema = Arc::new(DataSchema::new(input_fields));
+
+        let column_name = expr.column_name();
+        let data_type = expr.to_data_type(&input_schema)?;
+        let output_fields = vec![expr.to_data_field(&input_schema)?];
+        let output_schema = DataSchemaRefExt::create(output_fields);
+        let expression_executor = ExpressionExecutor::try_create(
+            "Constant expression evaluator.",
+            input_schema.clone(),
+            output_schema,
+            vec![expr],
+            false,
+        )?;
+
+        let dummy_columns = vec![DataColumn::Constant(DataValue::UInt8(Some(1)), 1)];
+        let data_block = DataBlock::create(input_schema, dummy_columns);
+        let executed_data_block = expression_executor.execute(&data_block)?;
+
+        Ok(Expression::Literal {
+            value: executed_data_block.column(0).try_get(0)?,
+            column_name: Some(column_name),
+            data_type,
+        })
+    }
+
+    // Interval frame bounds are offsets of the ORDER BY key, it must be a single date or datetime.
+    fn rewrite_window_frame(
+        &self,
+        frame: &WindowFrame,
+        order_by: &[Expression],
+    ) -> Result<WindowFrame> {
+        if !frame.has_interval_bound() {
+            return Ok(frame.clone());
+        }
+
+        if frame.units != WindowFrameUnits::Range {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Interval frame bounds are only supported in RANGE frames, but got {:?}",
+                frame.units
+            )));
+        }
+
+        let order_key = match order_by {
+            [Expression::Sort { expr, .. }] => expr.as_ref(),
+            [expr] => expr,
+            _ => {
+                return Err(ErrorCode::SyntaxException(
+                    "RANGE frame with interval bounds requires exactly one ORDER BY key",
+                ))
+            }
+        };
+
+        if let Expression::Column(column_name) = order_key {
+            match self.column_data_type(column_name) {
+                Some(data_type) if !is_date_or_date_time(&data_type) => {
+                    return Err(ErrorCode::IllegalDataType(format!(
+                        "RANGE frame with interval bounds requires a date or datetime ORDER BY key, but {} is {:?}",
+                        column_name, data_type
+                    )));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(WindowFrame {
+            units: frame.units,
+            start_bound: self.rewrite_window_frame_bound(&frame.start_bound)?,
+            end_bound: self.rewrite_window_frame_bound(&frame.end_bound)?,
+        })
+    }
+
+    fn rewrite_window_frame_bound(&self, bound: &WindowFrameBound) -> Result<WindowFrameBound> {
+        match bound {
+            WindowFrameBound::IntervalPreceding(interval) => Ok(
+                WindowFrameBound::IntervalPreceding(Box::new(self.rewrite_expr(interval)?)),
+            ),
+            WindowFrameBound::IntervalFollowing(interval) => Ok(
+                WindowFrameBound::IntervalFollowing(Box::new(self.rewrite_expr(interval)?)),
+            ),
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn validate_interval_unit(unit: &str) -> Result<()> {
+        match unit.to_uppercase().as_str() {
+            "SECOND" | "MINUTE" | "HOUR" | "DAY" | "WEEK" | "MONTH" | "QUARTER" | "YEAR" => Ok(()),
+            _ => Err(ErrorCode::SyntaxException(format!(
+                "Unsupported interval unit: {}",
+                unit
+            ))),
+        }
+    }
+
+    // Interval can only be added to or subtracted from a date or datetime column.
+    fn check_interval_arithmetic(&self, left: &Expression, right: &Expression) -> Result<()> {
+        let column_name = match (left, right) {
+            (Expression::Column(name), Expression::Interval { .. }) => name,
+            (Expression::Interval { .. }, Expression::Column(name)) => name,
+            _ => return Ok(()),
+        };
+
+        match self.column_data_type(column_name) {
+            Some(data_type) if !is_date_or_date_time(&data_type) => {
+                Err(ErrorCode::IllegalDataType(format!(
+                    "Interval arithmetic requires a date or datetime column, but {} is {:?}",
+                    column_name, data_type
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // The data type of a rewritten column of the current scope, if it's known. The column
+    // is named as `resolve_column` names it, the short name of an ambiguous column names
+    // no column.
+    fn column_data_type(&self, column_name: &str) -> Option<DataType> {
+        let scope = self.current_scope();
+        if let Some((_, column_desc)) = scope.find_column_table(column_name) {
+            return Some(column_desc.data_type.clone());
+        }
+
+        // A column qualified by `always_qualify_columns` though its short name isn't ambiguous.
+        if !self.always_qualify {
+            return None;
+        }
+
+        scope.get_tables_desc().iter().find_map(|table_desc| {
+            let relation = table_desc.get_name_parts().join(".");
+            let column_desc = table_desc.get_columns_desc().iter().find(|column_desc| {
+                !column_desc.is_ambiguity
+                    && format!("{}.{}", relation, column_desc.short_name) == column_name
+            })?;
+            Some(column_desc.data_type.clone())
+        })
+    }
+
+    fn validate_cast_type(data_type: &DataType) -> Result<()> {
+        match data_type {
+            DataType::Struct(fields) => {
+                let mut names = HashSet::with_capacity(fields.len());
+                for field in fields {
+                    if field.name().is_empty() || !names.insert(field.name()) {
+                        return Err(ErrorCode::BadDataValueType(format!(
+                            "Struct cast target type field names must be unique and non-empty, but got {:?}",
+                            field.name()
+                        )));
+                    }
+
+                    Self::validate_cast_type(field.data_type())?;
+                }
+
+                Ok(())
+            }
+            DataType::Null | DataType::Interval(_) | DataType::List(_) => {
+                Err(ErrorCode::BadDataValueType(format!(
+                    "Unsupported cast target type: {:?}",
+                    data_type
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Reject the casts that can never succeed if the type of the source column is known.
+    fn check_cast_source(&self, expr: &Expression, data_type: &DataType) -> Result<()> {
+        let column_name = match expr {
+            Expression::Column(name) => name,
+            _ => return Ok(()),
+        };
+
+        let source_type = match self.column_data_type(column_name) {
+            None => return Ok(()),
+            Some(source_type) => source_type,
+        };
+
+        match (&source_type, data_type) {
+            // Struct cast renames and retypes the fields by position, so the field count must match.
+            (DataType::Struct(source_fields), DataType::Struct(target_fields))
+                if source_fields.len() != target_fields.len() =>
+            {
+                Err(ErrorCode::BadDataValueType(format!(
+                    "Cannot cast struct column {} with {} fields to a struct with {} fields",
+                    column_name,
+                    source_fields.len(),
+                    target_fields.len()
+                )))
+            }
+            (DataType::Struct(_), DataType::Struct(_)) | (DataType::List(_), DataType::List(_)) => {
+                Ok(())
+            }
+            (DataType::Struct(_) | DataType::List(_), _) | (_, DataType::Struct(_)) => {
+                Err(ErrorCode::BadDataValueType(format!(
+                    "Cannot cast column {} of type {:?} to {:?}",
+                    column_name, source_type, data_type
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolve the column from the innermost scope outward, returns the column and
+    /// the depth of the scope it was found in(0 is the current query).
+    pub fn resolve_column(&self, name: &str) -> Result<(Expression, usize)> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_column(name) {
+                let mut column = Expression::Column(name.to_string());
+                if let Some((index, column_desc)) = scope.find_column_table(name) {
+                    let table_desc = &scope.get_tables_desc()[index];
+                    if self.always_qualify && !column_desc.is_ambiguity {
+                        let relation = table_desc.get_name_parts().join(".");
+                        column = Expression::Column(format!("{}.{}", relation, name));
+                    }
+
+                    self.add_resolved_column(ResolvedColumn {
+                        column: column.clone(),
+                        table_desc: table_desc.clone(),
+                        ambiguity_prefixed: false,
+                    });
+                }
+
+                return Ok((column, depth));
+            }
+
+            if let (0, Some(resolved)) = (depth, self.preferred_column(name)) {
+                return Ok((self.add_resolved_column(resolved), depth));
+            }
+        }
+
+        // A column qualified by `always_qualify_columns`, when the rewritten query is
+        // resolved again. Only its ambiguous columns are named so in the scope.
+        if self.always_qualify && name.contains('.') {
+            let ref_names = name.split('.').map(str::to_string).collect::<Vec<_>>();
+            if let Ok(resolved) = self.resolve_qualified_column(&ref_names) {
+                return Ok(resolved);
+            }
+        }
+
+        if let Some(column) = self.resolve_pseudo_column(self.current_scope(), None, name)? {
+            return Ok((column, 0));
+        }
+
+        let hidden = self
+            .hidden_scopes
+            .iter()
+            .any(|scope| scope.contains_column(name));
+        Err(Self::unknown_column(name, hidden))
+    }
+
+    // A pseudo column, such as `_row_id`, is only resolved if it's referenced by name and
+    // `enable_pseudo_columns` is set. It's qualified if more than one table provides it,
+    // or if `always_qualify_columns` is set.
+    fn resolve_pseudo_column(
+        &self,
+        scope: &JoinedSchema,
+        table_desc: Option<&JoinedTableDesc>,
+        name: &str,
+    ) -> Result<Option<Expression>> {
+        if !PSEUDO_COLUMN_NAMES.contains(&name) {
+            return Ok(None);
+        }
+
+        if self.ctx.get_settings().get_enable_pseudo_columns()? == 0 {
+            return Err(ErrorCode::UnknownColumn(format!(
+                "Pseudo column {} is disabled, set enable_pseudo_columns = 1 to reference it",
+                name
+            )));
+        }
+
+        let provides = |table_desc: &JoinedTableDesc| {
+            table_desc.is_output_visible()
+                && table_desc
+                    .get_pseudo_columns_desc()
+                    .iter()
+                    .any(|column_desc| column_desc.short_name == name)
+        };
+
+        let tables_desc = scope.get_tables_desc();
+        let providers = tables_desc
+            .iter()
+            .filter(|candidate| provides(*candidate))
+            .collect::<Vec<_>>();
+        let table_desc = match (table_desc, providers.as_slice()) {
+            (Some(table_desc), _) if provides(table_desc) => table_desc,
+            (Some(table_desc), _) => {
+                return Err(ErrorCode::UnknownColumn(format!(
+                    "Table {} has no pseudo column {}",
+                    table_desc.get_name_parts().join("."),
+                    name
+                )));
+            }
+            (None, [table_desc]) => *table_desc,
+            (None, []) => {
+                return Err(ErrorCode::UnknownColumn(format!(
+                    "Pseudo column {} is not provided by any table of the query",
+                    name
+                )));
+            }
+            (None, _) => {
+                return Err(ErrorCode::UnknownColumn(format!(
+                    "Pseudo column {} is provided by more than one table, it must be qualified",
+                    name
+                )));
+            }
+        };
+
+        match providers.len() == 1 && !self.always_qualify {
+            true => Ok(Some(Expression::Column(name.to_string()))),
+            false => Ok(Some(Expression::Column(format!(
+                "{}.{}",
+                table_desc.get_name_parts().join("."),
+                name
+            )))),
+        }
+    }
+
+    // The ambiguous column of the preferred table, named as the wildcard expands it.
+    fn preferred_column(&self, name: &str) -> Option<ResolvedColumn> {
+        let table_desc = &self.current_scope().get_tables_desc()[self.preferred_table?];
+        table_desc
+            .get_columns_desc()
+            .iter()
+            .find(|column_desc| column_desc.is_ambiguity && column_desc.short_name == name)
+            .map(|_| {
+                let relation = table_desc.get_name_parts().join(".");
+                ResolvedColumn {
+                    column: Expression::Column(format!("{}.{}", relation, name)),
+                    table_desc: table_desc.clone(),
+                    ambiguity_prefixed: true,
+                }
+            })
+    }
+
+    fn add_resolved_column(&self, resolved: ResolvedColumn) -> Expression {
+        let column = resolved.column.clone();
+        let mut resolved_columns = self.resolved_columns.lock();
+        let exists = resolved_columns.iter().any(|existing| {
+            existing.column == resolved.column
+                && existing.table_desc.get_name_parts() == resolved.table_desc.get_name_parts()
+        });
+
+        if !exists {
+            resolved_columns.push(resolved);
+        }
+
+        column
+    }
+
+    /// The columns resolved to the tables of the query so far, with the table each one is
+    /// bound to and whether it was prefixed because its short name is ambiguous. Pseudo
+    /// columns and the columns of the pattern variables are not included.
+    pub fn get_resolved_columns(&self) -> Vec<ResolvedColumn> {
+        self.resolved_columns.lock().clone()
+    }
+
+    /// Same as `resolve_column`, for `table.column` or `database.table.column`.
+    pub fn resolve_qualified_column(&self, ref_names: &[String]) -> Result<(Expression, usize)> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(column) = Self::resolve_pattern_variable(scope, ref_names)? {
+                return Ok((column, depth));
+            }
+
+            if let Some((pos, table_ref)) = self.best_match_table(scope, ref_names) {
+                let column_name = &ref_names[pos..];
+                return match column_name.len() {
+                    1 => match self.find_column(&table_ref, &column_name[0]) {
+                        Ok(resolved) => Ok((self.add_resolved_column(resolved), depth)),
+                        Err(cause) => {
+                            match self.resolve_pseudo_column(
+                                scope,
+                                Some(&table_ref),
+                                &column_name[0],
+                            )? {
+                                Some(column) => Ok((column, depth)),
+                                None => Err(cause),
+                            }
+                        }
+                    },
+                    // TODO: column.field_a.field_b => GetField(field_b, GetField(field_a, column))
+                    _ => Err(ErrorCode::SyntaxException(
+                        "Unsupported complex type field access",
+                    )),
+                };
+            }
+        }
+
+        let hidden =
+            self.hidden_scopes
+                .iter()
+                .any(|scope| match self.best_match_table(scope, ref_names) {
+                    Some((pos, table_ref)) if pos + 1 == ref_names.len() => {
+                        self.find_column(&table_ref, &ref_names[pos]).is_ok()
+                    }
+                    _ => false,
+                });
+
+        self.check_over_qualified_subquery(ref_names)?;
+        if ref_names.len() == 2 && !self.current_scope().get_pattern_variables().is_empty() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Undefined pattern variable {} in MATCH_RECOGNIZE",
+                ref_names[0]
+            )));
+        }
+
+        Err(Self::unknown_column(&ref_names.join("."), hidden))
+    }
+
+    // `x.c.column` where `c` is a CTE or subquery, whose name is always single-part.
+    fn check_over_qualified_subquery(&self, ref_names: &[String]) -> Result<()> {
+        if ref_names.len() < 3 {
+            return Ok(());
+        }
+
+        let (relation, column) = ref_names.split_at(ref_names.len() - 1);
+        let subquery_name = &relation[relation.len() - 1];
+        for scope in &self.scopes {
+            for table_desc in scope.get_tables_desc() {
+                if let JoinedTableDesc::Subquery { name_parts, .. } = table_desc {
+                    if name_parts.len() == 1 && &name_parts[0] == subquery_name {
+                        return Err(ErrorCode::UnknownTable(format!(
+                            "Unknown table {}, {} is a CTE or subquery, reference its columns as {}.{}",
+                            relation.join("."),
+                            subquery_name,
+                            subquery_name,
+                            column[0]
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // `variable.column` is the column of the rows matched by the pattern variable,
+    // it is rewritten to `variable.column` to be distinct from the column itself.
+    fn resolve_pattern_variable(
+        scope: &JoinedSchema,
+        ref_names: &[String],
+    ) -> Result<Option<Expression>> {
+        match ref_names {
+            [variable, column] if scope.get_pattern_variables().contains(variable) => {
+                match scope.contains_column(column) {
+                    true => Ok(Some(Expression::Column(format!("{}.{}", variable, column)))),
+                    false => Err(Self::unknown_column(&ref_names.join("."), false)),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Resolve the `DEFINE` and `MEASURES` of `MATCH_RECOGNIZE` over the current scope, whose
+    /// pattern variables are registered. The resolved `MEASURES` become the projection.
+    pub fn rewrite_match_recognize(
+        &self,
+        ir: &mut QueryASTIR,
+        defines: &[(String, Expression)],
+        measures: &[Expression],
+    ) -> Result<Vec<(String, Expression)>> {
+        let pattern_variables = self.current_scope().get_pattern_variables();
+        let mut rewritten_defines = Vec::with_capacity(defines.len());
+        for (variable, define) in defines {
+            if !pattern_variables.contains(variable) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Undefined pattern variable {} in MATCH_RECOGNIZE DEFINE",
+                    variable
+                )));
+            }
+
+            match self.rewrite_expr(define) {
+                Ok(expr) => rewritten_defines.push((variable.clone(), expr)),
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze define {}: {:?})",
+                        variable, define
+                    )));
+                }
+            }
+        }
+
+        let mut projection_expressions = Vec::with_capacity(measures.len());
+        for measure in measures {
+            match self.rewrite_expr(measure) {
+                Ok(expr) => projection_expressions.push(expr),
+                Err(cause) => {
+                    return Err(cause.add_message_back(format!(
+                        " (while in analyze measure expr: {:?})",
+                        measure
+                    )));
+                }
+            }
+        }
+
+        ir.projection_expressions = projection_expressions;
+        Ok(rewritten_defines)
+    }
+
+    fn unknown_column(name: &str, hidden: bool) -> ErrorCode {
+        match hidden {
+            true => ErrorCode::UnknownColumn(format!(
+                "Unknown column {}, missing FROM-clause entry, a subquery in FROM must be LATERAL to reference the outer query",
+                name
+            )),
+            false => ErrorCode::UnknownColumn(format!("Unknown column {}", name)),
+        }
+    }
+
+    // A grouped outer query has one row per group, only its GROUP BY keys are constant in a subquery.
+    fn check_outer_grouping(&self, column: &Expression, depth: usize) -> Result<()> {
+        if depth == 0 {
+            return Ok(());
+        }
+
+        let scope = &self.scopes[self.scopes.len() - 1 - depth];
+        match scope.get_group_by() {
+            Some(group_by) if !group_by.contains(column) => {
+                Err(ErrorCode::SyntaxException(format!(
+                    "column '{}' of the outer query must appear in its GROUP BY",
+                    column.column_name()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // A column resolved in an enclosing query is marked so that the planner can tell it
+    // apart from a column of the current query with the same name.
+    fn correlated_column(column: Expression, depth: usize) -> Expression {
+        match (column, depth) {
+            (column, 0) => column,
+            (Expression::Column(name), depth) => Expression::OuterColumn { name, depth },
+            (column, _) => column,
+        }
+    }
+
+    fn add_outer_reference(&self, column: &Expression, depth: usize) {
+        if depth > 0 {
+            let mut outer_references = self.outer_references.lock();
+            let outer_reference = (column.clone(), depth);
+            if !outer_references.contains(&outer_reference) {
+                outer_references.push(outer_reference);
+            }
+        }
+    }
+
+    fn find_column(&self, table_desc: &JoinedTableDesc, name: &str) -> Result<ResolvedColumn> {
+        let name_parts = table_desc.get_name_parts();
+        if !table_desc.is_output_visible() {
+            return Err(ErrorCode::UnknownColumn(format!(
+                "Unknown column: {}.{}, the columns of {} are only visible to the join condition of the semi-join",
+                name_parts.join("."),
+                name,
+                name_parts.join(".")
+            )));
+        }
+
+        for column_desc in table_desc.get_columns_desc() {
+            if column_desc.short_name == name {
+                let column = match column_desc.is_ambiguity || self.always_qualify {
+                    true => Expression::Column(format!("{}.{}", name_parts.join("."), name)),
+                    false => Expression::Column(name.to_string()),
+                };
+
+                return Ok(ResolvedColumn {
+                    column,
+                    table_desc: table_desc.clone(),
+                    ambiguity_prefixed: column_desc.is_ambiguity,
+                });
+            }
+        }
+
+        Err(ErrorCode::UnknownColumn(format!(
+            "Unknown column: {}.{}",
+            name_parts.join("."),
+            name
+        )))
+    }
+
+    fn first_diff_pos(left: &[String], right: &[String]) -> usize {
+        let min_len = std::cmp::min(left.len(), right.len());
+
+        for index in 0..min_len {
+            if left[index] != right[index] {
+                return index;
+            }
+        }
+
+        min_len
+    }
+
+    fn best_match_table(
+        &self,
+        scope: &JoinedSchema,
+        ref_names: &[String],
+    ) -> Option<(usize, JoinedTableDesc)> {
+        if ref_names.len() <= 1 {
+            return None;
+        }
+
+        let current_database = self.ctx.get_current_database();
+        for table_desc in scope.get_tables_desc() {
+            // A subquery without alias can't be referenced by name.
+            let name_parts = table_desc.get_name_parts();
+            if name_parts.is_empty() {
+                continue;
+            }
+
+            if name_parts.len() < ref_names.len()
+                && Self::first_diff_pos(ref_names, name_parts) == name_parts.len()
+            {
+                // alias.column or database.table.column
+                return Some((name_parts.len(), table_desc.clone()));
+            }
+
+            if name_parts.len() > 1
+                && Self::first_diff_pos(ref_names, &name_parts[1..]) == 1
+                && current_database == name_parts[0]
+            {
+                // use current_database; table.column
+                return Some((1, table_desc.clone()));
+            }
+
+            // A CTE or subquery doesn't belong to any database, it can't be qualified by one.
+            if ref_names.len() > name_parts.len() + 1
+                && matches!(table_desc, JoinedTableDesc::Table { .. })
+                && ref_names[0] == current_database
+                && Self::first_diff_pos(&ref_names[1..], name_parts) == name_parts.len()
+            {
+                // use current_database; current_database.alias.column
+                return Some((name_parts.len() + 1, table_desc.clone()));
+            }
+        }
+
+        None
+    }
+}
+
+// Folds the unary and binary expressions of literals bottom-up, regardless of the
+// `enable_constant_folding` setting. Used where the expression must be a constant.
+struct ConstantFolder;
+
+impl ExprRewriter for ConstantFolder {
+    fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+        QualifiedRewriter::fold_literal_operands(expr)
+    }
+}
+
+// Under `enable_lateral_column_aliases`, the aliases of the projection items before are
+// replaced by their expressions, then the alias of the item is visible to the items after
+// it. An alias referenced before it's defined is resolved as a column. An unaliased item
+// keeps the name it's written with, `x * 2` rather than the name of the replaced expression.
+struct LateralAliasResolver {
+    aliases: Vec<(String, Expression)>,
+}
+
+impl LateralAliasResolver {
+    fn resolve(&mut self, expr: &Expression) -> Result<Expression> {
+        let resolved = expr.clone().rewrite(self)?;
+        match &resolved {
+            Expression::Alias(alias, inner) => {
+                self.aliases.push((alias.clone(), inner.as_ref().clone()));
+                Ok(resolved)
+            }
+            _ if &resolved != expr => Ok(Expression::Alias(expr.column_name(), Box::new(resolved))),
+            _ => Ok(resolved),
+        }
+    }
+}
+
+impl ExprRewriter for LateralAliasResolver {
+    fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+        if let Expression::Column(name) = &expr {
+            if let Some((_, alias_expr)) =
+                self.aliases.iter().rev().find(|(alias, _)| alias == name)
+            {
+                return Ok(alias_expr.clone());
+            }
+        }
+
+        Ok(expr)
+    }
+}
+
+/// The clauses in the textual order of a query, the errors of the clauses are
+/// reported in this order. WHERE also covers HAVING and QUALIFY, which are
+/// rewritten together with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Clause {
+    Projection,
+    From,
+    Where,
+    GroupBy,
+    OrderBy,
+    Limit,
+}
+
+/// The errors of the rewrite phases under `analyze_error_mode`: 0 fails on the first
+/// phase in the call order, 1 reports the error of the earliest clause in the query
+/// text, 2 reports the errors of all the clauses in the text order.
+struct ClauseErrors {
+    mode: u64,
+    errors: Vec<(Clause, ErrorCode)>,
+}
+
+impl ClauseErrors {
+    fn create(mode: u64) -> ClauseErrors {
+        ClauseErrors {
+            mode,
+            errors: vec![],
+        }
+    }
+
+    fn collect(&mut self, clause: Clause, res: Result<()>) -> Result<()> {
+        match res {
+            Ok(_) => Ok(()),
+            Err(cause) if self.mode == 0 => Err(cause),
+            Err(cause) => {
+                self.errors.push((clause, cause));
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(mut self) -> Result<()> {
+        // Stable, the errors of the same clause are kept in the call order.
+        self.errors.sort_by_key(|(clause, _)| *clause);
+        let mut errors = self.errors.into_iter().map(|(_, cause)| cause);
+
+        match errors.next() {
+            None => Ok(()),
+            Some(first) if self.mode == 1 => Err(first),
+            Some(first) => Err(errors.fold(first, |all, cause| {
+                all.add_message_back(format!("; {}", cause.message()))
+            })),
+        }
+    }
+}
+
+struct WindowOrderPositionResolver {
+    projection: Vec<Expression>,
+}
+
+impl WindowOrderPositionResolver {
+    fn resolve_sort(&self, sort: Expression) -> Result<Expression> {
+        let (asc, nulls_first, position) = match &sort {
+            Expression::Sort {
+                asc,
+                nulls_first,
+                origin_expr,
+                ..
+            } => match QualifiedRewriter::order_position(origin_expr) {
+                Some(position) => (*asc, *nulls_first, position),
+                None => return Ok(sort),
+            },
+            _ => return Ok(sort),
+        };
+
+        if position == 0 || position > self.projection.len() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Window ORDER BY position {} is not in select list",
+                position
+            )));
+        }
+
+        // The positions after a wildcard are only known once it's expanded.
+        let is_wildcard = |expr: &Expression| {
+            matches!(
+                expr,
+                Expression::Wildcard | Expression::QualifiedWildcard { .. }
+            )
+        };
+        if self.projection[..position].iter().any(is_wildcard) {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Window ORDER BY position {} cannot be resolved through a wildcard of the select list",
+                position
+            )));
+        }
+
+        let expr = match &self.projection[position - 1] {
+            Expression::Alias(_, inner) => inner.as_ref().clone(),
+            other => other.clone(),
+        };
+
+        if !find_window_exprs_in_expr(&expr).is_empty() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Window ORDER BY position {} refers to the window function {:?}",
+                position, expr
+            )));
+        }
+
+        Ok(Expression::Sort {
+            expr: Box::new(expr.clone()),
+            asc,
+            nulls_first,
+            origin_expr: Box::new(expr),
+        })
+    }
+}
+
+impl ExprRewriter for WindowOrderPositionResolver {
+    fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+        match expr {
+            Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by,
+                window_frame,
+                null_treatment,
+            } => Ok(Expression::WindowFunction {
+                op,
+                params,
+                args,
+                partition_by,
+                order_by: order_by
+                    .into_iter()
+                    .map(|sort| self.resolve_sort(sort))
+                    .collect::<Result<Vec<_>>>()?,
+                window_frame,
+                null_treatment,
+            }),
+            expr => Ok(expr),
+        }
+    }
+}
+
+// Only the output columns of the set operations are visible to their ORDER BY, a column
+// qualified by a table of the first SELECT is accepted if it's named so in the output.
+struct SetOutputColumnResolver<'a> {
+    output_names: &'a [String],
+}
+
+impl<'a> ExprRewriter for SetOutputColumnResolver<'a> {
+    fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+        let name = match &expr {
+            Expression::Column(name) => name.clone(),
+            Expression::QualifiedColumn(names) => names.join("."),
+            _ => return Ok(expr),
+        };
+
+        match self.output_names.contains(&name) {
+            true => Ok(Expression::Column(name)),
+            false => Err(ErrorCode::UnknownColumn(format!(
+                "Unknown column {}, ORDER BY of the set operations can only reference the columns of the first SELECT",
+                name
+            ))),
+        }
+    }
+}
+
+/// The functions whose result is not determined by their arguments, or depends on the
+/// session rather than the data.
+const VOLATILE_FUNCTION_NAMES: &[&str] = &[
+    "now",
+    "today",
+    "yesterday",
+    "tomorrow",
+    "rand",
+    "uuid",
+    "sleep",
+    "database",
+    "current_user",
+    "runningDifference",
+];
+
+/// Whether the rewritten query returns the same result for the same data, with the volatile
+/// functions it calls if not. The subqueries in the expressions and the operands of the set
+/// operations are checked as well, the subqueries in FROM are not part of the IR.
+pub fn is_deterministic(ir: &QueryASTIR) -> (bool, Vec<String>) {
+    let mut collector = VolatileFunctionCollector { functions: vec![] };
+    match collector.visit_ir(ir) {
+        Ok(_) => (collector.functions.is_empty(), collector.functions),
+        Err(_) => (false, collector.functions),
+    }
+}
+
+struct VolatileFunctionCollector {
+    functions: Vec<String>,
+}
+
+impl VolatileFunctionCollector {
+    fn visit_ir(&mut self, ir: &QueryASTIR) -> Result<()> {
+        self.visit_exprs(&QualifiedRewriter::clause_expressions(ir))?;
+        for expr in ir.limit.iter().chain(&ir.offset) {
+            self.visit_expr(expr)?;
+        }
+
+        for set_operation in &ir.set_operations {
+            self.visit_ir(&set_operation.query)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_function(&mut self, op: &str) {
+        let volatile = VOLATILE_FUNCTION_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(op));
+        if volatile && !self.functions.iter().any(|f| f.eq_ignore_ascii_case(op)) {
+            self.functions.push(op.to_string());
+        }
+    }
+}
+
+impl ExpressionVisitor for VolatileFunctionCollector {
+    fn pre_visit(mut self, expr: &Expression) -> Result<Recursion<Self>> {
+        match expr {
+            Expression::ScalarFunction { op, .. } | Expression::AggregateFunction { op, .. } => {
+                self.add_function(op);
+            }
+            Expression::Subquery { query_plan, .. }
+            | Expression::ScalarSubquery { query_plan, .. } => {
+                self.visit_plan_node(query_plan)?;
+            }
+            _ => {}
+        }
+
+        Ok(Recursion::Continue(self))
+    }
+}
+
+impl PlanVisitor for VolatileFunctionCollector {
+    // The subqueries nested in the expression are visited by `pre_visit`.
+    fn visit_expr(&mut self, expr: &Expression) -> Result<()> {
+        let functions = std::mem::take(&mut self.functions);
+        let collector = expr.accept(VolatileFunctionCollector { functions })?;
+        self.functions = collector.functions;
+        Ok(())
+    }
+}
+
+// Collects the (name, is scalar, plan) of the subqueries in order of appearance.
+struct SubqueryCollector {
+    subqueries: Vec<(String, bool, Arc<PlanNode>)>,
+}
+
+impl ExpressionVisitor for SubqueryCollector {
+    fn pre_visit(mut self, expr: &Expression) -> Result<Recursion<Self>> {
+        match expr {
+            Expression::Subquery { name, query_plan } => {
+                self.subqueries
+                    .push((name.clone(), false, query_plan.clone()));
+            }
+            Expression::ScalarSubquery { name, query_plan } => {
+                self.subqueries
+                    .push((name.clone(), true, query_plan.clone()));
+            }
+            _ => {}
+        }
+
+        Ok(Recursion::Continue(self))
+    }
+}
+
+struct ReferencedTablesCollector {
+    tables: Vec<Vec<String>>,
+}
+
+impl ReferencedTablesCollector {
+    // The description of a table is `'database'.'table'`.
+    fn add_table(&mut self, table_info: &TableInfo) {
+        let desc = table_info.desc.trim_matches('\'');
+        let name_parts = desc.split("'.'").map(String::from).collect::<Vec<_>>();
+        if !self.tables.contains(&name_parts) {
+            self.tables.push(name_parts);
+        }
+    }
+
+    fn visit_analyze_state(&mut self, state: &QueryAnalyzeState) -> Result<()> {
+        match &state.relation {
+            QueryRelation::None => {}
+            QueryRelation::FromTable(plan) => self.add_table(&plan.table_info),
+            QueryRelation::Nested(state) => self.visit_analyze_state(state)?,
+        };
+
+        self.visit_exprs(&state.before_group_by_expressions)?;
+        self.visit_exprs(&state.group_by_expressions)?;
+        self.visit_exprs(&state.aggregate_expressions)?;
+        self.visit_exprs(&state.expressions)?;
+        self.visit_exprs(&state.projection_expressions)?;
+        self.visit_exprs(&state.order_by_expressions)?;
+
+        for predicate in state.filter.iter().chain(&state.having) {
+            self.visit_expr(predicate)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PlanVisitor for ReferencedTablesCollector {
+    fn visit_read_data_source(&mut self, plan: &ReadDataSourcePlan) -> Result<()> {
+        self.add_table(&plan.table_info);
+        Ok(())
+    }
+
+    // The subqueries nested in the expression are visited too.
+    fn visit_expr(&mut self, expr: &Expression) -> Result<()> {
+        let collector = expr.accept(SubqueryCollector { subqueries: vec![] })?;
+        for (_, _, query_plan) in collector.subqueries {
+            self.visit_plan_node(&query_plan)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct JoinScopeVisitor<'a> {
+    rewriter: &'a QualifiedRewriter,
+}
+
+impl<'a> ExpressionVisitor for JoinScopeVisitor<'a> {
+    fn pre_visit(self, expr: &Expression) -> Result<Recursion<Self>> {
+        match expr {
+            Expression::Column(name) => {
+                self.rewriter.resolve_column(name)?;
+            }
+            Expression::QualifiedColumn(names) => {
+                self.rewriter.resolve_qualified_column(names)?;
+            }
+            _ => {}
+        }
+
+        Ok(Recursion::Continue(self))
     }
 }
@@ -12,117 +12,4485 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use common_base::tokio;
+use common_datavalues::DataField;
+use common_datavalues::DataSchemaRefExt;
+use common_datavalues::DataType;
 use common_exception::ErrorCode;
 use common_exception::Result;
+use common_planners::find_columns_not_in_group_by;
+use common_planners::lit;
+use common_planners::CastOnError;
+use common_planners::Expression;
+use common_planners::NullTreatment;
+use common_planners::WindowFrame;
+use common_planners::WindowFrameBound;
+use common_planners::WindowFrameUnits;
+use sqlparser::ast::Expr;
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Tokenizer;
 
+use crate::catalogs::Catalog;
+use crate::datasources::table::fuse::table_test_fixture::TestFixture;
+use crate::sessions::QueryContext;
+use crate::sessions::RewritePhaseTiming;
+use crate::sql::statements::query::is_deterministic;
+use crate::sql::statements::query::ExpandedColumn;
+use crate::sql::statements::query::JoinedSchema;
 use crate::sql::statements::query::JoinedSchemaAnalyzer;
 use crate::sql::statements::query::QualifiedRewriter;
+use crate::sql::statements::query::QueryASTIR;
 use crate::sql::statements::query::QueryNormalizer;
+use crate::sql::statements::query::TablePivot;
+use crate::sql::statements::DfQueryStatement;
+use crate::sql::statements::QueryAnalyzeState;
 use crate::sql::DfParser;
 use crate::sql::DfStatement;
 use crate::tests::try_create_context;
 
-#[tokio::test]
-async fn test_query_qualified_rewriter() -> Result<()> {
-    struct TestCase {
-        name: &'static str,
-        query: &'static str,
-        expect: &'static str,
+fn create_context(settings: &[(&str, u64)]) -> Result<Arc<QueryContext>> {
+    let ctx = try_create_context()?;
+    for (name, value) in settings {
+        ctx.get_settings()
+            .update_settings(name, value.to_string())?;
+    }
+    Ok(ctx)
+}
+
+fn parse_query(query: &str) -> Result<DfQueryStatement> {
+    let (mut statements, _) = DfParser::parse_sql(query)?;
+
+    match statements.remove(0) {
+        DfStatement::Query(query) => Ok(query),
+        _ => Err(ErrorCode::LogicalError("Cannot get analyze query state.")),
+    }
+}
+
+fn parse_expr(sql: &str) -> Result<Expr> {
+    let dialect = PostgreSqlDialect {};
+    let mut tokenizer = Tokenizer::new(&dialect, sql);
+    let tokens = tokenizer.tokenize().unwrap();
+    let mut parser = Parser::new(tokens, &dialect);
+    Ok(parser.parse_expr()?)
+}
+
+// There are no tables with these column types yet, use a subquery schema instead.
+fn subquery_schema(alias: &str, fields: Vec<DataField>) -> Result<JoinedSchema> {
+    let state = QueryAnalyzeState {
+        finalize_schema: DataSchemaRefExt::create(fields),
+        ..Default::default()
+    };
+    JoinedSchema::from_subquery(Box::new(state), vec![alias.to_string()])
+}
+
+fn list_of(data_type: DataType) -> DataType {
+    DataType::List(Box::new(DataField::new("item", data_type, false)))
+}
+
+async fn analyze_outer_scopes(
+    ctx: &Arc<QueryContext>,
+    outer_queries: &[&str],
+) -> Result<Vec<JoinedSchema>> {
+    let mut outer_scopes = Vec::with_capacity(outer_queries.len());
+    for outer_query in outer_queries {
+        let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
+        outer_scopes.push(analyzer.analyze(&parse_query(outer_query)?).await?);
+    }
+    Ok(outer_scopes)
+}
+
+async fn normalize_query(
+    ctx: &Arc<QueryContext>,
+    query: &str,
+) -> Result<(JoinedSchema, QueryASTIR)> {
+    let query = parse_query(query)?;
+    let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
+    let joined_schema = analyzer.analyze(&query).await?;
+
+    let transform = QueryNormalizer::create(ctx.clone());
+    let data = transform.transform(&query).await?;
+    Ok((joined_schema, data))
+}
+
+async fn rewrite_query(ctx: &Arc<QueryContext>, query: &str) -> Result<QueryASTIR> {
+    let (joined_schema, data) = normalize_query(ctx, query).await?;
+    let rewriter = QualifiedRewriter::create(joined_schema, ctx.clone());
+    rewriter.rewrite(data)
+}
+
+fn format_rewritten(rewritten: Result<QueryASTIR>) -> String {
+    match rewritten {
+        Ok(ir) => format!("{:?}", ir),
+        Err(cause) => cause.message(),
+    }
+}
+
+// The syntax the sqlparser doesn't produce yet, built into the query by hand.
+enum Clause {
+    Qualify(&'static str),
+    GroupingSets(Vec<Vec<&'static str>>),
+    DistinctOn(Vec<&'static str>),
+    Hints(Vec<&'static str>),
+    Projection(Expression),
+    // Both the aggregate and the projection.
+    Aggregate(Expression),
+    Window(Expression),
+    Filter(Expression),
+    Having(Expression),
+    OrderBy(Expression),
+    // Turns the IN subquery of the filter into `left op quantifier (subquery)`.
+    Quantified {
+        op: &'static str,
+        quantifier: &'static str,
+    },
+}
+
+impl Clause {
+    fn build_query(&self, query: &mut DfQueryStatement) -> Result<()> {
+        match self {
+            Clause::Qualify(qualify) => query.qualify = Some(parse_expr(qualify)?),
+            Clause::GroupingSets(grouping_sets) => {
+                for grouping_set in grouping_sets {
+                    let keys = grouping_set.iter().map(|key| parse_expr(key));
+                    query.grouping_sets.push(keys.collect::<Result<Vec<_>>>()?);
+                }
+            }
+            Clause::DistinctOn(distinct_on) => {
+                for expr in distinct_on {
+                    query.distinct_on.push(parse_expr(expr)?);
+                }
+            }
+            Clause::Hints(hints) => {
+                for hint in hints {
+                    query.hints.push(parse_expr(hint)?);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build_ir(&self, data: &mut QueryASTIR) {
+        match self {
+            Clause::Projection(expr) => data.projection_expressions = vec![expr.clone()],
+            Clause::Aggregate(expr) => {
+                data.aggregate_expressions = vec![expr.clone()];
+                data.projection_expressions = vec![expr.clone()];
+            }
+            Clause::Window(expr) => data.window_expressions = vec![expr.clone()],
+            Clause::Filter(expr) => data.filter_predicate = Some(expr.clone()),
+            Clause::Having(expr) => data.having_predicate = Some(expr.clone()),
+            Clause::OrderBy(expr) => data.order_by_expressions = vec![expr.clone()],
+            Clause::Quantified { op, quantifier } => {
+                data.filter_predicate = match data.filter_predicate.take() {
+                    Some(Expression::InSubquery { expr, subquery, .. }) => {
+                        Some(Expression::QuantifiedComparison {
+                            op: op.to_string(),
+                            quantifier: quantifier.to_string(),
+                            left: expr,
+                            subquery,
+                        })
+                    }
+                    other => other,
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct TestCase {
+    name: &'static str,
+    query: &'static str,
+    // The settings changed from their defaults.
+    settings: &'static [(&'static str, u64)],
+    clause: Option<Clause>,
+    // The rewritten query, or the message of the error.
+    expect: &'static str,
+    error_code: Option<u16>,
+    warnings: &'static [&'static str],
+}
+
+// Rewrites the test cases against the given schema, or against the tables of the catalog.
+async fn run_test_cases(joined_schema: Option<&JoinedSchema>, tests: &[TestCase]) -> Result<()> {
+    for test_case in tests {
+        let ctx = create_context(test_case.settings)?;
+        let mut query = parse_query(test_case.query)?;
+        if let Some(clause) = &test_case.clause {
+            clause.build_query(&mut query)?;
+        }
+
+        let joined_schema = match joined_schema {
+            Some(joined_schema) => joined_schema.clone(),
+            None => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
+                analyzer.analyze(&query).await?
+            }
+        };
+
+        let transform = QueryNormalizer::create(ctx.clone());
+        let rewritten = match transform.transform(&query).await {
+            Ok(mut data) => {
+                if let Some(clause) = &test_case.clause {
+                    clause.build_ir(&mut data);
+                }
+                QualifiedRewriter::create(joined_schema, ctx.clone()).rewrite(data)
+            }
+            Err(cause) => Err(cause),
+        };
+
+        if let (Some(code), Err(cause)) = (test_case.error_code, &rewritten) {
+            assert_eq!(code, cause.code(), "{:#?}", test_case.name);
+        }
+        let actual = format_rewritten(rewritten);
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+        assert_eq!(
+            test_case.warnings,
+            ctx.get_warnings(),
+            "{:#?}",
+            test_case.name
+        );
     }
 
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter() -> Result<()> {
     let tests = vec![
         TestCase {
             name: "Simple query",
             query: "SELECT 1",
             expect: "NormalQuery { projection: [1] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query",
             query: "SELECT alias.name FROM system.databases AS alias",
             expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query",
             query: "SELECT system.databases.name FROM system.databases",
             expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Conditional function query",
+            query: "SELECT IFNULL(alias.name, 'x') FROM system.databases AS alias",
+            expect: "NormalQuery { projection: [coalesce(name, x)] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query with filter",
             query: "SELECT name FROM system.databases AS alias WHERE alias.name = 'XXX'",
             expect: "NormalQuery { filter: (name = XXX), projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query with filter",
             query: "SELECT name FROM system.databases WHERE system.databases.name = 'XXX'",
             expect: "NormalQuery { filter: (name = XXX), projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query with group",
             query: "SELECT name FROM system.databases AS alias GROUP BY alias.name",
             expect: "NormalQuery { group by: [name], projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query with group",
             query: "SELECT name FROM system.databases GROUP BY system.databases.name",
             expect: "NormalQuery { group by: [name], projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query with having",
             query: "SELECT name FROM system.databases AS alias HAVING alias.name = 'xxx'",
             expect: "NormalQuery { having: (name = xxx), projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query with having",
             query: "SELECT name FROM system.databases HAVING system.databases.name = 'xxx'",
             expect: "NormalQuery { having: (name = xxx), projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query with order",
             query: "SELECT name FROM system.databases AS alias ORDER BY alias.name",
             expect: "NormalQuery { order by: [name], projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query with order",
             query: "SELECT name FROM system.databases ORDER BY system.databases.name",
             expect: "NormalQuery { order by: [name], projection: [name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Alias query with aggregate",
             query: "SELECT COUNT(alias.name) AS name FROM system.databases AS alias WHERE name = 'xxx'",
             expect: "NormalQuery { filter: (name = xxx), aggregate: [COUNT(name)], projection: [COUNT(name) as name] }",
+            ..Default::default()
         },
         TestCase {
             name: "Database and table query with aggregate",
             query: "SELECT COUNT(system.databases.name) AS name FROM system.databases WHERE system.databases.name = 'xxx'",
             expect: "NormalQuery { filter: (name = xxx), aggregate: [COUNT(name)], projection: [COUNT(name) as name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Distinct aggregate with cast argument",
+            query: "SELECT COUNT(DISTINCT CAST(alias.name AS INT)) FROM system.databases AS alias",
+            expect: "NormalQuery { aggregate: [COUNT(distinct cast(name as Int32))], projection: [COUNT(distinct cast(name as Int32))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit and offset query",
+            query: "SELECT name FROM system.databases LIMIT 1 OFFSET 2",
+            expect: "NormalQuery { projection: [name], limit: 1, offset: 2 }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with constant arithmetic query",
+            query: "SELECT name FROM system.databases LIMIT 2 * 5",
+            expect: "NormalQuery { projection: [name], limit: 10 }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast map access query",
+            query: "SELECT CAST(alias.name['key'] AS INT) FROM system.databases AS alias",
+            expect: "NormalQuery { projection: [cast(name[key] as Int32)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window function query",
+            query: "SELECT ROW_NUMBER() OVER (PARTITION BY alias.name ORDER BY alias.name) FROM system.databases AS alias",
+            expect: "NormalQuery { window: [ROW_NUMBER() over (partition by name order by name)], projection: [ROW_NUMBER() over (partition by name order by name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Listagg query",
+            query: "SELECT LISTAGG(alias.name, ', ') WITHIN GROUP (ORDER BY alias.database) FROM system.tables AS alias",
+            expect: "NormalQuery { aggregate: [listagg(', ')(name) within group (order by database)], projection: [listagg(', ')(name) within group (order by database)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Listagg ordered by unqualified column query",
+            query: "SELECT LISTAGG(DISTINCT name, ', ') WITHIN GROUP (ORDER BY database DESC, name) FROM system.tables",
+            expect: "NormalQuery { aggregate: [listagg(', ')(distinct name) within group (order by database, name)], projection: [listagg(', ')(distinct name) within group (order by database, name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Rollup query",
+            query: "SELECT alias.name, GROUPING(alias.name) FROM system.tables AS alias GROUP BY ROLLUP(alias.database, alias.name)",
+            expect: "NormalQuery { group by: [database, name], grouping: ROLLUP(database, name), projection: [name, GROUPING(name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cube query",
+            query: "SELECT database FROM system.tables GROUP BY CUBE(database, name)",
+            expect: "NormalQuery { group by: [database, name], grouping: CUBE(database, name), projection: [database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fractional and negative interval query",
+            query: "SELECT INTERVAL '1.5' DAY, INTERVAL '-7' HOUR",
+            expect: "NormalQuery { projection: [INTERVAL 1.5 DAY, INTERVAL -7 HOUR] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Try cast query",
+            query: "SELECT TRY_CAST(name AS UInt32) FROM system.databases",
+            expect: "NormalQuery { projection: [try_cast(name as UInt32)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Nested try cast query",
+            query: "SELECT TRY_CAST(TRY_CAST(name AS UInt32) AS TEXT) FROM system.databases",
+            expect: "NormalQuery { projection: [try_cast(try_cast(name as UInt32) as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast of try cast query",
+            query: "SELECT CAST(TRY_CAST(name AS UInt32) AS TEXT), name::UInt32 FROM system.databases",
+            expect: "NormalQuery { projection: [cast(try_cast(name as UInt32) as String), cast(name as UInt32)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Aggregate without group query",
+            query: "SELECT COUNT(), MAX(name) AS m FROM system.databases",
+            expect: "NormalQuery { aggregate: [COUNT(), MAX(name)], projection: [COUNT(), MAX(name) as m] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by position of cast query",
+            query: "SELECT CAST(name AS TEXT) FROM system.databases ORDER BY 1",
+            expect: "NormalQuery { order by: [cast(name as String)], projection: [cast(name as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by position of aliased cast query",
+            query: "SELECT CAST(alias.name AS TEXT) AS text FROM system.databases AS alias ORDER BY 1",
+            expect: "NormalQuery { order by: [cast(name as String)], projection: [cast(name as String) as text] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with column query",
+            query: "SELECT name FROM system.databases LIMIT name",
+            expect: "LIMIT must be a constant expression (while in analyze limit name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Offset with column query",
+            query: "SELECT name FROM system.databases LIMIT 1 OFFSET name",
+            expect: "LIMIT must be a constant expression (while in analyze offset name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with float query",
+            query: "SELECT name FROM system.databases LIMIT 1.5",
+            expect: "LIMIT must be a constant expression (while in analyze limit 1.5)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with string query",
+            query: "SELECT name FROM system.databases LIMIT 'a'",
+            expect: "LIMIT must be a constant expression (while in analyze limit a)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with null query",
+            query: "SELECT name FROM system.databases LIMIT NULL",
+            expect: "LIMIT must be a constant expression (while in analyze limit NULL)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with division by zero query",
+            query: "SELECT name FROM system.databases LIMIT 10 % 0",
+            expect: "Division by zero in constant expression (10 % 0) (while in analyze limit (10 % 0))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Limit with overflowing arithmetic query",
+            query: "SELECT name FROM system.databases LIMIT 18446744073709551615 + 1",
+            expect: "Constant expression (18446744073709551615 + 1) overflows UInt64 (while in analyze limit (18446744073709551615 + 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Listagg with column separator query",
+            query: "SELECT LISTAGG(name, name) WITHIN GROUP (ORDER BY name) FROM system.databases",
+            expect: "LISTAGG separator must be a string literal, but got name (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Listagg ordered by unknown column query",
+            query: "SELECT LISTAGG(name, ', ') WITHIN GROUP (ORDER BY missing) FROM system.tables",
+            expect: "Unknown column missing (while in analyze aggregate expr: listagg(', ')(name) within group (order by missing))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping with non-group column query",
+            query: "SELECT GROUPING(name) FROM system.tables GROUP BY ROLLUP(database)",
+            expect: "GROUPING argument must be a grouping column (while in analyze projection expr: GROUPING(name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping with multiple arguments query",
+            query: "SELECT database, name, GROUPING(database, name) FROM system.tables GROUP BY CUBE(database, name)",
+            expect: "NormalQuery { group by: [database, name], grouping: CUBE(database, name), projection: [database, name, GROUPING(database, name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping with non-group column in multiple arguments query",
+            query: "SELECT GROUPING(database, name) FROM system.tables GROUP BY GROUPING SETS ((database), ())",
+            expect: "GROUPING argument must be a grouping column (while in analyze projection expr: GROUPING(database, name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Rollup with other group by items query",
+            query: "SELECT database FROM system.tables GROUP BY database, ROLLUP(name)",
+            expect: "ROLLUP must be the only GROUP BY item (while in analyze select group by)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Interval with non-temporal column query",
+            query: "SELECT name + INTERVAL '7' DAY FROM system.databases",
+            expect: "Interval arithmetic requires a date or datetime column, but name is String (while in analyze projection expr: (name + INTERVAL 7 DAY))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast to unknown type query",
+            query: "SELECT CAST(name AS frobnicate) FROM system.databases",
+            expect: "Unknown cast target type: frobnicate (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by position out of range query",
+            query: "SELECT name FROM system.databases ORDER BY 2",
+            expect: "ORDER BY position 2 is not in select list",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Column with aggregate without group query",
+            query: "SELECT name, COUNT() FROM system.databases",
+            expect: "column 'name' must appear in GROUP BY or be used in an aggregate",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Nested column with aggregate without group query",
+            query: "SELECT SUBSTRING(name, 1, 2) AS n, COUNT() FROM system.databases",
+            expect: "column 'name' must appear in GROUP BY or be used in an aggregate",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Coalesce without arguments query",
+            query: "SELECT COALESCE() FROM system.databases",
+            expect: "COALESCE requires at least 1 argument, but got 0 (while in analyze projection expr: COALESCE())",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Nullif with one argument query",
+            query: "SELECT NULLIF(name) FROM system.databases",
+            expect: "NULLIF requires exactly 2 arguments, but got 1 (while in analyze projection expr: NULLIF(name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Ifnull with three arguments query",
+            query: "SELECT ifnull(name, name, name) FROM system.databases",
+            expect: "IFNULL requires exactly 2 arguments, but got 3 (while in analyze projection expr: ifnull(name, name, name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Nullif with unknown column query",
+            query: "SELECT NULLIF(name, missing) FROM system.databases",
+            expect: "Unknown column missing (while in analyze projection expr: NULLIF(name, missing))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Try cast to unknown type query",
+            query: "SELECT TRY_CAST(name AS frobnicate) FROM system.databases",
+            expect: "Unknown cast target type: frobnicate (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Expression within the depth limit",
+            query: "SELECT (name = 'a') AND (name = 'b') FROM system.databases",
+            settings: &[("max_expression_depth", 3)],
+            expect: "NormalQuery { projection: [((name = a) AND (name = b))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Expression nesting too deep",
+            query: "SELECT (name = 'a') AND (name = 'b') FROM system.databases",
+            settings: &[("max_expression_depth", 2)],
+            expect: "expression nesting too deep (while in analyze projection expr: ((name = a) AND (name = b)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Joined tables within the limit",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.database",
+            settings: &[("max_joined_tables", 2)],
+            expect: "NormalQuery { join conditions: [(d.name = database)], projection: [d.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Too many joined tables",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.database",
+            settings: &[("max_joined_tables", 1)],
+            expect: "Too many joined tables: 2, the limit is max_joined_tables = 1",
+            ..Default::default()
         },
     ];
+    run_test_cases(None, &tests).await?;
 
-    for test_case in &tests {
-        let ctx = try_create_context()?;
-        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+    Ok(())
+}
 
-        match statements.remove(0) {
-            DfStatement::Query(query) => {
-                let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
-                let joined_schema = analyzer.analyze(&query).await?;
+#[tokio::test]
+async fn test_query_qualified_rewriter_relations() -> Result<()> {
+    let qualified = |table: &str, name: &str| {
+        Expression::QualifiedColumn(vec![table.to_string(), name.to_string()])
+    };
 
-                let transform = QueryNormalizer::create(ctx.clone());
-                let data = transform.transform(&query).await?;
+    let tests = vec![
+        TestCase {
+            name: "Join condition",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.database",
+            expect: "NormalQuery { join conditions: [(d.name = database)], projection: [d.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Join condition referencing the left tables",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.database INNER JOIN system.one AS o ON d.name = t.name",
+            expect: "NormalQuery { join conditions: [(d.name = database), (d.name = t.name)], projection: [d.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Join condition referencing a later table",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = o.name INNER JOIN system.one AS o ON d.name = t.name",
+            expect: "Unknown column o.name (while in analyze join condition (\"d.name\" = \"o.name\"))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Join condition with unknown column",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.missing",
+            expect: "Unknown column: t.missing (while in analyze join condition (\"d.name\" = \"t.missing\"))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Natural join wildcard outputs the key once",
+            query: "SELECT * FROM system.databases AS d NATURAL JOIN system.tables AS t",
+            expect: "NormalQuery { join conditions: [(name = t.name)], projection: [name, database, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Natural join key by short name and merged column by full name",
+            query: "SELECT name, d.name, t.name FROM system.databases AS d NATURAL JOIN system.tables AS t",
+            expect: "NormalQuery { join conditions: [(name = t.name)], projection: [name, name, t.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Natural right join keeps the key of the right side",
+            query: "SELECT * FROM system.databases AS d NATURAL RIGHT JOIN system.tables AS t",
+            expect: "NormalQuery { join conditions: [(d.name = name)], projection: [database, name, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Natural join qualified wildcards output the key once",
+            query: "SELECT d.*, t.* FROM system.databases AS d NATURAL JOIN system.tables AS t",
+            expect: "NormalQuery { join conditions: [(name = t.name)], projection: [name, database, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Natural join qualified wildcard of the merged side",
+            query: "SELECT t.* FROM system.databases AS d NATURAL JOIN system.tables AS t",
+            expect: "NormalQuery { join conditions: [(name = t.name)], projection: [database, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Implicit cross join",
+            query: "SELECT database FROM system.databases, system.tables",
+            settings: &[("forbid_implicit_cross_join", 1)],
+            expect: "implicit cross join between databases and tables; add a join condition or use CROSS JOIN",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Implicit cross join allowed",
+            query: "SELECT database FROM system.databases, system.tables",
+            expect: "NormalQuery { projection: [database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Implicit join with filter predicate",
+            query: "SELECT database FROM system.databases, system.tables WHERE databases.name = tables.database",
+            settings: &[("forbid_implicit_cross_join", 1)],
+            expect: "NormalQuery { filter: (system.databases.name = database), projection: [database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Implicit join with single table predicates",
+            query: "SELECT database FROM system.databases, system.tables WHERE databases.name = 'a' AND tables.name = 'b'",
+            settings: &[("forbid_implicit_cross_join", 1)],
+            expect: "implicit cross join between databases and tables; add a join condition or use CROSS JOIN",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Implicit join with unconnected third table",
+            query: "SELECT database FROM system.databases AS d, system.tables AS t, system.one AS o WHERE d.name = t.name",
+            settings: &[("forbid_implicit_cross_join", 1)],
+            expect: "implicit cross join between d and o; add a join condition or use CROSS JOIN",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Explicit cross join",
+            query: "SELECT database FROM system.databases CROSS JOIN system.tables",
+            settings: &[("forbid_implicit_cross_join", 1)],
+            expect: "NormalQuery { projection: [database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE output column",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT x, c.x FROM c",
+            expect: "NormalQuery { projection: [x, x] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE wildcard",
+            query: "WITH c AS (SELECT name AS x, name FROM system.databases) SELECT * FROM c",
+            expect: "NormalQuery { projection: [x, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE with alias",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT t.x FROM c AS t",
+            expect: "NormalQuery { projection: [x] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE referencing previous CTE",
+            query: "WITH c AS (SELECT name AS x FROM system.databases), d AS (SELECT x AS y FROM c) SELECT y FROM d",
+            expect: "NormalQuery { projection: [y] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE internal column",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT name FROM c",
+            expect: "Unknown column name (while in analyze projection expr: name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE qualified by current database",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT default.c.x FROM c",
+            expect: "Unknown table default.c, c is a CTE or subquery, reference its columns as c.x (while in analyze projection expr: \"default.c.x\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "CTE qualified by schema-like name",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT s.c.x FROM c",
+            expect: "Unknown table s.c, c is a CTE or subquery, reference its columns as c.x (while in analyze projection expr: \"s.c.x\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Column qualified by subquery alias",
+            query: "SELECT sub.name FROM (SELECT name FROM system.databases) AS sub WHERE sub.name = 'default'",
+            expect: "NormalQuery { filter: (name = default), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Bare column of the sole subquery",
+            query: "SELECT name FROM (SELECT name FROM system.databases) AS sub ORDER BY name",
+            expect: "NormalQuery { order by: [name], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Bare column of the subquery without alias",
+            query: "SELECT name FROM (SELECT name FROM system.databases)",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified column of the subquery without alias",
+            query: "SELECT x.name FROM (SELECT name FROM system.databases)",
+            expect: "Unknown column x.name (while in analyze projection expr: \"x.name\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Subquery alias qualified by database",
+            query: "SELECT default.sub.name FROM (SELECT name FROM system.databases) AS sub",
+            expect: "Unknown table default.sub, sub is a CTE or subquery, reference its columns as sub.name (while in analyze projection expr: \"default.sub.name\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Table name without column",
+            query: "SELECT system.databases FROM system.databases",
+            expect: "Unknown column system.databases (while in analyze projection expr: \"system.databases\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Filter column of the subquery output not selected",
+            query: "SELECT name FROM (SELECT name, database FROM system.tables) AS sub WHERE database = 'system'",
+            expect: "NormalQuery { filter: (database = system), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Filter column of the table inside the subquery",
+            query: "SELECT name FROM (SELECT name FROM system.tables) AS sub WHERE database = 'system'",
+            expect: "Unknown column database (while in analyze filter predicate (database = system))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified filter column of the table inside the subquery",
+            query: "SELECT name FROM (SELECT name FROM system.tables) AS sub WHERE sub.database = 'system'",
+            expect: "Unknown column: sub.database (while in analyze filter predicate (\"sub.database\" = system))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Filter column renamed by the subquery",
+            query: "SELECT x FROM (SELECT name AS x FROM system.tables) AS sub WHERE name = 'system'",
+            expect: "Unknown column name (while in analyze filter predicate (name = system))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified and bare values columns",
+            query: "SELECT v.id, name FROM (VALUES (1, 'a'), (2, 'b')) AS v(id, name) WHERE v.id > 1",
+            expect: "NormalQuery { filter: (id > 1), projection: [id, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Default values column names",
+            query: "SELECT column2 FROM (VALUES (1, 'a')) AS v ORDER BY v.column1",
+            expect: "NormalQuery { order by: [column1], projection: [column2] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown values column",
+            query: "SELECT v.missing FROM (VALUES (1, 'a')) AS v(id, name)",
+            expect: "Unknown column: v.missing (while in analyze projection expr: \"v.missing\")",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
 
-                let rewriter = QualifiedRewriter::create(joined_schema, ctx);
-                assert_eq!(
-                    test_case.expect,
-                    format!("{:?}", rewriter.rewrite(data).await?),
-                    "{:#?}",
-                    test_case.name
-                )
-            }
-            _ => {
-                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
-            }
+    let joined_schema = {
+        // The sqlparser doesn't produce SEMI JOIN yet, build
+        // `system.databases AS d SEMI JOIN system.tables AS t ON d.name = t.database` by hand.
+        let ctx = try_create_context()?;
+        let databases = ctx.get_table("system", "databases").await?;
+        let databases = JoinedSchema::from_table(databases, vec!["d".to_string()])?;
+        let tables = ctx.get_table("system", "tables").await?;
+        let tables = JoinedSchema::from_table(tables, vec!["t".to_string()])?;
+        let condition = Expression::create_binary_expression("=", vec![
+            qualified("d", "name"),
+            qualified("t", "database"),
+        ]);
+        databases.semi_join(&tables)?.with_join_condition(condition)
+    };
+    let tests = vec![
+        TestCase {
+            name: "Wildcard skips the semi-join side",
+            query: "SELECT * FROM d",
+            expect: "NormalQuery { join conditions: [(name = database)], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Short name shared with the semi-join side",
+            query: "SELECT name FROM d WHERE d.name = 'default'",
+            expect: "NormalQuery { join conditions: [(name = database)], filter: (name = default), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified column of the semi-join side",
+            query: "SELECT t.engine FROM d",
+            expect: "Unknown column: t.engine, the columns of t are only visible to the join condition of the semi-join (while in analyze projection expr: \"t.engine\")",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Short column of the semi-join side",
+            query: "SELECT engine FROM d",
+            expect: "Unknown column engine (while in analyze projection expr: engine)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard of the semi-join side",
+            query: "SELECT t.* FROM d",
+            expect: "Unknown table t of qualified wildcard (while in analyze projection expr: t.*)",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_projection() -> Result<()> {
+    // The sqlparser doesn't produce `* ILIKE 'pattern'` yet, build it by hand.
+    let wildcard_ilike = |pattern: &str| Expression::BinaryExpression {
+        op: String::from("ILIKE"),
+        left: Box::new(Expression::Wildcard),
+        right: Box::new(lit(pattern.as_bytes())),
+    };
+    // The sqlparser doesn't produce EXCEPT and RENAME of qualified wildcard yet,
+    // build the qualified wildcard by hand.
+    let to_strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    let qualified_wildcard = |qualifier: &[&str], except: &[&str], rename: &[(&str, &str)]| {
+        Expression::QualifiedWildcard {
+            qualifier: to_strings(qualifier),
+            except: to_strings(except),
+            rename: rename
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
         }
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Duplicate aliases",
+            query: "SELECT database AS x, name AS x FROM system.tables AS t",
+            expect: "duplicate output column name 'x' (from t.database and t.name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias conflicts with a column",
+            query: "SELECT database, name AS database FROM system.tables AS t",
+            expect: "duplicate output column name 'database' (from t.database and t.name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias conflicts with a wildcard column",
+            query: "SELECT *, 1 AS engine FROM system.tables AS t",
+            expect: "duplicate output column name 'engine' (from t.engine)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate aliases of expressions",
+            query: "SELECT 1 AS x, 2 AS x FROM system.tables",
+            expect: "duplicate output column name 'x'",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Same expression with the same alias",
+            query: "SELECT database AS x, database AS x FROM system.tables",
+            expect: "NormalQuery { projection: [database as x, database as x] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Output names are case sensitive",
+            query: "SELECT database AS x, name AS X FROM system.tables",
+            expect: "NormalQuery { projection: [database as x, name as X] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate projection",
+            query: "SELECT database, database, name = 'a', name = 'a' FROM system.tables",
+            settings: &[("dedup_projection", 1)],
+            expect: "NormalQuery { projection: [database, (name = a)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate projection without dedup",
+            query: "SELECT database, database FROM system.tables",
+            expect: "NormalQuery { projection: [database, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate projection keeps the first alias",
+            query: "SELECT database AS db, name, database FROM system.tables",
+            settings: &[("dedup_projection", 1)],
+            expect: "NormalQuery { projection: [database as db, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate projection with different aliases",
+            query: "SELECT database AS a, database AS b, database FROM system.tables",
+            settings: &[("dedup_projection", 1)],
+            expect: "NormalQuery { projection: [database as a, database as b] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate projection with order by position",
+            query: "SELECT database, database, name FROM system.tables ORDER BY 3",
+            settings: &[("dedup_projection", 1)],
+            expect: "NormalQuery { order by: [name], projection: [database, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias of previous item",
+            query: "SELECT name AS x, x AS y FROM system.databases",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "NormalQuery { projection: [name as x, name as y] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Chained aliases",
+            query: "SELECT name AS x, x AS y, y AS z FROM system.databases",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "NormalQuery { projection: [name as x, name as y, name as z] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unaliased item keeps its name",
+            query: "SELECT name AS x, crc32(x) FROM system.databases",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "NormalQuery { projection: [name as x, crc32(name) as crc32(x)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias of aggregate",
+            query: "SELECT COUNT() AS c, c AS d FROM system.databases",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "NormalQuery { aggregate: [COUNT()], projection: [COUNT() as c, COUNT() as d] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias shadowing column",
+            query: "SELECT database AS name, name AS n FROM system.tables",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "NormalQuery { projection: [database as name, database as n] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Forward reference",
+            query: "SELECT y, name AS y FROM system.databases",
+            settings: &[("enable_lateral_column_aliases", 1)],
+            expect: "Unknown column y (while in analyze projection expr: y)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Lateral aliases disabled",
+            query: "SELECT name AS x, x AS y FROM system.databases",
+            expect: "Unknown column x (while in analyze projection expr: x as y)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard ILIKE with matching columns",
+            query: "SELECT * FROM system.tables",
+            clause: Some(Clause::Projection(wildcard_ilike("%A%E"))),
+            expect: "NormalQuery { projection: [database, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard ILIKE with ambiguous columns",
+            query: "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            clause: Some(Clause::Projection(wildcard_ilike("N_ME"))),
+            expect: "NormalQuery { join conditions: [(d.name = t.name)], projection: [d.name, t.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard ILIKE with escaped pattern",
+            query: "SELECT * FROM system.tables",
+            clause: Some(Clause::Projection(wildcard_ilike("n\\_me"))),
+            expect: "NormalQuery",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard ILIKE without matching columns",
+            query: "SELECT * FROM system.tables",
+            clause: Some(Clause::Projection(wildcard_ilike("%price%"))),
+            expect: "NormalQuery",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard ILIKE with malformed pattern",
+            query: "SELECT * FROM system.tables",
+            clause: Some(Clause::Projection(wildcard_ilike("name\\"))),
+            expect: "Malformed wildcard ILIKE pattern name\\, it ends with an escape character (while in analyze projection expr: (* ILIKE name\\))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(&["t"], &[], &[]))),
+            expect: "NormalQuery { projection: [database, t.name, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard of ambiguous columns",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(&["d"], &[], &[]))),
+            expect: "NormalQuery { projection: [d.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard except",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(
+                &["t"],
+                &["name"],
+                &[],
+            ))),
+            expect: "NormalQuery { projection: [database, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard rename",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(
+                &["t"],
+                &["engine"],
+                &[("name", "table_name")],
+            ))),
+            expect: "NormalQuery { projection: [database, t.name as table_name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Except column of another table",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(
+                &["d"],
+                &["engine"],
+                &[],
+            ))),
+            expect: "Unknown column engine in EXCEPT of table d (while in analyze projection expr: d.* EXCEPT (engine))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Rename unknown column",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(
+                &["t"],
+                &[],
+                &[("missing", "x")],
+            ))),
+            expect: "Unknown column missing in RENAME of table t (while in analyze projection expr: t.* RENAME (missing AS x))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified wildcard of unknown table",
+            query: "SELECT d.* FROM system.databases AS d, system.tables AS t",
+            clause: Some(Clause::Projection(qualified_wildcard(&["other"], &[], &[]))),
+            expect: "Unknown table other of qualified wildcard (while in analyze projection expr: other.*)",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_clauses() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    let qualified = |table: &str, name: &str| {
+        Expression::QualifiedColumn(vec![table.to_string(), name.to_string()])
+    };
+    let sort = |expr: Expression, origin_expr: Expression| Expression::Sort {
+        expr: Box::new(expr),
+        asc: true,
+        nulls_first: true,
+        origin_expr: Box::new(origin_expr),
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Order by group key",
+            query: "SELECT database, COUNT(name) FROM system.tables GROUP BY database ORDER BY database",
+            expect: "NormalQuery { group by: [database], aggregate: [COUNT(name)], order by: [database], projection: [database, COUNT(name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by aggregate",
+            query: "SELECT database FROM system.tables GROUP BY database ORDER BY COUNT(name) + 1",
+            expect: "NormalQuery { group by: [database], aggregate: [COUNT(name)], order by: [(COUNT(name) + 1)], projection: [database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by ungrouped column",
+            query: "SELECT database, COUNT(name) FROM system.tables GROUP BY database ORDER BY engine",
+            expect: "ORDER BY column 'engine' must appear in GROUP BY or be used in an aggregate",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by column of aggregate without group by",
+            query: "SELECT COUNT(name) FROM system.tables ORDER BY engine",
+            expect: "ORDER BY column 'engine' must appear in GROUP BY or be used in an aggregate",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Order by unselected column without aggregation",
+            query: "SELECT name FROM system.tables ORDER BY engine",
+            expect: "NormalQuery { order by: [engine], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Group by all non-aggregate columns",
+            query: "SELECT database, name AS n, MAX(engine) FROM system.tables GROUP BY ALL",
+            expect: "NormalQuery { group by: [database, name], aggregate: [MAX(engine)], projection: [database, name as n, MAX(engine)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Group by all without non-aggregate columns",
+            query: "SELECT MAX(engine) FROM system.tables GROUP BY all",
+            expect: "NormalQuery { aggregate: [MAX(engine)], projection: [MAX(engine)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Group by all with wildcard",
+            query: "SELECT * FROM system.tables GROUP BY ALL",
+            expect: "NormalQuery { group by: [database, name, engine], projection: [database, name, engine] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Group by all with qualified and unqualified column",
+            query: "SELECT t.database, database, MAX(name) FROM system.tables AS t GROUP BY ALL",
+            expect: "NormalQuery { group by: [database], aggregate: [MAX(name)], projection: [database, database, MAX(name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Group by quoted all column",
+            query: "SELECT database FROM system.tables GROUP BY \"all\"",
+            expect: "Unknown column all (while in analyze group expr: all)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping sets",
+            query: "SELECT alias.database, GROUPING(alias.database) FROM system.tables AS alias",
+            clause: Some(Clause::GroupingSets(vec![
+                vec!["alias.database", "alias.name"],
+                vec!["database"],
+                vec![],
+            ])),
+            expect: "NormalQuery { group by: [database, name], grouping: GROUPING SETS ((database, name), (database), ()), projection: [database, GROUPING(database)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping sets with unknown column",
+            query: "SELECT database FROM system.tables",
+            clause: Some(Clause::GroupingSets(vec![
+                vec!["database"],
+                vec!["unknown_column"],
+            ])),
+            expect: "Unknown column unknown_column (while in analyze group expr: unknown_column)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping sets with computed key",
+            query: "SELECT SUBSTRING(name, 1, 2) AS prefix, COUNT() FROM system.tables",
+            clause: Some(Clause::GroupingSets(vec![
+                vec!["SUBSTRING(name, 1, 2)"],
+                vec![],
+            ])),
+            expect: "NormalQuery { group by: [substring(name, 1, 2)], grouping: GROUPING SETS ((substring(name, 1, 2)), ()), aggregate: [COUNT()], projection: [substring(name, 1, 2) as prefix, COUNT()] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping sets with column outside computed key",
+            query: "SELECT name, COUNT() FROM system.tables",
+            clause: Some(Clause::GroupingSets(vec![
+                vec!["SUBSTRING(name, 1, 2)"],
+                vec![],
+            ])),
+            expect: "column 'name' must appear in GROUP BY or be used in an aggregate",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Having aggregate alias",
+            query: "SELECT database, COUNT(*) AS c FROM system.tables GROUP BY database HAVING c > 5",
+            expect: "NormalQuery { group by: [database], having: (COUNT() > 5), aggregate: [COUNT()], projection: [database, COUNT() as c] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Having aggregate alias shadows the base column",
+            query: "SELECT database, COUNT(*) AS name FROM system.tables GROUP BY database HAVING name > 5",
+            expect: "NormalQuery { group by: [database], having: (COUNT() > 5), aggregate: [COUNT()], projection: [database, COUNT() as name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Having group key",
+            query: "SELECT database, COUNT(*) AS c FROM system.tables GROUP BY database HAVING database = 'db'",
+            expect: "NormalQuery { group by: [database], having: (database = db), aggregate: [COUNT()], projection: [database, COUNT() as c] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Having ungrouped column",
+            query: "SELECT database, COUNT(*) AS c FROM system.tables GROUP BY database HAVING engine = 'x'",
+            expect: "HAVING column 'engine' must appear in GROUP BY or be used in an aggregate (while in analyze having predicate (engine = x))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Sort with the same origin columns",
+            query: "SELECT name FROM system.tables AS alias",
+            clause: Some(Clause::OrderBy(sort(
+                column("name"),
+                qualified("alias", "name"),
+            ))),
+            expect: "NormalQuery { order by: [name], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Sort with different origin columns",
+            query: "SELECT name FROM system.tables AS alias",
+            clause: Some(Clause::OrderBy(sort(column("name"), column("database")))),
+            expect: "Sort expression name and its origin expression database reference different columns (while in analyze order expr: name)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Named distinct on",
+            query: "SELECT t.name, database FROM system.tables AS t ORDER BY name, database",
+            clause: Some(Clause::DistinctOn(vec!["t.name"])),
+            expect: "NormalQuery { order by: [name, database], distinct on: [name], projection: [name, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Positional distinct on",
+            query: "SELECT name, database FROM system.tables ORDER BY name",
+            clause: Some(Clause::DistinctOn(vec!["1"])),
+            expect: "NormalQuery { order by: [name], distinct on: [name], projection: [name, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Positional distinct on of aliased function",
+            query: "SELECT SUBSTRING(name, 1, 2) AS n, database FROM system.tables",
+            clause: Some(Clause::DistinctOn(vec!["1"])),
+            expect: "NormalQuery { distinct on: [substring(name, 1, 2)], projection: [substring(name, 1, 2) as n, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Positional distinct on matching positional order by",
+            query: "SELECT name, database FROM system.tables ORDER BY 2, 1",
+            clause: Some(Clause::DistinctOn(vec!["1", "2"])),
+            expect: "NormalQuery { order by: [database, name], distinct on: [name, database], projection: [name, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Mixed positional and named distinct on",
+            query: "SELECT name, database FROM system.tables ORDER BY database, 1, engine",
+            clause: Some(Clause::DistinctOn(vec!["name", "2"])),
+            expect: "NormalQuery { order by: [database, name, engine], distinct on: [name, database], projection: [name, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Distinct on position out of range",
+            query: "SELECT name, database FROM system.tables",
+            clause: Some(Clause::DistinctOn(vec!["name", "3"])),
+            expect: "DISTINCT ON position 3 is not in select list",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Distinct on not matching order by",
+            query: "SELECT name, database FROM system.tables ORDER BY 2",
+            clause: Some(Clause::DistinctOn(vec!["1"])),
+            expect: "DISTINCT ON expressions must match the leading ORDER BY expressions, but got database",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Distinct on positions disabled",
+            query: "SELECT name, database FROM system.tables",
+            settings: &[("enable_order_by_positions", 0)],
+            clause: Some(Clause::DistinctOn(vec!["1"])),
+            expect: "NormalQuery { distinct on: [1], projection: [name, database] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Top",
+            query: "SELECT TOP 2 name FROM system.databases",
+            expect: "NormalQuery { projection: [name], limit: 2 }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fetch first rows only",
+            query: "SELECT name FROM system.databases FETCH FIRST 2 ROWS ONLY",
+            expect: "NormalQuery { projection: [name], limit: 2 }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fetch first row only without count",
+            query: "SELECT name FROM system.databases FETCH FIRST ROW ONLY",
+            expect: "NormalQuery { projection: [name], limit: 1 }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fetch first rows with ties",
+            query: "SELECT name FROM system.databases ORDER BY name FETCH FIRST 2 ROWS WITH TIES",
+            expect: "NormalQuery { order by: [name], projection: [name], limit: 2, limit with ties: true }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Top negative count",
+            query: "SELECT TOP (-1) name FROM system.databases",
+            expect: "LIMIT must be a non-negative constant, but got -1 (while in analyze limit (- 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Top non-constant count",
+            query: "SELECT TOP (crc32(name)) name FROM system.databases",
+            expect: "LIMIT must be a constant expression (while in analyze limit crc32(name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fail on the first phase",
+            query: "SELECT missing1 FROM system.databases WHERE missing2 = 1 ORDER BY missing3",
+            expect: "Unknown column missing3 (while in analyze order expr: missing3)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Report the earliest clause",
+            query: "SELECT missing1 FROM system.databases WHERE missing2 = 1 ORDER BY missing3",
+            settings: &[("analyze_error_mode", 1)],
+            expect: "Unknown column missing1 (while in analyze projection expr: missing1)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Report all the clauses",
+            query: "SELECT missing1 FROM system.databases WHERE missing2 = 1 ORDER BY missing3",
+            settings: &[("analyze_error_mode", 2)],
+            expect: "Unknown column missing1 (while in analyze projection expr: missing1); Unknown column missing2 (while in analyze filter predicate (missing2 = 1)); Unknown column missing3 (while in analyze order expr: missing3)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Report the aggregate projection in its clause",
+            query: "SELECT missing1 FROM system.databases WHERE missing2 = 1 ORDER BY missing3",
+            settings: &[("analyze_error_mode", 2)],
+            expect: "column 'name' must appear in GROUP BY or be used in an aggregate; Unknown column missing2 (while in analyze filter predicate (missing2 = 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Broadcast hint",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["BROADCAST(b)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name], hints: [BROADCAST(b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Broadcast hint of several tables",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["broadcast(d, b)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name], hints: [BROADCAST(d), BROADCAST(b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Broadcast hint of table name",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Hints(vec!["BROADCAST(databases)"])),
+            expect: "NormalQuery { projection: [name], hints: [BROADCAST(databases)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Broadcast hint of unknown alias",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["BROADCAST(x)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name] }",
+            warnings: &["Ignored hint BROADCAST(x), unknown table x"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Skew hint",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["SKEW(b.database)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name], hints: [SKEW(database)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Skew hint of unknown column",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["SKEW(b.unknown_column)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name] }",
+            warnings: &["Ignored hint SKEW(\"b.unknown_column\"), Unknown column: b.unknown_column"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown hint",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["NO_MERGE(b)", "BROADCAST(b)"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name], hints: [BROADCAST(b)] }",
+            warnings: &["Ignored unknown hint NO_MERGE(b)"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Malformed hint argument",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS b ON d.name = b.name",
+            clause: Some(Clause::Hints(vec!["BROADCAST('b')"])),
+            expect: "NormalQuery { join conditions: [(d.name = b.name)], projection: [d.name] }",
+            warnings: &["Ignored malformed argument 'b' of hint BROADCAST('b')"],
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_windows() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    // The sqlparser doesn't produce interval frame bounds yet, build the window by hand.
+    let interval_window = |units: WindowFrameUnits, order_key: &str| {
+        let interval = Expression::Interval {
+            value: Box::new(lit(1u64)),
+            unit: "DAY".to_string(),
+        };
+        Expression::WindowFunction {
+            op: "SUM".to_string(),
+            params: vec![],
+            args: vec![column("value")],
+            partition_by: vec![],
+            order_by: vec![column(order_key)],
+            window_frame: Some(WindowFrame {
+                units,
+                start_bound: WindowFrameBound::IntervalPreceding(Box::new(interval)),
+                end_bound: WindowFrameBound::CurrentRow,
+            }),
+            null_treatment: None,
+        }
+    };
+    // The sqlparser doesn't produce RESPECT NULLS and IGNORE NULLS yet, build the window by hand.
+    let window = |op: &str, args: Vec<Expression>, null_treatment: Option<NullTreatment>| {
+        Expression::WindowFunction {
+            op: op.to_string(),
+            params: vec![],
+            args,
+            partition_by: vec![],
+            order_by: vec![column("name")],
+            window_frame: None,
+            null_treatment,
+        }
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Window order by position",
+            query: "SELECT name, ROW_NUMBER() OVER (ORDER BY 1) FROM system.databases",
+            expect: "NormalQuery { window: [ROW_NUMBER() over (order by name)], projection: [name, ROW_NUMBER() over (order by name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position of aliased qualified column",
+            query: "SELECT d.name AS n, RANK() OVER (ORDER BY 1) FROM system.databases AS d",
+            expect: "NormalQuery { window: [RANK() over (order by name)], projection: [name as n, RANK() over (order by name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position inside expression",
+            query: "SELECT name, ROW_NUMBER() OVER (ORDER BY 1 + 1) FROM system.databases",
+            expect: "NormalQuery { window: [ROW_NUMBER() over (order by (1 + 1))], projection: [name, ROW_NUMBER() over (order by (1 + 1))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position out of range",
+            query: "SELECT name, ROW_NUMBER() OVER (ORDER BY 3) FROM system.databases",
+            expect: "Window ORDER BY position 3 is not in select list",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position of window function",
+            query: "SELECT name, ROW_NUMBER() OVER (ORDER BY 2) FROM system.databases",
+            expect: "Window ORDER BY position 2 refers to the window function ROW_NUMBER() over (order by 2)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position after wildcard",
+            query: "SELECT *, ROW_NUMBER() OVER (ORDER BY 1) FROM system.databases",
+            expect: "Window ORDER BY position 1 cannot be resolved through a wildcard of the select list",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Window order by position disabled",
+            query: "SELECT name, ROW_NUMBER() OVER (ORDER BY 1) FROM system.databases",
+            settings: &[("enable_order_by_positions", 0)],
+            expect: "NormalQuery { window: [ROW_NUMBER() over (order by 1)], projection: [name, ROW_NUMBER() over (order by 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Top level order by position disabled",
+            query: "SELECT name FROM system.databases ORDER BY 1",
+            settings: &[("enable_order_by_positions", 0)],
+            expect: "NormalQuery { order by: [1], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Last value ignore nulls",
+            query: "SELECT name FROM system.tables",
+            clause: Some(Clause::Window(window(
+                "LAST_VALUE",
+                vec![column("engine")],
+                Some(NullTreatment::IgnoreNulls),
+            ))),
+            expect: "NormalQuery { window: [LAST_VALUE(engine) ignore nulls over (order by name)], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Lag respect nulls",
+            query: "SELECT name FROM system.tables",
+            clause: Some(Clause::Window(window(
+                "lag",
+                vec![column("engine")],
+                Some(NullTreatment::RespectNulls),
+            ))),
+            expect: "NormalQuery { window: [lag(engine) respect nulls over (order by name)], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row number without null treatment",
+            query: "SELECT name FROM system.tables",
+            clause: Some(Clause::Window(window("ROW_NUMBER", vec![], None))),
+            expect: "NormalQuery { window: [ROW_NUMBER() over (order by name)], projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row number ignore nulls",
+            query: "SELECT name FROM system.tables",
+            clause: Some(Clause::Window(window(
+                "ROW_NUMBER",
+                vec![],
+                Some(NullTreatment::IgnoreNulls),
+            ))),
+            expect: "IGNORE NULLS is only supported for LEAD, LAG, FIRST_VALUE, LAST_VALUE and NTH_VALUE, but got ROW_NUMBER (while in analyze window expr: ROW_NUMBER() ignore nulls over (order by name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualify with window function",
+            query: "SELECT name, ROW_NUMBER() OVER (PARTITION BY name) AS rn FROM system.databases AS alias",
+            clause: Some(Clause::Qualify(
+                "ROW_NUMBER() OVER (PARTITION BY alias.name) = 1",
+            )),
+            expect: "NormalQuery { qualify: (ROW_NUMBER() over (partition by name) = 1), window: [ROW_NUMBER() over (partition by name)], projection: [name, ROW_NUMBER() over (partition by name) as rn] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualify with window function alias",
+            query: "SELECT name, ROW_NUMBER() OVER (PARTITION BY name) AS rn FROM system.databases",
+            clause: Some(Clause::Qualify("rn = 1")),
+            expect: "NormalQuery { qualify: (ROW_NUMBER() over (partition by name) = 1), window: [ROW_NUMBER() over (partition by name)], projection: [name, ROW_NUMBER() over (partition by name) as rn] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualify without window function",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Qualify("name = 'xxx'")),
+            expect: "QUALIFY clause must contain at least one window function (while in analyze qualify predicate (name = xxx))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualify with unknown column",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Qualify(
+                "ROW_NUMBER() OVER (PARTITION BY unknown_column) = 1",
+            )),
+            expect: "Unknown column unknown_column (while in analyze window expr: ROW_NUMBER() over (partition by unknown_column))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    let joined_schema = subquery_schema("events", vec![
+        DataField::new("ts", DataType::DateTime32(None), false),
+        DataField::new("value", DataType::Int64, false),
+    ])?;
+    let tests = vec![
+        TestCase {
+            name: "Cast in window partition",
+            query: "SELECT RANK() OVER (PARTITION BY CAST(ts AS DATE)) FROM events",
+            expect: "NormalQuery { window: [RANK() over (partition by cast(ts as Date16))], projection: [RANK() over (partition by cast(ts as Date16))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast of qualified column in window partition",
+            query: "SELECT RANK() OVER (PARTITION BY CAST(events.ts AS DATE) ORDER BY value) FROM events",
+            expect: "NormalQuery { window: [RANK() over (partition by cast(ts as Date16) order by value)], projection: [RANK() over (partition by cast(ts as Date16) order by value)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast of unknown column in window partition",
+            query: "SELECT RANK() OVER (PARTITION BY CAST(unknown_column AS DATE)) FROM events",
+            expect: "Unknown column unknown_column (while in analyze window expr: RANK() over (partition by cast(unknown_column as Date16)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Interval range frame ordered by datetime",
+            query: "SELECT ts FROM events",
+            clause: Some(Clause::Window(interval_window(
+                WindowFrameUnits::Range,
+                "ts",
+            ))),
+            expect: "NormalQuery { window: [SUM(value) over (order by ts RANGE BETWEEN INTERVAL 1 DAY PRECEDING AND CURRENT ROW)], projection: [ts] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Interval range frame ordered by number",
+            query: "SELECT ts FROM events",
+            clause: Some(Clause::Window(interval_window(
+                WindowFrameUnits::Range,
+                "value",
+            ))),
+            expect: "RANGE frame with interval bounds requires a date or datetime ORDER BY key, but value is Int64 (while in analyze window expr: SUM(value) over (order by value RANGE BETWEEN INTERVAL 1 DAY PRECEDING AND CURRENT ROW))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Interval rows frame",
+            query: "SELECT ts FROM events",
+            clause: Some(Clause::Window(interval_window(
+                WindowFrameUnits::Rows,
+                "ts",
+            ))),
+            expect: "Interval frame bounds are only supported in RANGE frames, but got ROWS (while in analyze window expr: SUM(value) over (order by ts ROWS BETWEEN INTERVAL 1 DAY PRECEDING AND CURRENT ROW))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_functions() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    let qualified = |table: &str, name: &str| {
+        Expression::QualifiedColumn(vec![table.to_string(), name.to_string()])
+    };
+    let sort = |expr: Expression| Expression::Sort {
+        expr: Box::new(expr.clone()),
+        asc: true,
+        nulls_first: true,
+        origin_expr: Box::new(expr),
+    };
+    // The sqlparser only produces WITHIN GROUP of LISTAGG, build it by hand.
+    let ordered_set =
+        |op: &str, distinct: bool, within_group: Vec<Expression>| Expression::AggregateFunction {
+            op: op.to_string(),
+            distinct,
+            params: vec![],
+            args: vec![lit(0.5f64)],
+            within_group,
+        };
+
+    let tests = vec![
+        TestCase {
+            name: "Alias of substring",
+            query: "SELECT substr(name, 1, 2) FROM system.databases",
+            expect: "NormalQuery { projection: [substring(name, 1, 2)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Upper case alias",
+            query: "SELECT name FROM system.databases WHERE SUBSTR(name, 1) = 'd'",
+            expect: "NormalQuery { filter: (substring(name, 1) = d), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias of coalesce",
+            query: "SELECT ifnull(name, 'x') FROM system.databases",
+            expect: "NormalQuery { projection: [coalesce(name, x)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown function",
+            query: "SELECT no_such_function(name) FROM system.databases",
+            expect: "Unsupported function: \"no_such_function\" (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Functions of the special forms",
+            query: "SELECT TRIM(name), EXTRACT(YEAR FROM CAST(name AS DATE)), COALESCE(name, 'x') FROM system.databases",
+            expect: "NormalQuery { projection: [trim_both( , name), toYear(cast(name as Date16)), COALESCE(name, x)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Grouping function",
+            query: "SELECT GROUPING(name) FROM system.databases GROUP BY ROLLUP(name)",
+            expect: "NormalQuery { group by: [name], grouping: ROLLUP(name), projection: [GROUPING(name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Positional and named arguments",
+            query: "SELECT substring(name, len => 3, pos => 1) FROM system.tables",
+            expect: "NormalQuery { projection: [substring(name, 1, 3)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified column in named argument",
+            query: "SELECT substring(str => t.name, pos => 2) FROM system.tables AS t",
+            expect: "NormalQuery { projection: [substring(name, 2)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Positional argument after named argument",
+            query: "SELECT substring(str => name, 1) FROM system.tables",
+            expect: "Positional argument 1 of function substring cannot follow the named arguments (while in analyze projection expr: substring(str => name, 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Duplicate named arguments",
+            query: "SELECT substring(name, pos => 1, POS => 2) FROM system.tables",
+            expect: "Duplicate argument POS of function substring (while in analyze projection expr: substring(name, pos => 1, POS => 2))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Missing parameter before the named argument",
+            query: "SELECT substring(name, len => 3) FROM system.tables",
+            expect: "Missing parameter pos of function substring (while in analyze projection expr: substring(name, len => 3))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Named argument of function without parameter names",
+            query: "SELECT concat(name, sep => '-') FROM system.tables",
+            expect: "Function concat doesn't accept named arguments (while in analyze projection expr: concat(name, sep => -))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Declared parameter",
+            query: "SELECT sleep(seconds => 1) FROM system.tables",
+            expect: "NormalQuery { projection: [sleep(1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown parameter",
+            query: "SELECT sleep(secs => 1) FROM system.tables",
+            expect: "Unknown parameter secs of function sleep, expected one of seconds (while in analyze projection expr: sleep(secs => 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Parameter specified by position and by name",
+            query: "SELECT sleep(1, seconds => 1) FROM system.tables",
+            expect: "Parameter seconds of function sleep is specified both by position and by name (while in analyze projection expr: sleep(1, seconds => 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Plain trim",
+            query: "SELECT TRIM(name) FROM system.tables",
+            expect: "NormalQuery { projection: [trim_both( , name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim both with qualified source",
+            query: "SELECT TRIM(BOTH 'x' FROM t.name) FROM system.tables AS t",
+            expect: "NormalQuery { projection: [trim_both(x, name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim leading",
+            query: "SELECT TRIM(LEADING 'x' FROM name) FROM system.tables",
+            expect: "NormalQuery { projection: [trim_leading(x, name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim trailing with column characters",
+            query: "SELECT TRIM(TRAILING database FROM name) FROM system.tables",
+            expect: "NormalQuery { projection: [trim_trailing(database, name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim in filter",
+            query: "SELECT name FROM system.tables WHERE TRIM(name) = 'tables'",
+            expect: "NormalQuery { filter: (trim_both( , name) = tables), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim non-string characters",
+            query: "SELECT TRIM(BOTH 1 FROM name) FROM system.tables",
+            expect: "TRIM characters must be a string, but 1 is UInt8 (while in analyze projection expr: trim_both(1, name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim unknown column",
+            query: "SELECT TRIM(missing) FROM system.tables",
+            expect: "Unknown column missing (while in analyze projection expr: trim(missing))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Trim function with too many arguments",
+            query: "SELECT trim(name, name) FROM system.tables",
+            expect: "TRIM requires exactly 1 argument, but got 2 (while in analyze projection expr: trim(name, name))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract year from date",
+            query: "SELECT EXTRACT(YEAR FROM CAST(t.name AS DATE)) FROM system.tables AS t",
+            expect: "NormalQuery { projection: [toYear(cast(name as Date16))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract hour from timestamp",
+            query: "SELECT EXTRACT(HOUR FROM CAST(name AS TIMESTAMP)) FROM system.tables",
+            expect: "NormalQuery { projection: [toHour(cast(name as DateTime32(None)))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract function with lower case field",
+            query: "SELECT extract('dow', CAST(name AS DATE)) FROM system.tables",
+            expect: "NormalQuery { projection: [toDayOfWeek(cast(name as Date16))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract unknown field",
+            query: "SELECT extract('fortnight', CAST(name AS DATE)) FROM system.tables",
+            expect: "Unsupported EXTRACT field FORTNIGHT, expected one of YEAR, QUARTER, MONTH, WEEK, DAY, DOY, DOW, HOUR, MINUTE, SECOND or EPOCH (while in analyze projection expr: extract(fortnight, cast(name as Date16)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract from non-temporal column",
+            query: "SELECT EXTRACT(YEAR FROM number) FROM numbers(10)",
+            expect: "Cannot extract YEAR from number of type UInt64 (while in analyze projection expr: extract(YEAR, number))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract time field from date",
+            query: "SELECT EXTRACT(HOUR FROM CAST(name AS DATE)) FROM system.tables",
+            expect: "Cannot extract HOUR from cast(name as Date16) of type Date16 (while in analyze projection expr: extract(HOUR, cast(name as Date16)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Extract from unknown column",
+            query: "SELECT EXTRACT(DAY FROM missing) FROM system.tables",
+            expect: "Unknown column missing (while in analyze projection expr: extract(DAY, missing))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Concat two columns",
+            query: "SELECT database || name AS full_name FROM system.tables",
+            expect: "NormalQuery { projection: [concat(database, name) as full_name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Chained concat",
+            query: "SELECT database || '.' || name FROM system.tables",
+            expect: "NormalQuery { projection: [concat(database, ., name)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Right nested concat",
+            query: "SELECT database || (name || engine) FROM system.tables",
+            expect: "NormalQuery { projection: [concat(database, name, engine)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Concat with numeric literal",
+            query: "SELECT name || 1 FROM system.tables",
+            expect: "NormalQuery { projection: [concat(name, cast(1 as String))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Concat with numeric column",
+            query: "SELECT 'n' || number FROM numbers(10)",
+            expect: "NormalQuery { projection: [concat(n, cast(number as String))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Concat in filter",
+            query: "SELECT name FROM system.tables WHERE database || name = 'systemtables'",
+            expect: "NormalQuery { filter: (concat(database, name) = systemtables), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Single column in list",
+            query: "SELECT name FROM system.tables WHERE name IN ('a', 'b')",
+            expect: "NormalQuery { filter: ((name = a) or (name = b)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Single column not in list",
+            query: "SELECT name FROM system.tables WHERE name NOT IN ('a')",
+            expect: "NormalQuery { filter: (not (name = a)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row constructor in list",
+            query: "SELECT name FROM system.tables WHERE (name, database) IN (('a', 'b'), ('c', 'd'))",
+            expect: "NormalQuery { filter: (((name = a) and (database = b)) or ((name = c) and (database = d))), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row constructor in list with mismatched arity",
+            query: "SELECT name FROM system.tables WHERE (name, database) IN (('a', 'b'), 'c')",
+            expect: "IN list item 2 c has 1 columns, but the left side (name, database) has 2 (while in analyze filter predicate in((name, database), (a, b), c))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row constructor with unknown column",
+            query: "SELECT name FROM system.tables WHERE (name, unknown_column) IN (('a', 'b'))",
+            expect: "Unknown column unknown_column (while in analyze filter predicate in((name, unknown_column), (a, b)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Row constructor in subquery with mismatched arity",
+            query: "SELECT name FROM system.tables WHERE (name, database) IN (SELECT name FROM system.databases)",
+            expect: "IN subquery _subquery_1 returns 1 columns, but the left side (name, database) has 2 (while in analyze filter predicate ((name, database) IN subquery(_subquery_1)))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    let joined_schema = subquery_schema("t", vec![DataField::new(
+        "latency",
+        DataType::Float64,
+        false,
+    )])?;
+    let tests = vec![
+        TestCase {
+            name: "Ordered-set aggregate",
+            query: "SELECT latency FROM t",
+            clause: Some(Clause::Aggregate(ordered_set(
+                "percentile_cont",
+                false,
+                vec![sort(column("latency"))],
+            ))),
+            expect: "NormalQuery { aggregate: [percentile_cont(0.5) within group (order by latency)], projection: [percentile_cont(0.5) within group (order by latency)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Ordered-set aggregate with qualified order key",
+            query: "SELECT latency FROM t",
+            clause: Some(Clause::Aggregate(ordered_set(
+                "PERCENTILE_DISC",
+                false,
+                vec![sort(qualified("t", "latency"))],
+            ))),
+            expect: "NormalQuery { aggregate: [PERCENTILE_DISC(0.5) within group (order by latency)], projection: [PERCENTILE_DISC(0.5) within group (order by latency)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Ordered-set aggregate with unknown order key",
+            query: "SELECT latency FROM t",
+            clause: Some(Clause::Aggregate(ordered_set(
+                "percentile_cont",
+                false,
+                vec![sort(column("missing"))],
+            ))),
+            expect: "Unknown column missing (while in analyze aggregate expr: percentile_cont(0.5) within group (order by missing))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Ordered-set aggregate without WITHIN GROUP",
+            query: "SELECT latency FROM t",
+            clause: Some(Clause::Aggregate(ordered_set(
+                "percentile_cont",
+                false,
+                vec![],
+            ))),
+            expect: "Ordered-set aggregate percentile_cont requires WITHIN GROUP (ORDER BY ...) (while in analyze aggregate expr: percentile_cont(0.5))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Distinct ordered-set aggregate",
+            query: "SELECT latency FROM t",
+            clause: Some(Clause::Aggregate(ordered_set(
+                "percentile_cont",
+                true,
+                vec![sort(column("latency"))],
+            ))),
+            expect: "DISTINCT is not supported for the ordered-set aggregate percentile_cont (while in analyze aggregate expr: percentile_cont(distinct 0.5) within group (order by latency))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_casts() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    let qualified = |table: &str, name: &str| {
+        Expression::QualifiedColumn(vec![table.to_string(), name.to_string()])
+    };
+    // The sqlparser doesn't produce `STRUCT<...>` yet, build the casts by hand.
+    let point = || Box::new(Expression::Column("point".to_string()));
+    let to_struct = |fields: &[(&str, DataType)]| {
+        let fields = fields.iter();
+        DataType::Struct(
+            fields
+                .map(|(n, t)| DataField::new(n, t.clone(), false))
+                .collect(),
+        )
+    };
+    // The sqlparser doesn't produce `->>` yet, build `CAST(alias.col ->> 'n' AS INT)` by hand.
+    let json_cast = |name: &str| Expression::Cast {
+        expr: Box::new(Expression::BinaryExpression {
+            op: String::from("->>"),
+            left: Box::new(qualified("alias", name)),
+            right: Box::new(lit("n".as_bytes())),
+        }),
+        data_type: DataType::Int32,
+    };
+    // The sqlparser doesn't produce `DEFAULT ... ON ERROR` yet, build the cast by hand.
+    let cast_or_default = |data_type: DataType, default: Expression| Expression::TryCast {
+        expr: Box::new(column("a")),
+        data_type,
+        on_error: CastOnError::Default(Box::new(default)),
+    };
+    // The sqlparser doesn't produce ORDER BY inside aggregate arguments yet, build
+    // array_agg(CAST(value AS type), key) by hand.
+    let array_agg = |value: Expression, cast_type: DataType, order_key: Expression| {
+        Expression::AggregateFunction {
+            op: "array_agg".to_string(),
+            distinct: false,
+            params: vec![],
+            args: vec![
+                Expression::Cast {
+                    expr: Box::new(value),
+                    data_type: cast_type,
+                },
+                Expression::Sort {
+                    expr: Box::new(order_key.clone()),
+                    asc: true,
+                    nulls_first: true,
+                    origin_expr: Box::new(order_key),
+                },
+            ],
+            within_group: vec![],
+        }
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Cast to column reference query",
+            query: "SELECT CAST(name AS databases.name) FROM system.databases",
+            expect: "Unknown cast target type: databases.name (while in analyze select projection)",
+            error_code: Some(ErrorCode::BadDataValueType("").code()),
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast to qualified known type query",
+            query: "SELECT CAST(name AS uint8.name) FROM system.databases",
+            expect: "Unknown cast target type: uint8.name (while in analyze select projection)",
+            error_code: Some(ErrorCode::BadDataValueType("").code()),
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast literal within the length",
+            query: "SELECT CAST('abc' AS CHAR(5))",
+            expect: "NormalQuery { projection: [cast(abc as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast to zero length",
+            query: "SELECT CAST('abc' AS CHAR(0))",
+            expect: "Fixed-length string length must be positive, but got 0 (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast literal exceeding the length",
+            query: "SELECT CAST('abcdef' AS CHAR(5))",
+            expect: "NormalQuery { projection: [cast(abcdef as String)] }",
+            warnings: &["String literal \"abcdef\" of length 6 exceeds the fixed length 5"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast literal exceeding the length under strict mode",
+            query: "SELECT CAST('abcdef' AS CHAR(5))",
+            settings: &[("strict_mode", 1)],
+            expect: "String literal \"abcdef\" of length 6 exceeds the fixed length 5 (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast well-formed UUID literal",
+            query: "SELECT CAST('550e8400-e29b-41d4-a716-446655440000' AS UUID)",
+            expect: "NormalQuery { projection: [cast(550e8400-e29b-41d4-a716-446655440000 as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast malformed UUID literal",
+            query: "SELECT CAST('550e8400-e29b-41d4-a716' AS UUID)",
+            expect: "Malformed UUID literal \"550e8400-e29b-41d4-a716\", expect the form xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx (while in analyze select projection)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Try cast malformed UUID literal",
+            query: "SELECT TRY_CAST('not-a-uuid' AS UUID)",
+            expect: "NormalQuery { projection: [try_cast(not-a-uuid as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast column to UUID",
+            query: "SELECT CAST(name AS UUID) FROM system.databases",
+            expect: "NormalQuery { projection: [cast(name as String)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast json navigation",
+            query: "SELECT alias.name FROM system.databases AS alias",
+            clause: Some(Clause::Projection(json_cast("name"))),
+            expect: "NormalQuery { projection: [cast((name ->> n) as Int32)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast json navigation on unknown column",
+            query: "SELECT alias.name FROM system.databases AS alias",
+            clause: Some(Clause::Projection(json_cast("missing"))),
+            expect: "Unknown column alias.missing (while in analyze projection expr: cast((\"alias.missing\" ->> n) as Int32))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    let joined_schema = subquery_schema("t", vec![
+        DataField::new("a", DataType::String, false),
+        DataField::new("b", DataType::Int32, false),
+    ])?;
+    let tests = vec![
+        TestCase {
+            name: "Cast with default column on error",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Projection(cast_or_default(
+                DataType::Int32,
+                qualified("t", "b"),
+            ))),
+            expect: "NormalQuery { projection: [cast(a as Int32 default b on error)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast with default literal on error",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Projection(cast_or_default(
+                DataType::Int32,
+                lit(0i32),
+            ))),
+            expect: "NormalQuery { projection: [cast(a as Int32 default 0 on error)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast with unknown default column on error",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Projection(cast_or_default(
+                DataType::Int32,
+                column("missing"),
+            ))),
+            expect: "Unknown column missing (while in analyze projection expr: cast(a as Int32 default missing on error))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast to unsupported type with default on error",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Projection(cast_or_default(
+                DataType::Null,
+                lit(0i32),
+            ))),
+            expect: "Unsupported cast target type: Null (while in analyze projection expr: cast(a as Null default 0 on error))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    let joined_schema = {
+        let point = DataType::Struct(vec![
+            DataField::new("x", DataType::Int32, false),
+            DataField::new("y", DataType::Int32, false),
+        ]);
+        subquery_schema("points", vec![DataField::new("point", point, false)])?
+    };
+    let tests = vec![
+        TestCase {
+            name: "Cast struct with renamed fields",
+            query: "SELECT point FROM points",
+            clause: Some(Clause::Projection(Expression::Cast {
+                expr: point(),
+                data_type: to_struct(&[("lon", DataType::Int64), ("lat", DataType::String)]),
+            })),
+            expect: "NormalQuery { projection: [cast(point as Struct([DataField { name: \"lon\", data_type: Int64, nullable: false }, DataField { name: \"lat\", data_type: String, nullable: false }]))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast struct with incompatible field count",
+            query: "SELECT point FROM points",
+            clause: Some(Clause::Projection(Expression::Cast {
+                expr: point(),
+                data_type: to_struct(&[("lon", DataType::Int64)]),
+            })),
+            expect: "Cannot cast struct column point with 2 fields to a struct with 1 fields (while in analyze projection expr: cast(point as Struct([DataField { name: \"lon\", data_type: Int64, nullable: false }])))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast struct with duplicate field names",
+            query: "SELECT point FROM points",
+            clause: Some(Clause::Projection(Expression::Cast {
+                expr: point(),
+                data_type: to_struct(&[("lon", DataType::Int64), ("lon", DataType::String)]),
+            })),
+            expect: "Struct cast target type field names must be unique and non-empty, but got \"lon\" (while in analyze projection expr: cast(point as Struct([DataField { name: \"lon\", data_type: Int64, nullable: false }, DataField { name: \"lon\", data_type: String, nullable: false }])))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast struct to scalar",
+            query: "SELECT point FROM points",
+            clause: Some(Clause::Projection(Expression::Cast {
+                expr: point(),
+                data_type: DataType::Int32,
+            })),
+            expect: "Cannot cast column point of type Struct([DataField { name: \"x\", data_type: Int32, nullable: false }, DataField { name: \"y\", data_type: Int32, nullable: false }]) to Int32 (while in analyze projection expr: cast(point as Int32))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Try cast struct with incompatible field count",
+            query: "SELECT point FROM points",
+            clause: Some(Clause::Projection(Expression::TryCast {
+                expr: point(),
+                data_type: to_struct(&[("lon", DataType::Int64)]),
+                on_error: CastOnError::Null,
+            })),
+            expect: "Cannot cast struct column point with 2 fields to a struct with 1 fields (while in analyze projection expr: try_cast(point as Struct([DataField { name: \"lon\", data_type: Int64, nullable: false }])))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    let joined_schema = subquery_schema("t", vec![
+        DataField::new("a", DataType::Int64, false),
+        DataField::new("b", DataType::Int64, false),
+    ])?;
+    let tests = vec![
+        TestCase {
+            name: "Cast value ordered by another column",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Aggregate(array_agg(
+                column("a"),
+                DataType::String,
+                column("b"),
+            ))),
+            expect: "NormalQuery { aggregate: [array_agg(cast(a as String), b)], projection: [array_agg(cast(a as String), b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Cast qualified value ordered by qualified column",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Aggregate(array_agg(
+                qualified("t", "a"),
+                DataType::String,
+                qualified("t", "b"),
+            ))),
+            expect: "NormalQuery { aggregate: [array_agg(cast(a as String), b)], projection: [array_agg(cast(a as String), b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown order key",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Aggregate(array_agg(
+                column("a"),
+                DataType::String,
+                column("c"),
+            ))),
+            expect: "Unknown column c (while in analyze aggregate expr: array_agg(cast(a as String), c))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unknown cast value",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Aggregate(array_agg(
+                column("c"),
+                DataType::String,
+                column("b"),
+            ))),
+            expect: "Unknown column c (while in analyze aggregate expr: array_agg(cast(c as String), b))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unsupported cast target",
+            query: "SELECT a FROM t",
+            clause: Some(Clause::Aggregate(array_agg(
+                column("a"),
+                DataType::Null,
+                column("b"),
+            ))),
+            expect: "Unsupported cast target type: Null (while in analyze aggregate expr: array_agg(cast(a as Null), b))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_expressions() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    // The sqlparser doesn't produce IS [NOT] DISTINCT FROM yet, build it by hand.
+    let binary = |left: Expression, op: &str, right: Expression| Expression::BinaryExpression {
+        op: op.to_string(),
+        left: Box::new(left),
+        right: Box::new(right),
+    };
+    // The sqlparser doesn't produce `LIKE ... ESCAPE` yet, build it by hand.
+    let like = |op: &str, name: &str, pattern: &str, escape: Option<&str>| Expression::Like {
+        op: op.to_string(),
+        expr: Box::new(column(name)),
+        pattern: Box::new(lit(pattern.as_bytes())),
+        escape: escape.map(|escape| Box::new(lit(escape.as_bytes()))),
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Fold literal comparison",
+            query: "SELECT name FROM system.databases WHERE 1 = 1",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: true, projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Literal comparison without folding",
+            query: "SELECT name FROM system.databases WHERE 1 = 1",
+            expect: "NormalQuery { filter: (1 = 1), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fold nested literal arithmetic",
+            query: "SELECT 1 + 2 * 3",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { projection: [7] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fold only the literal operands of a column expression",
+            query: "SELECT name FROM system.databases WHERE crc32(name) > 1 + 1",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: (crc32(name) > 2), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fold division by zero",
+            query: "SELECT 1 % 0",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "Division by zero in constant expression (1 % 0) (while in analyze projection expr: (1 % 0))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Fold overflowing arithmetic",
+            query: "SELECT 18446744073709551615 + 1",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "Constant expression (18446744073709551615 + 1) overflows UInt64 (while in analyze projection expr: (18446744073709551615 + 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Drop TRUE of AND",
+            query: "SELECT name FROM system.databases WHERE TRUE AND name = 'x'",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: (name = x), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Drop FALSE of OR",
+            query: "SELECT name FROM system.databases WHERE name = 'x' OR FALSE",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: (name = x), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "FALSE absorbs AND",
+            query: "SELECT name FROM system.databases WHERE FALSE AND name = 'x'",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: false, projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "TRUE absorbs OR",
+            query: "SELECT name FROM system.databases WHERE name = 'x' OR TRUE",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: true, projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Simplify nested boolean literals",
+            query: "SELECT name FROM system.databases WHERE (TRUE AND name = 'x') OR (FALSE AND name = 'y')",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: (name = x), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Keep absorbed subquery",
+            query: "SELECT name FROM system.databases WHERE FALSE AND name IN (SELECT database FROM system.tables)",
+            settings: &[("enable_constant_folding", 1)],
+            expect: "NormalQuery { filter: (false AND (name IN subquery(_subquery_1))), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Boolean literals without folding",
+            query: "SELECT name FROM system.databases WHERE TRUE AND name = 'x'",
+            expect: "NormalQuery { filter: (true AND (name = x)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Is distinct from in filter",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(binary(
+                column("name"),
+                "IS DISTINCT FROM",
+                column("name"),
+            ))),
+            expect: "NormalQuery { filter: (name IS DISTINCT FROM name), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Lower case is not distinct from in filter",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(binary(
+                column("name"),
+                "is not  distinct from",
+                column("name"),
+            ))),
+            expect: "NormalQuery { filter: (name IS NOT DISTINCT FROM name), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Is not distinct from in having",
+            query: "SELECT name FROM system.databases GROUP BY name",
+            clause: Some(Clause::Having(binary(
+                column("name"),
+                "IS NOT DISTINCT FROM",
+                column("name"),
+            ))),
+            expect: "NormalQuery { group by: [name], having: (name IS NOT DISTINCT FROM name), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Is distinct from with unknown column",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(binary(
+                column("name"),
+                "IS DISTINCT FROM",
+                column("unknown_column"),
+            ))),
+            expect: "Unknown column unknown_column (while in analyze filter predicate (name IS DISTINCT FROM unknown_column))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Is distinct from of literals is not folded",
+            query: "SELECT name FROM system.databases",
+            settings: &[("enable_constant_folding", 1)],
+            clause: Some(Clause::Filter(binary(
+                lit(1u8),
+                "IS DISTINCT FROM",
+                lit(2u8),
+            ))),
+            expect: "NormalQuery { filter: (1 IS DISTINCT FROM 2), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "LIKE with backslash escape",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("LIKE", "name", "a\\_b", Some("\\")))),
+            expect: "NormalQuery { filter: (name LIKE a\\_b), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "LIKE with escape other than backslash",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("LIKE", "name", "a!_b", Some("!")))),
+            expect: "LIKE with ESCAPE other than backslash is not supported yet (while in analyze filter predicate (name LIKE a!_b ESCAPE !))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "ILIKE with escape",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("ilike", "name", "A!%", Some("!")))),
+            expect: "ILIKE is not supported yet, use LOWER(expr) LIKE LOWER(pattern) instead (while in analyze filter predicate (name ilike A!% ESCAPE !))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "NOT ILIKE without escape",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("not ilike", "name", "a%", None))),
+            expect: "NOT ILIKE is not supported yet, use LOWER(expr) LIKE LOWER(pattern) instead (while in analyze filter predicate (name not ilike a%))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "LIKE with multi-character escape",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("LIKE", "name", "a!_b", Some("!!")))),
+            expect: "ESCAPE must be a single character literal, but got !! (while in analyze filter predicate (name LIKE a!_b ESCAPE !!))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Escape on non-LIKE operator",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("=", "name", "a", Some("!")))),
+            expect: "ESCAPE is only supported by LIKE and ILIKE, but got = (while in analyze filter predicate (name = a ESCAPE !))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "LIKE with escape on unknown column",
+            query: "SELECT name FROM system.databases",
+            clause: Some(Clause::Filter(like("LIKE", "missing", "a%", Some("!")))),
+            expect: "Unknown column missing (while in analyze filter predicate (missing LIKE a% ESCAPE !))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    let joined_schema = subquery_schema("t", vec![
+        DataField::new("a", DataType::String, false),
+        DataField::new("b", DataType::Int32, false),
+        DataField::new("c", list_of(DataType::Int32), false),
+    ])?;
+    let tests = vec![
+        TestCase {
+            name: "Coalesce with cast",
+            query: "SELECT COALESCE(CAST(a AS INT), b) FROM t",
+            expect: "NormalQuery { projection: [COALESCE(cast(a as Int32), b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Coalesce with incompatible types",
+            query: "SELECT COALESCE(b, c) FROM t",
+            expect: "NormalQuery { projection: [COALESCE(b, c)] }",
+            warnings: &["COALESCE arguments b, c cannot be unified to a common type"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Coalesce with incompatible types under strict mode",
+            query: "SELECT COALESCE(b, c) FROM t",
+            settings: &[("strict_mode", 1)],
+            expect: "COALESCE arguments b, c cannot be unified to a common type (while in analyze projection expr: COALESCE(b, c))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Case with cast result",
+            query: "SELECT CASE WHEN b > 1 THEN CAST(a AS INT) ELSE b END FROM t",
+            expect: "NormalQuery { projection: [if((b > 1), cast(a as Int32), b)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Case with operand and without else",
+            query: "SELECT CASE b WHEN 1 THEN a WHEN 2 THEN CAST(b AS VARCHAR) END FROM t",
+            expect: "NormalQuery { projection: [if((b = 1), a, if((b = 2), cast(b as String), NULL))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Case with unknown column in result",
+            query: "SELECT CASE WHEN b > 1 THEN CAST(d AS INT) ELSE b END FROM t",
+            expect: "Unknown column d (while in analyze projection expr: if((b > 1), cast(d as Int32), b))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Case with incompatible results",
+            query: "SELECT CASE WHEN b > 1 THEN b ELSE c END FROM t",
+            expect: "NormalQuery { projection: [if((b > 1), b, c)] }",
+            warnings: &["IF or CASE results b and c cannot be unified to a common type"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "Case with incompatible results under strict mode",
+            query: "SELECT CASE WHEN b > 1 THEN b ELSE c END FROM t",
+            settings: &[("strict_mode", 1)],
+            expect: "IF or CASE results b and c cannot be unified to a common type (while in analyze projection expr: if((b > 1), b, c))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "String literal compared with integer column",
+            query: "SELECT a FROM t WHERE b = '5'",
+            expect: "NormalQuery { filter: (b = cast(5 as Int32)), projection: [a] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "String literal on the left side",
+            query: "SELECT a FROM t WHERE '5' >= b",
+            expect: "NormalQuery { filter: (cast(5 as Int32) >= b), projection: [a] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Numbers of different types",
+            query: "SELECT a FROM t WHERE b = 5",
+            expect: "NormalQuery { filter: (b = 5), projection: [a] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Same types",
+            query: "SELECT a FROM t WHERE a = 'x'",
+            expect: "NormalQuery { filter: (a = x), projection: [a] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Number literal compared with string column",
+            query: "SELECT a FROM t WHERE a = 5",
+            expect: "NormalQuery { filter: (a = 5), projection: [a] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Non-comparison operator",
+            query: "SELECT b + '5' FROM t",
+            expect: "NormalQuery { projection: [(b + 5)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array compared with number",
+            query: "SELECT a FROM t WHERE c = 1",
+            expect: "Cannot compare c of type List(DataField { name: \"item\", data_type: Int32, nullable: false }) with 1 of type UInt8 (while in analyze filter predicate (c = 1))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "String literal under strict type comparison",
+            query: "SELECT a FROM t WHERE b = '5'",
+            settings: &[("strict_type_comparison", 1)],
+            expect: "Cannot compare b of type Int32 with 5 of type String, the implicit cast is disabled by strict_type_comparison (while in analyze filter predicate (b = 5))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Same types under strict type comparison",
+            query: "SELECT a FROM t WHERE b = 5",
+            settings: &[("strict_type_comparison", 1)],
+            expect: "NormalQuery { filter: (b = 5), projection: [a] }",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_nested_types() -> Result<()> {
+    let column = |name: &str| Expression::Column(name.to_string());
+    let qualified = |table: &str, name: &str| {
+        Expression::QualifiedColumn(vec![table.to_string(), name.to_string()])
+    };
+    let subscript = |base: Expression, index: Expression| Expression::Subscript {
+        base: Box::new(base),
+        index: Box::new(index),
+    };
+    let field = |name: &str, expr: Expression| (name.to_string(), expr);
+
+    let joined_schema = {
+        let zone = DataType::Struct(vec![DataField::new("id", DataType::Int32, false)]);
+        let props = DataType::Struct(vec![
+            DataField::new("region", DataType::String, false),
+            DataField::new("zone", zone, false),
+        ]);
+        subquery_schema("t", vec![
+            DataField::new("tags", list_of(DataType::String), false),
+            DataField::new("props", props, false),
+            DataField::new("n", DataType::Int32, false),
+        ])?
+    };
+    let tests = vec![
+        TestCase {
+            name: "Array element access",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(column("tags"), lit(0i32)))),
+            expect: "NormalQuery { projection: [tags[0]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Struct field access",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(
+                column("props"),
+                lit("region".as_bytes()),
+            ))),
+            expect: "NormalQuery { projection: [props[region]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Chained struct field access",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(
+                subscript(column("props"), lit("zone".as_bytes())),
+                lit("id".as_bytes()),
+            ))),
+            expect: "NormalQuery { projection: [props[zone][id]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array element access with string key",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(
+                column("tags"),
+                lit("a".as_bytes()),
+            ))),
+            expect: "Subscript index a of type Some(String) mismatches tags of type List(DataField { name: \"item\", data_type: String, nullable: false }) (while in analyze projection expr: tags[a])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Chained struct field access with integer index",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(
+                subscript(column("props"), lit("zone".as_bytes())),
+                lit(0i32),
+            ))),
+            expect: "Subscript index 0 of type Some(Int32) mismatches props[zone] of type Struct([DataField { name: \"id\", data_type: Int32, nullable: false }]) (while in analyze projection expr: props[zone][0])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Chained struct field access with unknown field",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(
+                subscript(column("props"), lit("zone".as_bytes())),
+                lit("missing".as_bytes()),
+            ))),
+            expect: "Unknown field missing of props[zone] of type Struct([DataField { name: \"id\", data_type: Int32, nullable: false }]) (while in analyze projection expr: props[zone][missing])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Subscript access on scalar",
+            query: "SELECT tags FROM t",
+            clause: Some(Clause::Projection(subscript(column("n"), lit(0i32)))),
+            expect: "Subscript access is not supported on n of type Int32 (while in analyze projection expr: n[0])",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    let joined_schema = {
+        // Both tables have the columns k and c, of different types.
+        let t1 = subquery_schema("t1", vec![
+            DataField::new("k", DataType::Int32, false),
+            DataField::new("c", list_of(DataType::Int32), false),
+        ])?;
+        let t2 = subquery_schema("t2", vec![
+            DataField::new("k", DataType::String, false),
+            DataField::new("c", DataType::Int32, false),
+        ])?;
+        t1.join(&t2)?
+    };
+    let tests = vec![
+        TestCase {
+            name: "Array element access of the first table",
+            query: "SELECT 1",
+            clause: Some(Clause::Projection(subscript(
+                qualified("t1", "c"),
+                lit(0i32),
+            ))),
+            expect: "NormalQuery { projection: [t1.c[0]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Subscript access on the scalar of the second table",
+            query: "SELECT 1",
+            clause: Some(Clause::Projection(subscript(
+                qualified("t2", "c"),
+                lit(0i32),
+            ))),
+            expect: "Subscript access is not supported on t2.c of type Int32 (while in analyze projection expr: \"t2.c\"[0])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "String literal compared with the integer column of the first table",
+            query: "SELECT 1",
+            clause: Some(Clause::Projection(
+                qualified("t1", "k").eq(lit("5".as_bytes())),
+            )),
+            expect: "NormalQuery { projection: [(t1.k = cast(5 as Int32))] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "String literal compared with the string column of the second table",
+            query: "SELECT 1",
+            clause: Some(Clause::Projection(
+                qualified("t2", "k").eq(lit("5".as_bytes())),
+            )),
+            expect: "NormalQuery { projection: [(t2.k = 5)] }",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    let joined_schema = subquery_schema("t", vec![
+        DataField::new("name", DataType::String, false),
+        DataField::new("n", DataType::Int32, false),
+        DataField::new("tags", list_of(DataType::String), false),
+    ])?;
+    let tests = vec![
+        TestCase {
+            name: "Array of columns",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Array(vec![
+                column("name"),
+                qualified("t", "name"),
+            ]))),
+            expect: "NormalQuery { projection: [[name, name]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array of unified numbers",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Array(vec![
+                column("n"),
+                lit(1000u16),
+                lit(1u8),
+            ]))),
+            expect: "NormalQuery { projection: [[n, 1000, 1]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array of arrays",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Array(vec![
+                column("tags"),
+                Expression::Array(vec![column("name")]),
+            ]))),
+            expect: "NormalQuery { projection: [[tags, [name]]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array of mismatched types",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Array(vec![
+                column("name"),
+                lit(1u8),
+            ]))),
+            expect: "Array element 1 of type UInt8 cannot be unified with the elements of type String (while in analyze projection expr: [name, 1])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array of unknown column",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Array(vec![
+                column("name"),
+                column("missing"),
+            ]))),
+            expect: "Unknown column missing (while in analyze projection expr: [name, missing])",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Struct of columns",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Struct(vec![
+                field("k", qualified("t", "name")),
+                field("v", column("n")),
+            ]))),
+            expect: "NormalQuery { projection: [{'k': name, 'v': n}] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Struct with duplicate field",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(Expression::Struct(vec![
+                field("k", column("name")),
+                field("k", column("n")),
+            ]))),
+            expect: "Duplicate struct field k (while in analyze projection expr: {'k': name, 'k': n})",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Array constructor element access",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(subscript(
+                Expression::Array(vec![column("name"), qualified("t", "name")]),
+                lit(1i32),
+            ))),
+            expect: "NormalQuery { projection: [[name, name][1]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Struct constructor field access",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(subscript(
+                Expression::Struct(vec![field("x", column("n"))]),
+                lit("x".as_bytes()),
+            ))),
+            expect: "NormalQuery { projection: [{'x': n}[x]] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Struct constructor unknown field access",
+            query: "SELECT name FROM t",
+            clause: Some(Clause::Projection(subscript(
+                Expression::Struct(vec![field("x", column("n"))]),
+                lit("y".as_bytes()),
+            ))),
+            expect: "Unknown field y of {'x': n} of type Struct([DataField { name: \"x\", data_type: Int32, nullable: true }]) (while in analyze projection expr: {'x': n}[y])",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(Some(&joined_schema), &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_subqueries() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "Not in non-nullable subquery",
+            query: "SELECT name FROM system.databases WHERE name NOT IN (SELECT name FROM system.databases)",
+            expect: "NormalQuery { filter: (name NOT IN subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Not in nullable subquery",
+            query: "SELECT name FROM system.databases WHERE name NOT IN (SELECT host FROM system.processes)",
+            expect: "NormalQuery { filter: (name NOT IN subquery(_subquery_1)), projection: [name] }",
+            warnings: &["NOT IN subquery column 'host' is nullable, consider NOT EXISTS instead"],
+            ..Default::default()
+        },
+        TestCase {
+            name: "In nullable subquery",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT host FROM system.processes)",
+            expect: "NormalQuery { filter: (name IN subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Not in nullable subquery under strict mode",
+            query: "SELECT name FROM system.databases WHERE name NOT IN (SELECT host FROM system.processes)",
+            settings: &[("strict_mode", 1)],
+            expect: "NOT IN subquery column 'host' is nullable, consider NOT EXISTS instead (while in analyze filter predicate (name NOT IN subquery(_subquery_1)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Identical subqueries",
+            query: "SELECT (SELECT COUNT() FROM system.databases) AS c FROM system.tables WHERE name IN (SELECT name FROM system.databases) AND database IN (SELECT name FROM system.databases)",
+            settings: &[("merge_identical_subqueries", 1)],
+            expect: "NormalQuery { filter: ((name IN subquery(_subquery_1)) AND (database IN subquery(_subquery_2))), projection: [scalar subquery(_subquery_3) as c], subquery ids: [(\"_subquery_1\", 0), (\"_subquery_2\", 0), (\"_subquery_3\", 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Different subqueries",
+            query: "SELECT name FROM system.tables WHERE name IN (SELECT name FROM system.databases) AND database IN (SELECT name FROM system.tables)",
+            settings: &[("merge_identical_subqueries", 1)],
+            expect: "NormalQuery { filter: ((name IN subquery(_subquery_1)) AND (database IN subquery(_subquery_2))), projection: [name], subquery ids: [(\"_subquery_1\", 0), (\"_subquery_2\", 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Identical subqueries without merge",
+            query: "SELECT name FROM system.tables WHERE name IN (SELECT name FROM system.databases) AND database IN (SELECT name FROM system.databases)",
+            expect: "NormalQuery { filter: ((name IN subquery(_subquery_1)) AND (database IN subquery(_subquery_2))), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Greater than all",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: ">",
+                quantifier: "ALL",
+            }),
+            expect: "NormalQuery { filter: (name > ALL subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Some is any",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: "<=",
+                quantifier: "SOME",
+            }),
+            expect: "NormalQuery { filter: (name <= ANY subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Equal any is in",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: "=",
+                quantifier: "ANY",
+            }),
+            expect: "NormalQuery { filter: (name IN subquery(_subquery_1)), projection: [name], semi join: [\"_subquery_1\"] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Not equal all is not in",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: "<>",
+                quantifier: "ALL",
+            }),
+            expect: "NormalQuery { filter: (name NOT IN subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Multiple columns subquery",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT database, name FROM system.tables)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: ">",
+                quantifier: "ANY",
+            }),
+            expect: "Subquery _subquery_1 of ANY comparison must return exactly one column, but got 2 (while in analyze filter predicate (name > ANY subquery(_subquery_1)))",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unsupported operator",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            clause: Some(Clause::Quantified {
+                op: "LIKE",
+                quantifier: "ALL",
+            }),
+            expect: "Unsupported operator LIKE in ALL comparison (while in analyze filter predicate (name LIKE ALL subquery(_subquery_1)))",
+            ..Default::default()
+        },
+    ];
+    run_test_cases(None, &tests).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_ordinality() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        ordinality: Option<&'static str>,
+        expect: &'static str,
+        fields: Vec<(&'static str, DataType, bool)>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Ordinality column",
+            query: "SELECT number, idx, t.idx FROM numbers(10) AS t",
+            ordinality: Some("idx"),
+            expect: "NormalQuery { projection: [number, idx, idx] }",
+            fields: vec![
+                ("number", DataType::UInt64, false),
+                ("idx", DataType::UInt64, false),
+            ],
+        },
+        TestCase {
+            name: "Ordinality column without WITH ORDINALITY",
+            query: "SELECT number, idx FROM numbers(10) AS t",
+            ordinality: None,
+            expect: "Unknown column idx (while in analyze projection expr: idx)",
+            fields: vec![("number", DataType::UInt64, false)],
+        },
+        TestCase {
+            name: "Ordinality column conflicts with function column",
+            query: "SELECT number FROM numbers(10) AS t",
+            ordinality: Some("number"),
+            expect: "Ordinality column number conflicts with a column of t",
+            fields: vec![],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+
+        // The sqlparser doesn't produce `WITH ORDINALITY` yet, build the schema by hand.
+        let catalog = ctx.get_catalog();
+        let table_function = catalog.get_table_function("numbers", Some(vec![lit(10u64)]))?;
+        let joined_schema = match JoinedSchema::from_table_function(
+            table_function.as_table(),
+            vec!["t".to_string()],
+            test_case.ordinality.map(String::from),
+        ) {
+            Ok(joined_schema) => joined_schema,
+            Err(cause) => {
+                assert_eq!(test_case.expect, cause.message(), "{:#?}", test_case.name);
+                continue;
+            }
+        };
+
+        let data_schema = joined_schema.to_data_schema();
+        let fields = data_schema.fields().iter().map(|field| {
+            let name = field.name().as_str();
+            (name, field.data_type().clone(), field.is_nullable())
+        });
+        let fields = fields.collect::<Vec<_>>();
+        assert_eq!(test_case.fields, fields, "{:#?}", test_case.name);
+
+        let transform = QueryNormalizer::create(ctx.clone());
+        let data = transform.transform(&parse_query(test_case.query)?).await?;
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let actual = format_rewritten(rewriter.rewrite(data));
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_current_database() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "Column without the current database",
+            query: "SELECT tables.name FROM system.tables",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Column with the current database",
+            query: "SELECT system.tables.name FROM system.tables",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias column without the current database",
+            query: "SELECT t.name FROM system.tables AS t",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias column with the current database",
+            query: "SELECT system.t.name FROM system.tables AS t",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Alias column with other database",
+            query: "SELECT other.t.name FROM system.tables AS t",
+            expect:
+                "Unknown column other.t.name (while in analyze projection expr: \"other.t.name\")",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        ctx.set_current_database(String::from("system")).await?;
+        let actual = format_rewritten(rewrite_query(&ctx, test_case.query).await);
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_expand_wildcard() -> Result<()> {
+    let ctx = try_create_context()?;
+    let query =
+        "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name";
+    let (joined_schema, _) = normalize_query(&ctx, query).await?;
+
+    let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+    let (expressions, expanded_columns) = rewriter.expand_wildcard_with_metadata();
+    let column = |name: &str| Expression::Column(name.to_string());
+    assert_eq!(
+        vec![
+            column("d.name"),
+            column("database"),
+            column("t.name"),
+            column("engine"),
+        ],
+        expressions
+    );
+
+    let column = |relation: &str, short_name: &str, qualified: bool| ExpandedColumn {
+        relation: relation.to_string(),
+        short_name: short_name.to_string(),
+        qualified,
+        data_type: DataType::String,
+    };
+    assert_eq!(
+        vec![
+            column("d", "name", true),
+            column("t", "database", false),
+            column("t", "name", true),
+            column("t", "engine", false),
+        ],
+        expanded_columns
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_column_lineage() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: Vec<(&'static str, Vec<(Vec<&'static str>, &'static str)>)>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Lineage of join columns",
+            query: "SELECT d.name, database, SUBSTRING(t.name, 1, 2) AS prefix FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: vec![
+                ("d.name", vec![(vec!["d"], "name")]),
+                ("database", vec![(vec!["t"], "database")]),
+                ("prefix", vec![(vec!["t"], "name")]),
+            ],
+        },
+        TestCase {
+            name: "Lineage of wildcard",
+            query: "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: vec![
+                ("d.name", vec![(vec!["d"], "name")]),
+                ("database", vec![(vec!["t"], "database")]),
+                ("t.name", vec![(vec!["t"], "name")]),
+                ("engine", vec![(vec!["t"], "engine")]),
+            ],
+        },
+        TestCase {
+            name: "Lineage of aggregate",
+            query: "SELECT COUNT(name) + COUNT(database) AS c, 1 AS one FROM system.tables",
+            expect: vec![
+                (
+                    "c",
+                    vec![
+                        (vec!["system", "tables"], "name"),
+                        (vec!["system", "tables"], "database"),
+                    ],
+                ),
+                ("one", vec![]),
+            ],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema.clone(), ctx);
+        let ir = rewriter.rewrite(data)?;
+
+        let expect = test_case
+            .expect
+            .iter()
+            .map(|(output, sources)| {
+                let sources = sources.iter().map(|(table, column)| {
+                    let table = table.iter().map(|part| part.to_string()).collect();
+                    (table, column.to_string())
+                });
+                (output.to_string(), sources.collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            expect,
+            joined_schema.column_lineage(&ir),
+            "{:#?}",
+            test_case.name
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_outer_scopes() -> Result<()> {
+    let outer_queries = [
+        "SELECT * FROM system.settings AS level0",
+        "SELECT * FROM system.tables AS level1",
+    ];
+
+    let tests = vec![
+        TestCase {
+            name: "Resolve in current scope",
+            query: "SELECT level2.name FROM system.databases AS level2",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Resolve in outer scope",
+            query: "SELECT engine FROM system.databases AS level2",
+            expect: "NormalQuery { projection: [outer(engine, 1)], outer references: [(engine, 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Resolve in outermost scope",
+            query: "SELECT level0.value FROM system.databases AS level2 WHERE level1.name = level2.name",
+            expect: "NormalQuery { filter: (outer(name, 1) = name), projection: [outer(value, 2)], outer references: [(value, 2), (name, 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Resolve in no scope",
+            query: "SELECT missing FROM system.databases AS level2",
+            expect: "Unknown column missing (while in analyze projection expr: missing)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Subquery within the depth limit",
+            query: "SELECT name FROM system.databases",
+            settings: &[("max_subquery_depth", 2)],
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Subquery nesting too deep",
+            query: "SELECT name FROM system.databases",
+            settings: &[("max_subquery_depth", 1)],
+            expect: "Too deeply nested subqueries: 2, the limit is max_subquery_depth = 1",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let outer_scopes = analyze_outer_scopes(&ctx, &outer_queries).await?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter =
+            QualifiedRewriter::create_with_outer_scopes(outer_scopes, joined_schema, ctx);
+        let actual = format_rewritten(rewriter.rewrite(data));
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_correlated_columns() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "Inner column shadows the outer column of the same name",
+            query: "SELECT name FROM system.databases AS level2",
+            expect: "NormalQuery { projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified outer column",
+            query: "SELECT level1.name FROM system.databases AS level2",
+            expect: "NormalQuery { projection: [outer(name, 1)], outer references: [(name, 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Inner and outer columns of the same name",
+            query: "SELECT name FROM system.databases AS level2 WHERE level1.name = name",
+            expect: "NormalQuery { filter: (outer(name, 1) = name), projection: [name], outer references: [(name, 1)] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unqualified column only in the outer scope",
+            query: "SELECT database FROM system.databases AS level2 WHERE database = name",
+            expect: "NormalQuery { filter: (outer(database, 1) = name), projection: [outer(database, 1)], outer references: [(database, 1)] }",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let outer_scopes =
+            analyze_outer_scopes(&ctx, &["SELECT * FROM system.tables AS level1"]).await?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter =
+            QualifiedRewriter::create_with_outer_scopes(outer_scopes, joined_schema, ctx);
+        let ir = rewriter.rewrite(data)?;
+        assert_eq!(
+            test_case.expect,
+            format!("{:?}", ir),
+            "{:#?}",
+            test_case.name
+        );
+
+        // The correlated columns are kept by the serialized IR for the decorrelation.
+        for expr in ir.projection_expressions.iter().chain(&ir.filter_predicate) {
+            let serialized = serde_json::to_string(expr)?;
+            let deserialized = serde_json::from_str::<Expression>(&serialized)?;
+            assert_eq!(expr, &deserialized, "{:#?}", test_case.name);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_cast_grouped_outer_column() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        grouped: bool,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Cast grouped outer column",
+            query: "SELECT CAST(o.database AS TEXT) FROM system.databases",
+            grouped: true,
+            expect: "NormalQuery { projection: [cast(outer(database, 1) as String)], outer references: [(database, 1)] }",
+        },
+        TestCase {
+            name: "Cast unqualified grouped outer column",
+            query: "SELECT CAST(database AS INT) FROM system.databases",
+            grouped: true,
+            expect: "NormalQuery { projection: [cast(outer(database, 1) as Int32)], outer references: [(database, 1)] }",
+        },
+        TestCase {
+            name: "Cast ungrouped outer column",
+            query: "SELECT CAST(o.engine AS TEXT) FROM system.databases",
+            grouped: true,
+            expect: "column 'engine' of the outer query must appear in its GROUP BY (while in analyze projection expr: cast(\"o.engine\" as String))",
+        },
+        TestCase {
+            name: "Cast outer column of ungrouped outer query",
+            query: "SELECT CAST(o.engine AS TEXT) FROM system.databases",
+            grouped: false,
+            expect: "NormalQuery { projection: [cast(outer(engine, 1) as String)], outer references: [(engine, 1)] }",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let mut outer_scopes =
+            analyze_outer_scopes(&ctx, &["SELECT * FROM system.tables AS o"]).await?;
+        if test_case.grouped {
+            outer_scopes[0].set_group_by(vec![Expression::Column("database".to_string())]);
+        }
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter =
+            QualifiedRewriter::create_with_outer_scopes(outer_scopes, joined_schema, ctx);
+        let actual = format_rewritten(rewriter.rewrite(data));
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_semi_join_hint() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "In subquery",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            expect: "NormalQuery { filter: (name IN subquery(_subquery_1)), projection: [name], semi join: [\"_subquery_1\"] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "In subquery without semi join hint",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            expect: "NormalQuery { filter: (name IN subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Not in subquery",
+            query: "SELECT name FROM system.databases WHERE name NOT IN (SELECT name FROM system.databases)",
+            settings: &[("enable_semi_join_hint", 1)],
+            expect: "NormalQuery { filter: (name NOT IN subquery(_subquery_1)), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Correlated in subquery",
+            query: "SELECT name FROM system.databases AS d WHERE name IN (SELECT name FROM system.tables AS t WHERE t.database = d.name)",
+            settings: &[("enable_semi_join_hint", 1)],
+            expect: "Correlated subqueries are not yet supported in query plan (while in analyze select filter)",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let query = parse_query(test_case.query)?;
+        let actual = format_rewritten(query.resolve(ctx).await);
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_is_deterministic() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        deterministic: bool,
+        functions: Vec<&'static str>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Deterministic query",
+            query:
+                "SELECT name, COUNT() FROM system.databases WHERE name LIKE 'sys%' GROUP BY name",
+            deterministic: true,
+            functions: vec![],
+        },
+        TestCase {
+            name: "Volatile functions",
+            query: "SELECT now(), rand(), NOW() FROM system.databases",
+            deterministic: false,
+            functions: vec!["now", "rand"],
+        },
+        TestCase {
+            name: "Volatile function in filter",
+            query: "SELECT name FROM system.databases WHERE name = database()",
+            deterministic: false,
+            functions: vec!["database"],
+        },
+        TestCase {
+            name: "Volatile function in subquery",
+            query: "SELECT name FROM system.databases WHERE EXISTS(SELECT uuid())",
+            deterministic: false,
+            functions: vec!["uuid"],
+        },
+        TestCase {
+            name: "Volatile function in set operation",
+            query: "SELECT 1 UNION ALL SELECT rand()",
+            deterministic: false,
+            functions: vec!["rand"],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let ir = parse_query(test_case.query)?.resolve(ctx).await?;
+        let (deterministic, functions) = is_deterministic(&ir);
+        assert_eq!(
+            test_case.deterministic, deterministic,
+            "{:#?}",
+            test_case.name
+        );
+        assert_eq!(test_case.functions, functions, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_relations_used_by_projection() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: Vec<usize>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Joined relation only referenced by the filter",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name WHERE t.engine = 'Memory'",
+            expect: vec![0],
+        },
+        TestCase {
+            name: "Both relations referenced by the projection",
+            query: "SELECT engine, d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: vec![0, 1],
+        },
+        TestCase {
+            name: "No relation referenced by the projection",
+            query: "SELECT 1 FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: vec![],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let ir = rewriter.rewrite(data)?;
+
+        let mut relations = rewriter
+            .relations_used_by_projection(&ir)
+            .into_iter()
+            .collect::<Vec<_>>();
+        relations.sort_unstable();
+        assert_eq!(test_case.expect, relations, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_referenced_tables() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: Vec<Vec<&'static str>>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Base table",
+            query: "SELECT name FROM system.databases",
+            expect: vec![vec!["system", "databases"]],
+        },
+        TestCase {
+            name: "Base table with alias",
+            query: "SELECT d.name FROM system.databases AS d",
+            expect: vec![vec!["system", "databases"]],
+        },
+        TestCase {
+            name: "Subquery in FROM",
+            query: "SELECT sub.name FROM (SELECT name FROM system.tables) AS sub",
+            expect: vec![vec!["system", "tables"]],
+        },
+        TestCase {
+            name: "CTE",
+            query: "WITH c AS (SELECT name FROM system.tables) SELECT name FROM c",
+            expect: vec![vec!["system", "tables"]],
+        },
+        TestCase {
+            name: "Subquery in filter",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT database FROM system.tables)",
+            expect: vec![vec!["system", "databases"], vec!["system", "tables"]],
+        },
+        TestCase {
+            name: "Duplicate table references",
+            query: "SELECT (SELECT COUNT() FROM system.tables) AS c FROM system.tables WHERE name IN (SELECT name FROM system.tables)",
+            expect: vec![vec!["system", "tables"]],
+        },
+        TestCase {
+            name: "Values without table",
+            query: "SELECT id FROM (VALUES (1), (2)) AS v(id)",
+            expect: vec![],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let ir = rewriter.rewrite(data)?;
+        let actual = rewriter.referenced_tables(&ir)?;
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_resolved_columns() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        // The column, the table it is resolved from and whether it is prefixed to be unambiguous.
+        expect: Vec<(&'static str, &'static str, bool)>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Unambiguous columns",
+            query: "SELECT name FROM system.databases",
+            expect: vec![("name", "system.databases", false)],
+        },
+        TestCase {
+            name: "Ambiguous columns of the joined tables",
+            query: "SELECT d.name, t.name, database FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: vec![
+                ("d.name", "d", true),
+                ("database", "t", false),
+                ("t.name", "t", true),
+            ],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        rewriter.rewrite(data)?;
+
+        let mut actual = rewriter
+            .get_resolved_columns()
+            .iter()
+            .map(|resolved| {
+                let column = format!("{:?}", resolved.column);
+                let table = resolved.table_desc.get_name_parts().join(".");
+                (column, table, resolved.ambiguity_prefixed)
+            })
+            .collect::<Vec<_>>();
+        actual.sort();
+
+        let expect = test_case
+            .expect
+            .iter()
+            .map(|(column, table, prefixed)| (column.to_string(), table.to_string(), *prefixed))
+            .collect::<Vec<_>>();
+        assert_eq!(expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_group_by_cast_collate() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+        // The columns of the first projection not covered by the GROUP BY.
+        ungrouped: Vec<Expression>,
+    }
+
+    let column = |name: &str| Expression::Column(name.to_string());
+    let tests = vec![
+        TestCase {
+            name: "Collated cast key in projection",
+            query: "SELECT CAST(name AS TEXT) COLLATE \"C\" FROM system.databases GROUP BY CAST(name AS TEXT) COLLATE \"C\"",
+            expect: "NormalQuery { group by: [cast(name as String) collate C], projection: [cast(name as String) collate C] }",
+            ungrouped: vec![],
+        },
+        TestCase {
+            name: "Qualified collated cast key in projection",
+            query: "SELECT CAST(d.name AS TEXT) COLLATE \"C\" FROM system.databases AS d GROUP BY CAST(name AS TEXT) COLLATE \"C\"",
+            expect: "NormalQuery { group by: [cast(name as String) collate C], projection: [cast(name as String) collate C] }",
+            ungrouped: vec![],
+        },
+        TestCase {
+            name: "Column of collated cast key in projection",
+            query: "SELECT name FROM system.databases GROUP BY CAST(name AS TEXT) COLLATE \"C\"",
+            expect: "NormalQuery { group by: [cast(name as String) collate C], projection: [name] }",
+            ungrouped: vec![column("name")],
+        },
+        TestCase {
+            name: "Different collation in projection",
+            query: "SELECT CAST(name AS TEXT) COLLATE \"POSIX\" FROM system.databases GROUP BY CAST(name AS TEXT) COLLATE \"C\"",
+            expect: "NormalQuery { group by: [cast(name as String) collate C], projection: [cast(name as String) collate POSIX] }",
+            ungrouped: vec![column("name")],
+        },
+        TestCase {
+            name: "Unsupported collation",
+            query: "SELECT name FROM system.databases GROUP BY CAST(name AS TEXT) COLLATE \"de_DE\"",
+            expect: "Unsupported collation \"de_DE\", only C, POSIX and binary are supported (while in analyze group expr: cast(name as String) collate de_DE)",
+            ungrouped: vec![],
+        },
+        TestCase {
+            name: "Collate non-string cast",
+            query: "SELECT name FROM system.databases GROUP BY CAST(name AS BIGINT) COLLATE \"C\"",
+            expect: "COLLATE requires a string expression, but cast(name as Int64) is Int64 (while in analyze group expr: cast(name as Int64) collate C)",
+            ungrouped: vec![],
+        },
+        TestCase {
+            name: "Collate unknown column",
+            query: "SELECT name FROM system.databases GROUP BY CAST(missing AS TEXT) COLLATE \"C\"",
+            expect: "Unknown column missing (while in analyze group expr: cast(missing as String) collate C)",
+            ungrouped: vec![],
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        match rewrite_query(&ctx, test_case.query).await {
+            Ok(ir) => {
+                assert_eq!(
+                    test_case.expect,
+                    format!("{:?}", ir),
+                    "{:#?}",
+                    test_case.name
+                );
+                let ungrouped = find_columns_not_in_group_by(
+                    &ir.projection_expressions[0],
+                    &ir.group_by_expressions,
+                );
+                assert_eq!(test_case.ungrouped, ungrouped, "{:#?}", test_case.name);
+            }
+            Err(cause) => assert_eq!(test_case.expect, cause.message(), "{:#?}", test_case.name),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_null_literal() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        settings: &'static [(&'static str, u64)],
+        expect: &'static str,
+        // The types of the rewritten projection.
+        types: Vec<DataType>,
+        warnings: Vec<&'static str>,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Untyped null",
+            query: "SELECT NULL AS x FROM t",
+            settings: &[],
+            expect: "NormalQuery { projection: [NULL as x] }",
+            types: vec![DataType::Null],
+            warnings: vec![],
+        },
+        TestCase {
+            name: "Typed null",
+            query: "SELECT CAST(NULL AS INT) AS x, CAST(NULL AS VARCHAR) FROM t",
+            settings: &[],
+            expect: "NormalQuery { projection: [NULL as x, NULL] }",
+            types: vec![DataType::Int32, DataType::String],
+            warnings: vec![],
+        },
+        TestCase {
+            name: "Compare with null",
+            query: "SELECT a FROM t WHERE b = NULL",
+            settings: &[],
+            expect: "NormalQuery { filter: (b = NULL), projection: [a] }",
+            types: vec![DataType::String],
+            warnings: vec!["Comparison (b = NULL) is always NULL, use IS NULL instead"],
+        },
+        TestCase {
+            name: "Compare with null under strict mode",
+            query: "SELECT a FROM t WHERE NULL <> b",
+            settings: &[("strict_mode", 1)],
+            expect: "Comparison (b <> NULL) is always NULL, use IS NOT NULL instead (while in analyze filter predicate (NULL <> b))",
+            types: vec![],
+            warnings: vec![],
+        },
+        TestCase {
+            name: "Rewrite comparison with null",
+            query: "SELECT a FROM t WHERE b = NULL OR a <> NULL",
+            settings: &[("strict_mode", 1), ("rewrite_null_comparison", 1)],
+            expect: "NormalQuery { filter: (isnull(b) OR isnotnull(a)), projection: [a] }",
+            types: vec![DataType::String],
+            warnings: vec![],
+        },
+        TestCase {
+            name: "Compare null with null",
+            query: "SELECT a FROM t WHERE NULL = NULL",
+            settings: &[("strict_mode", 1), ("rewrite_null_comparison", 1)],
+            expect: "NormalQuery { filter: (NULL = NULL), projection: [a] }",
+            types: vec![DataType::String],
+            warnings: vec![],
+        },
+    ];
+
+    let fields = vec![
+        DataField::new("a", DataType::String, true),
+        DataField::new("b", DataType::Int32, true),
+    ];
+    let schema = DataSchemaRefExt::create(fields.clone());
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let joined_schema = subquery_schema("t", fields.clone())?;
+
+        let transform = QueryNormalizer::create(ctx.clone());
+        let data = transform.transform(&parse_query(test_case.query)?).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx.clone());
+        match rewriter.rewrite(data) {
+            Ok(ir) => {
+                assert_eq!(
+                    test_case.expect,
+                    format!("{:?}", ir),
+                    "{:#?}",
+                    test_case.name
+                );
+                let types = ir
+                    .projection_expressions
+                    .iter()
+                    .map(|expr| expr.to_data_type(&schema))
+                    .collect::<Result<Vec<_>>>()?;
+                assert_eq!(test_case.types, types, "{:#?}", test_case.name);
+            }
+            Err(cause) => assert_eq!(test_case.expect, cause.message(), "{:#?}", test_case.name),
+        }
+        assert_eq!(
+            test_case.warnings,
+            ctx.get_warnings(),
+            "{:#?}",
+            test_case.name
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_match_recognize() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        defines: Vec<(&'static str, Expression)>,
+        measures: Vec<Expression>,
+        expect: &'static str,
+    }
+
+    let column = |variable: &str, name: &str| {
+        Expression::QualifiedColumn(vec![variable.to_string(), name.to_string()])
+    };
+    let binary = |op: &str, left: Expression, right: Expression| Expression::BinaryExpression {
+        op: op.to_string(),
+        left: Box::new(left),
+        right: Box::new(right),
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Define and measure pattern variables",
+            defines: vec![
+                ("A", binary(">", column("A", "price"), lit(10i64))),
+                ("B", binary("<", column("B", "price"), column("A", "price"))),
+            ],
+            measures: vec![
+                Expression::Alias("start_price".to_string(), Box::new(column("A", "price"))),
+                column("B", "ts"),
+            ],
+            expect: "[(\"A\", (A.price > 10)), (\"B\", (B.price < A.price))] NormalQuery { projection: [A.price as start_price, B.ts] }",
+        },
+        TestCase {
+            name: "Unqualified column in define",
+            defines: vec![(
+                "A",
+                binary(">", Expression::Column("price".to_string()), lit(10i64)),
+            )],
+            measures: vec![column("A", "price")],
+            expect: "[(\"A\", (price > 10))] NormalQuery { projection: [A.price] }",
+        },
+        TestCase {
+            name: "Undefined pattern variable in define",
+            defines: vec![("A", binary(">", column("C", "price"), lit(10i64)))],
+            measures: vec![],
+            expect: "Undefined pattern variable C in MATCH_RECOGNIZE (while in analyze define A: (\"C.price\" > 10))",
+        },
+        TestCase {
+            name: "Unknown column of pattern variable in measure",
+            defines: vec![],
+            measures: vec![column("A", "missing")],
+            expect: "Unknown column A.missing (while in analyze measure expr: \"A.missing\")",
+        },
+        TestCase {
+            name: "Define of undefined pattern variable",
+            defines: vec![("C", binary(">", column("A", "price"), lit(10i64)))],
+            measures: vec![],
+            expect: "Undefined pattern variable C in MATCH_RECOGNIZE DEFINE",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let joined_schema = subquery_schema("t", vec![
+            DataField::new("price", DataType::Int64, false),
+            DataField::new("ts", DataType::DateTime32(None), false),
+        ])?
+        .with_pattern_variables(vec!["A".to_string(), "B".to_string()])?;
+
+        let transform = QueryNormalizer::create(ctx.clone());
+        let mut data = transform
+            .transform(&parse_query("SELECT price FROM t")?)
+            .await?;
+
+        // The sqlparser doesn't produce MATCH_RECOGNIZE yet, build DEFINE and MEASURES by hand.
+        let defines = test_case
+            .defines
+            .iter()
+            .map(|(variable, expr)| (variable.to_string(), expr.clone()))
+            .collect::<Vec<_>>();
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let actual =
+            match rewriter.rewrite_match_recognize(&mut data, &defines, &test_case.measures) {
+                Ok(defines) => format!("{:?} {:?}", defines, data),
+                Err(cause) => cause.message(),
+            };
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_pivot() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        pivot: TablePivot,
+        expect: &'static str,
+    }
+
+    let sum = |column: &str| Expression::AggregateFunction {
+        op: "sum".to_string(),
+        distinct: false,
+        params: vec![],
+        args: vec![Expression::Column(column.to_string())],
+        within_group: vec![],
+    };
+    let pivot = |aggregate: Expression, pivot_column: &str| TablePivot {
+        aggregate,
+        pivot_column: pivot_column.to_string(),
+        values: vec!["Q1".to_string(), "Q2".to_string()],
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Select pivot values",
+            query: "SELECT region, Q1, Q2 FROM sales",
+            pivot: pivot(sum("amt"), "quarter"),
+            expect: "NormalQuery { projection: [region, Q1, Q2] }",
+        },
+        TestCase {
+            name: "Wildcard of pivot in IN-list order",
+            query: "SELECT * FROM sales",
+            pivot: pivot(sum("amt"), "quarter"),
+            expect: "NormalQuery { projection: [region, Q1, Q2] }",
+        },
+        TestCase {
+            name: "Qualified pivot value",
+            query: "SELECT sales.Q2 FROM sales",
+            pivot: pivot(sum("amt"), "quarter"),
+            expect: "NormalQuery { projection: [Q2] }",
+        },
+        TestCase {
+            name: "Pivot value not in the IN list",
+            query: "SELECT Q3 FROM sales",
+            pivot: pivot(sum("amt"), "quarter"),
+            expect: "Unknown column Q3 (while in analyze projection expr: Q3)",
+        },
+        TestCase {
+            name: "Pivoted column is replaced",
+            query: "SELECT amt FROM sales",
+            pivot: pivot(sum("amt"), "quarter"),
+            expect: "Unknown column amt (while in analyze projection expr: amt)",
+        },
+        TestCase {
+            name: "Unknown aggregated column",
+            query: "SELECT Q1 FROM sales",
+            pivot: pivot(sum("price"), "quarter"),
+            expect: "Unknown column price in PIVOT of sales",
+        },
+        TestCase {
+            name: "Unknown FOR column",
+            query: "SELECT Q1 FROM sales",
+            pivot: pivot(sum("amt"), "month"),
+            expect: "Unknown column month in PIVOT of sales",
+        },
+        TestCase {
+            name: "Pivot without aggregate function",
+            query: "SELECT Q1 FROM sales",
+            pivot: pivot(Expression::Column("amt".to_string()), "quarter"),
+            expect: "PIVOT expects an aggregate function, but got amt",
+        },
+        TestCase {
+            name: "Pivot value conflicts with a grouping column",
+            query: "SELECT Q1 FROM sales",
+            pivot: TablePivot {
+                values: vec!["Q1".to_string(), "region".to_string()],
+                ..pivot(sum("amt"), "quarter")
+            },
+            expect: "PIVOT value region conflicts with a column of sales",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+
+        // The sqlparser doesn't produce PIVOT yet, pivot the schema by hand.
+        let joined_schema = subquery_schema("sales", vec![
+            DataField::new("region", DataType::String, false),
+            DataField::new("quarter", DataType::String, false),
+            DataField::new("amt", DataType::Int64, false),
+        ])?
+        .with_pivot(test_case.pivot.clone());
+
+        let actual = match joined_schema {
+            Err(cause) => cause.message(),
+            Ok(joined_schema) => {
+                let transform = QueryNormalizer::create(ctx.clone());
+                let data = transform.transform(&parse_query(test_case.query)?).await?;
+
+                let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+                format_rewritten(rewriter.rewrite(data))
+            }
+        };
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_profiling() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        settings: &'static [(&'static str, u64)],
+        expect_outer: Vec<&'static str>,
+        expect_subquery: Vec<&'static str>,
+    }
+
+    let phases = vec![
+        "join conditions",
+        "group",
+        "order",
+        "aggregate",
+        "window",
+        "projection",
+        "predicates",
+        "limit",
+    ];
+
+    let tests = vec![
+        TestCase {
+            name: "Profiling disabled",
+            settings: &[("enable_analyze_profiling", 0)],
+            expect_outer: vec![],
+            expect_subquery: vec![],
+        },
+        TestCase {
+            name: "Profiling enabled with nested subquery",
+            settings: &[("enable_analyze_profiling", 1)],
+            expect_outer: phases.clone(),
+            expect_subquery: phases.clone(),
+        },
+    ];
+
+    let query =
+        "SELECT name FROM system.databases WHERE name IN (SELECT database FROM system.tables)";
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let query = parse_query(query)?;
+        let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
+        let joined_schema = analyzer.analyze(&query).await?;
+
+        let scopes = vec![joined_schema.clone()];
+        let transform = QueryNormalizer::create_with_scopes(ctx.clone(), scopes);
+        let data = transform.transform(&query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx.clone());
+        rewriter.rewrite(data)?;
+
+        let timings = ctx.get_rewrite_timings();
+        let phases_at = |depth: usize| {
+            timings
+                .iter()
+                .filter(|timing| timing.depth == depth)
+                .map(|timing| timing.phase)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            test_case.expect_outer,
+            phases_at(0),
+            "{:#?}",
+            test_case.name
+        );
+        assert_eq!(
+            test_case.expect_subquery,
+            phases_at(1),
+            "{:#?}",
+            test_case.name
+        );
+    }
+
+    let timing = RewritePhaseTiming {
+        depth: 1,
+        phase: "projection",
+        elapsed: Duration::from_micros(3200),
+    };
+    assert_eq!("  projection rewrite: 3.2ms", timing.to_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_view_projection_aliases() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "Aliased computed column",
+            query: "SELECT name, crc32(name) AS len FROM system.databases",
+            settings: &[("strict_mode", 1)],
+            expect: "NormalQuery { projection: [name, crc32(name) as len] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unaliased computed column",
+            query: "SELECT name, crc32(name) FROM system.databases",
+            settings: &[("strict_mode", 1)],
+            expect: "Computed column crc32(name) of a view must have an explicit alias",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified bare column and wildcard",
+            query: "SELECT d.name, * FROM system.databases AS d",
+            settings: &[("strict_mode", 1)],
+            expect: "NormalQuery { projection: [name, name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unaliased computed column without strict mode",
+            query: "SELECT name, crc32(name) FROM system.databases",
+            expect: "NormalQuery { projection: [name, crc32(name)] }",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx).for_view_body();
+        let actual = format_rewritten(rewriter.rewrite(data));
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_preferred_table() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        preferred_table: Option<&'static str>,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Ambiguous column without preferred table",
+            query: "SELECT name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            preferred_table: None,
+            expect: "Unknown column name (while in analyze projection expr: name)",
+        },
+        TestCase {
+            name: "Ambiguous column with preferred table",
+            query: "SELECT name, engine FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name WHERE name = 'a'",
+            preferred_table: Some("t"),
+            expect: "NormalQuery { join conditions: [(d.name = t.name)], filter: (t.name = a), projection: [t.name, engine] }",
+        },
+        TestCase {
+            name: "Qualified column with preferred table",
+            query: "SELECT d.name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            preferred_table: Some("t"),
+            expect: "NormalQuery { join conditions: [(d.name = t.name)], projection: [d.name] }",
+        },
+        TestCase {
+            name: "Preferred table not joined",
+            query: "SELECT name FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            preferred_table: Some("x"),
+            expect: "Preferred table x is not one of the joined tables",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let rewriter = match test_case.preferred_table {
+            None => Ok(rewriter),
+            Some(table) => rewriter.with_preferred_table(&[table.to_string()]),
+        };
+
+        let actual = match rewriter {
+            Ok(rewriter) => format_rewritten(rewriter.rewrite(data)),
+            Err(cause) => cause.message(),
+        };
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_always_qualify_columns() -> Result<()> {
+    let tests = vec![
+        TestCase {
+            name: "Unambiguous column is not qualified by default",
+            query: "SELECT name FROM system.databases WHERE name = 'xxx'",
+            expect: "NormalQuery { filter: (name = xxx), projection: [name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Unambiguous column",
+            query: "SELECT name FROM system.databases WHERE name = 'xxx'",
+            settings: &[("always_qualify_columns", 1)],
+            expect: "NormalQuery { filter: (system.databases.name = xxx), projection: [system.databases.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified column of aliased table",
+            query: "SELECT d.name FROM system.databases AS d",
+            settings: &[("always_qualify_columns", 1)],
+            expect: "NormalQuery { projection: [d.name] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard",
+            query: "SELECT * FROM system.databases AS d",
+            settings: &[("always_qualify_columns", 1)],
+            expect: "NormalQuery { projection: [d.name] }",
+            ..Default::default()
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = create_context(test_case.settings)?;
+        let (joined_schema, data) = normalize_query(&ctx, test_case.query).await?;
+
+        let rewriter = QualifiedRewriter::create(joined_schema.clone(), ctx.clone());
+        let ir = rewriter.rewrite(data)?;
+        assert_eq!(
+            test_case.expect,
+            format!("{:?}", ir),
+            "{:#?}",
+            test_case.name
+        );
+
+        // The rewritten columns resolve to themselves on a second pass.
+        let rewriter = QualifiedRewriter::create(joined_schema, ctx);
+        let ir = rewriter.rewrite(ir)?;
+        assert_eq!(
+            test_case.expect,
+            format!("{:?}", ir),
+            "{:#?}",
+            test_case.name
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_to_sql_round_trip() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        from: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Alias and quoted literal",
+            query: "SELECT name AS \"Name\", 'it''s' FROM system.databases",
+            from: "FROM system.databases",
+        },
+        TestCase {
+            name: "Qualified column",
+            query: "SELECT databases.name FROM system.databases",
+            from: "FROM system.databases",
+        },
+        TestCase {
+            name: "Ambiguous columns of join",
+            query: "SELECT d.name, t.name, database FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            from: "FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+        },
+        TestCase {
+            name: "Operators and cast",
+            query: "SELECT NOT number = 1 AND -number + 1 * 2 > 3, CAST(number AS STRING) FROM numbers(10)",
+            from: "FROM numbers(10)",
+        },
+        TestCase {
+            name: "Nested try cast",
+            query: "SELECT TRY_CAST(TRY_CAST(name AS UInt32) AS TEXT) FROM system.databases",
+            from: "FROM system.databases",
+        },
+        TestCase {
+            name: "Aggregate with distinct",
+            query: "SELECT COUNT(DISTINCT number), SUM(number) + 1 FROM numbers(10)",
+            from: "FROM numbers(10)",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let expect = rewrite_query(&ctx, test_case.query).await?;
+        let projection = expect
+            .projection_expressions
+            .iter()
+            .map(Expression::to_sql)
+            .collect::<Result<Vec<_>>>()?;
+
+        let query = format!("SELECT {} {}", projection.join(", "), test_case.from);
+        let ctx = try_create_context()?;
+        let actual = rewrite_query(&ctx, &query).await?;
+        assert_eq!(
+            format!("{:?}", expect),
+            format!("{:?}", actual),
+            "{:#?}: {}",
+            test_case.name,
+            query
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_qualified_rewriter_pseudo_columns() -> Result<()> {
+    let fixture = TestFixture::new().await;
+    let create_table_plan = fixture.default_crate_table_plan();
+    fixture
+        .ctx()
+        .get_catalog()
+        .get_database(&create_table_plan.db)
+        .await?
+        .create_table(create_table_plan.into())
+        .await?;
+
+    let tests = vec![
+        TestCase {
+            name: "Pseudo column by name",
+            query: "SELECT _row_id, id FROM {table}",
+            settings: &[("enable_pseudo_columns", 1)],
+            expect: "NormalQuery { projection: [_row_id, id] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified pseudo column",
+            query: "SELECT t._segment FROM {table} AS t",
+            settings: &[("enable_pseudo_columns", 1)],
+            expect: "NormalQuery { projection: [_segment] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Wildcard skips pseudo columns",
+            query: "SELECT * FROM {table}",
+            settings: &[("enable_pseudo_columns", 1)],
+            expect: "NormalQuery { projection: [id] }",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Pseudo column disabled",
+            query: "SELECT _row_id FROM {table}",
+            expect: "Pseudo column _row_id is disabled, set enable_pseudo_columns = 1 to reference it (while in analyze projection expr: _row_id)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Pseudo column of unsupported table",
+            query: "SELECT _row_id FROM system.databases",
+            settings: &[("enable_pseudo_columns", 1)],
+            expect: "Pseudo column _row_id is not provided by any table of the query (while in analyze projection expr: _row_id)",
+            ..Default::default()
+        },
+        TestCase {
+            name: "Qualified pseudo column of unsupported table",
+            query: "SELECT d._row_id FROM system.databases AS d",
+            settings: &[("enable_pseudo_columns", 1)],
+            expect: "Table d has no pseudo column _row_id (while in analyze projection expr: \"d._row_id\")",
+            ..Default::default()
+        },
+    ];
+
+    let table = format!("{}.{}", fixture.default_db(), fixture.default_table());
+    for test_case in &tests {
+        let ctx = fixture.ctx();
+        for (name, value) in test_case.settings {
+            ctx.get_settings()
+                .update_settings(name, value.to_string())?;
+        }
+
+        let query = test_case.query.replace("{table}", &table);
+        let actual = format_rewritten(rewrite_query(&ctx, &query).await);
+        assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
     }
 
     Ok(())
@@ -16,16 +16,22 @@ use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Formatter;
+use std::ops::Range;
 use std::sync::Arc;
 
+use common_datavalues::merge_types;
 use common_datavalues::DataField;
 use common_datavalues::DataSchema;
 use common_datavalues::DataSchemaRef;
+use common_datavalues::DataSchemaRefExt;
 use common_datavalues::DataType;
 use common_exception::ErrorCode;
 use common_exception::Result;
+use common_planners::find_column_exprs;
+use common_planners::Expression;
 
 use crate::catalogs::Table;
+use crate::sql::statements::QueryASTIR;
 use crate::sql::statements::QueryAnalyzeState;
 
 #[derive(Clone)]
@@ -34,6 +40,27 @@ pub struct JoinedSchema {
     short_name_columns: HashMap<String, JoinedColumnDesc>,
     // Reference by full name, short name may be ambiguous.
     tables_long_name_columns: Vec<JoinedTableDesc>,
+    // The ON conditions of the joins, in the order of the joins.
+    join_conditions: Vec<JoinCondition>,
+    // The tables of each relation in a comma separated FROM list, such as `FROM a, b JOIN c`.
+    implicit_joined: Vec<Range<usize>>,
+    // The resolved GROUP BY keys of a grouped query, its subqueries can only reference them.
+    group_by: Option<Vec<Expression>>,
+    // The pattern variables of MATCH_RECOGNIZE, each one qualifies the columns like a table alias.
+    pattern_variables: Vec<String>,
+    // The join keys inferred for the NATURAL JOINs, in the order of the joins.
+    natural_join_keys: Vec<String>,
+}
+
+/// The index of a table in a joined schema, in the order of the FROM clause.
+pub type RelationId = usize;
+
+/// The `ON` condition of a join, it can only reference the tables in `tables`,
+/// which are the both sides of the join, not the tables joined later.
+#[derive(Clone)]
+pub struct JoinCondition {
+    pub tables: Range<usize>,
+    pub expr: Expression,
 }
 
 impl JoinedSchema {
@@ -41,14 +68,44 @@ impl JoinedSchema {
         JoinedSchema {
             short_name_columns: HashMap::new(),
             tables_long_name_columns: Vec::new(),
+            join_conditions: Vec::new(),
+            implicit_joined: Vec::new(),
+            group_by: None,
+            pattern_variables: Vec::new(),
+            natural_join_keys: Vec::new(),
         }
     }
 
     pub fn from_table(table: Arc<dyn Table>, prefix: Vec<String>) -> Result<JoinedSchema> {
-        let table_desc = JoinedTableDesc::from_table(table, prefix);
+        Self::from_sampled_table(table, prefix, None)
+    }
+
+    pub fn from_sampled_table(
+        table: Arc<dyn Table>,
+        prefix: Vec<String>,
+        sample: Option<TableSample>,
+    ) -> Result<JoinedSchema> {
+        if let Some(sample) = &sample {
+            sample.validate()?;
+        }
+
+        let table_desc = JoinedTableDesc::from_table(table, prefix, sample);
         Self::from_table_desc(table_desc)
     }
 
+    /// The schema of a table function source, with the `WITH ORDINALITY` column if named.
+    pub fn from_table_function(
+        table: Arc<dyn Table>,
+        prefix: Vec<String>,
+        ordinality: Option<String>,
+    ) -> Result<JoinedSchema> {
+        let table_desc = JoinedTableDesc::from_table(table, prefix, None);
+        match ordinality {
+            None => Self::from_table_desc(table_desc),
+            Some(ordinality) => Self::from_table_desc(table_desc.with_ordinality(&ordinality)?),
+        }
+    }
+
     pub fn from_subquery(
         state: Box<QueryAnalyzeState>,
         prefix: Vec<String>,
@@ -57,6 +114,16 @@ impl JoinedSchema {
         Self::from_table_desc(table_desc)
     }
 
+    /// The schema of a `VALUES` list in FROM, see [`JoinedTableDesc::from_values`].
+    pub fn from_values(
+        rows: Vec<Vec<Expression>>,
+        prefix: Vec<String>,
+        column_aliases: Vec<String>,
+    ) -> Result<JoinedSchema> {
+        let table_desc = JoinedTableDesc::from_values(rows, prefix, column_aliases)?;
+        Self::from_table_desc(table_desc)
+    }
+
     fn from_table_desc(table_desc: JoinedTableDesc) -> Result<JoinedSchema> {
         let mut short_name_columns = HashMap::new();
 
@@ -77,9 +144,63 @@ impl JoinedSchema {
         Ok(JoinedSchema {
             short_name_columns,
             tables_long_name_columns: vec![table_desc],
+            join_conditions: Vec::new(),
+            implicit_joined: Vec::new(),
+            group_by: None,
+            pattern_variables: Vec::new(),
+            natural_join_keys: Vec::new(),
         })
     }
 
+    // The short name appearing in more than one table is ambiguous,
+    // it can only be referenced by the full name.
+    // The columns of the output-invisible tables are not referenceable by the short name,
+    // and they are qualified if any other column has the same short name.
+    // The merged columns of NATURAL JOIN are always qualified, and don't make the key ambiguous.
+    fn from_tables_desc(
+        mut tables_desc: Vec<JoinedTableDesc>,
+        join_conditions: Vec<JoinCondition>,
+    ) -> JoinedSchema {
+        let mut short_name_count = HashMap::new();
+        let mut visible_short_name_count = HashMap::new();
+        for table_desc in &tables_desc {
+            for column_desc in table_desc.get_columns_desc() {
+                let short_name = column_desc.short_name.clone();
+                *short_name_count.entry(short_name.clone()).or_insert(0) += 1;
+                if table_desc.is_output_visible() && !column_desc.is_merged {
+                    *visible_short_name_count.entry(short_name).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut short_name_columns = HashMap::new();
+
+        for table_desc in &mut tables_desc {
+            let output_visible = table_desc.is_output_visible();
+            for column_desc in table_desc.get_columns_desc_mut() {
+                column_desc.is_ambiguity = match output_visible {
+                    _ if column_desc.is_merged => true,
+                    true => visible_short_name_count[&column_desc.short_name] > 1,
+                    false => short_name_count[&column_desc.short_name] > 1,
+                };
+
+                if output_visible && !column_desc.is_ambiguity {
+                    short_name_columns.insert(column_desc.short_name.clone(), column_desc.clone());
+                }
+            }
+        }
+
+        JoinedSchema {
+            short_name_columns,
+            tables_long_name_columns: tables_desc,
+            join_conditions,
+            implicit_joined: Vec::new(),
+            group_by: None,
+            pattern_variables: Vec::new(),
+            natural_join_keys: Vec::new(),
+        }
+    }
+
     pub fn contains_column(&self, column_name: &str) -> bool {
         self.short_name_columns.contains_key(column_name)
     }
@@ -121,8 +242,314 @@ impl JoinedSchema {
         Arc::new(DataSchema::new(fields))
     }
 
-    pub fn join(&self, _joined_schema: &JoinedSchema) -> Result<Arc<JoinedSchema>> {
-        unimplemented!("")
+    pub fn get_join_conditions(&self) -> &[JoinCondition] {
+        &self.join_conditions
+    }
+
+    pub fn get_natural_join_keys(&self) -> &[String] {
+        &self.natural_join_keys
+    }
+
+    pub fn get_implicit_joined(&self) -> &[Range<usize>] {
+        &self.implicit_joined
+    }
+
+    pub fn set_group_by(&mut self, group_by: Vec<Expression>) {
+        self.group_by = Some(group_by);
+    }
+
+    pub fn get_group_by(&self) -> Option<&[Expression]> {
+        self.group_by.as_deref()
+    }
+
+    /// Register the pattern variables of `MATCH_RECOGNIZE`, such as `A` in `A.price`.
+    pub fn with_pattern_variables(mut self, variables: Vec<String>) -> Result<JoinedSchema> {
+        for (index, variable) in variables.iter().enumerate() {
+            if variables[..index].contains(variable) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Duplicate pattern variable {} in MATCH_RECOGNIZE",
+                    variable
+                )));
+            }
+        }
+
+        self.pattern_variables = variables;
+        Ok(self)
+    }
+
+    /// Pivot the single relation of the schema, see [`JoinedTableDesc::with_pivot`].
+    pub fn with_pivot(self, pivot: TablePivot) -> Result<JoinedSchema> {
+        let mut tables_desc = self.take_tables_desc();
+        match tables_desc.len() {
+            1 => Self::from_table_desc(tables_desc.remove(0).with_pivot(pivot)?),
+            _ => Err(ErrorCode::SyntaxException(
+                "PIVOT is only supported on a single relation",
+            )),
+        }
+    }
+
+    pub fn get_pattern_variables(&self) -> &[String] {
+        &self.pattern_variables
+    }
+
+    /// The base `(table name parts, column)` pairs each output column of a rewritten query
+    /// derives from. Columns resolved in the outer queries are not included.
+    pub fn column_lineage(&self, ir: &QueryASTIR) -> Vec<(String, Vec<(Vec<String>, String)>)> {
+        let mut lineage = Vec::with_capacity(ir.projection_expressions.len());
+
+        for projection_expression in &ir.projection_expressions {
+            let columns = find_column_exprs(std::slice::from_ref(projection_expression));
+            let sources = columns
+                .iter()
+                .filter_map(|column| match column {
+                    Expression::Column(name) => self.column_source(name),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+
+            lineage.push((projection_expression.column_name(), sources));
+        }
+
+        lineage
+    }
+
+    fn column_source(&self, name: &str) -> Option<(Vec<String>, String)> {
+        let (index, column_desc) = self.find_column_table(name)?;
+        let name_parts = self.tables_long_name_columns[index].get_name_parts();
+        Some((name_parts.to_vec(), column_desc.short_name.clone()))
+    }
+
+    /// The index of the table a rewritten column belongs to, the rewritten column is
+    /// the short name, or `table.column` if the short name is ambiguous.
+    pub fn find_column_table(&self, name: &str) -> Option<(usize, &JoinedColumnDesc)> {
+        for (index, table_desc) in self.tables_long_name_columns.iter().enumerate() {
+            let name_parts = table_desc.get_name_parts();
+            for column_desc in table_desc.get_columns_desc() {
+                let column_name = match column_desc.is_ambiguity {
+                    true => format!("{}.{}", name_parts.join("."), column_desc.short_name),
+                    false => column_desc.short_name.clone(),
+                };
+
+                if column_name == name {
+                    return Some((index, column_desc));
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn join(&self, joined_schema: &JoinedSchema) -> Result<JoinedSchema> {
+        let offset = self.tables_long_name_columns.len();
+        let mut tables_desc = self.tables_long_name_columns.clone();
+        tables_desc.extend(joined_schema.tables_long_name_columns.iter().cloned());
+
+        let mut join_conditions = self.join_conditions.clone();
+        for join_condition in &joined_schema.join_conditions {
+            join_conditions.push(JoinCondition {
+                tables: join_condition.tables.start + offset..join_condition.tables.end + offset,
+                expr: join_condition.expr.clone(),
+            });
+        }
+
+        let mut schema = Self::from_tables_desc(tables_desc, join_conditions);
+        schema.natural_join_keys = self.natural_join_keys.clone();
+        schema
+            .natural_join_keys
+            .extend(joined_schema.natural_join_keys.iter().cloned());
+        Ok(schema)
+    }
+
+    /// `a NATURAL JOIN b`, joined on the equality of the columns named the same on both sides.
+    /// Each key is output once, the column of the other side of `merge_left` is kept, the
+    /// merged one can only be referenced by its full name.
+    pub fn natural_join(
+        &self,
+        joined_schema: &JoinedSchema,
+        merge_left: bool,
+    ) -> Result<JoinedSchema> {
+        let left_columns = self.natural_join_columns("left")?;
+        let right_columns = joined_schema.natural_join_columns("right")?;
+
+        let mut keys = Vec::new();
+        let mut left = self.clone();
+        let mut right = joined_schema.clone();
+        let mut condition: Option<Expression> = None;
+        for (name, left_index, left_desc) in &left_columns {
+            let (right_index, right_desc) = match right_columns.iter().find(|(n, ..)| n == name) {
+                None => continue,
+                Some((_, right_index, right_desc)) => (*right_index, right_desc),
+            };
+
+            if left_desc.data_type != right_desc.data_type {
+                return Err(ErrorCode::BadDataValueType(format!(
+                    "NATURAL JOIN column {} has type {:?} on the left side, but {:?} on the right side",
+                    name, left_desc.data_type, right_desc.data_type
+                )));
+            }
+
+            let mut left_name = left.tables_long_name_columns[*left_index]
+                .get_name_parts()
+                .to_vec();
+            let mut right_name = right.tables_long_name_columns[right_index]
+                .get_name_parts()
+                .to_vec();
+            left_name.push(name.clone());
+            right_name.push(name.clone());
+
+            let equality =
+                Expression::QualifiedColumn(left_name).eq(Expression::QualifiedColumn(right_name));
+            condition = Some(match condition {
+                None => equality,
+                Some(condition) => condition.and(equality),
+            });
+
+            let merged = match merge_left {
+                true => &mut left.tables_long_name_columns[*left_index],
+                false => &mut right.tables_long_name_columns[right_index],
+            };
+            for column_desc in merged.get_columns_desc_mut() {
+                if &column_desc.short_name == name {
+                    column_desc.is_merged = true;
+                }
+            }
+
+            keys.push(name.clone());
+        }
+
+        let condition = match condition {
+            None => {
+                return Err(ErrorCode::SyntaxException(
+                    "NATURAL JOIN has no common columns",
+                ))
+            }
+            Some(condition) => condition,
+        };
+
+        let mut schema = left.join(&right)?.with_join_condition(condition);
+        schema.natural_join_keys.extend(keys);
+        Ok(schema)
+    }
+
+    // The output columns of one side of NATURAL JOIN with the index of their tables. A name
+    // appearing in more than one table of the side can't be the key of the join.
+    fn natural_join_columns(&self, side: &str) -> Result<Vec<(String, usize, JoinedColumnDesc)>> {
+        let mut columns: Vec<(String, usize, JoinedColumnDesc)> = Vec::new();
+        for (index, table_desc) in self.tables_long_name_columns.iter().enumerate() {
+            if !table_desc.is_output_visible() {
+                continue;
+            }
+
+            for column_desc in table_desc.get_columns_desc() {
+                if column_desc.is_merged {
+                    continue;
+                }
+
+                if columns
+                    .iter()
+                    .any(|(name, ..)| name == &column_desc.short_name)
+                {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "NATURAL JOIN column {} is ambiguous on the {} side",
+                        column_desc.short_name, side
+                    )));
+                }
+
+                columns.push((column_desc.short_name.clone(), index, column_desc.clone()));
+            }
+        }
+
+        Ok(columns)
+    }
+
+    /// `a SEMI JOIN b` or `a ANTI JOIN b`, the tables of `b` only filter the rows of `a`,
+    /// so they are output-invisible, only the join condition can reference their columns.
+    pub fn semi_join(&self, joined_schema: &JoinedSchema) -> Result<JoinedSchema> {
+        let mut right = joined_schema.clone();
+        for table_desc in &mut right.tables_long_name_columns {
+            table_desc.set_output_visible(false);
+        }
+
+        self.join(&right)
+    }
+
+    /// The same schema with the output-invisible tables visible, as the join condition sees it.
+    /// The names of the columns are kept, so they are the same as in the schema.
+    pub fn to_output_visible(&self) -> JoinedSchema {
+        let mut schema = self.clone();
+        for table_desc in &mut schema.tables_long_name_columns {
+            if table_desc.is_output_visible() {
+                continue;
+            }
+
+            table_desc.set_output_visible(true);
+            for column_desc in table_desc.get_columns_desc() {
+                if !column_desc.is_ambiguity {
+                    let short_name = column_desc.short_name.clone();
+                    schema
+                        .short_name_columns
+                        .insert(short_name, column_desc.clone());
+                }
+            }
+        }
+
+        schema
+    }
+
+    pub fn has_output_invisible(&self) -> bool {
+        !self
+            .tables_long_name_columns
+            .iter()
+            .all(JoinedTableDesc::is_output_visible)
+    }
+
+    /// Join the schema as the next relation of a comma separated FROM list.
+    pub fn implicit_join(&self, joined_schema: &JoinedSchema) -> Result<JoinedSchema> {
+        let offset = self.tables_long_name_columns.len();
+        let mut implicit_joined = match self.implicit_joined.is_empty() {
+            true => vec![0..offset],
+            false => self.implicit_joined.clone(),
+        };
+        implicit_joined.push(offset..offset + joined_schema.tables_long_name_columns.len());
+
+        let mut schema = self.join(joined_schema)?;
+        schema.implicit_joined = implicit_joined;
+        Ok(schema)
+    }
+
+    /// The same schema with all the columns nullable, such as the outer side of an outer join.
+    pub fn to_nullable(&self) -> JoinedSchema {
+        let mut tables_desc = self.tables_long_name_columns.clone();
+        for table_desc in &mut tables_desc {
+            for column_desc in table_desc.get_columns_desc_mut() {
+                column_desc.nullable = true;
+            }
+        }
+
+        let mut schema = Self::from_tables_desc(tables_desc, self.join_conditions.clone());
+        schema.natural_join_keys = self.natural_join_keys.clone();
+        schema
+    }
+
+    /// Attach the ON condition of the join that produced this schema,
+    /// all the tables so far are visible to it.
+    pub fn with_join_condition(mut self, expr: Expression) -> JoinedSchema {
+        self.join_conditions.push(JoinCondition {
+            tables: 0..self.tables_long_name_columns.len(),
+            expr,
+        });
+        self
+    }
+
+    /// The schema of the given tables only, as if they were the whole FROM clause.
+    pub fn sub_schema(&self, tables: &Range<usize>) -> Result<JoinedSchema> {
+        match self.tables_long_name_columns.get(tables.clone()) {
+            Some(tables_desc) => Ok(Self::from_tables_desc(tables_desc.to_vec(), vec![])),
+            None => Err(ErrorCode::LogicalError(format!(
+                "Logical error: tables {:?} out of the joined schema, this is a bug.",
+                tables
+            ))),
+        }
     }
 }
 
@@ -154,6 +581,10 @@ impl Debug for JoinedSchema {
             debug_struct.field("ambiguity_names", &ambiguity_names);
         }
 
+        if !self.natural_join_keys.is_empty() {
+            debug_struct.field("natural_join_keys", &self.natural_join_keys);
+        }
+
         debug_struct.finish()
     }
 }
@@ -164,16 +595,35 @@ pub enum JoinedTableDesc {
         table: Arc<dyn Table>,
         name_parts: Vec<String>,
         columns_desc: Vec<JoinedColumnDesc>,
+        // The pseudo columns of the table, they are not in `columns_desc` so the wildcard
+        // doesn't expand them, and are only resolved if referenced by name.
+        pseudo_columns_desc: Vec<JoinedColumnDesc>,
+        sample: Option<TableSample>,
+        pivot: Option<TablePivot>,
+        output_visible: bool,
     },
     Subquery {
         state: Box<QueryAnalyzeState>,
         name_parts: Vec<String>,
         columns_desc: Vec<JoinedColumnDesc>,
+        pivot: Option<TablePivot>,
+        output_visible: bool,
+    },
+    Values {
+        rows: Vec<Vec<Expression>>,
+        name_parts: Vec<String>,
+        columns_desc: Vec<JoinedColumnDesc>,
+        pivot: Option<TablePivot>,
+        output_visible: bool,
     },
 }
 
 impl JoinedTableDesc {
-    pub fn from_table(table: Arc<dyn Table>, prefix: Vec<String>) -> JoinedTableDesc {
+    pub fn from_table(
+        table: Arc<dyn Table>,
+        prefix: Vec<String>,
+        sample: Option<TableSample>,
+    ) -> JoinedTableDesc {
         let schema = table.schema();
         let mut columns_desc = Vec::with_capacity(schema.fields().len());
 
@@ -181,13 +631,131 @@ impl JoinedTableDesc {
             columns_desc.push(JoinedColumnDesc::from_field(data_field, false));
         }
 
+        let pseudo_columns_desc = table
+            .pseudo_columns()
+            .iter()
+            .map(|data_field| JoinedColumnDesc::from_field(data_field, false))
+            .collect();
+
         JoinedTableDesc::Table {
             table,
             columns_desc,
+            pseudo_columns_desc,
             name_parts: prefix,
+            sample,
+            pivot: None,
+            output_visible: true,
+        }
+    }
+
+    /// Append the 1-based row number column of `WITH ORDINALITY`.
+    pub fn with_ordinality(mut self, name: &str) -> Result<JoinedTableDesc> {
+        if self.get_columns_desc().iter().any(|c| c.short_name == name) {
+            return Err(ErrorCode::SyntaxException(format!(
+                "Ordinality column {} conflicts with a column of {}",
+                name,
+                self.get_name_parts().join(".")
+            )));
         }
+
+        let ordinality = JoinedColumnDesc::create(name, DataType::UInt64, false);
+        match &mut self {
+            JoinedTableDesc::Table { columns_desc, .. } => columns_desc.push(ordinality),
+            JoinedTableDesc::Subquery { columns_desc, .. } => columns_desc.push(ordinality),
+            JoinedTableDesc::Values { columns_desc, .. } => columns_desc.push(ordinality),
+        };
+
+        Ok(self)
     }
 
+    /// Replace the aggregated columns and the `FOR` column by one column per `IN` value,
+    /// the other columns are the implicit grouping keys of `PIVOT`.
+    pub fn with_pivot(mut self, pivot: TablePivot) -> Result<JoinedTableDesc> {
+        let table_name = self.get_name_parts().join(".");
+        let columns_desc = self.get_columns_desc();
+        let input_schema = DataSchemaRefExt::create(
+            columns_desc
+                .iter()
+                .map(|c| DataField::new(&c.short_name, c.data_type.clone(), c.nullable))
+                .collect(),
+        );
+
+        if !matches!(pivot.aggregate, Expression::AggregateFunction { .. }) {
+            return Err(ErrorCode::SyntaxException(format!(
+                "PIVOT expects an aggregate function, but got {:?}",
+                pivot.aggregate
+            )));
+        }
+
+        let mut pivoted_columns = find_column_exprs(std::slice::from_ref(&pivot.aggregate))
+            .iter()
+            .map(Expression::column_name)
+            .collect::<Vec<_>>();
+        pivoted_columns.push(pivot.pivot_column.clone());
+
+        for column in &pivoted_columns {
+            if !columns_desc.iter().any(|c| &c.short_name == column) {
+                return Err(ErrorCode::UnknownColumn(format!(
+                    "Unknown column {} in PIVOT of {}",
+                    column, table_name
+                )));
+            }
+        }
+
+        let value_type = pivot.aggregate.to_data_type(&input_schema)?;
+        let mut pivot_columns_desc = columns_desc
+            .iter()
+            .filter(|c| !pivoted_columns.contains(&c.short_name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // A group without the value has no row to aggregate, so the value columns are nullable.
+        for value in &pivot.values {
+            if pivot_columns_desc.iter().any(|c| &c.short_name == value) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "PIVOT value {} conflicts with a column of {}",
+                    value, table_name
+                )));
+            }
+
+            pivot_columns_desc.push(JoinedColumnDesc::create(value, value_type.clone(), true));
+        }
+
+        match &mut self {
+            JoinedTableDesc::Table {
+                columns_desc,
+                pivot: table_pivot,
+                ..
+            }
+            | JoinedTableDesc::Subquery {
+                columns_desc,
+                pivot: table_pivot,
+                ..
+            }
+            | JoinedTableDesc::Values {
+                columns_desc,
+                pivot: table_pivot,
+                ..
+            } => {
+                *columns_desc = pivot_columns_desc;
+                *table_pivot = Some(pivot);
+            }
+        };
+
+        // The pivoted rows are aggregated, they have no pseudo columns of the table.
+        if let JoinedTableDesc::Table {
+            pseudo_columns_desc,
+            ..
+        } = &mut self
+        {
+            pseudo_columns_desc.clear();
+        }
+
+        Ok(self)
+    }
+
+    /// The columns are the output of the subquery, which is its projection. The columns of
+    /// the tables inside the subquery are not visible to the outer query.
     pub fn from_subquery(state: Box<QueryAnalyzeState>, prefix: Vec<String>) -> JoinedTableDesc {
         let schema = state.finalize_schema.clone();
         let mut columns_desc = Vec::with_capacity(schema.fields().len());
@@ -200,13 +768,78 @@ impl JoinedTableDesc {
             state,
             columns_desc,
             name_parts: prefix,
+            pivot: None,
+            output_visible: true,
+        }
+    }
+
+    /// The columns are named by the column aliases, or `column1`, `column2`, ... without them,
+    /// each one is typed by the common type of its values in all the rows.
+    pub fn from_values(
+        rows: Vec<Vec<Expression>>,
+        prefix: Vec<String>,
+        column_aliases: Vec<String>,
+    ) -> Result<JoinedTableDesc> {
+        let arity = rows.first().map(|row| row.len()).unwrap_or(0);
+        if let Some(row) = rows.iter().find(|row| row.len() != arity) {
+            return Err(ErrorCode::SyntaxException(format!(
+                "VALUES rows must have the same number of columns, but got {} and {}",
+                arity,
+                row.len()
+            )));
+        }
+
+        let names = match column_aliases.is_empty() {
+            true => (1..=arity).map(|i| format!("column{}", i)).collect(),
+            false if column_aliases.len() == arity => column_aliases,
+            false => {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "VALUES {} has {} columns, but {} column aliases are specified",
+                    prefix.join("."),
+                    arity,
+                    column_aliases.len()
+                )));
+            }
+        };
+
+        let empty_schema = DataSchemaRefExt::create(vec![]);
+        let mut columns_desc = Vec::with_capacity(arity);
+        for (index, name) in names.iter().enumerate() {
+            if names[..index].contains(name) {
+                return Err(ErrorCode::SyntaxException(format!(
+                    "Duplicate column alias {} of VALUES {}",
+                    name,
+                    prefix.join(".")
+                )));
+            }
+
+            let mut data_type = DataType::Null;
+            let mut nullable = false;
+            for row in &rows {
+                let value_type = row[index].to_data_type(&empty_schema)?;
+                nullable |= value_type == DataType::Null;
+                data_type = merge_types(&data_type, &value_type).map_err(|cause| {
+                    cause.add_message_back(format!(" (while in VALUES column {})", name))
+                })?;
+            }
+
+            columns_desc.push(JoinedColumnDesc::create(name, data_type, nullable));
         }
+
+        Ok(JoinedTableDesc::Values {
+            rows,
+            columns_desc,
+            name_parts: prefix,
+            pivot: None,
+            output_visible: true,
+        })
     }
 
     pub fn get_name_parts(&self) -> &[String] {
         match self {
             JoinedTableDesc::Table { name_parts, .. } => name_parts,
             JoinedTableDesc::Subquery { name_parts, .. } => name_parts,
+            JoinedTableDesc::Values { name_parts, .. } => name_parts,
         }
     }
 
@@ -214,6 +847,107 @@ impl JoinedTableDesc {
         match self {
             JoinedTableDesc::Table { columns_desc, .. } => columns_desc,
             JoinedTableDesc::Subquery { columns_desc, .. } => columns_desc,
+            JoinedTableDesc::Values { columns_desc, .. } => columns_desc,
+        }
+    }
+
+    /// The pseudo columns, such as `_row_id`, see [`Table::pseudo_columns`].
+    pub fn get_pseudo_columns_desc(&self) -> &[JoinedColumnDesc] {
+        match self {
+            JoinedTableDesc::Table {
+                pseudo_columns_desc,
+                ..
+            } => pseudo_columns_desc,
+            JoinedTableDesc::Subquery { .. } | JoinedTableDesc::Values { .. } => &[],
+        }
+    }
+
+    fn get_columns_desc_mut(&mut self) -> &mut [JoinedColumnDesc] {
+        match self {
+            JoinedTableDesc::Table { columns_desc, .. } => columns_desc,
+            JoinedTableDesc::Subquery { columns_desc, .. } => columns_desc,
+            JoinedTableDesc::Values { columns_desc, .. } => columns_desc,
+        }
+    }
+
+    pub fn get_sample(&self) -> Option<&TableSample> {
+        match self {
+            JoinedTableDesc::Table { sample, .. } => sample.as_ref(),
+            JoinedTableDesc::Subquery { .. } | JoinedTableDesc::Values { .. } => None,
+        }
+    }
+
+    pub fn get_pivot(&self) -> Option<&TablePivot> {
+        match self {
+            JoinedTableDesc::Table { pivot, .. } => pivot.as_ref(),
+            JoinedTableDesc::Subquery { pivot, .. } => pivot.as_ref(),
+            JoinedTableDesc::Values { pivot, .. } => pivot.as_ref(),
+        }
+    }
+
+    /// Whether the columns are part of the output, false for the right side of a semi-join.
+    pub fn is_output_visible(&self) -> bool {
+        match self {
+            JoinedTableDesc::Table { output_visible, .. } => *output_visible,
+            JoinedTableDesc::Subquery { output_visible, .. } => *output_visible,
+            JoinedTableDesc::Values { output_visible, .. } => *output_visible,
+        }
+    }
+
+    fn set_output_visible(&mut self, visible: bool) {
+        match self {
+            JoinedTableDesc::Table { output_visible, .. } => *output_visible = visible,
+            JoinedTableDesc::Subquery { output_visible, .. } => *output_visible = visible,
+            JoinedTableDesc::Values { output_visible, .. } => *output_visible = visible,
+        }
+    }
+}
+
+/// The `PIVOT` clause of a relation, such as `PIVOT (SUM(amt) FOR quarter IN ('Q1', 'Q2'))`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TablePivot {
+    pub aggregate: Expression,
+    pub pivot_column: String,
+    /// The `IN` values, each one is an output column in the same order.
+    pub values: Vec<String>,
+}
+
+/// The `TABLESAMPLE` clause of a base table, such as `TABLESAMPLE BERNOULLI (10) SEED (42)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableSample {
+    pub method: TableSampleMethod,
+    pub size: TableSampleSize,
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableSampleMethod {
+    Bernoulli,
+    System,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableSampleSize {
+    /// Percentage of the rows, in `[0, 100]`.
+    Fraction(f64),
+    /// Fixed number of rows.
+    Rows(u64),
+}
+
+impl TableSample {
+    pub fn validate(&self) -> Result<()> {
+        match (&self.method, &self.size) {
+            (TableSampleMethod::Bernoulli, TableSampleSize::Fraction(fraction))
+            | (TableSampleMethod::System, TableSampleSize::Fraction(fraction)) => {
+                match (0.0..=100.0).contains(fraction) {
+                    true => Ok(()),
+                    false => Err(ErrorCode::BadArguments(format!(
+                        "TABLESAMPLE fraction must be in [0, 100], but got {}",
+                        fraction
+                    ))),
+                }
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -224,6 +958,8 @@ pub struct JoinedColumnDesc {
     pub data_type: DataType,
     pub nullable: bool,
     pub is_ambiguity: bool,
+    // The duplicate of a NATURAL JOIN key, the key is output by the column of the other side.
+    pub is_merged: bool,
 }
 
 impl JoinedColumnDesc {
@@ -233,6 +969,7 @@ impl JoinedColumnDesc {
             data_type: field.data_type().clone(),
             nullable: field.is_nullable(),
             is_ambiguity,
+            is_merged: false,
         }
     }
 
@@ -242,6 +979,7 @@ impl JoinedColumnDesc {
             data_type,
             nullable,
             is_ambiguity: false,
+            is_merged: false,
         }
     }
 }
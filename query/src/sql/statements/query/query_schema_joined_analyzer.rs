@@ -12,15 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use common_exception::ErrorCode;
 use common_exception::Result;
+use common_planners::Expression;
+use common_planners::ExpressionVisitor;
+use common_planners::Recursion;
+use sqlparser::ast::Cte;
+use sqlparser::ast::Expr;
 use sqlparser::ast::FunctionArg;
 use sqlparser::ast::Ident;
+use sqlparser::ast::JoinConstraint;
 use sqlparser::ast::JoinOperator;
 use sqlparser::ast::ObjectName;
 use sqlparser::ast::Query;
+use sqlparser::ast::SetExpr;
 use sqlparser::ast::TableAlias;
 use sqlparser::ast::TableFactor;
 use sqlparser::ast::TableWithJoins;
@@ -29,41 +37,91 @@ use crate::catalogs::Catalog;
 use crate::sessions::QueryContext;
 use crate::sql::statements::analyzer_expr::ExpressionAnalyzer;
 use crate::sql::statements::query::query_schema_joined::JoinedSchema;
-use crate::sql::statements::AnalyzableStatement;
+use crate::sql::statements::query::query_schema_joined::TableSample;
+use crate::sql::statements::query::QualifiedRewriter;
 use crate::sql::statements::AnalyzedResult;
 use crate::sql::statements::DfQueryStatement;
 
 pub struct JoinedSchemaAnalyzer {
     ctx: Arc<QueryContext>,
+    // Scopes of the enclosing queries, visible to the LATERAL subqueries in FROM.
+    outer_scopes: Vec<JoinedSchema>,
 }
 
 impl JoinedSchemaAnalyzer {
     pub fn create(ctx: Arc<QueryContext>) -> JoinedSchemaAnalyzer {
-        JoinedSchemaAnalyzer { ctx }
+        Self::create_with_outer_scopes(ctx, vec![])
+    }
+
+    pub fn create_with_outer_scopes(
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+    ) -> JoinedSchemaAnalyzer {
+        JoinedSchemaAnalyzer { ctx, outer_scopes }
     }
 
     pub async fn analyze(&self, query: &DfQueryStatement) -> Result<JoinedSchema> {
         let mut analyzed_tables = Vec::new();
 
         // Build RPN for tables. because async function unsupported recursion
-        let rpn = RelationRPNBuilder::build(&query.from)?;
-        for rpn_item in &rpn {
+        let rpn = RelationRPNBuilder::build(&query.from, &query.table_samples)?;
+        for (index, rpn_item) in rpn.iter().enumerate() {
             match rpn_item {
-                RelationRPNItem::Join(_) => {
-                    return Err(ErrorCode::UnImplement("Unimplemented SELECT JOIN yet."));
+                RelationRPNItem::Join(join_operator) => {
+                    let right = analyzed_tables.pop();
+                    let left = analyzed_tables.pop();
+                    match (left, right) {
+                        (Some(left), Some(right)) => {
+                            let schema = self.join(&left, &right, join_operator);
+                            analyzed_tables.push(schema.await?);
+                        }
+                        _ => {
+                            return Err(ErrorCode::LogicalError(
+                                "Logical error: this is relation rpn bug.",
+                            ));
+                        }
+                    }
                 }
-                RelationRPNItem::Table(v) => {
-                    let schema = self.table(v);
-                    analyzed_tables.push(schema.await?);
+                RelationRPNItem::ImplicitJoin => {
+                    let right = analyzed_tables.pop();
+                    let left = analyzed_tables.pop();
+                    match (left, right) {
+                        (Some(left), Some(right)) => {
+                            analyzed_tables.push(left.implicit_join(&right)?);
+                        }
+                        _ => {
+                            return Err(ErrorCode::LogicalError(
+                                "Logical error: this is relation rpn bug.",
+                            ));
+                        }
+                    }
                 }
+                RelationRPNItem::Table(v) => match Self::find_cte(&query.ctes, &v.name) {
+                    Some(position) => {
+                        let schema = self.cte(&query.ctes, position, v);
+                        analyzed_tables.push(schema.await?);
+                    }
+                    None => {
+                        let schema = self.table(v);
+                        analyzed_tables.push(schema.await?);
+                    }
+                },
                 RelationRPNItem::TableFunction(v) => {
-                    let schema = self.table_function(v);
+                    // The sources on the stack are the ones before the function in FROM.
+                    let preceding = Self::preceding_scope(&analyzed_tables)?;
+                    let following = rpn[index + 1..].iter().filter_map(RelationRPNItem::name);
+                    let following = following.collect::<Vec<_>>();
+                    let schema = self.table_function(v, preceding, &following);
                     analyzed_tables.push(schema.await?);
                 }
                 RelationRPNItem::Derived(v) => {
                     let schema = self.subquery(v);
                     analyzed_tables.push(schema.await?);
                 }
+                RelationRPNItem::Values(v) => {
+                    let schema = self.values(v);
+                    analyzed_tables.push(schema.await?);
+                }
             }
         }
 
@@ -76,10 +134,62 @@ impl JoinedSchemaAnalyzer {
         Ok(analyzed_tables.remove(0))
     }
 
+    async fn join(
+        &self,
+        left: &JoinedSchema,
+        right: &JoinedSchema,
+        join_operator: &JoinOperator,
+    ) -> Result<JoinedSchema> {
+        // The columns of the side that may not match any row are null-extended.
+        let left = match join_operator {
+            JoinOperator::RightOuter(_) | JoinOperator::FullOuter(_) => left.to_nullable(),
+            _ => left.clone(),
+        };
+        let right = match join_operator {
+            JoinOperator::LeftOuter(_) | JoinOperator::FullOuter(_) => right.to_nullable(),
+            _ => right.clone(),
+        };
+
+        let joined_schema = left.join(&right)?;
+        let join_constraint = match join_operator {
+            JoinOperator::Inner(constraint)
+            | JoinOperator::LeftOuter(constraint)
+            | JoinOperator::RightOuter(constraint)
+            | JoinOperator::FullOuter(constraint) => constraint,
+            _ => return Ok(joined_schema),
+        };
+
+        match join_constraint {
+            JoinConstraint::On(expr) => {
+                let outer_scopes = self.outer_scopes.clone();
+                let analyzer =
+                    ExpressionAnalyzer::create_with_outer_scopes(self.ctx.clone(), outer_scopes);
+                let condition = analyzer.analyze(expr).await?;
+                Ok(joined_schema.with_join_condition(condition))
+            }
+            // The key of RIGHT JOIN is output by the right side, whose rows are all kept.
+            JoinConstraint::Natural => match join_operator {
+                JoinOperator::FullOuter(_) => Err(ErrorCode::UnImplement(
+                    "NATURAL FULL JOIN is not supported yet",
+                )),
+                JoinOperator::RightOuter(_) => left.natural_join(&right, true),
+                _ => left.natural_join(&right, false),
+            },
+            JoinConstraint::Using(_) => {
+                Err(ErrorCode::UnImplement("JOIN USING is not supported yet"))
+            }
+            JoinConstraint::None => Ok(joined_schema),
+        }
+    }
+
     async fn subquery(&self, v: &DerivedRPNItem) -> Result<JoinedSchema> {
         let subquery = &(*v.subquery);
         let subquery = DfQueryStatement::try_from(subquery.clone())?;
-        match subquery.analyze(self.ctx.clone()).await? {
+        let outer_scopes = self.outer_scopes.clone();
+        match subquery
+            .analyze_derived(self.ctx.clone(), outer_scopes, v.lateral)
+            .await?
+        {
             AnalyzedResult::SelectQuery(state) => match &v.alias {
                 None => JoinedSchema::from_subquery(state, Vec::new()),
                 Some(alias) => {
@@ -93,24 +203,118 @@ impl JoinedSchemaAnalyzer {
         }
     }
 
+    async fn values(&self, item: &ValuesRPNItem) -> Result<JoinedSchema> {
+        let outer_scopes = self.outer_scopes.clone();
+        let analyzer = ExpressionAnalyzer::create_with_outer_scopes(self.ctx.clone(), outer_scopes);
+
+        let mut rows = Vec::with_capacity(item.rows.len());
+        for row in &item.rows {
+            let mut values = Vec::with_capacity(row.len());
+            for expr in row {
+                values.push(analyzer.analyze(expr).await?);
+            }
+            rows.push(values);
+        }
+
+        match &item.alias {
+            None => JoinedSchema::from_values(rows, Vec::new(), Vec::new()),
+            Some(alias) => {
+                let name_prefix = vec![alias.name.value.clone()];
+                let columns = alias.columns.iter().map(|c| c.value.clone()).collect();
+                JoinedSchema::from_values(rows, name_prefix, columns)
+            }
+        }
+    }
+
+    fn find_cte(ctes: &[Cte], name: &ObjectName) -> Option<usize> {
+        match name.0.len() {
+            1 => ctes
+                .iter()
+                .position(|cte| cte.alias.name.value == name.0[0].value),
+            _ => None,
+        }
+    }
+
+    async fn cte(
+        &self,
+        ctes: &[Cte],
+        position: usize,
+        item: &TableRPNItem,
+    ) -> Result<JoinedSchema> {
+        let cte = &ctes[position];
+        if !cte.alias.columns.is_empty() {
+            return Err(ErrorCode::UnImplement(
+                "CTE column list is not yet supported",
+            ));
+        }
+
+        if item.sample.is_some() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "TABLESAMPLE is only supported on base tables, but '{}' is a CTE",
+                cte.alias.name.value
+            )));
+        }
+
+        // The CTEs before it are visible in the CTE query, but not itself and the ones after.
+        let mut subquery = DfQueryStatement::try_from(cte.query.clone())?;
+        let mut visible_ctes = ctes[..position].to_vec();
+        visible_ctes.extend(subquery.ctes);
+        subquery.ctes = visible_ctes;
+
+        let outer_scopes = self.outer_scopes.clone();
+        match subquery
+            .analyze_derived(self.ctx.clone(), outer_scopes, false)
+            .await?
+        {
+            AnalyzedResult::SelectQuery(state) => match &item.alias {
+                None => JoinedSchema::from_subquery(state, vec![cte.alias.name.value.clone()]),
+                Some(alias) => {
+                    let name_prefix = vec![alias.name.value.clone()];
+                    JoinedSchema::from_subquery(state, name_prefix)
+                }
+            },
+            _ => Err(ErrorCode::LogicalError(
+                "Logical error, CTE analyzed data must be SelectQuery, it's a bug.",
+            )),
+        }
+    }
+
     async fn table(&self, item: &TableRPNItem) -> Result<JoinedSchema> {
         // TODO(Winter): await query_context.get_table
         let (database, table) = self.resolve_table(&item.name)?;
         let read_table = self.ctx.get_table(&database, &table).await?;
 
+        let sample = item.sample.clone();
         match &item.alias {
             None => {
                 let name_prefix = vec![database, table];
-                JoinedSchema::from_table(read_table, name_prefix)
+                JoinedSchema::from_sampled_table(read_table, name_prefix, sample)
             }
             Some(table_alias) => {
                 let name_prefix = vec![table_alias.name.value.clone()];
-                JoinedSchema::from_table(read_table, name_prefix)
+                JoinedSchema::from_sampled_table(read_table, name_prefix, sample)
             }
         }
     }
 
-    async fn table_function(&self, item: &TableFunctionRPNItem) -> Result<JoinedSchema> {
+    fn preceding_scope(analyzed_tables: &[JoinedSchema]) -> Result<Option<JoinedSchema>> {
+        let mut preceding: Option<JoinedSchema> = None;
+        for analyzed_table in analyzed_tables {
+            preceding = Some(match preceding {
+                None => analyzed_table.clone(),
+                Some(preceding) => preceding.join(analyzed_table)?,
+            });
+        }
+
+        Ok(preceding)
+    }
+
+    async fn table_function(
+        &self,
+        item: &TableFunctionRPNItem,
+        preceding: Option<JoinedSchema>,
+        following: &[String],
+    ) -> Result<JoinedSchema> {
         if item.name.0.len() >= 2 {
             return Result::Err(ErrorCode::BadArguments(
                 "Currently table can't have arguments",
@@ -128,13 +332,76 @@ impl JoinedSchemaAnalyzer {
             });
         }
 
+        let references = ColumnReferenceCollector { references: vec![] };
+        let references = table_args
+            .iter()
+            .try_fold(references, |collector, arg| arg.accept(collector))?
+            .references;
+
+        if !references.is_empty() {
+            table_args =
+                self.resolve_lateral_args(item, &table_args, &references, preceding, following)?;
+        }
+
         let catalog = self.ctx.get_catalog();
         let table_function = catalog.get_table_function(&table_name, Some(table_args))?;
+        let table = table_function.as_table();
+
+        // The ordinality column is named by the column alias after the function columns,
+        // such as `idx` in `UNNEST(arr) WITH ORDINALITY AS t(val, idx)`.
+        let ordinality = match item.with_ordinality {
+            false => None,
+            true => {
+                let columns_count = table.schema().fields().len();
+                let alias_columns = item.alias.as_ref().map(|alias| &alias.columns);
+                match alias_columns.and_then(|columns| columns.get(columns_count)) {
+                    None => Some(String::from("ordinality")),
+                    Some(column) => Some(column.value.clone()),
+                }
+            }
+        };
+
         match &item.alias {
-            None => JoinedSchema::from_table(table_function.as_table(), Vec::new()),
+            None => JoinedSchema::from_table_function(table, Vec::new(), ordinality),
             Some(table_alias) => {
                 let name_prefix = vec![table_alias.name.value.clone()];
-                JoinedSchema::from_table(table_function.as_table(), name_prefix)
+                JoinedSchema::from_table_function(table, name_prefix, ordinality)
+            }
+        }
+    }
+
+    // The arguments of a table function may reference the columns of the sources before it
+    // in FROM, e.g. `FROM t, explode(t.arr) AS e`, they are resolved as the lateral columns.
+    fn resolve_lateral_args(
+        &self,
+        item: &TableFunctionRPNItem,
+        args: &[Expression],
+        references: &[Vec<String>],
+        preceding: Option<JoinedSchema>,
+        following: &[String],
+    ) -> Result<Vec<Expression>> {
+        for reference in references {
+            if reference.len() >= 2 && following.contains(&reference[reference.len() - 2]) {
+                return Err(ErrorCode::UnknownTable(format!(
+                    "Table function {} references {}, but table {} appears after it in FROM",
+                    item.name,
+                    reference.join("."),
+                    reference[reference.len() - 2]
+                )));
+            }
+        }
+
+        match preceding {
+            None => Err(ErrorCode::UnknownColumn(format!(
+                "Unknown column {} in arguments of table function {}",
+                references[0].join("."),
+                item.name
+            ))),
+            Some(preceding) => {
+                let outer_scopes = self.outer_scopes.clone();
+                let ctx = self.ctx.clone();
+                QualifiedRewriter::create_with_outer_scopes(outer_scopes, preceding, ctx)
+                    .resolve_exprs(args)
             }
         }
     }
@@ -154,38 +421,104 @@ impl JoinedSchemaAnalyzer {
 struct TableRPNItem {
     name: ObjectName,
     alias: Option<TableAlias>,
+    sample: Option<TableSample>,
 }
 
 struct DerivedRPNItem {
+    lateral: bool,
     subquery: Box<Query>,
     alias: Option<TableAlias>,
 }
 
+struct ValuesRPNItem {
+    rows: Vec<Vec<Expr>>,
+    alias: Option<TableAlias>,
+}
+
 struct TableFunctionRPNItem {
     name: ObjectName,
     args: Vec<FunctionArg>,
     alias: Option<TableAlias>,
+    with_ordinality: bool,
 }
 
 enum RelationRPNItem {
     Table(TableRPNItem),
     TableFunction(TableFunctionRPNItem),
     Derived(DerivedRPNItem),
+    // `(VALUES (1, 'a'), (2, 'b')) AS v(id, name)`
+    Values(ValuesRPNItem),
     Join(JoinOperator),
+    // The comma in `FROM a, b`, a cross join without any join syntax.
+    ImplicitJoin,
+}
+
+impl RelationRPNItem {
+    // The name the columns of the relation are qualified with.
+    fn name(&self) -> Option<String> {
+        match self {
+            RelationRPNItem::Table(item) => match &item.alias {
+                Some(alias) => Some(alias.name.value.clone()),
+                None => item.name.0.last().map(|ident| ident.value.clone()),
+            },
+            RelationRPNItem::TableFunction(item) => {
+                item.alias.as_ref().map(|alias| alias.name.value.clone())
+            }
+            RelationRPNItem::Derived(item) => {
+                item.alias.as_ref().map(|alias| alias.name.value.clone())
+            }
+            RelationRPNItem::Values(item) => {
+                item.alias.as_ref().map(|alias| alias.name.value.clone())
+            }
+            RelationRPNItem::Join(_) | RelationRPNItem::ImplicitJoin => None,
+        }
+    }
+}
+
+struct ColumnReferenceCollector {
+    references: Vec<Vec<String>>,
+}
+
+impl ExpressionVisitor for ColumnReferenceCollector {
+    fn pre_visit(mut self, expr: &Expression) -> Result<Recursion<Self>> {
+        match expr {
+            Expression::Column(name) => self.references.push(vec![name.clone()]),
+            Expression::QualifiedColumn(names) => self.references.push(names.clone()),
+            _ => {}
+        }
+
+        Ok(Recursion::Continue(self))
+    }
 }
 
 struct RelationRPNBuilder {
     rpn: Vec<RelationRPNItem>,
+    // Samples not yet attached to a relation, keyed by table alias or name.
+    samples: HashMap<String, TableSample>,
 }
 
 impl RelationRPNBuilder {
-    pub fn build(exprs: &[TableWithJoins]) -> Result<Vec<RelationRPNItem>> {
-        let mut builder = RelationRPNBuilder { rpn: Vec::new() };
+    pub fn build(
+        exprs: &[TableWithJoins],
+        samples: &HashMap<String, TableSample>,
+    ) -> Result<Vec<RelationRPNItem>> {
+        let mut builder = RelationRPNBuilder {
+            rpn: Vec::new(),
+            samples: samples.clone(),
+        };
+
         match exprs.is_empty() {
             true => builder.visit_dummy_table(),
             false => builder.visit(exprs)?,
         }
 
+        if let Some(name) = builder.samples.keys().next() {
+            return Err(ErrorCode::SyntaxException(format!(
+                "TABLESAMPLE refers to unknown table '{}'",
+                name
+            )));
+        }
+
         Ok(builder.rpn)
     }
 
@@ -193,6 +526,7 @@ impl RelationRPNBuilder {
         self.rpn.push(RelationRPNItem::Table(TableRPNItem {
             name: ObjectName(vec![Ident::new("system"), Ident::new("one")]),
             alias: None,
+            sample: None,
         }));
     }
 
@@ -204,8 +538,7 @@ impl RelationRPNBuilder {
                 }
                 false => {
                     self.visit_joins(expr)?;
-                    self.rpn
-                        .push(RelationRPNItem::Join(JoinOperator::CrossJoin));
+                    self.rpn.push(RelationRPNItem::ImplicitJoin);
                 }
             }
         }
@@ -249,11 +582,25 @@ impl RelationRPNBuilder {
                 subquery,
                 alias,
             } => {
-                if *lateral {
-                    return Err(ErrorCode::UnImplement("Cannot SELECT LATERAL subquery."));
+                if let Some(alias) = alias {
+                    if self.samples.contains_key(&alias.name.value) {
+                        return Err(ErrorCode::SyntaxException(format!(
+                            "TABLESAMPLE is only supported on base tables, but '{}' is a subquery",
+                            alias.name.value
+                        )));
+                    }
+                }
+
+                if let SetExpr::Values(values) = &subquery.body {
+                    self.rpn.push(RelationRPNItem::Values(ValuesRPNItem {
+                        rows: values.0.clone(),
+                        alias: alias.clone(),
+                    }));
+                    return Ok(());
                 }
 
                 self.rpn.push(RelationRPNItem::Derived(DerivedRPNItem {
+                    lateral: *lateral,
                     subquery: subquery.clone(),
                     alias: alias.clone(),
                 }));
@@ -267,13 +614,35 @@ impl RelationRPNBuilder {
     }
 
     fn visit_table(&mut self, name: &ObjectName, alias: &Option<TableAlias>) -> Result<()> {
+        let sample = self.take_sample(name, alias);
         self.rpn.push(RelationRPNItem::Table(TableRPNItem {
             name: name.clone(),
             alias: alias.clone(),
+            sample,
         }));
         Ok(())
     }
 
+    fn take_sample(
+        &mut self,
+        name: &ObjectName,
+        alias: &Option<TableAlias>,
+    ) -> Option<TableSample> {
+        match alias {
+            Some(alias) => self.samples.remove(&alias.name.value),
+            None => {
+                let full_name = name.0.iter().map(|ident| ident.value.clone());
+                let full_name = full_name.collect::<Vec<_>>().join(".");
+                let short_name = name.0.last().map(|ident| ident.value.clone());
+
+                match self.samples.remove(&full_name) {
+                    Some(sample) => Some(sample),
+                    None => short_name.and_then(|name| self.samples.remove(&name)),
+                }
+            }
+        }
+    }
+
     fn visit_table_function(
         &mut self,
         name: &ObjectName,
@@ -285,6 +654,8 @@ impl RelationRPNBuilder {
                 name: name.clone(),
                 args: args.to_owned(),
                 alias: alias.clone(),
+                // TODO: the sqlparser doesn't produce `WITH ORDINALITY` yet
+                with_ordinality: false,
             }));
         Ok(())
     }
@@ -17,6 +17,9 @@ use common_exception::ErrorCode;
 use common_exception::Result;
 
 use crate::sql::statements::query::query_schema_joined_analyzer::JoinedSchemaAnalyzer;
+use crate::sql::statements::query::TableSample;
+use crate::sql::statements::query::TableSampleMethod;
+use crate::sql::statements::query::TableSampleSize;
 use crate::sql::DfParser;
 use crate::sql::DfStatement;
 use crate::tests::try_create_context;
@@ -49,6 +52,11 @@ async fn test_joined_schema_analyzer() -> Result<()> {
             query: "SELECT * FROM (SELECT * FROM system.databases)",
             expect: "QuerySchema { short_names: [\"name\"] }",
         },
+        TestCase {
+            name: "Join query",
+            query: "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: "QuerySchema { short_names: [\"database\", \"engine\"], ambiguity_names: [[\"d\", \"name\"], [\"t\", \"name\"]] }",
+        },
     ];
 
     for test_case in &tests {
@@ -73,3 +81,420 @@ async fn test_joined_schema_analyzer() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_outer_join_nullability() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Inner join",
+            query: "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name",
+            expect: "d.name: false, database: false, t.name: false, engine: false",
+        },
+        TestCase {
+            name: "Left outer join",
+            query: "SELECT * FROM system.databases AS d LEFT OUTER JOIN system.tables AS t ON d.name = t.name",
+            expect: "d.name: false, database: true, t.name: true, engine: true",
+        },
+        TestCase {
+            name: "Right outer join",
+            query: "SELECT * FROM system.databases AS d RIGHT OUTER JOIN system.tables AS t ON d.name = t.name",
+            expect: "d.name: true, database: false, t.name: false, engine: false",
+        },
+        TestCase {
+            name: "Full outer join",
+            query: "SELECT * FROM system.databases AS d FULL OUTER JOIN system.tables AS t ON d.name = t.name",
+            expect: "d.name: true, database: true, t.name: true, engine: true",
+        },
+        TestCase {
+            name: "Inner join after left outer join",
+            query: "SELECT * FROM system.databases AS d LEFT OUTER JOIN system.tables AS t ON d.name = t.name INNER JOIN system.one AS o ON o.dummy = 1",
+            expect: "d.name: false, database: true, t.name: true, engine: true, dummy: false",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx);
+                let joined_schema = analyzer.analyze(&query).await?;
+                let data_schema = joined_schema.to_data_schema();
+                let actual = data_schema
+                    .fields()
+                    .iter()
+                    .map(|field| format!("{}: {}", field.name(), field.is_nullable()))
+                    .collect::<Vec<_>>();
+                assert_eq!(test_case.expect, actual.join(", "), "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_table_sample() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        sample_table: &'static str,
+        sample: TableSample,
+        expect: &'static str,
+    }
+
+    let bernoulli = |fraction: f64| TableSample {
+        method: TableSampleMethod::Bernoulli,
+        size: TableSampleSize::Fraction(fraction),
+        seed: None,
+    };
+
+    let tests = vec![
+        TestCase {
+            name: "Sample table by name",
+            query: "SELECT * FROM system.databases",
+            sample_table: "databases",
+            sample: bernoulli(10.0),
+            expect: "QuerySchema { short_names: [\"name\"] }, Some(TableSample { method: Bernoulli, size: Fraction(10.0), seed: None })",
+        },
+        TestCase {
+            name: "Sample table by alias",
+            query: "SELECT * FROM system.databases AS d",
+            sample_table: "d",
+            sample: TableSample {
+                method: TableSampleMethod::System,
+                size: TableSampleSize::Rows(100),
+                seed: Some(42),
+            },
+            expect: "QuerySchema { short_names: [\"name\"] }, Some(TableSample { method: System, size: Rows(100), seed: Some(42) })",
+        },
+        TestCase {
+            name: "Sample fraction out of range",
+            query: "SELECT * FROM system.databases",
+            sample_table: "databases",
+            sample: bernoulli(120.0),
+            expect: "TABLESAMPLE fraction must be in [0, 100], but got 120",
+        },
+        TestCase {
+            name: "Sample subquery",
+            query: "SELECT * FROM (SELECT name FROM system.databases) AS sub",
+            sample_table: "sub",
+            sample: bernoulli(10.0),
+            expect: "TABLESAMPLE is only supported on base tables, but 'sub' is a subquery",
+        },
+        TestCase {
+            name: "Sample unknown table",
+            query: "SELECT * FROM system.databases",
+            sample_table: "tables",
+            sample: bernoulli(10.0),
+            expect: "TABLESAMPLE refers to unknown table 'tables'",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(mut query) => {
+                query
+                    .table_samples
+                    .insert(test_case.sample_table.to_string(), test_case.sample.clone());
+
+                let analyzer = JoinedSchemaAnalyzer::create(ctx);
+                let actual = match analyzer.analyze(&query).await {
+                    Ok(schema) => format!(
+                        "{:?}, {:?}",
+                        schema,
+                        schema.get_tables_desc()[0].get_sample()
+                    ),
+                    Err(cause) => cause.message(),
+                };
+
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_lateral() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Subquery without outer reference",
+            query: "SELECT * FROM (SELECT name FROM system.databases)",
+            expect: "QuerySchema { short_names: [\"name\"] }",
+        },
+        TestCase {
+            name: "Subquery with outer reference",
+            query: "SELECT * FROM (SELECT value FROM system.databases)",
+            expect: "Unknown column value, missing FROM-clause entry, a subquery in FROM must be LATERAL to reference the outer query (while in analyze projection expr: value)",
+        },
+        TestCase {
+            name: "Subquery with qualified outer reference",
+            query: "SELECT * FROM (SELECT outer_query.value FROM system.databases)",
+            expect: "Unknown column outer_query.value, missing FROM-clause entry, a subquery in FROM must be LATERAL to reference the outer query (while in analyze projection expr: \"outer_query.value\")",
+        },
+        TestCase {
+            name: "Subquery with unknown column",
+            query: "SELECT * FROM (SELECT missing FROM system.databases)",
+            expect: "Unknown column missing (while in analyze projection expr: missing)",
+        },
+        TestCase {
+            name: "Lateral subquery with outer reference",
+            query: "SELECT * FROM LATERAL (SELECT outer_query.value FROM system.databases)",
+            expect: "Correlated subqueries are not yet supported in query plan",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+
+        let (mut statements, _) =
+            DfParser::parse_sql("SELECT * FROM system.settings AS outer_query")?;
+        let outer_scopes = match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
+                vec![analyzer.analyze(&query).await?]
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        };
+
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create_with_outer_scopes(ctx, outer_scopes);
+                let actual = match analyzer.analyze(&query).await {
+                    Ok(schema) => format!("{:?}", schema),
+                    Err(cause) => cause.message(),
+                };
+
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_lateral_table_function() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Literal arguments",
+            query: "SELECT * FROM system.tables AS t, numbers(10) AS n",
+            expect: "QuerySchema { short_names: [\"database\", \"name\", \"engine\", \"number\"] }",
+        },
+        // The column is resolved, but the numbers table function only accepts a literal.
+        TestCase {
+            name: "Lateral column of the preceding table",
+            query: "SELECT * FROM system.tables AS t, numbers(t.engine)",
+            expect: "Must have exactly one number argument for table function.numbers",
+        },
+        TestCase {
+            name: "Unknown lateral column",
+            query: "SELECT * FROM system.tables AS t, numbers(t.missing)",
+            expect: "Unknown column t.missing",
+        },
+        TestCase {
+            name: "Lateral column of the following table",
+            query: "SELECT * FROM numbers(t.engine), system.tables AS t",
+            expect:
+                "Table function numbers references t.engine, but table t appears after it in FROM",
+        },
+        TestCase {
+            name: "Lateral column without preceding table",
+            query: "SELECT * FROM numbers(engine)",
+            expect: "Unknown column engine in arguments of table function numbers",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx);
+                let actual = match analyzer.analyze(&query).await {
+                    Ok(joined_schema) => format!("{:?}", joined_schema),
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_values() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Values with column aliases",
+            query: "SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS v(id, name)",
+            expect: "QuerySchema { short_names: [\"id\", \"name\"] }",
+        },
+        TestCase {
+            name: "Values without column aliases",
+            query: "SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS v",
+            expect: "QuerySchema { short_names: [\"column1\", \"column2\"] }",
+        },
+        TestCase {
+            name: "Values joined with table",
+            query: "SELECT * FROM (VALUES ('default', 1)) AS v(database, n), system.tables",
+            expect: "QuerySchema { short_names: [\"n\", \"name\", \"engine\"], ambiguity_names: [[\"v\", \"database\"], [\"system\", \"tables\", \"database\"]] }",
+        },
+        TestCase {
+            name: "Values with null and number",
+            query: "SELECT * FROM (VALUES (NULL), (1), (1000)) AS v(id)",
+            expect: "QuerySchema { short_names: [\"id\"] }",
+        },
+        TestCase {
+            name: "Column aliases mismatch",
+            query: "SELECT * FROM (VALUES (1, 'a'), (2, 'b')) AS v(id)",
+            expect: "VALUES v has 2 columns, but 1 column aliases are specified",
+        },
+        TestCase {
+            name: "Rows with different number of columns",
+            query: "SELECT * FROM (VALUES (1, 'a'), (2)) AS v(id, name)",
+            expect: "VALUES rows must have the same number of columns, but got 2 and 1",
+        },
+        TestCase {
+            name: "Rows with different types",
+            query: "SELECT * FROM (VALUES (1), ('a')) AS v(id)",
+            expect: "Can't merge types from UInt8 and String (while in VALUES column id)",
+        },
+        TestCase {
+            name: "Duplicate column aliases",
+            query: "SELECT * FROM (VALUES (1, 'a')) AS v(id, id)",
+            expect: "Duplicate column alias id of VALUES v",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx);
+                let actual = match analyzer.analyze(&query).await {
+                    Ok(joined_schema) => format!("{:?}", joined_schema),
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_joined_schema_analyzer_natural_join() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Natural join",
+            query: "SELECT * FROM system.databases AS d NATURAL JOIN system.tables AS t",
+            expect: "QuerySchema { short_names: [\"name\", \"database\", \"engine\"], ambiguity_names: [[\"t\", \"name\"]], natural_join_keys: [\"name\"] }",
+        },
+        TestCase {
+            name: "Natural right join",
+            query: "SELECT * FROM system.databases AS d NATURAL RIGHT JOIN system.tables AS t",
+            expect: "QuerySchema { short_names: [\"database\", \"name\", \"engine\"], ambiguity_names: [[\"d\", \"name\"]], natural_join_keys: [\"name\"] }",
+        },
+        TestCase {
+            name: "Natural full join",
+            query: "SELECT * FROM system.databases AS d NATURAL FULL JOIN system.tables AS t",
+            expect: "NATURAL FULL JOIN is not supported yet",
+        },
+        TestCase {
+            name: "Natural join without common columns",
+            query: "SELECT * FROM system.databases AS d NATURAL JOIN system.one AS o",
+            expect: "NATURAL JOIN has no common columns",
+        },
+        TestCase {
+            name: "Natural join of columns with different types",
+            query: "SELECT * FROM (SELECT 1 AS name) AS n NATURAL JOIN system.databases AS d",
+            expect: "NATURAL JOIN column name has type UInt8 on the left side, but String on the right side",
+        },
+        TestCase {
+            name: "Natural join of ambiguous columns",
+            query: "SELECT * FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.name NATURAL JOIN system.settings AS s",
+            expect: "NATURAL JOIN column name is ambiguous on the left side",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let analyzer = JoinedSchemaAnalyzer::create(ctx);
+                let actual = match analyzer.analyze(&query).await {
+                    Ok(joined_schema) => format!("{:?}", joined_schema),
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
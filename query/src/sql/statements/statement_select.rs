@@ -12,80 +12,512 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use common_datablocks::DataBlock;
+use common_datavalues::merge_types;
 use common_datavalues::DataSchemaRefExt;
+use common_datavalues::DataType;
 use common_exception::ErrorCode;
 use common_exception::Result;
+use common_exception::ToErrorCode;
 use common_planners::expand_aggregate_arg_exprs;
 use common_planners::find_aggregate_exprs;
 use common_planners::find_aggregate_exprs_in_expr;
 use common_planners::rebase_expr;
+use common_planners::CastOnError;
+use common_planners::ExprRewriter;
 use common_planners::Expression;
 use common_planners::Extras;
 use common_tracing::tracing;
+use futures::future::BoxFuture;
+use sqlparser::ast::Cte;
 use sqlparser::ast::Expr;
+use sqlparser::ast::JoinConstraint;
+use sqlparser::ast::JoinOperator;
 use sqlparser::ast::Offset;
 use sqlparser::ast::OrderByExpr;
 use sqlparser::ast::SelectItem;
+use sqlparser::ast::SetOperator;
+use sqlparser::ast::TableFactor;
 use sqlparser::ast::TableWithJoins;
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::Token;
+use sqlparser::tokenizer::Tokenizer;
 
 use crate::catalogs::ToReadDataSourcePlan;
 use crate::sessions::QueryContext;
+use crate::sql::statements::analyzer_expr::ExpressionAnalyzer;
 use crate::sql::statements::analyzer_statement::QueryAnalyzeState;
+use crate::sql::statements::analyzer_value_expr::ValueExprAnalyzer;
+use crate::sql::statements::query::GroupingSpec;
 use crate::sql::statements::query::JoinedSchema;
 use crate::sql::statements::query::JoinedSchemaAnalyzer;
 use crate::sql::statements::query::JoinedTableDesc;
 use crate::sql::statements::query::QualifiedRewriter;
 use crate::sql::statements::query::QueryASTIR;
 use crate::sql::statements::query::QueryNormalizer;
+use crate::sql::statements::query::SetOperation;
+use crate::sql::statements::query::TableSample;
 use crate::sql::statements::AnalyzableStatement;
 use crate::sql::statements::AnalyzedResult;
 use crate::sql::statements::QueryRelation;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct DfQueryStatement {
+    // The non-recursive CTEs of `WITH`, each one can reference the ones before it.
+    pub ctes: Vec<Cte>,
     pub from: Vec<TableWithJoins>,
+    // TABLESAMPLE clauses keyed by table alias or name, not yet produced by the sqlparser.
+    pub table_samples: HashMap<String, TableSample>,
     pub projection: Vec<SelectItem>,
     pub selection: Option<Expr>,
     pub group_by: Vec<Expr>,
+    // GROUPING SETS, not yet produced by the sqlparser, filled by the caller for now.
+    pub grouping_sets: Vec<Vec<Expr>>,
     pub having: Option<Expr>,
+    // Not yet produced by the sqlparser, filled by the caller for now.
+    pub qualify: Option<Expr>,
+    // DISTINCT ON, not yet produced by the sqlparser, filled by the caller for now.
+    pub distinct_on: Vec<Expr>,
     pub order_by: Vec<OrderByExpr>,
+    // LIMIT, or the same limit of `TOP n` and `FETCH FIRST n ROWS`.
     pub limit: Option<Expr>,
+    // `WITH TIES` of TOP or FETCH, the rows tied with the last one by ORDER BY are kept.
+    pub limit_with_ties: bool,
     pub offset: Option<Offset>,
+    // Optimizer hints of `/*+ ... */`, such as `BROADCAST(b)`, not yet produced by the
+    // sqlparser, filled by the caller for now.
+    pub hints: Vec<Expr>,
+    // The UNION, INTERSECT and EXCEPT operands after the first SELECT, which is the query
+    // itself. ORDER BY, LIMIT and OFFSET apply to the result of the set operations.
+    pub set_operations: Vec<DfSetOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DfSetOperation {
+    pub op: SetOperator,
+    pub all: bool,
+    pub query: DfQueryStatement,
 }
 
 #[async_trait::async_trait]
 impl AnalyzableStatement for DfQueryStatement {
     #[tracing::instrument(level = "info", skip(self, ctx), fields(ctx.id = ctx.get_id().as_str()))]
     async fn analyze(&self, ctx: Arc<QueryContext>) -> Result<AnalyzedResult> {
-        let analyzer = JoinedSchemaAnalyzer::create(ctx.clone());
-        let joined_schema = analyzer.analyze(self).await?;
+        self.analyze_with_outer_scopes(ctx, vec![]).await
+    }
+}
+
+impl DfQueryStatement {
+    /// Analyze the query as a subquery, whose columns can be resolved in the outer scopes.
+    pub fn analyze_with_outer_scopes(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+    ) -> BoxFuture<'_, Result<AnalyzedResult>> {
+        self.analyze_with_scopes(ctx, outer_scopes, vec![])
+    }
+
+    /// Analyze the query as a subquery in FROM, only a LATERAL one can reference the outer scopes.
+    pub fn analyze_derived(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+        lateral: bool,
+    ) -> BoxFuture<'_, Result<AnalyzedResult>> {
+        match lateral {
+            true => self.analyze_with_scopes(ctx, outer_scopes, vec![]),
+            false => self.analyze_with_scopes(ctx, vec![], outer_scopes),
+        }
+    }
+
+    /// Resolve the query without planning it, returns the IR with the columns resolved,
+    /// the wildcards expanded and the ambiguous columns prefixed.
+    pub async fn resolve(&self, ctx: Arc<QueryContext>) -> Result<QueryASTIR> {
+        let (_, ir) = self.resolve_with_scopes(ctx, vec![], vec![]).await?;
+        Ok(ir)
+    }
+
+    /// Same as `resolve`, but the resolved IR is serialized to JSON.
+    pub async fn resolve_to_json(&self, ctx: Arc<QueryContext>) -> Result<String> {
+        let ir = self.resolve(ctx).await?;
+        serde_json::to_string(&ir).map_err_to_code(ErrorCode::LogicalError, || {
+            "Logical error: cannot serialize the resolved query."
+        })
+    }
+
+    // Boxed because the subqueries in expressions are analyzed recursively.
+    fn analyze_with_scopes(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+        hidden_scopes: Vec<JoinedSchema>,
+    ) -> BoxFuture<'_, Result<AnalyzedResult>> {
+        Box::pin(async move {
+            let resolved = self.resolve_with_scopes(ctx.clone(), outer_scopes, hidden_scopes);
+            let (joined_schema, normalized_result) = resolved.await?;
+
+            let analyze_state = self.analyze_query(normalized_result).await?;
+            self.check_and_finalize(joined_schema, analyze_state, ctx)
+                .await
+        })
+    }
+
+    async fn resolve_with_scopes(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+        hidden_scopes: Vec<JoinedSchema>,
+    ) -> Result<(JoinedSchema, QueryASTIR)> {
+        if !self.set_operations.is_empty() {
+            let resolved = self.resolve_set_operations(ctx, outer_scopes, hidden_scopes);
+            return resolved.await;
+        }
+
+        let resolved = self.resolve_select(ctx.clone(), outer_scopes, hidden_scopes);
+        let (joined_schema, normalized_result) = resolved.await?;
+        self.warn_unused_aliases(&ctx);
+        Ok((joined_schema, normalized_result))
+    }
+
+    // The SELECT itself, the set operations are resolved by the caller.
+    async fn resolve_select(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+        hidden_scopes: Vec<JoinedSchema>,
+    ) -> Result<(JoinedSchema, QueryASTIR)> {
+        let analyzer =
+            JoinedSchemaAnalyzer::create_with_outer_scopes(ctx.clone(), outer_scopes.clone());
+        let mut joined_schema = analyzer.analyze(self).await?;
+        if let Some(group_by) = self.group_by_columns(&ctx, &outer_scopes, &joined_schema) {
+            joined_schema.set_group_by(group_by);
+        }
 
-        let normal_transform = QueryNormalizer::create(ctx.clone());
+        let mut scopes = outer_scopes.clone();
+        scopes.push(joined_schema.clone());
+        let normal_transform = QueryNormalizer::create_with_scopes(ctx.clone(), scopes);
         let normalized_result = normal_transform.transform(self).await?;
 
         let schema = joined_schema.clone();
-        let qualified_rewriter = QualifiedRewriter::create(schema, ctx.clone());
-        let normalized_result = qualified_rewriter.rewrite(normalized_result).await?;
+        let qualified_rewriter =
+            QualifiedRewriter::create_with_outer_scopes(outer_scopes, schema, ctx)
+                .with_hidden_scopes(hidden_scopes);
+        let normalized_result = qualified_rewriter.rewrite(normalized_result)?;
+        Ok((joined_schema, normalized_result))
+    }
+
+    // The operands are resolved in their own scopes, the first SELECT without the ORDER BY,
+    // which is resolved to the output columns of the set operations instead. Boxed because
+    // an operand may have the set operations of its own.
+    fn resolve_set_operations(
+        &self,
+        ctx: Arc<QueryContext>,
+        outer_scopes: Vec<JoinedSchema>,
+        hidden_scopes: Vec<JoinedSchema>,
+    ) -> BoxFuture<'_, Result<(JoinedSchema, QueryASTIR)>> {
+        Box::pin(async move {
+            let mut first = self.clone();
+            first.order_by = vec![];
+            first.set_operations = vec![];
+            let resolved =
+                first.resolve_select(ctx.clone(), outer_scopes.clone(), hidden_scopes.clone());
+            let (joined_schema, mut ir) = resolved.await?;
+
+            let mut output_columns = Self::output_columns(&joined_schema, &ir);
+            for set_operation in &self.set_operations {
+                let operand = &set_operation.query;
+                let resolved = operand.resolve_with_scopes(
+                    ctx.clone(),
+                    outer_scopes.clone(),
+                    hidden_scopes.clone(),
+                );
+                let (operand_schema, operand_ir) = resolved.await?;
+
+                let operand_columns = Self::output_columns(&operand_schema, &operand_ir);
+                if operand_columns.len() != output_columns.len() {
+                    return Err(ErrorCode::SyntaxException(format!(
+                        "Each {} operand must have the same number of columns, but got {} and {}",
+                        set_operation.op,
+                        output_columns.len(),
+                        operand_columns.len()
+                    )));
+                }
+
+                for ((name, data_type), (_, operand_type)) in
+                    output_columns.iter_mut().zip(operand_columns)
+                {
+                    *data_type = merge_types(data_type, &operand_type).map_err(|cause| {
+                        cause.add_message_back(format!(
+                            " (while in {} column {})",
+                            set_operation.op, name
+                        ))
+                    })?;
+                }
+
+                ir.set_operations.push(SetOperation {
+                    op: set_operation.op.to_string(),
+                    all: set_operation.all,
+                    query: operand_ir,
+                });
+            }
+
+            let expression_analyzer = ExpressionAnalyzer::create(ctx.clone());
+            let mut order_by = Vec::with_capacity(self.order_by.len());
+            for order_by_expr in &self.order_by {
+                let expression = expression_analyzer.analyze(&order_by_expr.expr).await?;
+                order_by.push(Expression::Sort {
+                    expr: Box::new(expression.clone()),
+                    asc: order_by_expr.asc.unwrap_or(true),
+                    nulls_first: order_by_expr.asc.unwrap_or(true),
+                    origin_expr: Box::new(expression),
+                });
+            }
 
-        let analyze_state = self.analyze_query(normalized_result).await?;
-        self.check_and_finalize(joined_schema, analyze_state, ctx)
-            .await
+            let rewriter = QualifiedRewriter::create(joined_schema.clone(), ctx.clone());
+            let output_names = output_columns.iter().map(|(name, _)| name.clone());
+            ir.order_by_expressions =
+                rewriter.rewrite_set_order_by(order_by, &output_names.collect::<Vec<_>>())?;
+            ir.set_output_columns = output_columns;
+            // The ORDER BY of the set operations references the aliases of the first SELECT.
+            self.warn_unused_aliases(&ctx);
+            Ok((joined_schema, ir))
+        })
+    }
+
+    // The columns are named as the projection, the type of an expression that can't be
+    // typed statically, such as a correlated column, is left to the other operands.
+    fn output_columns(joined_schema: &JoinedSchema, ir: &QueryASTIR) -> Vec<(String, DataType)> {
+        let schema = joined_schema.to_data_schema();
+        ir.projection_expressions
+            .iter()
+            .map(|expr| {
+                let data_type = expr.to_data_type(&schema).unwrap_or(DataType::Null);
+                (expr.column_name(), data_type)
+            })
+            .collect()
+    }
+
+    // Under `warn_unused_aliases`, the projection aliases no other clause or projection item
+    // references and the table aliases no column is qualified by are reported as warnings.
+    // It's a diagnostic of the query text, it never changes the resolution or fails the query.
+    fn warn_unused_aliases(&self, ctx: &Arc<QueryContext>) {
+        match ctx.get_settings().get_warn_unused_aliases() {
+            Ok(value) if value != 0 => {}
+            _ => return,
+        }
+
+        let mut clause_exprs: Vec<&Expr> = vec![];
+        clause_exprs.extend(self.selection.iter());
+        clause_exprs.extend(self.group_by.iter());
+        clause_exprs.extend(self.grouping_sets.iter().flatten());
+        clause_exprs.extend(self.having.iter());
+        clause_exprs.extend(self.qualify.iter());
+        clause_exprs.extend(self.distinct_on.iter());
+        clause_exprs.extend(self.order_by.iter().map(|order_by| &order_by.expr));
+
+        let projection_exprs = self
+            .projection
+            .iter()
+            .map(|item| match item {
+                SelectItem::UnnamedExpr(expr) => Some(expr),
+                SelectItem::ExprWithAlias { expr, .. } => Some(expr),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for (index, item) in self.projection.iter().enumerate() {
+            if let SelectItem::ExprWithAlias { alias, .. } = item {
+                let other_items = projection_exprs
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .filter_map(|(_, expr)| *expr);
+                let exprs = clause_exprs.iter().copied().chain(other_items);
+                let referenced = Self::expr_identifiers(exprs)
+                    .iter()
+                    .any(|(name, qualifies)| !qualifies && name == &alias.value);
+
+                if !referenced {
+                    ctx.push_warning(format!("Unused projection alias {}", alias.value));
+                }
+            }
+        }
+
+        let mut qualifiers = Self::expr_identifiers(
+            clause_exprs
+                .iter()
+                .copied()
+                .chain(projection_exprs.iter().filter_map(|expr| *expr))
+                .chain(self.join_conditions()),
+        );
+
+        for item in &self.projection {
+            if let SelectItem::QualifiedWildcard(name) = item {
+                let parts = name.0.iter().map(|ident| (ident.value.clone(), true));
+                qualifiers.extend(parts);
+            }
+        }
+
+        for alias in Self::table_aliases(&self.from) {
+            let referenced = qualifiers
+                .iter()
+                .any(|(name, qualifies)| *qualifies && name == &alias);
+
+            if !referenced {
+                ctx.push_warning(format!("Unused table alias {}", alias));
+            }
+        }
+    }
+
+    fn join_conditions(&self) -> impl Iterator<Item = &Expr> {
+        self.from
+            .iter()
+            .flat_map(|table_with_joins| &table_with_joins.joins)
+            .filter_map(|join| match &join.join_operator {
+                JoinOperator::Inner(JoinConstraint::On(expr))
+                | JoinOperator::LeftOuter(JoinConstraint::On(expr))
+                | JoinOperator::RightOuter(JoinConstraint::On(expr))
+                | JoinOperator::FullOuter(JoinConstraint::On(expr)) => Some(expr),
+                _ => None,
+            })
+    }
+
+    fn table_aliases(from: &[TableWithJoins]) -> Vec<String> {
+        let mut aliases = vec![];
+        for table_with_joins in from {
+            let joined = table_with_joins.joins.iter().map(|join| &join.relation);
+            for relation in std::iter::once(&table_with_joins.relation).chain(joined) {
+                match relation {
+                    TableFactor::Table {
+                        alias: Some(alias), ..
+                    }
+                    | TableFactor::Derived {
+                        alias: Some(alias), ..
+                    } => aliases.push(alias.name.value.clone()),
+                    TableFactor::NestedJoin(joins) => {
+                        aliases.extend(Self::table_aliases(std::slice::from_ref(joins.as_ref())))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        aliases
+    }
+
+    // The identifiers in the text of the expressions and whether they qualify a column, such
+    // as `t` of `t.a`, the subqueries included. The function names are not identifiers.
+    fn expr_identifiers<'a>(exprs: impl Iterator<Item = &'a Expr>) -> Vec<(String, bool)> {
+        let mut identifiers = vec![];
+        for expr in exprs {
+            let sql = expr.to_string();
+            let tokens = match Tokenizer::new(&GenericDialect {}, &sql).tokenize() {
+                Ok(tokens) => tokens,
+                Err(_) => continue,
+            };
+
+            let tokens = tokens
+                .into_iter()
+                .filter(|token| !matches!(token, Token::Whitespace(_)))
+                .collect::<Vec<_>>();
+
+            for (index, token) in tokens.iter().enumerate() {
+                if let Token::Word(word) = token {
+                    let after_period = index > 0 && tokens[index - 1] == Token::Period;
+                    match tokens.get(index + 1) {
+                        Some(Token::LParen) => {}
+                        Some(Token::Period) => identifiers.push((word.value.clone(), true)),
+                        _ if !after_period => identifiers.push((word.value.clone(), false)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        identifiers
+    }
+
+    // The GROUP BY keys visible to the subqueries. Only the plain GROUP BY of columns is
+    // resolved here, the others are left unchecked rather than analyzed twice.
+    fn group_by_columns(
+        &self,
+        ctx: &Arc<QueryContext>,
+        outer_scopes: &[JoinedSchema],
+        joined_schema: &JoinedSchema,
+    ) -> Option<Vec<Expression>> {
+        if self.group_by.is_empty() || !self.grouping_sets.is_empty() {
+            return None;
+        }
+
+        let mut columns = Vec::with_capacity(self.group_by.len());
+        for group_by_expr in &self.group_by {
+            match group_by_expr {
+                Expr::Identifier(ident) => columns.push(Expression::Column(ident.value.clone())),
+                Expr::CompoundIdentifier(idents) => columns.push(Expression::QualifiedColumn(
+                    idents.iter().map(|ident| ident.value.clone()).collect(),
+                )),
+                _ => return None,
+            }
+        }
+
+        let rewriter = QualifiedRewriter::create_with_outer_scopes(
+            outer_scopes.to_vec(),
+            joined_schema.clone(),
+            ctx.clone(),
+        );
+        rewriter.resolve_exprs(&columns).ok()
     }
-}
 
-impl DfQueryStatement {
     async fn analyze_query(&self, ir: QueryASTIR) -> Result<QueryAnalyzeState> {
-        let limit = ir.limit;
-        let offset = ir.offset;
+        let ir = Self::lower_ir_expressions(ir)?;
+        let limit = Self::limit_literal(&ir.limit)?;
+        let offset = Self::limit_literal(&ir.offset)?;
         let mut analyze_state = QueryAnalyzeState {
             limit,
             offset,
             ..Default::default()
         };
 
+        if ir.grouping != GroupingSpec::Plain {
+            return Err(ErrorCode::UnImplement(
+                "GROUPING SETS, ROLLUP and CUBE are not yet supported in query plan",
+            ));
+        }
+
+        if ir.limit_with_ties {
+            return Err(ErrorCode::UnImplement(
+                "WITH TIES is not yet supported in query plan",
+            ));
+        }
+
+        if !ir.outer_references.is_empty() {
+            return Err(ErrorCode::UnImplement(
+                "Correlated subqueries are not yet supported in query plan",
+            ));
+        }
+
+        if !ir.set_operations.is_empty() {
+            return Err(ErrorCode::UnImplement(
+                "UNION, INTERSECT and EXCEPT are not yet supported in query plan",
+            ));
+        }
+
+        if !ir.window_expressions.is_empty() || ir.qualify_predicate.is_some() {
+            return Err(ErrorCode::UnImplement(
+                "Window functions are not yet supported in query plan",
+            ));
+        }
+
+        let has_within_group = |expr: &Expression| matches!(expr, Expression::AggregateFunction { within_group, .. } if !within_group.is_empty());
+        if ir.aggregate_expressions.iter().any(has_within_group) {
+            return Err(ErrorCode::UnImplement(
+                "WITHIN GROUP of ordered-set aggregates is not yet supported in query plan",
+            ));
+        }
+
         if let Some(predicate) = &ir.filter_predicate {
             Self::verify_no_aggregate(predicate, "filter")?;
             analyze_state.filter = Some(predicate.clone());
@@ -181,6 +613,43 @@ impl DfQueryStatement {
         Ok(())
     }
 
+    // The resolved IR has the expressions the expression chain cannot evaluate, lower them
+    // into the ones it can, or reject them before planning.
+    fn lower_ir_expressions(mut ir: QueryASTIR) -> Result<QueryASTIR> {
+        let lower_exprs = |exprs: Vec<Expression>| -> Result<Vec<Expression>> {
+            exprs
+                .into_iter()
+                .map(|expr| expr.rewrite(&mut IRExpressionLowering))
+                .collect()
+        };
+
+        let lower_expr = |expr: Option<Expression>| -> Result<Option<Expression>> {
+            match expr {
+                None => Ok(None),
+                Some(expr) => Ok(Some(expr.rewrite(&mut IRExpressionLowering)?)),
+            }
+        };
+
+        ir.filter_predicate = lower_expr(ir.filter_predicate)?;
+        ir.group_by_expressions = lower_exprs(ir.group_by_expressions)?;
+        ir.having_predicate = lower_expr(ir.having_predicate)?;
+        ir.aggregate_expressions = lower_exprs(ir.aggregate_expressions)?;
+        ir.order_by_expressions = lower_exprs(ir.order_by_expressions)?;
+        ir.projection_expressions = lower_exprs(ir.projection_expressions)?;
+        Ok(ir)
+    }
+
+    fn limit_literal(expr: &Option<Expression>) -> Result<Option<usize>> {
+        match expr {
+            None => Ok(None),
+            Some(Expression::Literal { value, .. }) => Ok(Some(value.as_u64()? as usize)),
+            Some(expr) => Err(ErrorCode::SyntaxException(format!(
+                "Unexpected expression for LIMIT clause: {:?}",
+                expr
+            ))),
+        }
+    }
+
     fn verify_no_aggregate(expr: &Expression, info: &str) -> Result<()> {
         match find_aggregate_exprs_in_expr(expr).is_empty() {
             true => Ok(()),
@@ -209,6 +678,24 @@ impl DfQueryStatement {
         }
 
         match tables_desc.remove(0) {
+            JoinedTableDesc::Table {
+                sample: Some(_), ..
+            } => {
+                return Err(ErrorCode::UnImplement(
+                    "TABLESAMPLE is not yet supported in query plan",
+                ));
+            }
+            JoinedTableDesc::Table { pivot: Some(_), .. }
+            | JoinedTableDesc::Subquery { pivot: Some(_), .. } => {
+                return Err(ErrorCode::UnImplement(
+                    "PIVOT is not yet supported in query plan",
+                ));
+            }
+            JoinedTableDesc::Values { .. } => {
+                return Err(ErrorCode::UnImplement(
+                    "VALUES in FROM is not yet supported in query plan",
+                ));
+            }
             JoinedTableDesc::Table { table, .. } => {
                 // TODO: collect push down
                 let source_plan = table
@@ -343,3 +830,63 @@ impl DfQueryStatement {
         )))
     }
 }
+
+struct IRExpressionLowering;
+
+impl ExprRewriter for IRExpressionLowering {
+    fn mutate(&mut self, expr: Expression) -> Result<Expression> {
+        match expr {
+            Expression::Interval { value, unit } => match *value {
+                Expression::Literal { value, .. } => {
+                    ValueExprAnalyzer::interval_literal(&value, &unit)
+                }
+                value => Err(ErrorCode::UnImplement(format!(
+                    "Non-literal interval is not yet supported in query plan: {:?}",
+                    Expression::Interval {
+                        value: Box::new(value),
+                        unit
+                    }
+                ))),
+            },
+            // The C, POSIX and binary collations compare the bytes, which is how the strings
+            // are compared without a collation.
+            Expression::Collate { expr, .. } => Ok(*expr),
+            // The origin expression of the sort key isn't visited by the rewrite.
+            Expression::Sort {
+                expr,
+                asc,
+                nulls_first,
+                origin_expr,
+            } => Ok(Expression::Sort {
+                expr,
+                asc,
+                nulls_first,
+                origin_expr: Box::new(origin_expr.rewrite(self)?),
+            }),
+            Expression::TryCast {
+                on_error: CastOnError::Default(_),
+                ..
+            } => Err(ErrorCode::UnImplement(format!(
+                "DEFAULT ON ERROR cast is not yet supported in query plan: {:?}",
+                expr
+            ))),
+            Expression::Subscript { .. } => Err(ErrorCode::UnImplement(format!(
+                "Subscript access is not yet supported in query plan: {:?}",
+                expr
+            ))),
+            Expression::Array(_) | Expression::Struct(_) => Err(ErrorCode::UnImplement(format!(
+                "Array and struct constructors are not yet supported in query plan: {:?}",
+                expr
+            ))),
+            Expression::InSubquery { .. } => Err(ErrorCode::UnImplement(format!(
+                "IN subquery is not yet supported in query plan: {:?}",
+                expr
+            ))),
+            Expression::QuantifiedComparison { .. } => Err(ErrorCode::UnImplement(format!(
+                "ANY, SOME and ALL subqueries are not yet supported in query plan: {:?}",
+                expr
+            ))),
+            expr => Ok(expr),
+        }
+    }
+}
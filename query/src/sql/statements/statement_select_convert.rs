@@ -12,36 +12,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
+use sqlparser::ast::Expr;
 use sqlparser::ast::Query;
 use sqlparser::ast::Select;
 use sqlparser::ast::SetExpr;
+use sqlparser::ast::SetOperator;
+use sqlparser::ast::Value;
 use sqlparser::parser::ParserError;
 
 use crate::sql::statements::DfQueryStatement;
+use crate::sql::statements::DfSetOperation;
 
 impl TryFrom<Query> for DfQueryStatement {
     type Error = ParserError;
 
     fn try_from(query: Query) -> Result<Self, Self::Error> {
-        let query_body = Self::get_body(&query)?;
+        let (query_body, set_operations) = Self::get_body(&query)?;
 
-        if query.with.is_some() {
-            return Err(ParserError::ParserError(String::from(
-                "CTE is not yet implement",
-            )));
+        let ctes = match &query.with {
+            None => vec![],
+            Some(with) if with.recursive => {
+                return Err(ParserError::ParserError(String::from(
+                    "Recursive CTE is not yet implement",
+                )));
+            }
+            Some(with) => with.cte_tables.clone(),
+        };
+
+        let mut limit = query.limit.clone();
+        let mut limit_clause = limit.as_ref().map(|_| "LIMIT");
+        let mut limit_with_ties = false;
+        if let Some(top) = &query_body.top {
+            limit = Some(Self::row_limit(
+                "TOP",
+                limit_clause,
+                top.percent,
+                &top.quantity,
+            )?);
+            limit_clause = Some("TOP");
+            limit_with_ties = top.with_ties;
         }
 
-        if query.fetch.is_some() {
-            return Err(ParserError::ParserError(String::from(
-                "FETCH is not yet implement",
-            )));
+        if let Some(fetch) = &query.fetch {
+            limit = Some(Self::row_limit(
+                "FETCH",
+                limit_clause,
+                fetch.percent,
+                &fetch.quantity,
+            )?);
+            limit_with_ties = fetch.with_ties;
         }
 
-        if query_body.top.is_some() {
+        if limit_with_ties && query.order_by.is_empty() {
             return Err(ParserError::ParserError(String::from(
-                "TOP is not yet implement",
+                "WITH TIES requires ORDER BY",
             )));
         }
 
@@ -64,26 +91,110 @@ impl TryFrom<Query> for DfQueryStatement {
         }
 
         Ok(DfQueryStatement {
+            ctes,
             from: query_body.from.clone(),
+            table_samples: HashMap::new(),
             projection: query_body.projection.clone(),
             selection: query_body.selection.clone(),
             group_by: query_body.group_by.clone(),
+            grouping_sets: vec![],
             having: query_body.having.clone(),
+            qualify: None,
+            distinct_on: vec![],
             order_by: query.order_by.clone(),
-            limit: query.limit.clone(),
+            limit,
+            limit_with_ties,
             offset: query.offset.clone(),
+            hints: vec![],
+            set_operations,
         })
     }
 }
 
 impl DfQueryStatement {
-    fn get_body(query: &Query) -> Result<&Select, ParserError> {
-        match &query.body {
-            SetExpr::Select(query) => Ok(query),
-            other => Err(ParserError::ParserError(format!(
-                "Query {} is not yet implemented",
-                other
-            ))),
+    // `TOP n` and `FETCH FIRST n ROWS` are the same as `LIMIT n`, only one of them is allowed.
+    // `FETCH FIRST ROW ONLY` without the count is `LIMIT 1`.
+    fn row_limit(
+        clause: &str,
+        limit_clause: Option<&str>,
+        percent: bool,
+        quantity: &Option<Expr>,
+    ) -> Result<Expr, ParserError> {
+        if let Some(limit_clause) = limit_clause {
+            return Err(ParserError::ParserError(format!(
+                "{} conflicts with {}",
+                clause, limit_clause
+            )));
+        }
+
+        if percent {
+            return Err(ParserError::ParserError(format!(
+                "{} PERCENT is not yet implement",
+                clause
+            )));
         }
+
+        match quantity {
+            Some(quantity) => Ok(quantity.clone()),
+            None => Ok(Expr::Value(Value::Number("1".to_string(), false))),
+        }
+    }
+
+    // `A UNION B EXCEPT C` is nested to the left, it's flattened into the first SELECT and
+    // the set operations applied to it from left to right.
+    fn get_body(query: &Query) -> Result<(&Select, Vec<DfSetOperation>), ParserError> {
+        let mut set_operations = vec![];
+        let mut body = &query.body;
+        loop {
+            match body {
+                SetExpr::Select(select) => {
+                    set_operations.reverse();
+                    return Ok((select.as_ref(), set_operations));
+                }
+                SetExpr::SetOperation {
+                    op,
+                    all,
+                    left,
+                    right,
+                } => {
+                    set_operations.push(Self::set_operation(query, op, *all, right)?);
+                    body = left.as_ref();
+                }
+                other => {
+                    return Err(ParserError::ParserError(format!(
+                        "Query {} is not yet implemented",
+                        other
+                    )));
+                }
+            }
+        }
+    }
+
+    // The operand shares the CTEs of the query, but not its ORDER BY and LIMIT, which apply
+    // to the result of the set operations.
+    fn set_operation(
+        query: &Query,
+        op: &SetOperator,
+        all: bool,
+        operand: &SetExpr,
+    ) -> Result<DfSetOperation, ParserError> {
+        let operand = match operand {
+            SetExpr::Query(subquery) => subquery.as_ref().clone(),
+            _ => {
+                let mut subquery = query.clone();
+                subquery.body = operand.clone();
+                subquery.order_by = vec![];
+                subquery.limit = None;
+                subquery.offset = None;
+                subquery.fetch = None;
+                subquery
+            }
+        };
+
+        Ok(DfSetOperation {
+            op: op.clone(),
+            all,
+            query: DfQueryStatement::try_from(operand)?,
+        })
     }
 }
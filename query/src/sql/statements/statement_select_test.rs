@@ -56,6 +56,11 @@ async fn test_statement_select_analyze() -> Result<()> {
             query: "SELECT * FROM system.databases ORDER BY name = 'xxx'",
             expect: "QueryAnalyzeState { before_order_by: [name, (name = xxx)], order_by: [(name = 'xxx')], projection: [name] }",
         },
+        TestCase {
+            name: "Order by position of cast query",
+            query: "SELECT CAST(name AS TEXT) FROM system.databases ORDER BY 1",
+            expect: "QueryAnalyzeState { before_order_by: [cast(name as String)], order_by: [cast(name as String)], projection: [cast(name as String)] }",
+        },
         TestCase {
             name: "Group by query with filter",
             query: "SELECT number % 2 AS number FROM numbers(10) WHERE number > 2 GROUP BY number",
@@ -116,6 +121,21 @@ async fn test_statement_select_analyze() -> Result<()> {
             query: "SELECT avg(number), max(number + 1) + 1 FROM numbers_mt(10000) GROUP BY 1;",
             expect: "QueryAnalyzeState { before_group_by: [1, number, (number + 1)], group_by: [1], aggregate: [avg(number), max((number + 1))], before_projection: [avg(number), (max((number + 1)) + 1)], projection: [avg(number), (max((number + 1)) + 1)] }",
         },
+        TestCase {
+            name: "Group by cast alias query",
+            query: "SELECT CAST(number AS DATE) AS d, COUNT() AS count FROM numbers(10) GROUP BY d",
+            expect: "QueryAnalyzeState { before_group_by: [cast(number as Date16)], group_by: [cast(number as Date16)], aggregate: [COUNT()], before_projection: [cast(number as Date16), COUNT()], projection: [cast(number as Date16) as d, COUNT() as count] }",
+        },
+        TestCase {
+            name: "Group by cast query",
+            query: "SELECT CAST(number AS DATE) AS d, COUNT() AS count FROM numbers(10) GROUP BY CAST(number AS DATE)",
+            expect: "QueryAnalyzeState { before_group_by: [cast(number as Date16)], group_by: [cast(number as Date16)], aggregate: [COUNT()], before_projection: [cast(number as Date16), COUNT()], projection: [cast(number as Date16) as d, COUNT() as count] }",
+        },
+        TestCase {
+            name: "CTE query",
+            query: "WITH c AS (SELECT name AS x FROM system.databases) SELECT x FROM c WHERE x = 'xxx'",
+            expect: "QueryAnalyzeState { filter: (x = xxx), before_projection: [x], projection: [x] }",
+        },
     ];
 
     for test_case in &tests {
@@ -148,3 +168,249 @@ async fn test_statement_select_analyze() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_statement_select_analyze_ir_expressions() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Collation is lowered into the collated expression",
+            query: "SELECT CAST(name AS TEXT) COLLATE \"C\" FROM system.databases ORDER BY 1",
+            expect: "QueryAnalyzeState { before_order_by: [cast(name as String)], order_by: [cast(name as String)], projection: [cast(name as String)] }",
+        },
+        TestCase {
+            name: "Subscript access",
+            query: "SELECT name['key'] FROM system.databases",
+            expect: "Subscript access is not yet supported in query plan: name[key]",
+        },
+        TestCase {
+            name: "IN subquery",
+            query: "SELECT name FROM system.databases WHERE name IN (SELECT name FROM system.databases)",
+            expect: "IN subquery is not yet supported in query plan: (name IN subquery(_subquery_1))",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let actual = match query.analyze(ctx).await {
+                    Ok(AnalyzedResult::SelectQuery(state)) => format!("{:?}", state),
+                    Ok(_) => {
+                        return Err(ErrorCode::LogicalError(
+                            "Query analyzed must be return QueryAnalyzeState",
+                        ));
+                    }
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_statement_select_resolve_to_json() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Wildcard query",
+            query: "SELECT * FROM system.databases",
+            expect: r#"{"join_conditions":[],"filter_predicate":null,"group_by_expressions":[],"grouping":"Plain","having_predicate":null,"qualify_predicate":null,"aggregate_expressions":[],"window_expressions":[],"order_by_expressions":[],"distinct_on_expressions":[],"projection_expressions":[{"Column":"name"}],"limit":null,"limit_with_ties":false,"offset":null,"outer_references":[],"semi_join_subqueries":[],"subquery_ids":[],"hints":[],"set_operations":[],"set_output_columns":[]}"#,
+        },
+        TestCase {
+            name: "Qualified column query",
+            query: "SELECT alias.name FROM system.databases AS alias",
+            expect: r#"{"join_conditions":[],"filter_predicate":null,"group_by_expressions":[],"grouping":"Plain","having_predicate":null,"qualify_predicate":null,"aggregate_expressions":[],"window_expressions":[],"order_by_expressions":[],"distinct_on_expressions":[],"projection_expressions":[{"Column":"name"}],"limit":null,"limit_with_ties":false,"offset":null,"outer_references":[],"semi_join_subqueries":[],"subquery_ids":[],"hints":[],"set_operations":[],"set_output_columns":[]}"#,
+        },
+        TestCase {
+            name: "Unknown column query",
+            query: "SELECT missing FROM system.databases",
+            expect: "Unknown column missing (while in analyze projection expr: missing)",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let actual = match query.resolve_to_json(ctx).await {
+                    Ok(json) => json,
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_statement_select_resolve_set_operations() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Order by the output column of union",
+            query: "SELECT name FROM system.databases UNION SELECT database FROM system.tables ORDER BY name",
+            expect: "NormalQuery { order by: [name], projection: [name], set operations: [UNION NormalQuery { projection: [database] }], set output: [name String] }",
+        },
+        TestCase {
+            name: "Order by position of union",
+            query: "SELECT name AS n FROM system.databases UNION ALL SELECT name FROM system.tables ORDER BY 1 DESC",
+            expect: "NormalQuery { order by: [n], projection: [name as n], set operations: [UNION ALL NormalQuery { projection: [name] }], set output: [n String] }",
+        },
+        TestCase {
+            name: "Chained set operations",
+            query: "SELECT name FROM system.databases UNION SELECT name FROM system.tables EXCEPT SELECT database FROM system.tables",
+            expect: "NormalQuery { projection: [name], set operations: [UNION NormalQuery { projection: [name] }, EXCEPT NormalQuery { projection: [database] }], set output: [name String] }",
+        },
+        TestCase {
+            name: "Unified numeric types",
+            query: "SELECT 1 AS x UNION ALL SELECT 1000",
+            expect: "NormalQuery { projection: [1 as x], set operations: [UNION ALL NormalQuery { projection: [1000] }], set output: [x UInt16] }",
+        },
+        TestCase {
+            name: "Order by column of the other operand",
+            query: "SELECT name FROM system.databases UNION SELECT database FROM system.tables ORDER BY database",
+            expect: "Unknown column database, ORDER BY of the set operations can only reference the columns of the first SELECT",
+        },
+        TestCase {
+            name: "Order by position out of range",
+            query: "SELECT name FROM system.databases UNION SELECT database FROM system.tables ORDER BY 2",
+            expect: "ORDER BY position 2 is not in select list",
+        },
+        TestCase {
+            name: "Mismatched column count",
+            query: "SELECT name FROM system.databases UNION SELECT name, database FROM system.tables",
+            expect: "Each UNION operand must have the same number of columns, but got 1 and 2",
+        },
+        TestCase {
+            name: "Incompatible types",
+            query: "SELECT name FROM system.databases INTERSECT SELECT 1",
+            expect: "Can't merge types from String and UInt8 (while in INTERSECT column name)",
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                let actual = match query.resolve(ctx).await {
+                    Ok(ir) => format!("{:?}", ir),
+                    Err(cause) => cause.message(),
+                };
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_statement_select_warn_unused_aliases() -> Result<()> {
+    struct TestCase {
+        name: &'static str,
+        query: &'static str,
+        warn_unused_aliases: u64,
+        expect: &'static str,
+    }
+
+    let tests = vec![
+        TestCase {
+            name: "Unused aliases",
+            query: "SELECT name AS n FROM system.databases AS d",
+            warn_unused_aliases: 1,
+            expect: r#"["Unused projection alias n", "Unused table alias d"]"#,
+        },
+        TestCase {
+            name: "Unused aliases without the setting",
+            query: "SELECT name AS n FROM system.databases AS d",
+            warn_unused_aliases: 0,
+            expect: "[]",
+        },
+        TestCase {
+            name: "Aliases referenced by the clauses",
+            query: "SELECT d.name AS n, COUNT() AS c FROM system.databases AS d GROUP BY n HAVING c > 0 ORDER BY n",
+            warn_unused_aliases: 1,
+            expect: "[]",
+        },
+        TestCase {
+            name: "Some aliases unused",
+            query: "SELECT t.name AS n, database AS db FROM system.tables AS t ORDER BY n",
+            warn_unused_aliases: 1,
+            expect: r#"["Unused projection alias db"]"#,
+        },
+        TestCase {
+            name: "Table aliases qualifying columns",
+            query: "SELECT d.* FROM system.databases AS d INNER JOIN system.tables AS t ON d.name = t.database",
+            warn_unused_aliases: 1,
+            expect: "[]",
+        },
+        TestCase {
+            name: "Table alias referenced by subquery",
+            query: "SELECT name FROM system.databases AS d WHERE EXISTS (SELECT 1 FROM system.tables AS t WHERE t.database = d.name)",
+            warn_unused_aliases: 1,
+            expect: "[]",
+        },
+        TestCase {
+            name: "Aliases of set operations",
+            query: "SELECT name AS n FROM system.databases UNION SELECT name AS m FROM system.tables ORDER BY n",
+            warn_unused_aliases: 1,
+            expect: r#"["Unused projection alias m"]"#,
+        },
+    ];
+
+    for test_case in &tests {
+        let ctx = try_create_context()?;
+        ctx.get_settings()
+            .set_warn_unused_aliases(test_case.warn_unused_aliases)?;
+        let (mut statements, _) = DfParser::parse_sql(test_case.query)?;
+
+        match statements.remove(0) {
+            DfStatement::Query(query) => {
+                query.resolve(ctx.clone()).await?;
+                let actual = format!("{:?}", ctx.get_warnings());
+                assert_eq!(test_case.expect, actual, "{:#?}", test_case.name);
+            }
+            _ => {
+                return Err(ErrorCode::LogicalError("Cannot get analyze query state."));
+            }
+        }
+    }
+
+    Ok(())
+}